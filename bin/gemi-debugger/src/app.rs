@@ -69,6 +69,9 @@ impl EmulatorApplication {
             eframe::APP_KEY
         )?;
 
+        // breakpoints are not part of the emulator state and need to be installed again
+        app.get_state_mut().emu.refresh_breakpoints();
+
         // when successfully restored and there's a ROM loaded, notify all views
         if app.get_state().emu.is_emulator_loaded() {
             visit_tiles(
@@ -131,6 +134,7 @@ impl Default for EmulatorApplication {
         let tiles_bottom = add_views(vec![
             ViewClass::new_memory(),
             ViewClass::new_disassembly(),
+            ViewClass::new_breakpoints(),
             ViewClass::new_oam(),
         ]);
 
@@ -296,6 +300,16 @@ impl EmulatorApplication {
             self.open_file_dialog();
         }
 
+        // "Save Symbols" button to write labels back into the symbol file of the ROM
+        let can_save_symbols = self.get_state().emu.can_save_symbols();
+        if ui.add_enabled(can_save_symbols, egui::Button::new("Save Symbols")).clicked() {
+            ui.close_menu();
+
+            if let Err(e) = self.get_state().emu.save_symbols() {
+                self.display_message_box(&e);
+            }
+        }
+
         #[cfg(not(target_arch = "wasm32"))]
         {
             // "Quit" button to close the application
@@ -402,6 +416,13 @@ impl EmulatorApplication {
             }
         }
 
+        // "Step Back" button
+        ui.add_enabled_ui(state.emu.can_step_back(), |ui| {
+            if ui.button(BUTTON_LABEL_STEP_BACK).clicked() {
+                state.step_back();
+            }
+        });
+
         // "Step" button
         if ui.button(BUTTON_LABEL_STEP).clicked() {
             state.ui.set_update_mode(UpdateMode::Step);
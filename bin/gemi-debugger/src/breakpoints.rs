@@ -0,0 +1,349 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fmt::{Display, Formatter};
+
+use gemi_core::debug::breakpoints::{Breakpoint, BreakpointId, MemoryAccess, ValueCondition};
+use gemi_core::gameboy::{EmulatorUpdateResults, GameBoy};
+
+use crate::expression::Expression;
+
+
+/// The kind of memory access observed by a watchpoint.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Copy, Clone, PartialEq)]
+pub enum WatchAccess {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+
+/// The condition which will stop the emulator.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Clone, PartialEq)]
+pub enum BreakpointKind {
+    /// Stops before executing the instruction on the given address,
+    /// optionally limited to a specific ROM bank.
+    Execute {
+        address: u16,
+        bank:    Option<u32>,
+    },
+
+    /// Stops after accessing any address within a memory range,
+    /// optionally only if a specific value was read or written.
+    Watch {
+        begin:  u16,
+        end:    u16,
+        access: WatchAccess,
+        value:  Option<u8>,
+    },
+
+    /// Stops after an instruction, once the expression changes from `false` to `true`.
+    Condition(String),
+}
+
+
+/// A single entry of the [BreakpointList].
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct BreakpointEntry {
+    /// The condition to stop the emulator.
+    pub kind: BreakpointKind,
+
+    /// Whether this breakpoint is enabled.
+    pub enabled: bool,
+
+    /// The number of times this breakpoint was hit since the debugger was started.
+    #[serde(skip)]
+    pub hit_count: u32,
+
+    /// The ID of this breakpoint within the emulator, if it was installed.
+    #[serde(skip)]
+    core_id: Option<BreakpointId>,
+
+    /// The parsed expression of a [BreakpointKind::Condition].
+    #[serde(skip)]
+    expression: Option<Expression>,
+
+    /// The result of the expression after the last instruction.
+    #[serde(skip)]
+    last_result: bool,
+}
+
+
+/// The list of breakpoints, watchpoints and conditions set up by the user.
+/// Breakpoints and watchpoints are installed into the emulator instance,
+/// while conditions are evaluated by the debugger after each instruction.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Default)]
+pub struct BreakpointList {
+    /// All breakpoints in the order they were added.
+    entries: Vec<BreakpointEntry>,
+}
+
+
+impl From<WatchAccess> for MemoryAccess {
+    fn from(value: WatchAccess) -> Self {
+        match value {
+            WatchAccess::Read      => MemoryAccess::Read,
+            WatchAccess::Write     => MemoryAccess::Write,
+            WatchAccess::ReadWrite => MemoryAccess::ReadWrite,
+        }
+    }
+}
+
+
+impl Display for WatchAccess {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WatchAccess::Read      => write!(f, "Read"),
+            WatchAccess::Write     => write!(f, "Write"),
+            WatchAccess::ReadWrite => write!(f, "Read/Write"),
+        }
+    }
+}
+
+
+impl Display for BreakpointKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BreakpointKind::Execute { address, bank: None } => {
+                write!(f, "Execute {address:04x}")
+            }
+
+            BreakpointKind::Execute { address, bank: Some(bank) } => {
+                write!(f, "Execute {bank:02x}:{address:04x}")
+            }
+
+            BreakpointKind::Watch { begin, end, access, value } => {
+                if begin == end {
+                    write!(f, "{access} {begin:04x}")?;
+                }
+                else {
+                    write!(f, "{access} {begin:04x}-{end:04x}")?;
+                }
+
+                if let Some(value) = value {
+                    write!(f, " == {value:02x}")?;
+                }
+
+                Ok(())
+            }
+
+            BreakpointKind::Condition(expression) => {
+                write!(f, "If {expression}")
+            }
+        }
+    }
+}
+
+
+impl BreakpointKind {
+    /// Converts this breakpoint into a breakpoint to be installed into the emulator.
+    /// Returns `None` for conditions, which are evaluated by the debugger.
+    fn to_core_breakpoint(&self) -> Option<Breakpoint> {
+        match self {
+            BreakpointKind::Execute { address, bank } => {
+                Some(Breakpoint::Execute {
+                    address: *address,
+                    bank:    *bank,
+                })
+            }
+
+            BreakpointKind::Watch { begin, end, access, value } => {
+                Some(Breakpoint::Memory {
+                    begin:      *begin,
+                    end:        *end,
+                    access:     (*access).into(),
+                    condition:  match value {
+                        Some(value) => ValueCondition::Equals(*value),
+                        None        => ValueCondition::Any,
+                    },
+                })
+            }
+
+            BreakpointKind::Condition(_) => None,
+        }
+    }
+}
+
+
+impl BreakpointEntry {
+    /// Parses the expression of a condition, if not already done.
+    fn prepare(&mut self) -> Result<(), String> {
+        if let BreakpointKind::Condition(expression) = &self.kind {
+            if self.expression.is_none() {
+                self.expression = Some(Expression::parse(expression)?);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+impl BreakpointList {
+    /// Get all breakpoints.
+    pub fn get_entries(&self) -> &[BreakpointEntry] {
+        &self.entries
+    }
+
+
+    /// Adds a new breakpoint and installs it into the emulator, if any.
+    /// Fails if the breakpoint is invalid, like when containing an invalid expression.
+    pub fn add(&mut self, kind: BreakpointKind, gb: Option<&mut GameBoy>) -> Result<(), String> {
+        if let BreakpointKind::Watch { begin, end, .. } = kind {
+            if begin > end {
+                return Err(format!("Invalid memory range {begin:04x}-{end:04x}"));
+            }
+        }
+
+        let mut entry = BreakpointEntry {
+            kind,
+            enabled:     true,
+            hit_count:   0,
+            core_id:     None,
+            expression:  None,
+            last_result: false,
+        };
+
+        entry.prepare()?;
+        self.entries.push(entry);
+
+        if let Some(gb) = gb {
+            self.apply_to(gb);
+        }
+
+        Ok(())
+    }
+
+
+    /// Removes a breakpoint.
+    pub fn remove(&mut self, index: usize, gb: Option<&mut GameBoy>) {
+        if index < self.entries.len() {
+            self.entries.remove(index);
+        }
+
+        if let Some(gb) = gb {
+            self.apply_to(gb);
+        }
+    }
+
+
+    /// Enables or disables a breakpoint.
+    pub fn set_enabled(&mut self, index: usize, enabled: bool, gb: Option<&mut GameBoy>) {
+        if let Some(entry) = self.entries.get_mut(index) {
+            entry.enabled     = enabled;
+            entry.last_result = false;
+        }
+
+        if let Some(gb) = gb {
+            self.apply_to(gb);
+        }
+    }
+
+
+    /// Adds or removes a breakpoint matching the given kind.
+    pub fn toggle(&mut self, kind: BreakpointKind, gb: Option<&mut GameBoy>) -> Result<(), String> {
+        match self.entries.iter().position(|entry| entry.kind == kind) {
+            Some(index) => {
+                self.remove(index, gb);
+                Ok(())
+            }
+
+            None => self.add(kind, gb),
+        }
+    }
+
+
+    /// Checks whether there is an enabled execution breakpoint on the given address.
+    pub fn has_execute_breakpoint(&self, address: u16) -> bool {
+        self.entries.iter().any(|entry|
+                entry.enabled
+            &&  matches!(entry.kind, BreakpointKind::Execute { address: a, .. } if a == address)
+        )
+    }
+
+
+    /// Installs all enabled breakpoints into an emulator instance, replacing any
+    /// breakpoints installed before. This needs to be done each time the emulator
+    /// instance was replaced, since breakpoints are not part of the emulator state.
+    pub fn apply_to(&mut self, gb: &mut GameBoy) {
+        let breakpoints = gb.get_breakpoints_mut();
+        breakpoints.clear();
+
+        for entry in &mut self.entries {
+            entry.core_id = None;
+
+            if let Err(e) = entry.prepare() {
+                eprintln!("invalid breakpoint '{}': {e}", entry.kind);
+                continue;
+            }
+
+            if !entry.enabled {
+                continue;
+            }
+
+            if let Some(breakpoint) = entry.kind.to_core_breakpoint() {
+                match breakpoints.add(breakpoint) {
+                    Ok(id) => entry.core_id = Some(id),
+                    Err(e) => eprintln!("failed to install breakpoint '{}': {e}", entry.kind),
+                }
+            }
+        }
+    }
+
+
+    /// Checks whether there are any enabled conditions, which need to be evaluated
+    /// after each instruction.
+    fn has_conditions(&self) -> bool {
+        self.entries.iter().any(|entry| entry.enabled && entry.expression.is_some())
+    }
+
+
+    /// Checks the results of a single step of the emulator for any breakpoints
+    /// being hit and evaluates all conditions.
+    /// Returns whether the emulator should stop.
+    pub fn check_step(&mut self, gb: &GameBoy, results: &EmulatorUpdateResults) -> bool {
+        let mut stop = false;
+
+        for hit in &results.breakpoint_hits {
+            if let Some(entry) = self.entries.iter_mut().find(|entry| entry.core_id == Some(hit.id)) {
+                entry.hit_count += 1;
+                stop = true;
+            }
+        }
+
+        if self.has_conditions() {
+            for entry in self.entries.iter_mut().filter(|entry| entry.enabled) {
+                if let Some(expression) = &entry.expression {
+                    let result = expression.is_true(gb);
+
+                    // only stop when the condition becomes true
+                    if result && !entry.last_result {
+                        entry.hit_count += 1;
+                        stop = true;
+                    }
+
+                    entry.last_result = result;
+                }
+            }
+        }
+
+        stop
+    }
+}
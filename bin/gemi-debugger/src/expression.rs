@@ -0,0 +1,525 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
+use std::str::Chars;
+
+use gemi_core::cpu::cpu::{RegisterR16, RegisterR8};
+use gemi_core::gameboy::GameBoy;
+
+
+/// A CPU register which can be used within an expression.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Register {
+    R8(RegisterR8),
+    R16(RegisterR16),
+    SP,
+    PC,
+}
+
+
+/// Operators with a single operand.
+#[derive(Copy, Clone, PartialEq)]
+pub enum UnaryOperator {
+    Not,
+    Negate,
+}
+
+
+/// Operators with two operands.
+#[derive(Copy, Clone, PartialEq)]
+pub enum BinaryOperator {
+    Or,
+    And,
+    Equals,
+    NotEquals,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    BitOr,
+    BitAnd,
+    Add,
+    Subtract,
+}
+
+
+/// A parsed expression, which can be evaluated against the current state of the emulator,
+/// like `A == 3f && [ff44] >= 90`.
+///
+/// Expressions may contain:
+/// * CPU registers `A`, `F`, `B`, `C`, `D`, `E`, `H`, `L`, `AF`, `BC`, `DE`, `HL`, `SP` and `PC`
+/// * Numbers, which are always hexadecimal, optionally prefixed by `0x` or `$`
+/// * Memory values via `[address]`, where the address can be any expression
+/// * Operators `||`, `&&`, `==`, `!=`, `<`, `<=`, `>`, `>=`, `|`, `&`, `+`, `-` and `!`
+/// * Parentheses to group sub expressions
+///
+/// Register names take precedence over numbers, so values like `a`, `bc` or `de`
+/// refer to the registers. To use them as hexadecimal numbers, they need a prefix,
+/// like `$a`, `0xbc` or `$de`.
+///
+/// Any expression with a non-zero result is considered to be `true`.
+#[derive(Clone, PartialEq)]
+pub enum Expression {
+    Value(u32),
+    Register(Register),
+    Memory(Box<Expression>),
+    Unary(UnaryOperator, Box<Expression>),
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>),
+}
+
+
+/// A single token of an expression string.
+#[derive(Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Operator(&'static str),
+}
+
+
+/// A parser to create an [Expression] from a list of tokens.
+struct Parser {
+    tokens:   Vec<Token>,
+    position: usize,
+}
+
+
+/// All operators known by the tokenizer. Operators with multiple characters
+/// need to be listed before those with a single character.
+const OPERATORS: [&str; 17] = [
+    "||", "&&", "==", "!=", "<=", ">=",
+    "<", ">", "|", "&", "+", "-", "!", "(", ")", "[", "]",
+];
+
+
+impl Register {
+    /// Get a register by its name.
+    fn by_name(name: &str) -> Option<Register> {
+        match name.to_ascii_uppercase().as_str() {
+            "A"  => Some(Register::R8(RegisterR8::A)),
+            "F"  => Some(Register::R8(RegisterR8::F)),
+            "B"  => Some(Register::R8(RegisterR8::B)),
+            "C"  => Some(Register::R8(RegisterR8::C)),
+            "D"  => Some(Register::R8(RegisterR8::D)),
+            "E"  => Some(Register::R8(RegisterR8::E)),
+            "H"  => Some(Register::R8(RegisterR8::H)),
+            "L"  => Some(Register::R8(RegisterR8::L)),
+            "AF" => Some(Register::R16(RegisterR16::AF)),
+            "BC" => Some(Register::R16(RegisterR16::BC)),
+            "DE" => Some(Register::R16(RegisterR16::DE)),
+            "HL" => Some(Register::R16(RegisterR16::HL)),
+            "SP" => Some(Register::SP),
+            "PC" => Some(Register::PC),
+            _    => None,
+        }
+    }
+
+
+    /// Reads the current value of this register.
+    fn read(&self, gb: &GameBoy) -> u32 {
+        match self {
+            Register::R8(r)  => gb.cpu.get_r8(*r) as u32,
+            Register::R16(r) => gb.cpu.get_r16(*r) as u32,
+            Register::SP     => gb.cpu.get_stack_pointer() as u32,
+            Register::PC     => gb.cpu.get_instruction_pointer() as u32,
+        }
+    }
+}
+
+
+impl BinaryOperator {
+    /// Applies this operator on two values.
+    fn apply(&self, left: u32, right: u32) -> u32 {
+        match self {
+            BinaryOperator::Or             => (left != 0 || right != 0) as u32,
+            BinaryOperator::And            => (left != 0 && right != 0) as u32,
+            BinaryOperator::Equals         => (left == right) as u32,
+            BinaryOperator::NotEquals      => (left != right) as u32,
+            BinaryOperator::Less           => (left <  right) as u32,
+            BinaryOperator::LessOrEqual    => (left <= right) as u32,
+            BinaryOperator::Greater        => (left >  right) as u32,
+            BinaryOperator::GreaterOrEqual => (left >= right) as u32,
+            BinaryOperator::BitOr          => left | right,
+            BinaryOperator::BitAnd         => left & right,
+            BinaryOperator::Add            => left.wrapping_add(right),
+            BinaryOperator::Subtract       => left.wrapping_sub(right),
+        }
+    }
+}
+
+
+impl Expression {
+    /// Parses an expression from a string.
+    pub fn parse(s: &str) -> Result<Expression, String> {
+        let tokens = tokenize(s)?;
+
+        if tokens.is_empty() {
+            return Err("Empty expression".to_string());
+        }
+
+        let mut parser = Parser { tokens, position: 0 };
+        let expression = parser.parse_or()?;
+
+        match parser.peek() {
+            None        => Ok(expression),
+            Some(token) => Err(format!("Unexpected '{token}'")),
+        }
+    }
+
+
+    /// Evaluates this expression against the current state of the emulator.
+    pub fn evaluate(&self, gb: &GameBoy) -> u32 {
+        match self {
+            Expression::Value(value)       => *value,
+            Expression::Register(register) => register.read(gb),
+
+            Expression::Memory(address) => {
+                let address = address.evaluate(gb) as u16;
+                gb.get_mmu().debug_read_u8(address) as u32
+            }
+
+            Expression::Unary(UnaryOperator::Not, operand) => {
+                (operand.evaluate(gb) == 0) as u32
+            }
+
+            Expression::Unary(UnaryOperator::Negate, operand) => {
+                operand.evaluate(gb).wrapping_neg()
+            }
+
+            Expression::Binary(operator, left, right) => {
+                operator.apply(left.evaluate(gb), right.evaluate(gb))
+            }
+        }
+    }
+
+
+    /// Evaluates this expression and checks whether the result is `true`.
+    pub fn is_true(&self, gb: &GameBoy) -> bool {
+        self.evaluate(gb) != 0
+    }
+}
+
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Word(word)         => write!(f, "{word}"),
+            Token::Operator(operator) => write!(f, "{operator}"),
+        }
+    }
+}
+
+
+impl Parser {
+    /// Get the current token without consuming it.
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+
+    /// Consumes the current token if it's the given operator.
+    fn take_operator(&mut self, operator: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Operator(token)) if *token == operator) {
+            self.position += 1;
+            return true;
+        }
+
+        false
+    }
+
+
+    /// Consumes the current token if it's one of the given operators.
+    fn take_any_operator<const N: usize>(&mut self, operators: [(&str, BinaryOperator); N]) -> Option<BinaryOperator> {
+        operators
+            .into_iter()
+            .find(|(token, _)| self.take_operator(token))
+            .map(|(_, operator)| operator)
+    }
+
+
+    /// Expects the current token to be the given operator.
+    fn expect_operator(&mut self, operator: &str) -> Result<(), String> {
+        if self.take_operator(operator) {
+            Ok(())
+        }
+        else {
+            Err(format!("Expected '{operator}'"))
+        }
+    }
+
+
+    /// Parses a chain of binary operations with the same precedence.
+    fn parse_binary<const N: usize>(
+            &mut self,
+            operators: [(&str, BinaryOperator); N],
+            parse_operand: fn(&mut Parser) -> Result<Expression, String>
+    ) -> Result<Expression, String> {
+        let mut expression = parse_operand(self)?;
+
+        while let Some(operator) = self.take_any_operator(operators) {
+            let right  = parse_operand(self)?;
+            expression = Expression::Binary(operator, Box::new(expression), Box::new(right));
+        }
+
+        Ok(expression)
+    }
+
+
+    fn parse_or(&mut self) -> Result<Expression, String> {
+        self.parse_binary([("||", BinaryOperator::Or)], Parser::parse_and)
+    }
+
+
+    fn parse_and(&mut self) -> Result<Expression, String> {
+        self.parse_binary([("&&", BinaryOperator::And)], Parser::parse_comparison)
+    }
+
+
+    fn parse_comparison(&mut self) -> Result<Expression, String> {
+        self.parse_binary(
+            [
+                ("==", BinaryOperator::Equals),
+                ("!=", BinaryOperator::NotEquals),
+                ("<=", BinaryOperator::LessOrEqual),
+                (">=", BinaryOperator::GreaterOrEqual),
+                ("<",  BinaryOperator::Less),
+                (">",  BinaryOperator::Greater),
+            ],
+            Parser::parse_bit_or
+        )
+    }
+
+
+    fn parse_bit_or(&mut self) -> Result<Expression, String> {
+        self.parse_binary([("|", BinaryOperator::BitOr)], Parser::parse_bit_and)
+    }
+
+
+    fn parse_bit_and(&mut self) -> Result<Expression, String> {
+        self.parse_binary([("&", BinaryOperator::BitAnd)], Parser::parse_sum)
+    }
+
+
+    fn parse_sum(&mut self) -> Result<Expression, String> {
+        self.parse_binary(
+            [
+                ("+", BinaryOperator::Add),
+                ("-", BinaryOperator::Subtract),
+            ],
+            Parser::parse_unary
+        )
+    }
+
+
+    fn parse_unary(&mut self) -> Result<Expression, String> {
+        if self.take_operator("!") {
+            return Ok(Expression::Unary(UnaryOperator::Not, Box::new(self.parse_unary()?)));
+        }
+
+        if self.take_operator("-") {
+            return Ok(Expression::Unary(UnaryOperator::Negate, Box::new(self.parse_unary()?)));
+        }
+
+        self.parse_primary()
+    }
+
+
+    fn parse_primary(&mut self) -> Result<Expression, String> {
+        if self.take_operator("(") {
+            let expression = self.parse_or()?;
+            self.expect_operator(")")?;
+            return Ok(expression);
+        }
+
+        if self.take_operator("[") {
+            let address = self.parse_or()?;
+            self.expect_operator("]")?;
+            return Ok(Expression::Memory(Box::new(address)));
+        }
+
+        match self.peek().cloned() {
+            Some(Token::Word(word)) => {
+                self.position += 1;
+                parse_word(&word)
+            }
+
+            Some(token) => Err(format!("Unexpected '{token}'")),
+            None        => Err("Unexpected end of expression".to_string()),
+        }
+    }
+}
+
+
+/// Parses a single word, which is either a register name or a hexadecimal number.
+/// Words matching a register name are always taken as register.
+fn parse_word(word: &str) -> Result<Expression, String> {
+    if let Some(register) = Register::by_name(word) {
+        return Ok(Expression::Register(register));
+    }
+
+    let digits = word
+        .strip_prefix("0x")
+        .or_else(|| word.strip_prefix("0X"))
+        .or_else(|| word.strip_prefix('$'))
+        .unwrap_or(word)
+    ;
+
+    u32::from_str_radix(digits, 16)
+        .map(Expression::Value)
+        .map_err(|_| format!("Invalid value '{word}'"))
+}
+
+
+/// Splits an expression string into tokens.
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars  = s.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        }
+        else if c.is_ascii_alphanumeric() || c == '$' {
+            tokens.push(Token::Word(take_word(&mut chars)));
+        }
+        else {
+            let remaining = chars.clone().collect::<String>();
+            let operator  = OPERATORS
+                .iter()
+                .find(|op| remaining.starts_with(**op))
+                .ok_or_else(|| format!("Unexpected character '{c}'"))?
+            ;
+
+            for _ in 0 .. operator.len() {
+                chars.next();
+            }
+
+            tokens.push(Token::Operator(operator));
+        }
+    }
+
+    Ok(tokens)
+}
+
+
+/// Reads a word consisting of alphanumeric characters from the input.
+fn take_word(chars: &mut Peekable<Chars>) -> String {
+    let mut word = String::new();
+
+    if let Some(c) = chars.next_if_eq(&'$') {
+        word.push(c);
+    }
+
+    while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric()) {
+        word.push(c);
+    }
+
+    word
+}
+
+
+#[cfg(test)]
+mod tests {
+    use gemi_core::mmu::locations::MEMORY_LOCATION_LCD_STATUS;
+    use super::*;
+
+    fn create_gameboy() -> GameBoy {
+        let mut gb = GameBoy::build().finish().unwrap();
+        gb.initialize();
+        gb
+    }
+
+
+    fn evaluate(s: &str, gb: &GameBoy) -> u32 {
+        Expression::parse(s).unwrap().evaluate(gb)
+    }
+
+
+    #[test]
+    fn parse_numbers() {
+        let gb = create_gameboy();
+
+        assert_eq!(0x3f,   evaluate("3f",     &gb));
+        assert_eq!(0x3f,   evaluate("0x3f",   &gb));
+        assert_eq!(0x3f,   evaluate("$3f",    &gb));
+        assert_eq!(0xff44, evaluate("FF44",   &gb));
+        assert!(Expression::parse("3g").is_err());
+        assert!(Expression::parse("0x").is_err());
+    }
+
+
+    #[test]
+    fn register_names_shadow_numbers() {
+        let mut gb = create_gameboy();
+        gb.cpu.set_r8(RegisterR8::A, 0x12);
+        gb.cpu.set_r16(RegisterR16::BC, 0x3456);
+
+        assert_eq!(0x12,   evaluate("a",    &gb));
+        assert_eq!(0x3456, evaluate("bc",   &gb));
+        assert_eq!(0x0a,   evaluate("$a",   &gb));
+        assert_eq!(0xbc,   evaluate("0xbc", &gb));
+        assert_eq!(0xde,   evaluate("$de",  &gb));
+    }
+
+
+    #[test]
+    fn operator_precedence() {
+        let gb = create_gameboy();
+
+        assert_eq!(1,   evaluate("1 || 0 && 0",       &gb));
+        assert_eq!(0,   evaluate("(1 || 0) && 0",     &gb));
+        assert_eq!(1,   evaluate("2 + 3 == 5",        &gb));
+        assert_eq!(0x3, evaluate("1 | 6 & 3",         &gb));
+        assert_eq!(1,   evaluate("!0",                &gb));
+        assert_eq!(0,   evaluate("!(4 - 4 == 0)",     &gb));
+        assert_eq!(2,   evaluate("5 - 2 - 1",         &gb));
+        assert_eq!(0,   evaluate("-1 + 1",            &gb));
+        assert_eq!(1,   evaluate("3 < 4 && 4 <= 4 && 5 > 4 && 5 >= 5 && 1 != 2", &gb));
+    }
+
+
+    #[test]
+    fn parse_errors() {
+        assert!(Expression::parse("").is_err());
+        assert!(Expression::parse("(1 + 2").is_err());
+        assert!(Expression::parse("[ff44").is_err());
+        assert!(Expression::parse("1 +").is_err());
+        assert!(Expression::parse("1 2").is_err());
+        assert!(Expression::parse("a ~ b").is_err());
+    }
+
+
+    #[test]
+    fn read_memory() {
+        let mut gb = create_gameboy();
+        gb.get_mmu_mut().write_u8(0xc000, 0x42);
+        gb.get_mmu_mut().write_u8(0x8000, 0x24);
+
+        assert_eq!(0x42, evaluate("[c000]",             &gb));
+        assert_eq!(0x43, evaluate("[c001 - 1] + 1",     &gb));
+        assert_eq!(1,    evaluate("[ff44] == [ff00 + 44]", &gb));
+
+        // VRAM can be read while it's locked for the CPU
+        while gb.get_mmu().peek_u8(MEMORY_LOCATION_LCD_STATUS) & 0x03 != 3 {
+            gb.get_peripherals_mut().ppu.update(1);
+        }
+
+        assert_eq!(0xff, gb.get_mmu().peek_u8(0x8000));
+        assert_eq!(0x24, evaluate("[8000]", &gb));
+    }
+}
@@ -17,7 +17,9 @@
 
 pub mod app;
 pub mod behaviour;
+pub mod breakpoints;
 pub mod event;
+pub mod expression;
 pub mod highlight;
 pub mod selection;
 pub mod state;
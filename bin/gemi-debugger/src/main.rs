@@ -26,7 +26,9 @@ use crate::app::EmulatorApplication;
 mod views;
 mod app;
 mod behaviour;
+mod breakpoints;
 mod event;
+mod expression;
 mod selection;
 mod state;
 mod strings;
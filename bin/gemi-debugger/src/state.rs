@@ -17,15 +17,18 @@
 
 use gemi_core::cartridge::Cartridge;
 use gemi_core::debug::DebugEvent;
+use gemi_core::debug::symbols::{Symbol, SymbolTable, FILE_EXT_SYM};
 use gemi_core::gameboy::{Clock, DeviceType, EmulatorUpdateResults, GameBoy};
 use gemi_core::input::InputButton;
 use gemi_core::mmu::memory_data::MemoryData;
 use gemi_core::ppu::ppu::CPU_CYCLES_PER_FRAME;
+use gemi_core::rewind::RewindBuffer;
 use gemi_utils::keybindings::KeyBindings;
 use serde::{Deserialize, Deserializer};
 use std::fmt::{Display, Formatter};
 use std::path::{Path, PathBuf};
 
+use crate::breakpoints::{BreakpointKind, BreakpointList};
 use crate::selection::{Kind, Selection};
 
 /// An enum to store the device type to be emulated
@@ -103,6 +106,27 @@ pub struct EmulatorInstance {
     /// Will be [None] if no ROM is loaded.
     #[serde(deserialize_with = "deserialize_emulator_instance")]
     gb: Option<GameBoy>,
+
+    /// Stores previous states of the emulator to step back in time.
+    #[serde(skip)]
+    rewind: RewindBuffer,
+
+    /// Breakpoints set up by the user, which will be installed
+    /// into each emulator instance being loaded.
+    #[serde(default)]
+    breakpoints: BreakpointList,
+
+    /// Symbols of the ROM currently loaded, which are displayed as labels.
+    #[serde(default)]
+    symbols: SymbolTable,
+
+    /// The file the symbols were loaded from and will be saved into.
+    #[serde(default)]
+    symbols_file: Option<PathBuf>,
+
+    /// Counts each change of the symbols, so views are able to refresh cached labels.
+    #[serde(skip)]
+    symbols_revision: u32,
 }
 
 
@@ -230,6 +254,9 @@ impl EmulatorState {
 
         self.load_cartridge(cartridge)?;
 
+        // load the symbol file next to the ROM file, if any
+        self.emu.load_symbols(&path.with_extension(FILE_EXT_SYM));
+
         // store the path to the rom file opened
         self.last_rom_file = Some(path.to_path_buf());
 
@@ -244,6 +271,9 @@ impl EmulatorState {
         // no path known
         self.last_rom_file = None;
 
+        // symbols of any previous ROM are no longer valid
+        self.emu.set_symbols(SymbolTable::new(), None);
+
         self.instantiate_emulator_with_cartridge(cartridge)
     }
 
@@ -279,8 +309,12 @@ impl EmulatorState {
         // reset key states after emulator loading
         self.ui.key_bindings.reset_key_states(&mut gb);
 
-        // store the new emulator instance
+        // install all breakpoints into the new instance
+        self.emu.breakpoints.apply_to(&mut gb);
+
+        // store the new emulator instance, previous states are no longer valid
         self.emu.gb = Some(gb);
+        self.emu.rewind.clear();
 
         // success!
         Ok(())
@@ -302,10 +336,15 @@ impl EmulatorState {
                 UpdateMode::Paused
             }
 
-            // process the next frame and stay in continuous mode
+            // process the next frame and stay in continuous mode,
+            // unless a breakpoint was hit
             UpdateMode::Continuous => {
-                self.emu.run_frame();
-                UpdateMode::Continuous
+                if self.emu.run_frame() {
+                    UpdateMode::Paused
+                }
+                else {
+                    UpdateMode::Continuous
+                }
             }
 
             // process the next step and switch into pause mode
@@ -314,7 +353,7 @@ impl EmulatorState {
                     UpdateStepMode::Frame       => self.emu.run_frame(),
                     UpdateStepMode::Line        => self.emu.run_line(),
                     UpdateStepMode::Instruction => self.emu.run_single_step(),
-                }
+                };
 
                 UpdateMode::Paused
             }
@@ -322,6 +361,15 @@ impl EmulatorState {
     }
 
 
+    /// Restores the previous state of the emulator from the rewind buffer, if any,
+    /// and pauses the emulation.
+    pub fn step_back(&mut self) {
+        if self.emu.step_back() {
+            self.ui.update_mode = UpdateMode::Paused;
+        }
+    }
+
+
     /// Forward key events into the emulator.
     pub fn set_key_pressed(&mut self, key: egui::Key, pressed: bool) {
         if let Some(gb) = &mut self.emu.gb {
@@ -358,47 +406,245 @@ impl EmulatorInstance {
 
 
     /// Process a single frame of the emulator, if any.
-    pub fn run_frame(&mut self) {
-        self.run_until(|_emu, cycles, result|
+    /// Returns whether the emulator was stopped by a breakpoint.
+    pub fn run_frame(&mut self) -> bool {
+        let stopped = self.run_until(|_emu, cycles, result|
                 result.events.contains(DebugEvent::PpuFrameCompleted)
             ||  cycles >= CPU_CYCLES_PER_FRAME
         );
+
+        if let Some(gb) = &self.gb {
+            if let Err(e) = self.rewind.on_frame_completed(gb) {
+                eprintln!("failed to capture rewind state: {e}");
+            }
+        }
+
+        stopped
+    }
+
+
+    /// Checks whether there are any previous states stored to step back to.
+    pub fn can_step_back(&self) -> bool {
+        !self.rewind.is_empty()
+    }
+
+
+    /// Restores the previous state of the emulator from the rewind buffer.
+    /// Returns whether a state was restored.
+    pub fn step_back(&mut self) -> bool {
+        match self.rewind.step_back() {
+            Ok(Some(mut gb)) => {
+                if let Some(previous) = &mut self.gb {
+                    gb.get_peripherals_mut().mem.take_frontend_state_from(&mut previous.get_peripherals_mut().mem);
+                }
+
+                self.breakpoints.apply_to(&mut gb);
+                self.gb = Some(gb);
+                true
+            }
+
+            Ok(None) => false,
+
+            Err(e) => {
+                eprintln!("failed to restore rewind state: {e}");
+                self.rewind.clear();
+                false
+            }
+        }
     }
 
 
     /// Run the emulator until the next scanline was completed drawing.
-    pub fn run_line(&mut self) {
+    /// Returns whether the emulator was stopped by a breakpoint.
+    pub fn run_line(&mut self) -> bool {
         self.run_until(|_emu, cycles, result|
                 result.events.contains(DebugEvent::PpuLineCompleted)
             ||  cycles >= CPU_CYCLES_PER_FRAME
-        );
+        )
     }
 
 
     /// Run the emulator for a single instruction.
-    pub fn run_single_step(&mut self) {
-        if let Some(emu) = self.get_emulator_mut() {
-            emu.run_single_step();
-        }
+    /// Returns whether the emulator was stopped by a breakpoint.
+    pub fn run_single_step(&mut self) -> bool {
+        self.run_until(|_emu, _cycles, _result| true)
     }
 
 
-    /// Run the emulator until a certain condition is met. 
-    pub fn run_until<F>(&mut self, condition: F)
+    /// Run the emulator until a certain condition is met or a breakpoint was hit.
+    /// Returns whether the emulator was stopped by a breakpoint.
+    pub fn run_until<F>(&mut self, condition: F) -> bool
         where F: Fn(&GameBoy, Clock, EmulatorUpdateResults) -> bool
     {
-        if let Some(emu) = self.get_emulator_mut() {
+        if let Some(emu) = &mut self.gb {
             let mut cycles = 0;
 
             loop {
                 let result = emu.run_single_step();
                 cycles += result.cycles;
 
+                if self.breakpoints.check_step(emu, &result) {
+                    return true;
+                }
+
                 if condition(emu, cycles, result) {
                     break;
                 }
             }
         }
+
+        false
+    }
+
+
+    /// Get the list of breakpoints set up by the user.
+    pub fn get_breakpoints(&self) -> &BreakpointList {
+        &self.breakpoints
+    }
+
+
+    /// Adds a new breakpoint and installs it into the current emulator instance, if any.
+    pub fn add_breakpoint(&mut self, kind: BreakpointKind) -> Result<(), String> {
+        self.breakpoints.add(kind, self.gb.as_mut())
+    }
+
+
+    /// Adds or removes a breakpoint matching the given kind.
+    pub fn toggle_breakpoint(&mut self, kind: BreakpointKind) -> Result<(), String> {
+        self.breakpoints.toggle(kind, self.gb.as_mut())
+    }
+
+
+    /// Removes a breakpoint from the list.
+    pub fn remove_breakpoint(&mut self, index: usize) {
+        self.breakpoints.remove(index, self.gb.as_mut());
+    }
+
+
+    /// Enables or disables a breakpoint.
+    pub fn set_breakpoint_enabled(&mut self, index: usize, enabled: bool) {
+        self.breakpoints.set_enabled(index, enabled, self.gb.as_mut());
+    }
+
+
+    /// Installs all breakpoints into the current emulator instance.
+    /// This is required after an emulator instance was restored from a serialized state.
+    pub fn refresh_breakpoints(&mut self) {
+        if let Some(gb) = &mut self.gb {
+            self.breakpoints.apply_to(gb);
+        }
+    }
+
+
+    /// Get the symbols of the ROM currently loaded.
+    pub fn get_symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+
+    /// Get a number which changes each time the symbols were modified.
+    pub fn get_symbols_revision(&self) -> u32 {
+        self.symbols_revision
+    }
+
+
+    /// Replaces the current symbols. `file` is used as target when saving the symbols.
+    pub fn set_symbols(&mut self, symbols: SymbolTable, file: Option<PathBuf>) {
+        self.symbols          = symbols;
+        self.symbols_file     = file;
+        self.symbols_revision = self.symbols_revision.wrapping_add(1);
+    }
+
+
+    /// Loads symbols from a symbol file. If the file does not exist, the symbols will be cleared,
+    /// but the file will still be used to save any labels created by the user.
+    pub fn load_symbols(&mut self, path: &Path) {
+        let symbols = if path.exists() {
+            SymbolTable::read_from_file(path).unwrap_or_else(|e| {
+                eprintln!("failed to load symbols from {}: {e}", path.display());
+                SymbolTable::new()
+            })
+        }
+        else {
+            SymbolTable::new()
+        };
+
+        self.set_symbols(symbols, Some(path.to_path_buf()));
+    }
+
+
+    /// Saves the current symbols into the file they were loaded from.
+    pub fn save_symbols(&self) -> Result<(), String> {
+        let path = self.symbols_file.as_ref().ok_or("No symbol file known for this ROM")?;
+
+        self.symbols
+            .write_to_file(path)
+            .map_err(|e| format!("Failed to save symbols to {}: {e}", path.display()))
+    }
+
+
+    /// Checks whether the symbols can be saved into a file.
+    pub fn can_save_symbols(&self) -> bool {
+        self.symbols_file.is_some()
+    }
+
+
+    /// Get the bank of a symbol located on an address, based on the current memory mapping.
+    pub fn get_symbol_bank(&self, address: u16) -> Option<u32> {
+        self.gb.as_ref().and_then(|gb| gb.get_mapped_rom_bank(address))
+    }
+
+
+    /// Get the label located exactly on an address, if any.
+    pub fn get_label_at(&self, address: u16) -> Option<&Symbol> {
+        self.symbols.find_at(address, self.get_symbol_bank(address))
+    }
+
+
+    /// Formats an address as label with an offset, like `Main+$4`, if there is any label in front of it.
+    pub fn format_address(&self, address: u16) -> Option<String> {
+        self.symbols.format_address(address, self.get_symbol_bank(address))
+    }
+
+
+    /// Sets the label on an address. If there's already a label, it will be renamed,
+    /// otherwise a new label will be created.
+    pub fn set_label(&mut self, address: u16, name: &str) -> Result<(), String> {
+        let name = name.trim();
+
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!("Invalid label name '{name}'"));
+        }
+
+        match self.get_label_at(address).map(|symbol| symbol.name.clone()) {
+            Some(old_name) if old_name == name => { }
+            Some(old_name) => self.symbols.rename(&old_name, name)?,
+
+            None => {
+                if self.symbols.find_by_name(name).is_some() {
+                    return Err(format!("Symbol '{name}' already exists"));
+                }
+
+                self.symbols.add(Symbol {
+                    name:   name.to_string(),
+                    bank:   self.get_symbol_bank(address).unwrap_or(0),
+                    address,
+                });
+            }
+        }
+
+        self.symbols_revision = self.symbols_revision.wrapping_add(1);
+
+        Ok(())
+    }
+
+
+    /// Removes the label located on an address, if any.
+    pub fn remove_label(&mut self, address: u16) {
+        if let Some(name) = self.get_label_at(address).map(|symbol| symbol.name.clone()) {
+            self.symbols.remove(&name);
+            self.symbols_revision = self.symbols_revision.wrapping_add(1);
+        }
     }
 }
 
@@ -475,7 +721,12 @@ impl Default for EmulatorState {
             last_rom_file: None,
 
             emu: EmulatorInstance {
-                gb: None,
+                gb:                 None,
+                rewind:             RewindBuffer::default(),
+                breakpoints:        BreakpointList::default(),
+                symbols:            SymbolTable::new(),
+                symbols_file:       None,
+                symbols_revision:   0,
             },
 
             ui: UiStates {
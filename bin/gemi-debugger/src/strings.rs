@@ -18,6 +18,7 @@
 pub const BUTTON_LABEL_PLAY: &str   = "\u{23f5}";
 pub const BUTTON_LABEL_PAUSE: &str  = "\u{23f8}";
 pub const BUTTON_LABEL_STEP: &str   = "\u{23ed}";
+pub const BUTTON_LABEL_STEP_BACK: &str = "\u{23ee}";
 pub const BUTTON_LABEL_RELOAD: &str = "\u{27f3}";
 
 pub const BUTTON_LABEL_ADD: &str    = "\u{2795}";
@@ -16,6 +16,7 @@
  */
 
 use std::cmp::{max, min};
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
 use std::ops::{Range, RangeInclusive};
 use std::string::ToString;
@@ -169,6 +170,15 @@ struct MemoryEditorRuntimeData {
 
     /// The current cursor position within the editor text box.
     edit_label_cursor_position: Option<usize>,
+
+    /// Address ranges of memory cells to be marked, like watchpoints.
+    marked_address_ranges: Vec<RangeInclusive<usize>>,
+
+    /// Labels to be displayed along the memory cells, by their address.
+    labels: BTreeMap<usize, String>,
+
+    /// The address of the memory cell the user clicked with the secondary mouse button, if any.
+    secondary_clicked_address: Option<usize>,
 }
 
 
@@ -330,6 +340,25 @@ impl<Source> MemoryEditor<Source> {
     }
 
 
+    /// Sets the address ranges of memory cells to be marked.
+    pub fn set_marked_ranges(&mut self, ranges: Vec<RangeInclusive<usize>>) {
+        self.rt.marked_address_ranges = ranges;
+    }
+
+
+    /// Sets the labels to be displayed along the memory cells, by their address.
+    pub fn set_labels(&mut self, labels: BTreeMap<usize, String>) {
+        self.rt.labels = labels;
+    }
+
+
+    /// Takes the address of the memory cell the user clicked with the
+    /// secondary mouse button since the last call, if any.
+    pub fn take_secondary_clicked_address(&mut self) -> Option<usize> {
+        self.rt.secondary_clicked_address.take()
+    }
+
+
     /// Stores the address which should be edited and let the memory
     /// editor switch into edit mode at the beginning of the next frame.
     pub fn edit(&mut self, address: usize) {
@@ -578,8 +607,8 @@ impl<Source> MemoryEditor<Source> {
             on_read: &Box<impl Fn(&Source, usize) -> Option<u8>>,
             on_write: &mut Box<impl FnMut(&mut Source, usize, u8)>
     ) {
-        // the column for the category header is just empty here
-        ui.allocate_space(vec2(self.rt.column_width_category, 0.0));
+        // the column for the category header displays the labels of this line, if any
+        self.display_line_label(ui, start_address);
 
         self.display_memory_line(
                 ui,
@@ -592,6 +621,21 @@ impl<Source> MemoryEditor<Source> {
     }
 
 
+    /// Display the first label located within a line in the column of the category header.
+    fn display_line_label(&mut self, ui: &mut Ui, start_address: usize) {
+        let (_, rect)  = ui.allocate_space(vec2(self.rt.column_width_category, 0.0));
+        let line_range = start_address .. (start_address + self.rt.columns_per_line);
+        let indent     = ui.spacing().indent;
+
+        if let Some((_, label)) = self.rt.labels.range(line_range).next() {
+            let widget_text = WidgetText::from(GemiStyle::LABEL.rich_text(label));
+            let galley      = widget_text.into_galley(ui, Some(TextWrapMode::Truncate), rect.width() - indent, TextStyle::Monospace);
+
+            ui.painter().galley(pos2(rect.left() + indent, rect.top()), galley, ui.visuals().text_color());
+        }
+    }
+
+
     /// Display the header of a catagory.
     fn display_category_header(&mut self, ui: &mut Ui, title: String, expanded: bool) -> Option<bool> {
         let button_padding = ui.spacing().button_padding;
@@ -832,15 +876,38 @@ impl<Source> MemoryEditor<Source> {
             None => PLACEHOLDER_NO_VALUE.to_string()
         };
 
-        let response = Label::new(style.rich_text(&value_str))
+        let mut response = Label::new(style.rich_text(&value_str))
             .sense(Sense::click())
             .ui(ui)
         ;
 
+        // underline cells with a label and display the label when hovered
+        if let Some(label) = self.rt.labels.get(&address) {
+            if let Some(color) = GemiStyle::LABEL.color {
+                ui.painter().hline(response.rect.x_range(), response.rect.bottom(), Stroke::new(1.0, color));
+            }
+
+            response = response.on_hover_text(GemiStyle::LABEL.rich_text(label));
+        }
+
+        // draw an outline around marked cells
+        if self.rt.marked_address_ranges.iter().any(|range| range.contains(&address)) {
+            ui.painter().rect_stroke(
+                response.rect.expand(1.0),
+                2.0,
+                Stroke::new(1.0, GemiStyle::BREAKPOINT_MARKER)
+            );
+        }
+
         // when clicked, switch into edito mode
         if self.is_editable() && writable && response.clicked_by(PointerButton::Primary) {
             self.edit(address);
         }
+
+        // store secondary clicks to be handled by the owner of the editor
+        if response.clicked_by(PointerButton::Secondary) {
+            self.rt.secondary_clicked_address = Some(address);
+        }
     }
 
 
@@ -1203,6 +1270,9 @@ impl Default for MemoryEditorRuntimeData {
             edit_string:                    String::new(),
             edit_label_width:               0.0,
             edit_label_cursor_position:     None,
+            marked_address_ranges:          Vec::new(),
+            labels:                         BTreeMap::new(),
+            secondary_clicked_address:      None,
         }
     }
 }
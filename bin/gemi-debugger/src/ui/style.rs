@@ -92,6 +92,9 @@ impl GemiStyle {
     /// The background color of highlighted areas.
     pub const BACKGROUND_HIGHLIGHT_SELECTION: Color32 = Color32::from_rgb(0x00, 0x7e, 0xe1);
     pub const BACKGROUND_HIGHLIGHT_HOVER:     Color32 = Color32::from_rgb(0x7e, 0xb5, 0xe1);
+
+    /// The color of markers displaying breakpoints or watchpoints.
+    pub const BREAKPOINT_MARKER: Color32 = Color32::from_rgb(0xe0, 0x30, 0x30);
     
     /// A slightly stronger text for caption lines.
     pub const CAPTION: TextFormatting = TextFormatting {
@@ -125,6 +128,14 @@ impl GemiStyle {
         italic: false,
     };
 
+    /// Text formatting to display labels of symbol files.
+    pub const LABEL: TextFormatting = TextFormatting {
+        style: TextStyle::Monospace,
+        color: Some(Color32::from_rgb(0xff, 0xc6, 0x6d)),
+        bold: false,
+        italic: false,
+    };
+
     /// Text formatting to display keywords.
     pub const KEYWORD: TextFormatting = TextFormatting {
         style: TextStyle::Monospace,
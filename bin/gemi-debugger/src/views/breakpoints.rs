@@ -0,0 +1,325 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fmt::{Display, Formatter};
+
+use egui::{ComboBox, TextEdit, TextStyle, Ui};
+use egui_extras::{Column, TableBuilder, TableRow};
+
+use crate::breakpoints::{BreakpointKind, WatchAccess};
+use crate::state::EmulatorState;
+use crate::strings::{BUTTON_LABEL_ADD, BUTTON_LABEL_DELETE};
+use crate::ui::style::GemiStyle;
+use crate::views::View;
+
+
+/// The type of breakpoint to be created via the toolbar.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Copy, Clone, PartialEq)]
+enum NewBreakpointType {
+    Execute,
+    Watch,
+    Condition,
+}
+
+
+/// A view to list, add, enable and disable breakpoints, watchpoints and conditions.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct BreakpointsView {
+    /// The type of the breakpoint to be created.
+    new_type: NewBreakpointType,
+
+    /// The kind of memory access to be observed by new watchpoints.
+    new_access: WatchAccess,
+
+    /// The text input describing the breakpoint to be created.
+    input: String,
+
+    /// The error of the last attempt to create a breakpoint, if any.
+    #[serde(skip)]
+    error: Option<String>,
+
+    /// The index of the currently selected entry.
+    selected_entry: Option<usize>,
+}
+
+
+impl Display for NewBreakpointType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NewBreakpointType::Execute   => write!(f, "Execute"),
+            NewBreakpointType::Watch     => write!(f, "Watch"),
+            NewBreakpointType::Condition => write!(f, "Condition"),
+        }
+    }
+}
+
+
+impl NewBreakpointType {
+    /// Get a hint about the input format of this type.
+    fn input_hint(&self) -> &'static str {
+        match self {
+            NewBreakpointType::Execute   => "[bank:]address, like 0150 or 02:4000",
+            NewBreakpointType::Watch     => "begin[-end] [== value], like c000-c0ff == 3f",
+            NewBreakpointType::Condition => "expression, like A == 3f && [ff44] >= 90",
+        }
+    }
+}
+
+
+impl View for BreakpointsView {
+    fn title(&self, _state: &mut EmulatorState) -> &str {
+        "Breakpoints"
+    }
+
+
+    fn ui(&mut self, state: &mut EmulatorState, ui: &mut Ui) {
+        let item_height = ui.text_style_height(&TextStyle::Monospace);
+
+        ui.vertical(|ui| {
+            egui::TopBottomPanel::top("breakpoints_menu_bar").show_inside(ui, |ui| {
+                ui.horizontal(|ui| {
+                    self.update_toolbar(ui, state);
+                });
+
+                if let Some(error) = &self.error {
+                    ui.label(GemiStyle::CAPTION.rich_text(error));
+                }
+            });
+
+            TableBuilder::new(ui)
+                    .column(Column::auto().resizable(false))    // enabled
+                    .column(Column::auto().resizable(true))     // description
+                    .column(Column::auto().resizable(true))     // hit count
+                    .column(Column::remainder())                // empty remainder
+
+                    .vscroll(true)
+                    .striped(true)
+                    .sense(egui::Sense::click())
+
+                    .header(item_height, |mut header| {
+                        header.col(|_|  { /* enabled */             } );
+                        header.col(|ui| { ui.heading("Breakpoint"); } );
+                        header.col(|ui| { ui.heading("Hits");       } );
+                        header.col(|_|  {                           } );
+                    })
+
+                    .body(|body| {
+                        body.rows(
+                            item_height,
+                            state.emu.get_breakpoints().get_entries().len(),
+                            |row| {
+                                self.display_entry(state, row);
+                            }
+                        )
+                    })
+            ;
+        });
+    }
+}
+
+
+impl BreakpointsView {
+    pub fn new() -> Self {
+        Self {
+            new_type:       NewBreakpointType::Execute,
+            new_access:     WatchAccess::Write,
+            input:          String::new(),
+            error:          None,
+            selected_entry: None,
+        }
+    }
+
+
+    fn update_toolbar(&mut self, ui: &mut Ui, state: &mut EmulatorState) {
+        // type of the new breakpoint
+        {
+            let all_types = [
+                NewBreakpointType::Execute,
+                NewBreakpointType::Watch,
+                NewBreakpointType::Condition,
+            ];
+
+            ComboBox::from_id_salt("breakpoint_type")
+                    .selected_text(self.new_type.to_string())
+                    .show_ui(ui, |ui| {
+                        for t in all_types {
+                            ui.selectable_value(&mut self.new_type, t, t.to_string());
+                        }
+                    })
+            ;
+        }
+
+        // access of new watchpoints
+        if self.new_type == NewBreakpointType::Watch {
+            let all_access = [
+                WatchAccess::Read,
+                WatchAccess::Write,
+                WatchAccess::ReadWrite,
+            ];
+
+            ComboBox::from_id_salt("breakpoint_access")
+                    .selected_text(self.new_access.to_string())
+                    .show_ui(ui, |ui| {
+                        for access in all_access {
+                            ui.selectable_value(&mut self.new_access, access, access.to_string());
+                        }
+                    })
+            ;
+        }
+
+        // the breakpoint description
+        let response = TextEdit::singleline(&mut self.input)
+                .hint_text(self.new_type.input_hint())
+                .desired_width(240.0)
+                .show(ui)
+                .response
+        ;
+
+        let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+        // Button "new"
+        if ui.button(BUTTON_LABEL_ADD).clicked() || submitted {
+            let result = self
+                    .parse_input()
+                    .and_then(|kind| state.emu.add_breakpoint(kind))
+            ;
+
+            match result {
+                Ok(()) => {
+                    self.input.clear();
+                    self.error = None;
+                }
+
+                Err(e) => {
+                    self.error = Some(e);
+                }
+            }
+        }
+
+        // Button "delete"
+        {
+            let has_selected_line = self.selected_entry.is_some();
+            ui.add_enabled_ui(has_selected_line, |ui| {
+                if ui.button(BUTTON_LABEL_DELETE).clicked() {
+                    if let Some(index) = self.selected_entry {
+                        state.emu.remove_breakpoint(index);
+                        self.selected_entry = None;
+                    }
+                }
+            });
+        }
+    }
+
+
+    fn display_entry(&mut self, state: &mut EmulatorState, mut row: TableRow) {
+        let index = row.index();
+        let entry = &state.emu.get_breakpoints().get_entries()[index];
+
+        let mut enabled = entry.enabled;
+        let description = entry.kind.to_string();
+        let hit_count   = entry.hit_count;
+
+        // is selected?
+        row.set_selected(self.selected_entry == Some(index));
+
+        // enabled
+        row.col(|ui| {
+            if ui.checkbox(&mut enabled, "").changed() {
+                state.emu.set_breakpoint_enabled(index, enabled);
+            }
+        });
+
+        // description
+        row.col(|ui| {
+            ui.label(GemiStyle::MONOSPACE.rich_text(description));
+        });
+
+        // hit count
+        row.col(|ui| {
+            ui.label(hit_count.to_string());
+        });
+
+        // Remainder
+        row.col(|_| { });
+
+        // handle interactions
+        if row.response().clicked() {
+            self.selected_entry = Some(index);
+        }
+    }
+
+
+    /// Parses the current input into a new breakpoint of the selected type.
+    fn parse_input(&self) -> Result<BreakpointKind, String> {
+        let input = self.input.trim();
+
+        match self.new_type {
+            NewBreakpointType::Execute => {
+                let (bank, address) = match input.split_once(':') {
+                    Some((bank, address)) => (Some(parse_hex(bank)?), address),
+                    None                  => (None, input),
+                };
+
+                Ok(BreakpointKind::Execute {
+                    address: parse_hex(address)? as u16,
+                    bank,
+                })
+            }
+
+            NewBreakpointType::Watch => {
+                let (range, value) = match input.split_once("==") {
+                    Some((range, value)) => (range.trim(), Some(parse_hex(value)? as u8)),
+                    None                 => (input, None),
+                };
+
+                let (begin, end) = match range.split_once('-') {
+                    Some((begin, end)) => (parse_hex(begin)?, parse_hex(end)?),
+                    None               => (parse_hex(range)?, parse_hex(range)?),
+                };
+
+                if begin > 0xffff || end > 0xffff {
+                    return Err(format!("Invalid memory range {range}"));
+                }
+
+                Ok(BreakpointKind::Watch {
+                    begin:  begin as u16,
+                    end:    end as u16,
+                    access: self.new_access,
+                    value,
+                })
+            }
+
+            NewBreakpointType::Condition => {
+                Ok(BreakpointKind::Condition(input.to_string()))
+            }
+        }
+    }
+}
+
+
+/// Parses a hexadecimal number, optionally prefixed by `0x` or `$`.
+fn parse_hex(s: &str) -> Result<u32, String> {
+    let s      = s.trim();
+    let digits = s
+            .strip_prefix("0x")
+            .or_else(|| s.strip_prefix('$'))
+            .unwrap_or(s)
+    ;
+
+    u32::from_str_radix(digits, 16).map_err(|_| format!("Invalid value '{s}'"))
+}
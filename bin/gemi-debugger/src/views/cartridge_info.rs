@@ -68,6 +68,7 @@ impl View for CartridgeInfoView {
         let rom_size_str = format!("{} kiB", cart.get_rom_size() / 1024);
         let ram_size_str = format!("{} kiB", cart.get_ram_size() / 1024);
         let requires_cgb = matches!(cart.get_cgb_support(), GameBoyColorSupport::Required);
+        let header       = cart.get_header_validation();
 
         self.data_list.clear();
         self.data_list.add_text("Title",                    cart.get_title());
@@ -82,6 +83,9 @@ impl View for CartridgeInfoView {
         self.data_list.add_bool("Super GameBoy Support",    cart.supports_sgb());
         self.data_list.add_bool("GameBoy Color Support",    cart.supports_cgb());
         self.data_list.add_bool("GameBoy Color Required",   requires_cgb);
+        self.data_list.add_bool("Valid Logo",               header.logo_valid);
+        self.data_list.add_bool("Valid Header Checksum",    header.header_checksum_valid);
+        self.data_list.add_bool("Valid Global Checksum",    header.global_checksum_valid);
     }
 }
 
@@ -135,12 +135,18 @@ impl CpuView {
 
                 // stack-pointer register
                 ui.label(address_style.rich_text("SP"));
-                self.display_register_sp(ui, state);
+                ui.horizontal(|ui| {
+                    self.display_register_sp(ui, state);
+                    self.display_address_label(ui, state, |emu| emu.cpu.get_stack_pointer());
+                });
                 ui.end_row();
 
                 // instruction-pointer register
                 ui.label(address_style.rich_text("PC"));
-                self.display_register_pc(ui, state);
+                ui.horizontal(|ui| {
+                    self.display_register_pc(ui, state);
+                    self.display_address_label(ui, state, |emu| emu.cpu.get_instruction_pointer());
+                });
                 ui.end_row();
             })
         ;
@@ -230,6 +236,19 @@ impl CpuView {
     }
 
 
+    /// Displays the label of the address a register is pointing to, if any.
+    fn display_address_label(&self, ui: &mut Ui, state: &EmulatorState, get_address: impl FnOnce(&GameBoy) -> u16) {
+        let label = state.emu.get_emulator()
+            .map(get_address)
+            .and_then(|address| state.emu.format_address(address))
+        ;
+
+        if let Some(label) = label {
+            ui.label(GemiStyle::LABEL.rich_text(label));
+        }
+    }
+
+
     /// Utility function to display any value or allows to edit on click.
     /// The value is read using the `on_read_value` callback and the
     /// written by using the `on_write_value` callback.
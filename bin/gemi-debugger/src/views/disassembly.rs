@@ -17,11 +17,13 @@
 
 use std::ops::Range;
 
-use egui::{vec2, Grid, RichText, ScrollArea, Sense, TextStyle, Ui};
+use egui::{vec2, Align2, Grid, RichText, ScrollArea, Sense, TextStyle, Ui};
 
 use gemi_core::cpu::opcode::{Instruction, Token};
+use gemi_core::debug::symbols::{Symbol, SymbolTable};
 use gemi_core::gameboy::GameBoy;
 
+use crate::breakpoints::{BreakpointKind, BreakpointList};
 use crate::event::UiEvent;
 use crate::highlight::test_selection;
 use crate::selection::{Kind, Selected};
@@ -31,6 +33,10 @@ use crate::views::View;
 
 const ADDITIONAL_LINES_BEYOND_VIEW : usize = 10;
 
+/// The width of the column left to each instruction, which displays the
+/// current instruction and breakpoints.
+const GUTTER_WIDTH : f32 = 12.0;
+
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct DisassemblyView {
@@ -58,6 +64,28 @@ struct RuntimeData {
     /// When set, cause the [ScrollArea] to bring the requested line into
     /// the center of its viewport.
     scroll_to_line: Option<usize>,
+
+    /// The revision of the symbols used to create the disassembly cache.
+    symbols_revision: u32,
+
+    /// The label currently being edited by the user, if any.
+    label_edit: Option<LabelEdit>,
+}
+
+
+/// A label being added or renamed by the user.
+struct LabelEdit {
+    /// The address of the label.
+    address: u16,
+
+    /// The name of the label being edited.
+    name: String,
+
+    /// Whether there is already a label on this address.
+    exists: bool,
+
+    /// The error of the last attempt to apply the label, if any.
+    error: Option<String>,
 }
 
 
@@ -65,8 +93,8 @@ struct RuntimeData {
 /// with the least effort as possible.
 #[derive(Default)]
 struct DisassemblyCache {
-    /// The list of instructions disassembled.
-    instruction_entries: Vec<InstructionDisplayEntry>,
+    /// The list of lines to be displayed, containing the instructions disassembled.
+    lines: Vec<DisassemblyLine>,
 
     /// The memory range of the instructions disassembled.
     /// Usually from the address of the first instruction to the address of the
@@ -77,12 +105,25 @@ struct DisassemblyCache {
 }
 
 
+/// A single line within the disassembly.
+enum DisassemblyLine {
+    /// A header displaying the name of a label located on the following instruction.
+    Label(String),
+
+    /// A single instruction.
+    Instruction(InstructionDisplayEntry),
+}
+
+
 /// The data of a single instruction, already prepared to be rendered with
 /// the least effort as possible.
 struct InstructionDisplayEntry {
     /// The instruction to be displayed.
     instruction: Instruction,
 
+    /// The name of the label located on the instruction's address, if any.
+    label: Option<String>,
+
     /// The actual bytes forming the instruction.
     instruction_bytes: Vec<u8>,
 
@@ -100,9 +141,37 @@ impl View for DisassemblyView {
 
 
     fn ui(&mut self, state: &mut EmulatorState, ui: &mut Ui) {
+        let mut toggle_breakpoint = None;
+
         if let Some(emu) = state.emu.get_emulator() {
-            self.update_disassembly(ui, &mut state.ui, emu);
-            self.render_disassembly_list(ui, &mut state.ui, emu);
+            let symbols = state.emu.get_symbols();
+
+            // after the symbols were changed, all labels need to be updated
+            if self.rt.symbols_revision != state.emu.get_symbols_revision() {
+                self.rt.symbols_revision = state.emu.get_symbols_revision();
+                self.rt.disassembly_cache.reset();
+            }
+
+            self.update_disassembly(ui, &mut state.ui, emu, symbols);
+
+            toggle_breakpoint = self.render_disassembly_list(
+                ui,
+                &mut state.ui,
+                emu,
+                symbols,
+                state.emu.get_breakpoints()
+            );
+        }
+
+        self.display_label_editor(ui, state);
+
+        // clicking on the gutter of an instruction toggles a breakpoint
+        if let Some(address) = toggle_breakpoint {
+            let result = state.emu.toggle_breakpoint(BreakpointKind::Execute { address, bank: None });
+
+            if let Err(e) = result {
+                eprintln!("failed to toggle breakpoint: {e}");
+            }
         }
     }
 
@@ -136,7 +205,7 @@ impl DisassemblyView {
 
 
     /// Updates the currently cached disassembly as needed.
-    fn update_disassembly(&mut self, ui: &mut Ui, ui_states: &mut UiStates, emu: &GameBoy) {
+    fn update_disassembly(&mut self, ui: &mut Ui, ui_states: &mut UiStates, emu: &GameBoy, symbols: &SymbolTable) {
         let current_pc = emu.cpu.get_instruction_pointer();
 
         // when the instruction pointer did change, we want to focus the new active line.
@@ -161,6 +230,7 @@ impl DisassemblyView {
 
             self.rt.disassembly_cache = DisassemblyCache::disassemble_entries_from_pc(
                 emu,
+                symbols,
                 visible_lines + ADDITIONAL_LINES_BEYOND_VIEW
             );
 
@@ -181,7 +251,18 @@ impl DisassemblyView {
 
 
     /// Renders the actual UI using the currently stored disassembly cache.
-    fn render_disassembly_list(&mut self, ui: &mut Ui, ui_states: &mut UiStates, emu: &GameBoy) {
+    /// Returns the address of an instruction, if the user clicked on its gutter
+    /// to toggle a breakpoint.
+    fn render_disassembly_list(
+            &mut self,
+            ui: &mut Ui,
+            ui_states: &mut UiStates,
+            emu: &GameBoy,
+            symbols: &SymbolTable,
+            breakpoints: &BreakpointList
+    ) -> Option<u16> {
+        let mut toggle_breakpoint = None;
+        let mut label_edit        = None;
         let (line_content_height, line_height_padded) = Self::compute_line_height(ui);
         let available_rows = self.rt.disassembly_cache.get_lines_count();
 
@@ -222,7 +303,7 @@ impl DisassemblyView {
                         self.rt.disassembly_cache.get_lines_count() < preferred_number_of_lines
                     &&  !self.rt.disassembly_cache.is_at_end()
                 {
-                    let lines_added = self.rt.disassembly_cache.fill_up(emu, preferred_number_of_lines);
+                    let lines_added = self.rt.disassembly_cache.fill_up(emu, symbols, preferred_number_of_lines);
 
                     // request a repaint after changing the number of lines available
                     if lines_added > 0 {
@@ -252,11 +333,18 @@ impl DisassemblyView {
                         .show(ui, |ui| -> Option<()> {
                             // verify all lines which need to be drawn. 
                             // Stops if at least one line fails to be verified
-                            self.rt.disassembly_cache.verify_lines(display_rows.clone(), emu)?;
+                            self.rt.disassembly_cache.verify_lines(display_rows.clone(), emu, symbols)?;
                             
                             for row in display_rows {
                                 // get an entry, if still valid, otherwise will leave the rendering
-                                let entry = self.rt.disassembly_cache.get_instruction(row)?;
+                                let entry = match self.rt.disassembly_cache.get_line(row)? {
+                                    DisassemblyLine::Label(name) => {
+                                        Self::render_label_row(ui, name);
+                                        continue;
+                                    }
+
+                                    DisassemblyLine::Instruction(entry) => entry,
+                                };
 
                                 // bounding box of the whole line
                                 let line_bounds = egui::Rect::from_min_size(
@@ -281,11 +369,20 @@ impl DisassemblyView {
                                 }
 
                                 // render the actual element
-                                entry.render_as_row(ui, emu);
+                                let has_breakpoint = breakpoints.has_execute_breakpoint(entry.instruction.opcode_address);
+                                entry.render_as_row(ui, emu, has_breakpoint);
 
                                 // mouse interaction with the current row
                                 let line_response = ui.interact(line_bounds, ui.id().with(row), Sense::click());
 
+                                // mouse interaction with the gutter, which toggles breakpoints
+                                let gutter_bounds   = line_bounds.with_max_x(line_bounds.min.x + GUTTER_WIDTH);
+                                let gutter_response = ui.interact(gutter_bounds, ui.id().with(("gutter", row)), Sense::click());
+
+                                if gutter_response.clicked() {
+                                    toggle_breakpoint = Some(entry.instruction.opcode_address);
+                                }
+
                                 // handle hover state
                                 ui_states.hover.set(selection_key.clone(), line_response.hovered());
 
@@ -293,6 +390,16 @@ impl DisassemblyView {
                                 if line_response.clicked() {
                                     ui_states.focus.toggle(selection_key.clone());
                                 }
+
+                                // the context menu allows to add or rename the label of an instruction
+                                line_response.context_menu(|ui| {
+                                    let caption = if entry.label.is_some() { "Rename label" } else { "Add label" };
+
+                                    if ui.button(caption).clicked() {
+                                        label_edit = Some(LabelEdit::new(entry.instruction.opcode_address, entry.label.clone()));
+                                        ui.close_menu();
+                                    }
+                                });
                             }
 
                             Some(())
@@ -306,6 +413,77 @@ impl DisassemblyView {
                 }
             }
         );
+
+        if label_edit.is_some() {
+            self.rt.label_edit = label_edit;
+        }
+
+        toggle_breakpoint
+    }
+
+
+    /// Renders the header of a label in front of the instruction located on its address.
+    fn render_label_row(ui: &mut Ui, name: &str) {
+        let line_height = ui.text_style_height(&TextStyle::Monospace);
+        let position    = ui.cursor().left_top() + vec2(GUTTER_WIDTH + ui.spacing().item_spacing.x, 0.0);
+
+        // paint the label across all columns without affecting their width
+        ui.painter().text(
+            position,
+            Align2::LEFT_TOP,
+            format!("{name}:"),
+            TextStyle::Monospace.resolve(ui.style()),
+            GemiStyle::LABEL.color.unwrap_or(ui.visuals().text_color()),
+        );
+
+        ui.allocate_space(vec2(GUTTER_WIDTH, line_height));
+        ui.end_row();
+    }
+
+
+    /// Displays a window to edit a label, if the user requested to add or rename one.
+    fn display_label_editor(&mut self, ui: &mut Ui, state: &mut EmulatorState) {
+        let Some(label_edit) = &mut self.rt.label_edit else {
+            return;
+        };
+
+        let mut apply  = false;
+        let mut remove = false;
+        let mut close  = false;
+
+        egui::Window::new(format!("Label at {:04x}", label_edit.address))
+            .collapsible(false)
+            .resizable(false)
+            .show(ui.ctx(), |ui| {
+                let response = GemiStyle::LABEL.text_edit_singleline(&mut label_edit.name).show(ui).response;
+                response.request_focus();
+
+                if let Some(error) = &label_edit.error {
+                    ui.label(error);
+                }
+
+                ui.horizontal(|ui| {
+                    apply  = ui.button("Ok").clicked() || ui.input(|input| input.key_pressed(egui::Key::Enter));
+                    close  = ui.button("Cancel").clicked() || ui.input(|input| input.key_pressed(egui::Key::Escape));
+                    remove = label_edit.exists && ui.button("Remove").clicked();
+                });
+            })
+        ;
+
+        if remove {
+            state.emu.remove_label(label_edit.address);
+            close = true;
+        }
+        else if apply {
+            match state.emu.set_label(label_edit.address, &label_edit.name) {
+                Ok(())     => close = true,
+                Err(error) => label_edit.error = Some(error),
+            }
+        }
+
+        if close {
+            self.rt.label_edit = None;
+        }
     }
 
 
@@ -340,7 +518,7 @@ impl DisassemblyView {
 impl DisassemblyCache {
     /// Creates a new disassembly, starting at the current address of the instruction
     /// pointer, creating a specific number of lines.
-    fn disassemble_entries_from_pc(emu: &GameBoy, max_entries: usize) -> Self {
+    fn disassemble_entries_from_pc(emu: &GameBoy, symbols: &SymbolTable, max_entries: usize) -> Self {
         let current_pc = emu.cpu.get_instruction_pointer();
 
         // creates an empty disassembly on the address of the current instruction pointer
@@ -350,7 +528,7 @@ impl DisassemblyCache {
         };
 
         // fill the disassembly with 'n' entries
-        disassembly.fill_up(emu, max_entries);
+        disassembly.fill_up(emu, symbols, max_entries);
 
         disassembly
     }
@@ -358,24 +536,30 @@ impl DisassemblyCache {
 
     /// Continues to disassemble from the last address until [max_entries] lines
     /// are stored in the disassembly cache.
-    fn fill_up(&mut self, emu: &GameBoy, max_entries: usize) -> usize {
+    fn fill_up(&mut self, emu: &GameBoy, symbols: &SymbolTable, max_entries: usize) -> usize {
         let mut pc          = self.address_range.end;
         let mut added_lines = 0;
 
         // accessor to read from emulator memory
-        let read_emu = |address| emu.get_mmu().read_u8(address);
+        let read_emu = |address| emu.get_mmu().debug_read_u8(address);
 
         // keep adding entries until reaching the maximum number
         // or the instruction pointer reaches the end of address range
         while
-                self.instruction_entries.len() < max_entries
+                self.lines.len() < max_entries
             &&  pc < 0xffff
         {
             let instruction = Instruction::read_instruction(pc, read_emu);
-            let entry       = InstructionDisplayEntry::prepare_instruction_display(instruction, emu);
+            let entry       = InstructionDisplayEntry::prepare_instruction_display(instruction, emu, symbols);
+
+            // add a header line in front of instructions with a label
+            if let Some(label) = &entry.label {
+                self.lines.push(DisassemblyLine::Label(label.clone()));
+                added_lines += 1;
+            }
 
             let instruction_length = entry.instruction.get_instruction_length();
-            self.instruction_entries.push(entry);
+            self.lines.push(DisassemblyLine::Instruction(entry));
 
             // compute the address of the next instruction
             pc = pc.saturating_add(instruction_length);
@@ -398,13 +582,13 @@ impl DisassemblyCache {
 
     /// Checks whether this cache is currently empty or not.
     fn is_empty(&self) -> bool {
-        self.instruction_entries.is_empty()
+        self.lines.is_empty()
     }
 
 
     /// Get the number of lines in this cache.
     fn get_lines_count(&self) -> usize {
-        self.instruction_entries.len()
+        self.lines.len()
     }
 
 
@@ -424,7 +608,11 @@ impl DisassemblyCache {
         }
 
         for line in 0..self.get_lines_count() {
-            let instruction = &self.instruction_entries[line].instruction;
+            let Some(entry) = self.get_instruction(line) else {
+                continue;
+            };
+
+            let instruction = &entry.instruction;
 
             // return the line if it matches the requested address
             if instruction.opcode_address == address {
@@ -445,10 +633,12 @@ impl DisassemblyCache {
     /// it tries to re-read them.
     /// Returns `Some` if all lines are valid, `None` if at least one
     /// line was invalid and couldn't be re-read.
-    fn verify_lines(&mut self, lines: Range<usize>, emu: &GameBoy) -> Option<()> {
-        // iterate over all lines, stop on the first failure
+    fn verify_lines(&mut self, lines: Range<usize>, emu: &GameBoy, symbols: &SymbolTable) -> Option<()> {
+        // iterate over all instruction lines, stop on the first failure
         for line in lines {
-            self.verify_and_get_instruction(line, emu)?;
+            if self.get_instruction(line).is_some() {
+                self.verify_and_get_instruction(line, emu, symbols)?;
+            }
         }
 
         Some(())
@@ -459,34 +649,34 @@ impl DisassemblyCache {
     /// the instruction.
     /// Returns the reference to the requested instruction if possible, or `None`,
     /// if there's no valid instruction available.
-    fn verify_and_get_instruction(&mut self, line: usize, emu: &GameBoy) -> Option<&InstructionDisplayEntry> {
+    fn verify_and_get_instruction(&mut self, line: usize, emu: &GameBoy, symbols: &SymbolTable) -> Option<&InstructionDisplayEntry> {
+        let entry = self.get_instruction(line)?;
+
         // check whether the current line is still valid
-        {
-            if self.instruction_entries[line].verify(emu) {
-                return Some(&self.instruction_entries[line]);
-            }
+        if entry.verify(emu) {
+            return self.get_instruction(line);
         }
 
         // if not, try to fetch a new instruction from the same address
         {
-            let original_instruction_address = self.instruction_entries[line].instruction.opcode_address;
-            let original_instruction_length = self.instruction_entries[line].get_length();
+            let original_instruction_address = entry.instruction.opcode_address;
+            let original_instruction_length = entry.get_length();
 
             // read the instruction again from memory
             let new_instruction = Instruction::read_instruction(
                 original_instruction_address,
-                |address| emu.get_mmu().read_u8(address)
+                |address| emu.get_mmu().debug_read_u8(address)
             );
 
             // only if the length is matching, we can replace the old one with the new one
             // otherwise this means all the following instructions are invalid as well
             let new_instruction_length = new_instruction.get_instruction_length() as usize;
             if new_instruction_length == original_instruction_length {
-                let new_entry = InstructionDisplayEntry::prepare_instruction_display(new_instruction, emu);
-                self.instruction_entries[line] = new_entry;
+                let new_entry = InstructionDisplayEntry::prepare_instruction_display(new_instruction, emu, symbols);
+                self.lines[line] = DisassemblyLine::Instruction(new_entry);
 
                 // return a reference to the new instruction
-                return Some(&self.instruction_entries[line]);
+                return self.get_instruction(line);
             }
         }
 
@@ -497,8 +687,18 @@ impl DisassemblyCache {
 
     /// Get a specific line.
     /// This does not attempt to verify them, which has to be done in a separate step.
+    fn get_line(&self, line: usize) -> Option<&DisassemblyLine> {
+        self.lines.get(line)
+    }
+
+
+    /// Get the instruction of a specific line, if the line contains an instruction.
+    /// This does not attempt to verify them, which has to be done in a separate step.
     fn get_instruction(&self, line: usize) -> Option<&InstructionDisplayEntry> {
-        self.instruction_entries.get(line)
+        match self.lines.get(line) {
+            Some(DisassemblyLine::Instruction(entry)) => Some(entry),
+            _                                         => None,
+        }
     }
 }
 
@@ -507,7 +707,16 @@ impl InstructionDisplayEntry {
     /// Creates a new [InstructionDisplayEntry] for a given [Instruction].
     /// This will fetch any data required to display the instruction
     /// and stores it in a format ready to be rendered.
-    fn prepare_instruction_display(instruction: Instruction, emu: &GameBoy) -> Self {
+    fn prepare_instruction_display(instruction: Instruction, emu: &GameBoy, symbols: &SymbolTable) -> Self {
+        // the label located on this instruction
+        let label = find_label(symbols, emu, instruction.opcode_address).map(|symbol| symbol.name.clone());
+
+        // the label of the target of jumps and calls, which replaces the target address
+        let branch_label = instruction.get_branch_target()
+                .and_then(|address| find_label(symbols, emu, address))
+                .map(|symbol| symbol.name.clone())
+        ;
+
         // address
         let label_address = {
             let address_str = format!("{:04x}", instruction.opcode_address);
@@ -522,7 +731,7 @@ impl InstructionDisplayEntry {
             (0..num_instruction_bytes)
                     .into_iter()
                     .map(|offset| instruction.opcode_address.wrapping_add(offset))
-                    .map(|address| emu.get_mmu().read_u8(address))
+                    .map(|address| emu.get_mmu().debug_read_u8(address))
                     .collect::<Vec<_>>()
         };
 
@@ -545,20 +754,32 @@ impl InstructionDisplayEntry {
 
         // format the opcode label
         let label_opcode_desc = {
-            instruction.opcode.tokenize()
-                    .into_iter()
-                    .map(|token| {
-                        match token {
-                            Token::Command(cmd) => {
-                                GemiStyle::KEYWORD.rich_text(cmd)
+            let tokens = instruction.opcode.tokenize();
+
+            tokens.iter()
+                    .enumerate()
+                    .map(|(index, token)| {
+                        match (token, &branch_label) {
+                            (Token::Command(cmd), _) => {
+                                GemiStyle::KEYWORD.rich_text(*cmd)
                             }
 
-                            Token::Text(t) => {
+                            // strip the number prefix in front of an address being replaced by a label
+                            (Token::Text(t), Some(_)) if matches!(tokens.get(index + 1), Some(Token::Argument(_))) => {
+                                let t = t.strip_suffix("0x").or_else(|| t.strip_suffix('$')).unwrap_or(t);
                                 GemiStyle::KEYWORD_LOW.rich_text(t)
                             }
 
-                            Token::Argument(arg) => {
-                                let str = instruction.resolve_argument(&arg);
+                            (Token::Text(t), _) => {
+                                GemiStyle::KEYWORD_LOW.rich_text(*t)
+                            }
+
+                            (Token::Argument(_), Some(label)) => {
+                                GemiStyle::LABEL.rich_text(label)
+                            }
+
+                            (Token::Argument(arg), None) => {
+                                let str = instruction.resolve_argument(arg);
                                 GemiStyle::KEYWORD_LOW.rich_text(str)
                             }
                         }
@@ -568,6 +789,7 @@ impl InstructionDisplayEntry {
 
         Self {
             instruction,
+            label,
             instruction_bytes,
             label_address,
             label_opcode_bytes,
@@ -595,7 +817,7 @@ impl InstructionDisplayEntry {
     fn verify(&self, emu: &GameBoy) -> bool {
         for offset in 0..self.get_length() {
             let address           = self.instruction.opcode_address + (offset as u16);
-            let value_at_address  = emu.get_mmu().read_u8(address);
+            let value_at_address  = emu.get_mmu().debug_read_u8(address);
             let instruction_value = self.instruction_bytes[offset];
 
             if value_at_address != instruction_value {
@@ -608,7 +830,15 @@ impl InstructionDisplayEntry {
 
 
     /// Renders a single instruction into a row
-    fn render_as_row(&self, ui: &mut Ui, emu: &GameBoy) {
+    fn render_as_row(&self, ui: &mut Ui, emu: &GameBoy, has_breakpoint: bool) {
+        // breakpoint marker
+        if has_breakpoint {
+            let line_height = ui.text_style_height(&TextStyle::Monospace);
+            let center      = ui.cursor().left_top() + vec2(GUTTER_WIDTH / 2.0, line_height / 2.0);
+
+            ui.painter().circle_filled(center, GUTTER_WIDTH / 3.0, GemiStyle::BREAKPOINT_MARKER);
+        }
+
         // is current
         {
             let current_pc = emu.cpu.get_instruction_pointer();
@@ -618,7 +848,7 @@ impl InstructionDisplayEntry {
                 ui.label("\u{23f5}");
             }
             else {
-                ui.allocate_space(vec2(12.0, 0.0));
+                ui.allocate_space(vec2(GUTTER_WIDTH, 0.0));
             }
         }
 
@@ -647,3 +877,22 @@ impl InstructionDisplayEntry {
         ui.end_row();
     }
 }
+
+
+impl LabelEdit {
+    /// Starts editing the label on an address, with the name of the existing label, if any.
+    fn new(address: u16, name: Option<String>) -> Self {
+        Self {
+            address,
+            exists: name.is_some(),
+            name:   name.unwrap_or_else(|| format!("label_{address:04x}")),
+            error:  None,
+        }
+    }
+}
+
+
+/// Finds the label located exactly on an address, based on the current memory mapping.
+fn find_label<'a>(symbols: &'a SymbolTable, emu: &GameBoy, address: u16) -> Option<&'a Symbol> {
+    symbols.find_at(address, emu.get_mapped_rom_bank(address))
+}
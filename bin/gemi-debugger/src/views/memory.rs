@@ -15,6 +15,7 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
+use std::collections::BTreeMap;
 use std::ops::Range;
 
 use egui::Ui;
@@ -23,6 +24,7 @@ use gemi_core::gameboy::GameBoy;
 use gemi_core::mmu::locations::{MEMORY_LOCATION_OAM_BEGIN, MEMORY_LOCATION_SPRITES_BEGIN};
 use gemi_core::ppu::graphic_data::TileMap;
 
+use crate::breakpoints::{BreakpointKind, WatchAccess};
 use crate::event::UiEvent;
 use crate::event::UiEvent::SelectionChanged;
 use crate::selection::{Kind, Selected};
@@ -35,6 +37,11 @@ use crate::views::View;
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct MemoryView {
     memory_editor: MemoryEditor<GameBoy>,
+
+    /// The revision of the symbols and the mapped ROM bank the labels
+    /// of the memory editor were created for.
+    #[serde(skip)]
+    labels_key: Option<(u32, Option<u32>)>,
 }
 
 
@@ -49,6 +56,7 @@ impl MemoryView {
 
         Self {
             memory_editor,
+            labels_key: None,
         }
     }
 }
@@ -172,10 +180,44 @@ impl MemoryView {
     }
 
 
+    /// Updates the labels displayed in the memory editor,
+    /// when the symbols or the mapped ROM bank did change.
+    fn refresh_labels(&mut self, state: &EmulatorState) {
+        let labels_key = Some((state.emu.get_symbols_revision(), state.emu.get_symbol_bank(0x4000)));
+
+        if self.labels_key != labels_key {
+            let labels: BTreeMap<usize, String> = state.emu.get_symbols()
+                .get_symbols()
+                .iter()
+                .filter(|symbol| state.emu.get_symbol_bank(symbol.address).is_none_or(|bank| bank == symbol.bank))
+                .map(|symbol| (symbol.address as usize, symbol.name.clone()))
+                .collect()
+            ;
+
+            self.memory_editor.set_labels(labels);
+            self.labels_key = labels_key;
+        }
+    }
+
+
     /// Display the memory editor.
     fn display_memory_editor(&mut self, state: &mut EmulatorState, ui: &mut Ui) {
         let is_paused = state.ui.is_paused();
 
+        // mark all addresses observed by watchpoints
+        self.memory_editor.set_marked_ranges(
+            state.emu.get_breakpoints().get_entries()
+                .iter()
+                .filter(|entry| entry.enabled)
+                .filter_map(|entry| match entry.kind {
+                    BreakpointKind::Watch { begin, end, .. } => Some(begin as usize ..= end as usize),
+                    _                                        => None,
+                })
+                .collect()
+        );
+
+        self.refresh_labels(state);
+
         if let Some(emu) = state.emu.get_emulator_mut() {
             // allow editing while paused
             self.memory_editor.set_editable(is_paused);
@@ -189,7 +231,7 @@ impl MemoryView {
                     match address {
                         // we can only read an address, if the address is in a valid 16 bit range
                         0x0000 ..= 0xffff => {
-                            let value = emu.get_mmu().read_u8(address as u16);
+                            let value = emu.get_mmu().debug_read_u8(address as u16);
                             Some(value)
                         }
 
@@ -215,5 +257,19 @@ impl MemoryView {
                 }
             );
         }
+
+        // clicking a cell with the secondary mouse button toggles a watchpoint
+        if let Some(address) = self.memory_editor.take_secondary_clicked_address() {
+            let result = state.emu.toggle_breakpoint(BreakpointKind::Watch {
+                begin:  address as u16,
+                end:    address as u16,
+                access: WatchAccess::Write,
+                value:  None,
+            });
+
+            if let Err(e) = result {
+                eprintln!("failed to toggle watchpoint: {e}");
+            }
+        }
     }
 }
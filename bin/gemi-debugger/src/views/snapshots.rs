@@ -244,12 +244,6 @@ impl SnapshotEntry {
         // create the snapshot itself
         let snapshot = Snapshot::create_from(emu)?;
 
-        // get ROM title
-        let rom_title = match emu.get_peripherals().mem.get_cartridge() {
-            Some(cartridge) => cartridge.get_title().to_string(),
-            None => String::from("---"),
-        };
-
         // create image data of the screen to generate a thumbnail
         let lcd  = emu.get_peripherals().ppu.get_lcd();
         let image = ColorImage::from_rgba_unmultiplied(
@@ -257,13 +251,22 @@ impl SnapshotEntry {
             lcd.get_pixels().as_slice()
         );
 
+        // take ROM information from the snapshot's header
+        let header = snapshot.get_header();
+        let rom_title = if header.rom_title.is_empty() {
+            String::from("---")
+        }
+        else {
+            header.rom_title.clone()
+        };
+
         Ok(SnapshotEntry {
             rom_title,
-            snapshot,
             created_at:         Utc::now(),
-            created_on_device:  emu.get_config().device,
+            created_on_device:  header.device,
             runtime_seconds:    emu.get_total_seconds_processed(),
-            runtime_cycles:     emu.get_total_cycles_processed(),
+            runtime_cycles:     header.created_at_cycles,
+            snapshot,
             thumbnail_data:     image,
             thumbnail_texture:  None,
         })
@@ -0,0 +1,47 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::gameboy::GameBoy;
+use gemi_utils::test_results::{check_blargg_test_passed, check_mooneye_test_passed, MOONEYE_RESULT_SEQ_PASS};
+
+use crate::options::ResultCheck;
+
+
+impl ResultCheck {
+    /// Checks whether the ROM has reported its result, so the emulator can be stopped.
+    pub fn has_result(&self, gb: &GameBoy) -> bool {
+        let serial = gb.get_peripherals().serial.get_output();
+
+        match self {
+            ResultCheck::Mooneye => serial.len() >= MOONEYE_RESULT_SEQ_PASS.len(),
+            ResultCheck::Blargg  => {
+                let text = gb.get_peripherals().serial.get_output_as_text();
+                text.contains("Passed") || text.contains("Failed")
+            }
+        }
+    }
+
+
+    /// Checks whether the ROM reported a successful test result,
+    /// using the same checks as the test runner.
+    pub fn check(&self, gb: &GameBoy) -> Result<(), String> {
+        match self {
+            ResultCheck::Mooneye => check_mooneye_test_passed(gb),
+            ResultCheck::Blargg  => check_blargg_test_passed(gb),
+        }
+    }
+}
@@ -0,0 +1,209 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+use std::path::Path;
+
+use gemi_core::input::{Input, InputButton};
+
+
+/// A single entry of the input script, which sets the button states
+/// starting at a specific frame.
+struct InputScriptEntry {
+    /// The frame from which the button states will be applied.
+    frame: u32,
+
+    /// The buttons pressed, all other buttons are released.
+    buttons: Vec<InputButton>,
+}
+
+
+/// A script of button states to be applied at specific frames.
+///
+/// Each line of the script contains a frame number followed by a list
+/// of buttons being pressed from this frame on, until the next entry.
+/// A `-` instead of the button list releases all buttons.
+/// Empty lines and anything after a `#` will be ignored.
+///
+/// ```text
+/// # press start on the title screen
+/// 120 start
+/// 125 -
+/// # walk to the right while holding B
+/// 200 right b
+/// 260 -
+/// ```
+///
+/// Valid button names are `right`, `left`, `up`, `down`, `a`, `b`, `select` and `start`.
+pub struct InputScript {
+    /// All entries of the script, ordered by their frame.
+    entries: Vec<InputScriptEntry>,
+
+    /// The index of the next entry to be applied.
+    next_entry: usize,
+}
+
+
+impl InputScript {
+    /// Reads an input script from a file.
+    pub fn read_from_file(path: &Path) -> Result<InputScript, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read input script {}: {e}", path.display()))
+            ?;
+
+        Self::parse(&text)
+    }
+
+
+    /// Parses an input script from its text representation.
+    pub fn parse(text: &str) -> Result<InputScript, String> {
+        let mut entries = Vec::new();
+
+        for (line_index, line) in text.lines().enumerate() {
+            let line_number = line_index + 1;
+
+            // strip comments
+            let line = match line.split_once('#') {
+                Some((content, _)) => content,
+                None               => line,
+            };
+
+            let mut tokens = line.split_whitespace();
+
+            let Some(frame) = tokens.next() else {
+                continue;
+            };
+
+            let frame = frame.parse::<u32>()
+                .map_err(|_| format!("Line {line_number}: Invalid frame '{frame}'"))
+                ?;
+
+            let mut buttons = Vec::new();
+            for token in tokens.flat_map(|token| token.split(',')).filter(|token| !token.is_empty()) {
+                if token == "-" {
+                    continue;
+                }
+
+                let button = parse_button(token)
+                    .ok_or_else(|| format!("Line {line_number}: Unknown button '{token}'"))
+                    ?;
+
+                buttons.push(button);
+            }
+
+            entries.push(InputScriptEntry { frame, buttons });
+        }
+
+        // keep the order of entries on the same frame, so the last one wins
+        entries.sort_by_key(|entry| entry.frame);
+
+        Ok(InputScript {
+            entries,
+            next_entry: 0,
+        })
+    }
+
+
+    /// Applies all entries scheduled up to the given frame.
+    pub fn apply(&mut self, frame: u32, input: &mut Input) {
+        while let Some(entry) = self.entries.get(self.next_entry) {
+            if entry.frame > frame {
+                break;
+            }
+
+            for button in InputButton::ALL {
+                input.set_button_pressed(button, entry.buttons.contains(&button));
+            }
+
+            self.next_entry += 1;
+        }
+    }
+}
+
+
+/// Get the button matching a name used in input scripts.
+fn parse_button(name: &str) -> Option<InputButton> {
+    match name.to_lowercase().as_str() {
+        "right"  => Some(InputButton::DPadRight),
+        "left"   => Some(InputButton::DPadLeft),
+        "up"     => Some(InputButton::DPadUp),
+        "down"   => Some(InputButton::DPadDown),
+        "a"      => Some(InputButton::A),
+        "b"      => Some(InputButton::B),
+        "select" => Some(InputButton::Select),
+        "start"  => Some(InputButton::Start),
+        _        => None,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_pressed_buttons(input: &Input) -> Vec<InputButton> {
+        InputButton::ALL.into_iter()
+            .filter(|button| input.is_button_pressed(*button))
+            .collect()
+    }
+
+
+    #[test]
+    fn parse_script() {
+        let script = InputScript::parse("
+            # comment line
+            120 start       # trailing comment
+            125 -
+            200 Right,b
+            260
+        ").unwrap();
+
+        assert_eq!(4, script.entries.len());
+        assert_eq!(120, script.entries[0].frame);
+        assert!(vec![InputButton::Start] == script.entries[0].buttons);
+        assert!(script.entries[1].buttons.is_empty());
+        assert!(vec![InputButton::DPadRight, InputButton::B] == script.entries[2].buttons);
+        assert!(script.entries[3].buttons.is_empty());
+    }
+
+
+    #[test]
+    fn parse_invalid_script() {
+        assert_eq!(Err("Line 2: Invalid frame 'x'".to_string()), InputScript::parse("1 a\nx b").map(|_| ()));
+        assert_eq!(Err("Line 1: Unknown button 'c'".to_string()), InputScript::parse("1 c").map(|_| ()));
+    }
+
+
+    #[test]
+    fn apply_script() {
+        let mut script = InputScript::parse("10 a\n5 b up\n10 start\n20 -").unwrap();
+        let mut input  = Input::new();
+
+        script.apply(4, &mut input);
+        assert!(get_pressed_buttons(&input).is_empty());
+
+        script.apply(5, &mut input);
+        assert!(vec![InputButton::DPadUp, InputButton::B] == get_pressed_buttons(&input));
+
+        // of multiple entries on the same frame, the last one wins
+        script.apply(12, &mut input);
+        assert!(vec![InputButton::Start] == get_pressed_buttons(&input));
+
+        script.apply(20, &mut input);
+        assert!(get_pressed_buttons(&input).is_empty());
+    }
+}
@@ -0,0 +1,381 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+use std::io::{BufReader, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::{env, io};
+
+use gemi_core::boot_rom::BootRom;
+use gemi_core::cartridge::Cartridge;
+use gemi_core::debug::DebugEvent;
+use gemi_core::gameboy::{EmulatorUpdateResults, GameBoy};
+use gemi_core::movie::{Movie, MoviePlayer};
+use gemi_core::snapshots::Snapshot;
+use gemi_gdb::server::{GdbServer, SessionEnd};
+use gemi_utils::camera::load_camera_image;
+use gemi_utils::screenshot::save_lcd_as_png;
+
+use crate::input_script::InputScript;
+use crate::options::{Dump, DumpFrame, DumpKind, Options};
+
+mod checks;
+mod input_script;
+mod options;
+
+/// Exit code when the emulator did run successfully and all checks passed.
+const EXIT_SUCCESS: u8 = 0;
+
+/// Exit code when the result check failed.
+const EXIT_CHECK_FAILED: u8 = 1;
+
+/// Exit code when the emulator could not be set up or failed while running.
+const EXIT_ERROR: u8 = 2;
+
+/// Exit code when the maximum number of frames was reached before any stop condition was met.
+const EXIT_TIMEOUT: u8 = 3;
+
+
+/// The source of the input applied while running the emulator.
+enum InputSource {
+    None,
+    Script(InputScript),
+    Movie(MoviePlayer),
+}
+
+
+/// The reason why the emulator has stopped.
+enum StopReason {
+    /// The maximum number of frames was processed.
+    FramesCompleted,
+
+    /// Any of the stop conditions was met.
+    StopConditionMet,
+
+    /// The CPU was locked up by an illegal opcode.
+    CpuLocked,
+}
+
+
+fn print_usage() {
+    println!("Usage: gemi-headless [options] <rom file>");
+    println!();
+    println!("Options:");
+    println!("  --dmg, --mgb, --gbc, --gba, --ags, --sgb, --sgb2");
+    println!("                                  The device type to be emulated.");
+    println!("  --boot <file>                   Run a boot ROM before the cartridge.");
+    println!("  --frames <n>                    The maximum number of frames to run (default: {}).", options::DEFAULT_MAX_FRAMES);
+    println!("  --until-halt                    Stop after the frame in which the CPU entered HALT mode.");
+    println!("  --until-serial <text>           Stop when the serial output contains a text.");
+    println!("  --input <file>                  Apply the button states of an input script.");
+    println!("  --movie <file>                  Play an input movie.");
+    println!("  --camera-image <file>           An image to be seen by the Game Boy Camera sensor.");
+    println!("  --screenshot <frame>:<file>     Save the display as PNG image.");
+    println!("  --dump-serial <frame>:<file>    Save the serial output, '-' prints to stdout.");
+    println!("  --dump-memory <frame>:<begin>-<end>:<file>");
+    println!("                                  Save a memory region, like 'c000-dfff'.");
+    println!("  --snapshot <frame>:<file>       Save a snapshot of the emulator state.");
+    println!("  --check <mooneye|blargg>        Check the test result reported by the ROM.");
+    println!("  --gdb <port>                    Wait for a GDB client to connect on localhost before running.");
+    println!("  --dap                           Run a Debug Adapter Protocol server on stdin and stdout.");
+    println!("                                  The ROM is loaded by the client's launch request.");
+    println!();
+    println!("Frames of dumps may be 'end' to be written after the emulator has stopped.");
+    println!();
+    println!("Exit codes:");
+    println!("  {EXIT_SUCCESS}  The ROM did run successfully.");
+    println!("  {EXIT_CHECK_FAILED}  The result check failed.");
+    println!("  {EXIT_ERROR}  The emulator failed to run.");
+    println!("  {EXIT_TIMEOUT}  No stop condition was met within the maximum number of frames.");
+}
+
+
+/// Creates the emulator instance based on the commandline options.
+fn make_gameboy_instance(options: &Options) -> Result<GameBoy, String> {
+    let mut builder = GameBoy::build();
+
+    let cartridge_path = options.cartridge_path.as_ref()
+        .ok_or_else(|| "No ROM file specified".to_string())
+        ?;
+
+    let cartridge = Cartridge::load_file(cartridge_path)
+        .map_err(|e| format!("Failed to load cartridge: {e}"))
+        ?;
+
+    builder.set_cartridge(cartridge);
+
+    if let Some(boot_rom_path) = &options.boot_rom_path {
+        let boot_rom = BootRom::load_file(boot_rom_path)
+            .map_err(|e| format!("Failed to load boot ROM: {e}"))
+            ?;
+
+        builder.set_boot_rom(boot_rom);
+    }
+
+    if let Some(device_type) = options.device_type {
+        builder.set_device_type(device_type);
+    }
+
+    let mut gb = builder.finish()?;
+    gb.initialize();
+
+    if let Some(camera_image_path) = &options.camera_image_path {
+        let image_source = load_camera_image(camera_image_path)?;
+        gb.get_peripherals_mut().mem.set_camera_image_source(Box::new(image_source));
+    }
+
+    // collect all data sent via serial port for checks and dumps
+    gb.get_peripherals_mut().serial.enable_output_queue(true);
+
+    Ok(gb)
+}
+
+
+/// Creates the source of input to be applied while running.
+fn make_input_source(options: &Options, gb: &mut GameBoy) -> Result<InputSource, String> {
+    if let Some(input_script_path) = &options.input_script_path {
+        return Ok(InputSource::Script(InputScript::read_from_file(input_script_path)?));
+    }
+
+    if let Some(movie_path) = &options.movie_path {
+        let movie = Movie::read_from_file(movie_path)
+            .map_err(|e| format!("Failed to load movie: {e}"))
+            ?;
+
+        let player = MoviePlayer::new(movie, gb)
+            .map_err(|e| format!("Failed to play movie: {e}"))
+            ?;
+
+        return Ok(InputSource::Movie(player));
+    }
+
+    Ok(InputSource::None)
+}
+
+
+/// Checks whether any of the stop conditions was met.
+/// HALT is checked on the events of the whole frame, so it won't be missed
+/// when the CPU was woken up again before the frame ended.
+fn is_stop_condition_met(options: &Options, gb: &GameBoy, frame_results: &EmulatorUpdateResults) -> bool {
+    if options.stop_on_halt && frame_results.events.contains(DebugEvent::CpuHalted) {
+        return true;
+    }
+
+    if let Some(text) = &options.stop_on_serial {
+        if gb.get_peripherals().serial.get_output_as_text().contains(text.as_str()) {
+            return true;
+        }
+    }
+
+    if let Some(check) = &options.check {
+        if check.has_result(gb) {
+            return true;
+        }
+    }
+
+    false
+}
+
+
+/// Waits for a GDB client to connect and runs a debugging session until the client detaches.
+fn run_gdb_session(port: u16, gb: &mut GameBoy) -> Result<SessionEnd, String> {
+    let server = GdbServer::bind(("127.0.0.1", port))
+        .map_err(|e| format!("Failed to listen on port {port}: {e}"))
+        ?;
+
+    eprintln!("Waiting for GDB to connect on 127.0.0.1:{port}");
+
+    server.serve(gb).map_err(|e| format!("GDB session failed: {e}"))
+}
+
+
+/// Runs the emulator until any stop condition is met, while writing all dumps
+/// scheduled for the frames being processed.
+fn run(options: &Options, gb: &mut GameBoy, input: &mut InputSource) -> Result<StopReason, String> {
+    let mut frames = 0;
+
+    while frames < options.max_frames {
+        let frame_results = match input {
+            InputSource::None => {
+                gb.run_frame()
+            }
+
+            InputSource::Script(script) => {
+                script.apply(frames, &mut gb.get_peripherals_mut().input);
+                gb.run_frame()
+            }
+
+            InputSource::Movie(player) => {
+                player.run_frame(gb).map_err(|e| format!("Movie playback failed: {e}"))?
+            }
+        };
+
+        frames += 1;
+
+        write_dumps(options, gb, DumpFrame::Frame(frames))?;
+
+        if frame_results.events.contains(DebugEvent::CpuLocked) {
+            return Ok(StopReason::CpuLocked);
+        }
+
+        if is_stop_condition_met(options, gb, &frame_results) {
+            return Ok(StopReason::StopConditionMet);
+        }
+    }
+
+    Ok(StopReason::FramesCompleted)
+}
+
+
+/// Writes all dumps scheduled for a given frame.
+fn write_dumps(options: &Options, gb: &GameBoy, frame: DumpFrame) -> Result<(), String> {
+    for dump in options.dumps.iter().filter(|dump| dump.frame == frame) {
+        write_dump(dump, gb)?;
+    }
+
+    Ok(())
+}
+
+
+/// Writes a single dump into its file.
+fn write_dump(dump: &Dump, gb: &GameBoy) -> Result<(), String> {
+    let path = dump.path.as_path();
+
+    match dump.kind {
+        DumpKind::Screenshot => {
+            save_lcd_as_png(gb.get_peripherals().ppu.get_lcd(), path)
+        }
+
+        DumpKind::Serial => {
+            write_data(path, &gb.get_peripherals().serial.get_output())
+        }
+
+        DumpKind::Memory { begin, end } => {
+            let data: Vec<u8> = (begin ..= end)
+                .map(|address| gb.get_mmu().debug_read_u8(address))
+                .collect();
+
+            write_data(path, &data)
+        }
+
+        DumpKind::Snapshot => {
+            Snapshot::create_from(gb)
+                .and_then(|snapshot| snapshot.save_to_file(path))
+                .map_err(|e| format!("Failed to save snapshot to {}: {e}", path.display()))
+        }
+    }
+}
+
+
+/// Writes binary data into a file, or into the standard output if the path is '-'.
+fn write_data(path: &Path, data: &[u8]) -> Result<(), String> {
+    let result = if path == Path::new("-") {
+        io::stdout().write_all(data)
+    }
+    else {
+        fs::write(path, data)
+    };
+
+    result.map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+
+/// Sets up and runs the emulator, returning the exit code of the application.
+fn run_with_options(options: &Options) -> Result<u8, String> {
+    // the ROM to be debugged is selected by the client
+    if options.dap {
+        gemi_dap::server::serve(BufReader::new(io::stdin()), io::stdout())
+            .map_err(|e| format!("Debug adapter failed: {e}"))
+            ?;
+
+        return Ok(EXIT_SUCCESS);
+    }
+
+    let mut gb    = make_gameboy_instance(options)?;
+    let mut input = make_input_source(options, &mut gb)?;
+
+    // after the debugger detached, continue running as usual
+    if let Some(port) = options.gdb_port {
+        if run_gdb_session(port, &mut gb)? == SessionEnd::Killed {
+            return Ok(EXIT_SUCCESS);
+        }
+    }
+
+    let stop_reason = run(options, &mut gb, &mut input)?;
+
+    write_dumps(options, &gb, DumpFrame::End)?;
+
+    if let StopReason::CpuLocked = stop_reason {
+        eprintln!("CPU locked up after executing an illegal opcode");
+    }
+
+    // the result check decides about the exit code, if any
+    if let Some(check) = &options.check {
+        return match check.check(&gb) {
+            Ok(()) => Ok(EXIT_SUCCESS),
+            Err(e) => {
+                eprintln!("Check failed: {e}");
+                Ok(EXIT_CHECK_FAILED)
+            }
+        };
+    }
+
+    match stop_reason {
+        StopReason::FramesCompleted if options.has_stop_conditions() => {
+            eprintln!("No stop condition was met within {} frames", options.max_frames);
+            Ok(EXIT_TIMEOUT)
+        }
+
+        StopReason::CpuLocked => Ok(EXIT_ERROR),
+
+        _ => Ok(EXIT_SUCCESS),
+    }
+}
+
+
+fn main() -> ExitCode {
+    // skip first argument, which is the executable name
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    // explicitly asking for help is not an error
+    if args.iter().any(|arg| arg == "--help") {
+        print_usage();
+        return ExitCode::from(EXIT_SUCCESS);
+    }
+
+    if args.is_empty() {
+        print_usage();
+        return ExitCode::from(EXIT_ERROR);
+    }
+
+    let options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+
+    match run_with_options(&options) {
+        Ok(exit_code) => ExitCode::from(exit_code),
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
@@ -0,0 +1,394 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::path::PathBuf;
+
+use gemi_core::gameboy::DeviceType;
+
+/// The maximum number of frames to be processed, if not specified otherwise.
+pub const DEFAULT_MAX_FRAMES: u32 = 10_000;
+
+
+/// The frame on which a dump will be written.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum DumpFrame {
+    /// After the given number of frames was completed.
+    Frame(u32),
+
+    /// After the emulator has stopped.
+    End,
+}
+
+
+/// The kind of data to be written into a dump file.
+#[derive(Copy, Clone)]
+pub enum DumpKind {
+    /// The content of the emulator display as PNG image.
+    Screenshot,
+
+    /// All data sent over the serial port so far.
+    Serial,
+
+    /// A memory region between two addresses, both inclusive.
+    Memory {
+        begin: u16,
+        end:   u16,
+    },
+
+    /// A snapshot of the whole emulator state.
+    Snapshot,
+}
+
+
+/// A request to write some data of the emulator into a file.
+pub struct Dump {
+    /// The frame on which the dump will be written.
+    pub frame: DumpFrame,
+
+    /// The kind of data to be written.
+    pub kind: DumpKind,
+
+    /// The file to write the data into, or '-' to write into the standard output.
+    pub path: PathBuf,
+}
+
+
+/// A check to be run after the emulator has stopped, which
+/// decides about the exit code of the application.
+#[derive(Copy, Clone)]
+pub enum ResultCheck {
+    /// Check for the fibonacci sequence sent by mooneye test ROMs via serial port.
+    Mooneye,
+
+    /// Check for the 'Passed' message sent by blargg test ROMs via serial port or display.
+    Blargg,
+}
+
+
+/// All options to run the emulator, as passed via commandline.
+pub struct Options {
+    /// Path to the ROM file to run.
+    pub cartridge_path: Option<PathBuf>,
+
+    /// Optional: Boot ROM to be run before the actual cartridge is run.
+    pub boot_rom_path: Option<PathBuf>,
+
+    /// Optional: The device type to be emulated.
+    pub device_type: Option<DeviceType>,
+
+    /// The maximum number of frames to be processed.
+    pub max_frames: u32,
+
+    /// Stops the emulator after a frame in which the CPU entered HALT mode.
+    pub stop_on_halt: bool,
+
+    /// Stops the emulator when the serial output contains the given text.
+    pub stop_on_serial: Option<String>,
+
+    /// Optional: A script containing the input to be applied while running.
+    pub input_script_path: Option<PathBuf>,
+
+    /// Optional: An input movie to be played while running.
+    pub movie_path: Option<PathBuf>,
+
+    /// Optional: An image file to be seen by the sensor of a Game Boy Camera cartridge.
+    pub camera_image_path: Option<PathBuf>,
+
+    /// All data to be written into files while running.
+    pub dumps: Vec<Dump>,
+
+    /// Optional: A check to decide whether the ROM did run successfully.
+    pub check: Option<ResultCheck>,
+
+    /// Optional: Waits for a GDB client to connect on this port before running.
+    pub gdb_port: Option<u16>,
+
+    /// Runs a Debug Adapter Protocol server on stdin and stdout instead of running a ROM.
+    pub dap: bool,
+}
+
+
+impl Options {
+    /// Parses the options from a list of commandline arguments,
+    /// without the executable name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args    = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut next_value = || {
+                args.next().ok_or_else(|| format!("'{arg}' needs to be followed by a value"))
+            };
+
+            match arg.as_str() {
+                "--boot" => {
+                    options.boot_rom_path = Some(PathBuf::from(next_value()?));
+                }
+
+                "--frames" => {
+                    let value = next_value()?;
+                    options.max_frames = value.parse()
+                        .map_err(|_| format!("Invalid number of frames: '{value}'"))
+                        ?;
+                }
+
+                "--until-halt" => {
+                    options.stop_on_halt = true;
+                }
+
+                "--until-serial" => {
+                    options.stop_on_serial = Some(next_value()?);
+                }
+
+                "--input" => {
+                    options.input_script_path = Some(PathBuf::from(next_value()?));
+                }
+
+                "--movie" => {
+                    options.movie_path = Some(PathBuf::from(next_value()?));
+                }
+
+                "--camera-image" => {
+                    options.camera_image_path = Some(PathBuf::from(next_value()?));
+                }
+
+                "--screenshot" => {
+                    options.dumps.push(parse_dump(&next_value()?, DumpKind::Screenshot)?);
+                }
+
+                "--dump-serial" => {
+                    options.dumps.push(parse_dump(&next_value()?, DumpKind::Serial)?);
+                }
+
+                "--dump-memory" => {
+                    options.dumps.push(parse_memory_dump(&next_value()?)?);
+                }
+
+                "--snapshot" => {
+                    options.dumps.push(parse_dump(&next_value()?, DumpKind::Snapshot)?);
+                }
+
+                "--gdb" => {
+                    let value = next_value()?;
+                    options.gdb_port = Some(value.parse()
+                        .map_err(|_| format!("Invalid port: '{value}'"))
+                        ?);
+                }
+
+                "--dap" => {
+                    options.dap = true;
+                }
+
+                "--check" => {
+                    let value = next_value()?;
+                    options.check = Some(match value.as_str() {
+                        "mooneye" => ResultCheck::Mooneye,
+                        "blargg"  => ResultCheck::Blargg,
+                        _         => return Err(format!("Unknown check: '{value}'")),
+                    });
+                }
+
+                _ => {
+                    // device types are identified by their abbreviation, like '--dmg'
+                    if let Some(device_type) = arg.strip_prefix("--").and_then(DeviceType::from_abbreviation) {
+                        options.device_type = Some(device_type);
+                    }
+                    else if arg.starts_with("--") {
+                        return Err(format!("Unknown option: '{arg}'"));
+                    }
+                    else {
+                        options.cartridge_path = Some(PathBuf::from(arg));
+                    }
+                }
+            }
+        }
+
+        if options.input_script_path.is_some() && options.movie_path.is_some() {
+            return Err("'--input' and '--movie' cannot be used together".to_string());
+        }
+
+        Ok(options)
+    }
+
+
+    /// Checks whether any stop condition was configured,
+    /// which is expected to stop the emulator before reaching the maximum number of frames.
+    pub fn has_stop_conditions(&self) -> bool {
+        self.stop_on_halt || self.stop_on_serial.is_some() || self.check.is_some()
+    }
+}
+
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            cartridge_path:     None,
+            boot_rom_path:      None,
+            device_type:        None,
+            max_frames:         DEFAULT_MAX_FRAMES,
+            stop_on_halt:       false,
+            stop_on_serial:     None,
+            input_script_path:  None,
+            movie_path:         None,
+            camera_image_path:  None,
+            dumps:              Vec::new(),
+            check:              None,
+            gdb_port:           None,
+            dap:                false,
+        }
+    }
+}
+
+
+/// Parses the frame of a dump, which is either a number or 'end'.
+fn parse_dump_frame(value: &str) -> Result<DumpFrame, String> {
+    match value {
+        "end" => Ok(DumpFrame::End),
+        _     => value.parse()
+            .map(DumpFrame::Frame)
+            .map_err(|_| format!("Invalid frame: '{value}'")),
+    }
+}
+
+
+/// Parses a dump in the format `<frame>:<file>`.
+fn parse_dump(value: &str, kind: DumpKind) -> Result<Dump, String> {
+    let (frame, path) = value.split_once(':')
+        .ok_or_else(|| format!("Expected '<frame>:<file>', got '{value}'"))
+        ?;
+
+    Ok(Dump {
+        frame: parse_dump_frame(frame)?,
+        kind,
+        path:  PathBuf::from(path),
+    })
+}
+
+
+/// Parses a memory dump in the format `<frame>:<begin>-<end>:<file>`,
+/// where the addresses are given as hexadecimal numbers.
+fn parse_memory_dump(value: &str) -> Result<Dump, String> {
+    let (frame, remaining) = value.split_once(':')
+        .ok_or_else(|| format!("Expected '<frame>:<begin>-<end>:<file>', got '{value}'"))
+        ?;
+
+    let (range, path) = remaining.split_once(':')
+        .ok_or_else(|| format!("Expected '<frame>:<begin>-<end>:<file>', got '{value}'"))
+        ?;
+
+    let (begin, end) = range.split_once('-')
+        .ok_or_else(|| format!("Expected a memory range like 'c000-dfff', got '{range}'"))
+        ?;
+
+    let parse_address = |address: &str| {
+        u16::from_str_radix(address.trim_start_matches("0x"), 16)
+            .map_err(|_| format!("Invalid address: '{address}'"))
+    };
+
+    let begin = parse_address(begin)?;
+    let end   = parse_address(end)?;
+
+    if begin > end {
+        return Err(format!("Invalid memory range: '{range}'"));
+    }
+
+    Ok(Dump {
+        frame: parse_dump_frame(frame)?,
+        kind:  DumpKind::Memory { begin, end },
+        path:  PathBuf::from(path),
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+
+    #[test]
+    fn parse_options() {
+        let options = parse(&["--gbc", "--frames", "120", "--until-halt", "--check", "blargg", "rom.gb"]).unwrap();
+
+        assert_eq!(Some(PathBuf::from("rom.gb")), options.cartridge_path);
+        assert_eq!(Some(DeviceType::GameBoyColor), options.device_type);
+        assert_eq!(120, options.max_frames);
+        assert!(options.stop_on_halt);
+        assert!(matches!(options.check, Some(ResultCheck::Blargg)));
+        assert!(options.has_stop_conditions());
+    }
+
+
+    #[test]
+    fn parse_defaults() {
+        let options = parse(&["rom.gb"]).unwrap();
+
+        assert_eq!(DEFAULT_MAX_FRAMES, options.max_frames);
+        assert_eq!(None, options.device_type);
+        assert!(options.dumps.is_empty());
+        assert!(!options.has_stop_conditions());
+    }
+
+
+    #[test]
+    fn parse_dumps() {
+        let options = parse(&[
+            "--screenshot",  "60:screen.png",
+            "--dump-serial", "end:-",
+            "--dump-memory", "10:0xc000-c0ff:wram.bin",
+        ]).unwrap();
+
+        assert_eq!(3, options.dumps.len());
+
+        assert!(options.dumps[0].frame == DumpFrame::Frame(60));
+        assert!(matches!(options.dumps[0].kind, DumpKind::Screenshot));
+        assert_eq!(PathBuf::from("screen.png"), options.dumps[0].path);
+
+        assert!(options.dumps[1].frame == DumpFrame::End);
+        assert!(matches!(options.dumps[1].kind, DumpKind::Serial));
+        assert_eq!(PathBuf::from("-"), options.dumps[1].path);
+
+        assert!(options.dumps[2].frame == DumpFrame::Frame(10));
+        assert!(matches!(options.dumps[2].kind, DumpKind::Memory { begin: 0xc000, end: 0xc0ff }));
+        assert_eq!(PathBuf::from("wram.bin"), options.dumps[2].path);
+    }
+
+
+    #[test]
+    fn parse_invalid_options() {
+        assert!(parse(&["--unknown"]).is_err());
+        assert!(parse(&["--frames"]).is_err());
+        assert!(parse(&["--frames", "many"]).is_err());
+        assert!(parse(&["--check", "unknown"]).is_err());
+        assert!(parse(&["--gdb", "70000"]).is_err());
+        assert!(parse(&["--screenshot", "screen.png"]).is_err());
+        assert!(parse(&["--screenshot", "never:screen.png"]).is_err());
+        assert!(parse(&["--dump-memory", "end:c000:wram.bin"]).is_err());
+        assert!(parse(&["--dump-memory", "end:d000-c000:wram.bin"]).is_err());
+    }
+
+
+    #[test]
+    fn input_and_movie_are_exclusive() {
+        assert!(parse(&["--input", "input.txt", "rom.gb"]).is_ok());
+        assert!(parse(&["--movie", "input.gmv", "rom.gb"]).is_ok());
+        assert!(parse(&["--input", "input.txt", "--movie", "input.gmv", "rom.gb"]).is_err());
+    }
+}
@@ -25,13 +25,40 @@ use gemi_core::boot_rom::BootRom;
 use gemi_core::cartridge::Cartridge;
 use gemi_core::cartridge::GameBoyColorSupport;
 use gemi_core::cpu::cpu::CPU_CLOCK_SPEED;
+use gemi_core::debug::DebugEvent;
 use gemi_core::gameboy::{DeviceType, GameBoy};
+use gemi_core::movie::{Movie, MovieConfig, MoviePlayer, MovieRecorder};
+use gemi_core::ppu::ppu::CPU_CYCLES_PER_FRAME;
+use gemi_core::rewind::RewindBuffer;
+use gemi_utils::rumble::RumbleTracker;
 
 use crate::window::Window;
 
 mod sound_queue;
 mod window;
 
+
+/// Input movie to be recorded or played, as requested via commandline.
+enum MovieArg {
+    None,
+    Record(PathBuf),
+    Play(PathBuf),
+}
+
+
+/// The state of an input movie while running the emulator.
+enum MovieMode {
+    /// No movie is used, the input is controlled by the user.
+    None,
+
+    /// Records the user input into a movie, which is saved into a file after the emulator was closed.
+    Record(PathBuf, MovieRecorder),
+
+    /// Plays a movie, while ignoring the user input until the movie is finished.
+    Play(MoviePlayer),
+}
+
+
 fn print_rom_info(file: &Path, cartridge: &Cartridge) {
     let mut features: Vec<&str> = vec![];
 
@@ -51,6 +78,21 @@ fn print_rom_info(file: &Path, cartridge: &Cartridge) {
         features.push("Rumble");
     }
 
+    let header = cartridge.get_header_validation();
+    let mut header_errors: Vec<&str> = vec![];
+
+    if !header.logo_valid {
+        header_errors.push("Invalid logo");
+    }
+
+    if !header.header_checksum_valid {
+        header_errors.push("Invalid header checksum");
+    }
+
+    if !header.global_checksum_valid {
+        header_errors.push("Invalid global checksum");
+    }
+
     let gbc = match cartridge.get_cgb_support() {
         GameBoyColorSupport::Supported => "Supported",
         GameBoyColorSupport::Required => "Required",
@@ -67,24 +109,96 @@ fn print_rom_info(file: &Path, cartridge: &Cartridge) {
     println!("RAM size:      {} kiB", cartridge.get_ram_size() / 1024);
     println!("GameBoy Color: {}",     gbc);
     println!("SuperGameBoy:  {}",     cartridge.supports_sgb());
+    println!("Header:        {}",     if header.is_valid() { "Valid".to_string() } else { header_errors.join(", ") });
 }
 
 
-fn run(window: &mut Window, gb: &mut GameBoy) {
+fn run(window: &mut Window, gb: &mut GameBoy, movie: &mut MovieMode) {
     let mut interval_begin  = time::Instant::now();
     let mut interval_cycles = 0;
+    let mut rewind          = RewindBuffer::default();
+    let mut rumble          = RumbleTracker::new();
+
+    // collect the state changes of the rumble motor to be forwarded to the game controller
+    gb.get_peripherals_mut().mem.enable_rumble_events(true);
 
     while window.is_opened() {
-        let frame_results = gb.run_frame();
-        let frame_cycles  = frame_results.cycles;
-        interval_cycles += frame_cycles;
+        // stop the playback after the whole movie was played
+        if let MovieMode::Play(player) = movie {
+            if player.is_finished(gb) {
+                println!("Movie playback finished after {} frames", player.get_current_frame());
+                *movie = MovieMode::None;
+            }
+        }
+
+        // rewinding is not possible while recording or playing a movie
+        if window.is_rewind_pressed() && matches!(movie, MovieMode::None) {
+            // while rewinding, restore the previous state instead of running the emulator
+            match rewind.step_back() {
+                Ok(Some(mut restored)) => {
+                    restored.get_peripherals_mut().mem.take_frontend_state_from(&mut gb.get_peripherals_mut().mem);
+                    *gb = restored;
+
+                    if let Err(e) = window.reconnect_audio(gb) {
+                        println!("Failed to reconnect audio: {}", e);
+                    }
+                }
+
+                Ok(None) => { }
+
+                Err(e) => {
+                    println!("Failed to rewind: {}", e);
+                    rewind.clear();
+                }
+            }
+
+            interval_cycles += CPU_CYCLES_PER_FRAME;
+
+            // no rumble while going back in time
+            window.set_rumble(0.0);
+        }
+        else {
+            let frame_results = match movie {
+                MovieMode::None => gb.run_frame(),
+                MovieMode::Record(_, recorder) => recorder.run_frame(gb),
+                MovieMode::Play(player) => match player.run_frame(gb) {
+                    Ok(frame_results) => frame_results,
+                    Err(e) => {
+                        println!("Movie playback stopped: {}", e);
+                        let frame_results = gb.run_frame();
+                        *movie = MovieMode::None;
+                        frame_results
+                    }
+                },
+            };
+
+            let frame_cycles  = frame_results.cycles;
+            interval_cycles += frame_cycles;
+
+            if frame_results.events.contains(DebugEvent::CpuLocked) {
+                println!("CPU locked up after executing an illegal opcode");
+            }
+
+            // forward the rumble motor's intensity during this frame to the game controller
+            let rumble_events    = gb.get_peripherals_mut().mem.take_rumble_events();
+            let rumble_intensity = rumble.on_frame_completed(&rumble_events, gb.get_total_cycles_processed());
+            window.set_rumble(rumble_intensity);
+
+            if let Err(e) = rewind.on_frame_completed(gb) {
+                println!("Failed to capture rewind state: {}", e);
+            }
+        }
 
         // update window
         {
             let peripherals = gb.get_peripherals_mut();
 
             window.poll_events();
-            window.apply_button_states(&mut peripherals.input);
+
+            // while playing a movie, the input is controlled by the movie
+            if !matches!(movie, MovieMode::Play(_)) {
+                window.apply_button_states(&mut peripherals.input);
+            }
             window.present(peripherals.ppu.get_lcd(), &peripherals.ppu);
         }
 
@@ -113,9 +227,10 @@ fn run(window: &mut Window, gb: &mut GameBoy) {
 }
 
 
-fn make_gameboy_instance() -> Result<GameBoy, String> {
+fn make_gameboy_instance() -> Result<(GameBoy, MovieArg), String> {
     let mut args    = env::args().into_iter();
     let mut builder = GameBoy::build();
+    let mut movie   = MovieArg::None;
 
     // skip first argument, which is the executable name
     _ = args.next();
@@ -164,6 +279,20 @@ fn make_gameboy_instance() -> Result<GameBoy, String> {
                 builder.set_print_opcodes(true);
             }
 
+            "--record" => {
+                let filename = args.next()
+                    .expect("'--record' needs to be followed by the path of the movie file to be recorded");
+
+                movie = MovieArg::Record(PathBuf::from(filename));
+            }
+
+            "--play" => {
+                let filename = args.next()
+                    .expect("'--play' needs to be followed by the path to a valid movie file");
+
+                movie = MovieArg::Play(PathBuf::from(filename));
+            }
+
             _ => {
                 let file = PathBuf::from(arg);
                 let cart = Cartridge::load_files_with_default_ram(&file)
@@ -177,15 +306,48 @@ fn make_gameboy_instance() -> Result<GameBoy, String> {
         }
     }
 
-    builder.finish()
+    let gb = builder.finish()?;
+
+    Ok((gb, movie))
+}
+
+
+/// Starts recording or playing a movie, as requested via commandline.
+fn start_movie(gb: &mut GameBoy, movie: MovieArg) -> Result<MovieMode, String> {
+    match movie {
+        MovieArg::None => Ok(MovieMode::None),
+
+        MovieArg::Record(filepath) => {
+            let recorder = MovieRecorder::new(gb, MovieConfig::default())
+                .map_err(|e| format!("Failed to start recording: {}", e))
+                ?;
+
+            Ok(MovieMode::Record(filepath, recorder))
+        }
+
+        MovieArg::Play(filepath) => {
+            let movie = Movie::read_from_file(&filepath)
+                .map_err(|e| format!("Failed to load movie: {}", e))
+                ?;
+
+            let player = MoviePlayer::new(movie, gb)
+                .map_err(|e| format!("Failed to play movie: {}", e))
+                ?;
+
+            Ok(MovieMode::Play(player))
+        }
+    }
 }
 
 
 fn main() -> Result<(), String> {
     // create the gb instance using the current commandline arguments
-    let mut gb = make_gameboy_instance()?;
+    let (mut gb, movie_arg) = make_gameboy_instance()?;
     gb.initialize();
 
+    // start recording or playing a movie, if requested
+    let mut movie = start_movie(&mut gb, movie_arg)?;
+
     // determine the title based on the cartridge available
     let title = match gb.get_peripherals().mem.get_cartridge() {
         Some(cartridge) => cartridge.get_title().to_string(),
@@ -196,7 +358,14 @@ fn main() -> Result<(), String> {
     let mut window = Window::create(&title, &mut gb)?;
 
     // run the game
-    run(&mut window, &mut gb);
+    run(&mut window, &mut gb, &mut movie);
+
+    // save the recorded movie, if any
+    if let MovieMode::Record(filepath, recorder) = movie {
+        recorder.finish().save_to_file(&filepath)
+            .map_err(|e| format!("Failed to save movie: {}", e))
+            ?;
+    }
 
     // after running the cartridge, save it's on-chip-RAM, if any
     gb.get_peripherals().mem.save_cartridge_ram_to_file_if_any()
@@ -61,14 +61,7 @@ impl SoundQueue {
         };
 
         // open a channel to the APU backend to receive audio data
-        let receiver = apu.get_audio_output().open_channel(
-            AudioOutputSpec {
-                sample_rate: SAMPLE_FREQ,
-            }
-        ).ok_or_else(
-            || String::from("Cannot connect to emulator")
-        )
-        ?;
+        let receiver = Self::open_channel(apu)?;
 
         let audio_device = sdl_audio.open_playback(
             None,
@@ -90,6 +83,28 @@ impl SoundQueue {
     }
 
 
+    /// Connects the audio device to a different APU,
+    /// for example after the emulator state was restored.
+    pub fn reconnect(&mut self, apu: &mut Apu) -> Result<(), String> {
+        let receiver = Self::open_channel(apu)?;
+        self.audio_device.lock().receiver = receiver;
+
+        Ok(())
+    }
+
+
+    /// Opens a channel to the APU backend to receive audio data.
+    fn open_channel(apu: &mut Apu) -> Result<SamplesReceiver, String> {
+        apu.get_audio_output().open_channel(
+            AudioOutputSpec {
+                sample_rate: SAMPLE_FREQ,
+            }
+        ).ok_or_else(
+            || String::from("Cannot connect to emulator")
+        )
+    }
+
+
     /// Set the playback volume.
     pub fn set_volume(&mut self, volume: f32) {
         let volume_clamped = volume.clamp(0.0, 1.0);
@@ -23,13 +23,20 @@ use gemi_core::mmu::locations::MEMORY_LOCATION_SPRITES_BEGIN;
 use gemi_core::ppu::flags::LcdControlFlag;
 use gemi_core::ppu::graphic_data::{Color, DmgPalette, TileMap, TileSet};
 use gemi_core::ppu::ppu::{LcdBuffer, Ppu, SCREEN_H, SCREEN_W};
+use sdl2::controller::GameController;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::rect::Rect;
 use sdl2::render::{Texture, TextureCreator, UpdateTextureError, WindowCanvas};
+use sdl2::GameControllerSubsystem;
 use crate::sound_queue::SoundQueue;
 
 
+/// The duration of a rumble effect in milliseconds. This is a bit longer than a single frame,
+/// so the effect keeps running until it gets updated on the next frame.
+const RUMBLE_DURATION_MS: u32 = 100;
+
+
 #[derive(PartialEq)]
 pub enum DisplayMode {
     Game,
@@ -66,6 +73,10 @@ pub struct Window {
     display_mode:       DisplayMode,
     key_bindings:       KeyBindings,
     audio:              SoundQueue,
+    rewind_pressed:     bool,
+    tilt:               (f32, f32),
+    game_controller:    GameControllerSubsystem,
+    controller:         Option<GameController>,
 }
 
 
@@ -158,6 +169,7 @@ impl Window {
         let sdl = sdl2::init()?;
         let video = sdl.video()?;
         let event_pump = sdl.event_pump()?;
+        let game_controller = sdl.game_controller()?;
 
         let window = video
             .window(title, SCREEN_W * display_scale, SCREEN_H * display_scale)
@@ -194,6 +206,10 @@ impl Window {
             display_mode: DisplayMode::Game,
             key_bindings: make_keybindings(),
             audio,
+            rewind_pressed: false,
+            tilt: (0.0, 0.0),
+            game_controller,
+            controller: None,
         })
     }
 
@@ -228,6 +244,18 @@ impl Window {
                     self.handle_key_up(keycode);
                 }
 
+                Event::MouseMotion { x, y, .. } => {
+                    self.handle_mouse_motion(x, y);
+                }
+
+                Event::ControllerDeviceAdded { which, .. } => {
+                    self.handle_controller_added(which);
+                }
+
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    self.handle_controller_removed(which);
+                }
+
                 _ => { }
             }
         }
@@ -247,6 +275,7 @@ impl Window {
             Keycode::F1     => { self.set_display_mode(DisplayMode::Game); }
             Keycode::F2     => { self.set_display_mode(DisplayMode::Background); }
             Keycode::F3     => { self.set_display_mode(DisplayMode::Objects); }
+            Keycode::Backspace => { self.rewind_pressed = true; }
 
             Keycode::KpMinus => {
                 let volume = self.audio.get_volume();
@@ -268,12 +297,86 @@ impl Window {
             keycode,
             false
         );
+
+        if keycode == Keycode::Backspace {
+            self.rewind_pressed = false;
+        }
+    }
+
+
+    /// Tilts the device depending on the position of the mouse cursor relative
+    /// to the center of the window, for cartridges with an accelerometer.
+    fn handle_mouse_motion(&mut self, x: i32, y: i32) {
+        let (width, height) = self.canvas.window().size();
+        let half_width  = (width  as f32 / 2.0).max(1.0);
+        let half_height = (height as f32 / 2.0).max(1.0);
+
+        self.tilt = (
+            ((x as f32 - half_width)  / half_width).clamp(-1.0, 1.0),
+            ((y as f32 - half_height) / half_height).clamp(-1.0, 1.0),
+        );
+    }
+
+
+    /// Opens a game controller after being connected, which is used for rumble effects.
+    /// Only the first controller is used, any further controller will be ignored.
+    fn handle_controller_added(&mut self, joystick_index: u32) {
+        if self.controller.is_some() {
+            return;
+        }
+
+        match self.game_controller.open(joystick_index) {
+            Ok(controller) => {
+                println!("Controller connected: {}", controller.name());
+                self.controller = Some(controller);
+            }
+
+            Err(e) => {
+                println!("Failed to open controller: {}", e);
+            }
+        }
+    }
+
+
+    /// Closes the game controller currently in use, after it was disconnected.
+    fn handle_controller_removed(&mut self, instance_id: u32) {
+        if let Some(controller) = &self.controller {
+            if controller.instance_id() == instance_id {
+                self.controller = None;
+            }
+        }
+    }
+
+
+    /// Set the intensity of the rumble effect on the connected game controller,
+    /// from 0.0 (off) to 1.0 (full strength).
+    pub fn set_rumble(&mut self, intensity: f32) {
+        if let Some(controller) = &mut self.controller {
+            let strength = (intensity.clamp(0.0, 1.0) * (u16::MAX as f32)) as u16;
+
+            // not all controllers support rumble, so errors are ignored
+            _ = controller.set_rumble(strength, strength, RUMBLE_DURATION_MS);
+        }
+    }
+
+
+    /// Checks whether the key to rewind the game is currently being pressed.
+    pub fn is_rewind_pressed(&self) -> bool {
+        self.rewind_pressed
+    }
+
+
+    /// Connects the audio output to a new emulator instance,
+    /// for example after being restored from a previous state.
+    pub fn reconnect_audio(&mut self, gb: &mut GameBoy) -> Result<(), String> {
+        self.audio.reconnect(&mut gb.get_peripherals_mut().apu)
     }
 
 
-    /// Set the pressed state for gameboy buttons.
+    /// Set the pressed state for gameboy buttons and the current tilt of the device.
     pub fn apply_button_states(&self, input: &mut Input) {
         self.key_bindings.apply_button_states_to_input(input);
+        input.set_tilt(self.tilt.0, self.tilt.1);
     }
 
 
@@ -24,6 +24,7 @@ use gemi_core::apu::audio_output::{AudioOutputSpec, SamplesReceiver};
 use gemi_core::gameboy::{DeviceType, EmulationType, GameBoy};
 use gemi_core::input::InputButton;
 use gemi_core::mmu::memory_data::MemoryData;
+use gemi_utils::rumble::RumbleTracker;
 
 use crate::cartridge::Cartridge;
 
@@ -42,6 +43,12 @@ pub struct WasmPlayer {
 
     /// The key bindings to use for mapping JS key events to emulator input.
     key_bindings: KeyBindings,
+
+    /// Converts the state changes of the cartridge's rumble motor into an intensity per frame.
+    rumble: RumbleTracker,
+
+    /// The intensity of the rumble motor during the last frame.
+    rumble_intensity: f32,
 }
 
 
@@ -115,6 +122,9 @@ impl WasmPlayer {
         let mut gb = builder.finish()?;
         gb.initialize();
 
+        // collect the state changes of the rumble motor to be forwarded to the gamepad
+        gb.get_peripherals_mut().mem.enable_rumble_events(true);
+
         Ok(
             WasmPlayer {
                 gb,
@@ -123,6 +133,9 @@ impl WasmPlayer {
                 samples_receiver: None,
 
                 key_bindings: default_keymap(),
+
+                rumble:           RumbleTracker::new(),
+                rumble_intensity: 0.0,
             }
         )
     }
@@ -212,6 +225,15 @@ impl WasmPlayer {
    }
 
 
+    /// Get the intensity of the cartridge's rumble motor during the last frame,
+    /// from 0.0 (off) to 1.0 (full strength). This is intended to be forwarded
+    /// to the vibration actuator of a gamepad via the Gamepad API after each frame.
+    #[wasm_bindgen]
+    pub fn get_rumble_intensity(&self) -> f32 {
+        self.rumble_intensity
+    }
+
+
     /// Set the pressed state of a key.
     /// `key` is the key identifier as provided by the JS key event and will be mapped into
     /// the corresponding emulator [InputButton] value.
@@ -226,6 +248,9 @@ impl WasmPlayer {
     /// Process a single frame until the next VBlank completion.
     pub fn process_frame(&mut self) {
         self.gb.run_frame();
+
+        let rumble_events     = self.gb.get_peripherals_mut().mem.take_rumble_events();
+        self.rumble_intensity = self.rumble.on_frame_completed(&rumble_events, self.gb.get_total_cycles_processed());
     }
 
 
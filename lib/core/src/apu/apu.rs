@@ -22,7 +22,7 @@ use crate::apu::channels::noise::NoiseGenerator;
 use crate::apu::channels::pulse::PulseGenerator;
 use crate::apu::channels::wave::WaveGenerator;
 use crate::apu::mixer::Mixer;
-use crate::gameboy::{Clock, DeviceConfig};
+use crate::gameboy::{Clock, DeviceConfig, EmulationType};
 use crate::mmu::locations::*;
 use crate::mmu::memory_bus::MemoryBusConnection;
 use crate::utils::{as_bit_flag, get_bit};
@@ -185,6 +185,12 @@ impl Apu {
     }
 
 
+    /// Switches the APU of a GameBoy Color into DMG compatibility mode.
+    pub fn enter_dmg_compatibility_mode(&mut self) {
+        self.state.device_config.emulation = EmulationType::DMG;
+    }
+
+
     /// Updates the APUs internal components with the time passed.
     pub fn update(&mut self, cycles: Clock) {
         if self.state.apu_on {
@@ -363,6 +369,33 @@ impl MemoryBusConnection for Apu {
     }
 
 
+    fn get_register_value(&self, address: u16) -> u8 {
+        match address {
+            MEMORY_LOCATION_APU_NR10 ..= MEMORY_LOCATION_APU_NR14 => {
+                self.ch1.get_register_value(address - MEMORY_LOCATION_APU_NR10, &self.state)
+            }
+
+            MEMORY_LOCATION_APU_NR21 ..= MEMORY_LOCATION_APU_NR24 => {
+                self.ch2.get_register_value(address - MEMORY_LOCATION_APU_NR20, &self.state)
+            }
+
+            MEMORY_LOCATION_APU_NR30 ..= MEMORY_LOCATION_APU_NR34 => {
+                self.ch3.get_register_value(address - MEMORY_LOCATION_APU_NR30, &self.state)
+            }
+
+            MEMORY_LOCATION_APU_NR41 ..= MEMORY_LOCATION_APU_NR44 => {
+                self.ch4.get_register_value(address - MEMORY_LOCATION_APU_NR40, &self.state)
+            }
+
+            MEMORY_LOCATION_APU_WAVE_RAM_BEGIN ..= MEMORY_LOCATION_APU_WAVE_RAM_END => {
+                self.ch3.get_register_value(address, &self.state)
+            }
+
+            _ => self.on_read(address),
+        }
+    }
+
+
     fn on_write(&mut self, address: u16, value: u8) {
         match address {
             // Channel 1
@@ -93,6 +93,12 @@ pub trait ChannelComponent {
         default_on_read_register(number, apu_state)
     }
 
+    /// Called to get the raw value of a register, including write-only bits.
+    /// The default implementation returns the same value as reading the register.
+    fn get_register_value(&self, number: u16, apu_state: &ApuState) -> u8 {
+        self.on_read_register(number, apu_state)
+    }
+
     /// Called when the value of a register was written.
     fn on_write_register(&mut self, number: u16, value: u8, apu_state: &ApuState) -> TriggerAction {
        default_on_write_register(number, value, apu_state)
@@ -342,6 +348,12 @@ impl<
     }
 
 
+    /// Get the raw value of a register which belongs to this channel, including write-only bits.
+    pub fn get_register_value(&self, number: u16, apu_state: &ApuState) -> u8 {
+        self.for_each_component(|c| c.get_register_value(number, apu_state))
+    }
+
+
     /// Writes to a register which belongs to this channel.
     /// When NRx4 bit 7 was set, this will also fire the trigger event for this channel.
     pub fn on_write_register(&mut self, number: u16, value: u8, apu_state: &ApuState) -> TriggerActionSet {
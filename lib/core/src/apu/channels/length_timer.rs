@@ -96,6 +96,15 @@ impl<const LENGTH_BITS: u8> ChannelComponent for LengthTimer<LENGTH_BITS> {
     }
 
 
+    fn get_register_value(&self, number: u16, apu_state: &ApuState) -> u8 {
+        match number {
+            // the initial value, which results in the current timer value
+            1 => (Self::LENGTH_MAX.wrapping_sub(self.length_timer) as u8) & Self::LENGTH_MASK,
+            _ => self.on_read_register(number, apu_state),
+        }
+    }
+
+
     fn on_write_register(&mut self, number: u16, value: u8, apu_state: &ApuState) -> TriggerAction {
         match number {
             1 => {
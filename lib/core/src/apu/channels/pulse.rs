@@ -79,6 +79,15 @@ impl ChannelComponent for PulseGenerator {
     }
 
 
+    fn get_register_value(&self, number: u16, apu_state: &ApuState) -> u8 {
+        match number {
+            3 => self.frequency.get_low(),
+            4 => self.frequency.get_high(),
+            _ => self.on_read_register(number, apu_state),
+        }
+    }
+
+
     fn on_write_register(&mut self, number: u16, value: u8, apu_state: &ApuState) -> TriggerAction {
         match number {
             1 => {
@@ -182,6 +182,21 @@ impl ChannelComponent for WaveGenerator {
     }
 
 
+    fn get_register_value(&self, number: u16, apu_state: &ApuState) -> u8 {
+        match number {
+            3 => self.frequency.get_low(),
+            4 => self.frequency.get_high(),
+
+            // Wave RAM, regardless of being accessible while the channel is playing
+            MEMORY_LOCATION_APU_WAVE_RAM_BEGIN ..= MEMORY_LOCATION_APU_WAVE_RAM_END => {
+                self.wave_ram[(number - MEMORY_LOCATION_APU_WAVE_RAM_BEGIN) as u8]
+            }
+
+            _ => self.on_read_register(number, apu_state),
+        }
+    }
+
+
     fn on_write_register(&mut self, number: u16, value: u8, apu_state: &ApuState) -> TriggerAction {
         match number {
             0 => {
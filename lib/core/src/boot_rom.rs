@@ -15,44 +15,127 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
-use crate::utils::SerializableArray;
+use crate::device_type::DeviceType;
 use std::fs::File;
 use std::io;
 use std::io::Read;
+use std::path::Path;
 
-/// A data object containing a 256 byte boot ROM.
+
+/// Size of the boot ROM of DMG, MGB, SGB and SGB2 devices.
+pub const BOOT_ROM_SIZE_CLASSIC: usize = 256;
+
+/// Size of the boot ROM of GBC and GBA devices.
+pub const BOOT_ROM_SIZE_COLOR: usize = 2304;
+
+
+/// The layout of a boot ROM, which determines the size of the
+/// boot ROM and the address ranges it overlays the cartridge ROM.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BootRomLayout {
+    /// 256 bytes mapped into 0x0000 - 0x00ff, used by DMG, MGB, SGB and SGB2.
+    Classic,
+
+    /// 2304 bytes mapped into 0x0000 - 0x00ff and 0x0200 - 0x08ff, used by GBC and GBA.
+    /// The cartridge header in 0x0100 - 0x01ff remains visible while the boot ROM is active.
+    Color,
+}
+
+
+/// A data object containing the boot ROM of any supported device.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BootRom {
-    rom: Box<SerializableArray<u8, 256>>,
+    layout: BootRomLayout,
+    rom:    Vec<u8>,
+}
+
+
+impl BootRomLayout {
+    /// Get the boot ROM layout used by a specific device.
+    pub fn for_device(device: DeviceType) -> Self {
+        match device {
+            DeviceType::GameBoyDmg       => BootRomLayout::Classic,
+            DeviceType::GameBoyPocket    => BootRomLayout::Classic,
+            DeviceType::SuperGameBoy     => BootRomLayout::Classic,
+            DeviceType::SuperGameBoy2    => BootRomLayout::Classic,
+            DeviceType::GameBoyColor     => BootRomLayout::Color,
+            DeviceType::GameBoyAdvance   => BootRomLayout::Color,
+            DeviceType::GameBoyAdvanceSP => BootRomLayout::Color,
+        }
+    }
+
+    /// Get the boot ROM layout matching the size of a boot ROM image.
+    pub fn for_size(size: usize) -> Option<Self> {
+        match size {
+            BOOT_ROM_SIZE_CLASSIC => Some(BootRomLayout::Classic),
+            BOOT_ROM_SIZE_COLOR   => Some(BootRomLayout::Color),
+            _                     => None,
+        }
+    }
+
+    /// Get the size of a boot ROM with this layout.
+    pub fn get_size(&self) -> usize {
+        match self {
+            BootRomLayout::Classic => BOOT_ROM_SIZE_CLASSIC,
+            BootRomLayout::Color   => BOOT_ROM_SIZE_COLOR,
+        }
+    }
+
+    /// Checks whether an address is overlaid by the boot ROM with this layout.
+    pub fn is_mapped(&self, address: u16) -> bool {
+        match self {
+            BootRomLayout::Classic => matches!(address, 0x0000 ..= 0x00ff),
+            BootRomLayout::Color   => matches!(address, 0x0000 ..= 0x00ff | 0x0200 ..= 0x08ff),
+        }
+    }
 }
 
 
 impl BootRom {
     /// Load a boot rom from a file.
-    pub fn load_file(filepath: &String) -> Result<BootRom, io::Error> {
-        let mut file = File::open(filepath)?;
-        let metadata  = file.metadata()?;
-        let file_size = metadata.len();
+    pub fn load_file<P: AsRef<Path>>(filepath: P) -> Result<BootRom, io::Error> {
+        let filepath   = filepath.as_ref();
+        let mut file   = File::open(filepath)?;
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer)?;
+
+        Self::load_from_bytes(buffer).map_err(|e| {
+            io::Error::new(e.kind(), format!("{}: {}", filepath.display(), e))
+        })
+    }
 
+    /// Creates a boot rom from its binary data.
+    /// The layout of the boot ROM will be determined by the size of the data.
+    pub fn load_from_bytes(data: Vec<u8>) -> Result<BootRom, io::Error> {
         // fail when the boot rom image has an unexpected size
-        if file_size != 256 {
+        let Some(layout) = BootRomLayout::for_size(data.len()) else {
             let msg = format!(
-                "Unexpected Boot ROM size: {} is {} bytes, expected: 256 bytes",
-                *filepath,
-                file_size
+                "Unexpected Boot ROM size: {} bytes, expected: {} or {} bytes",
+                data.len(),
+                BOOT_ROM_SIZE_CLASSIC,
+                BOOT_ROM_SIZE_COLOR
             );
 
-            return Err(io::Error::new(io::ErrorKind::Other, msg));
-        }
-
-        let mut buffer = [0u8; 256];
-        file.read_exact(&mut buffer)?;
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        };
 
         Ok(BootRom {
-            rom: Box::new(buffer.into())
+            layout,
+            rom: data,
         })
     }
 
+    /// Get the layout of this boot ROM.
+    pub fn get_layout(&self) -> BootRomLayout {
+        self.layout
+    }
+
+    /// Checks whether an address is overlaid by this boot ROM.
+    pub fn is_mapped(&self, address: u16) -> bool {
+        self.layout.is_mapped(address)
+    }
+
     /// Get data from the boot ROM.
     pub fn read(&self, address: u16) -> u8 {
         self.rom[address as usize]
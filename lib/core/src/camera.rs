@@ -0,0 +1,153 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::ppu::ppu::LcdBuffer;
+
+
+/// The width of the image captured by the camera sensor.
+pub const CAMERA_IMAGE_WIDTH: usize = 128;
+
+/// The height of the image captured by the camera sensor.
+pub const CAMERA_IMAGE_HEIGHT: usize = 112;
+
+
+/// A grayscale image captured by the camera sensor, stored row by row.
+/// Each pixel contains the brightness from 0 (black) to 255 (white).
+pub type CameraImage = [u8; CAMERA_IMAGE_WIDTH * CAMERA_IMAGE_HEIGHT];
+
+
+/// Provides the images seen by the sensor of a Game Boy Camera cartridge.
+/// Frontends or tests implement this to feed images from a file,
+/// a webcam or any other source into the emulated camera.
+pub trait CameraImageSource : Send {
+    /// Called each time the camera takes a picture.
+    /// The source fills the image with the brightness of each pixel the sensor sees.
+    fn capture(&mut self, image: &mut CameraImage);
+}
+
+
+/// An image source generating a static test pattern,
+/// which is a diagonal gradient from black in the top left
+/// to white in the bottom right corner.
+/// This is used when no other image source is connected.
+#[derive(Default)]
+pub struct TestPatternImageSource;
+
+
+/// An image source which always provides the same image.
+pub struct StaticImageSource {
+    image: Box<CameraImage>,
+}
+
+
+impl CameraImageSource for TestPatternImageSource {
+    fn capture(&mut self, image: &mut CameraImage) {
+        let max_distance = CAMERA_IMAGE_WIDTH + CAMERA_IMAGE_HEIGHT - 2;
+
+        for y in 0..CAMERA_IMAGE_HEIGHT {
+            for x in 0..CAMERA_IMAGE_WIDTH {
+                image[y * CAMERA_IMAGE_WIDTH + x] = ((x + y) * 255 / max_distance) as u8;
+            }
+        }
+    }
+}
+
+
+impl StaticImageSource {
+    /// Creates an image source from an image with the size of the camera sensor.
+    pub fn new(image: CameraImage) -> Self {
+        Self {
+            image: Box::new(image),
+        }
+    }
+
+
+    /// Creates an image source filled with a single brightness value.
+    pub fn with_brightness(brightness: u8) -> Self {
+        Self::new([brightness; CAMERA_IMAGE_WIDTH * CAMERA_IMAGE_HEIGHT])
+    }
+
+
+    /// Creates an image source from grayscale pixels of any size, stored row by row.
+    /// The image will be scaled to cover the whole sensor, cutting off the
+    /// parts of the image exceeding the aspect ratio of the sensor.
+    pub fn from_grayscale(pixels: &[u8], width: usize, height: usize) -> Self {
+        if width == 0 || height == 0 || pixels.len() < width * height {
+            return Self::with_brightness(0xff);
+        }
+
+        Self::new(scale_to_sensor(width, height, |x, y| pixels[y * width + x]))
+    }
+
+
+    /// Creates an image source from the current content of an emulator display.
+    pub fn from_lcd_buffer(lcd: &LcdBuffer) -> Self {
+        let width  = lcd.get_width()  as usize;
+        let height = lcd.get_height() as usize;
+
+        Self::new(scale_to_sensor(width, height, |x, y| {
+            let color = lcd.get_pixel(x as u32, y as u32);
+            ((color.r as u32 * 299 + color.g as u32 * 587 + color.b as u32 * 114) / 1000) as u8
+        }))
+    }
+
+
+    /// Replaces the image provided by this source.
+    pub fn set_image(&mut self, image: CameraImage) {
+        *self.image = image;
+    }
+
+
+    /// Get the image provided by this source.
+    pub fn get_image(&self) -> &CameraImage {
+        &self.image
+    }
+}
+
+
+impl CameraImageSource for StaticImageSource {
+    fn capture(&mut self, image: &mut CameraImage) {
+        image.copy_from_slice(self.image.as_slice());
+    }
+}
+
+
+/// Scales an image of any size to the size of the camera sensor by picking the nearest pixel.
+/// The image is scaled to cover the whole sensor, keeping its center.
+fn scale_to_sensor(width: usize, height: usize, get_pixel: impl Fn(usize, usize) -> u8) -> CameraImage {
+    let mut image = [0u8; CAMERA_IMAGE_WIDTH * CAMERA_IMAGE_HEIGHT];
+
+    // use the smaller scale of both axes, so the image covers the whole sensor
+    let scale = f32::min(
+        width  as f32 / CAMERA_IMAGE_WIDTH  as f32,
+        height as f32 / CAMERA_IMAGE_HEIGHT as f32,
+    );
+
+    let offset_x = (width  as f32 - CAMERA_IMAGE_WIDTH  as f32 * scale) / 2.0;
+    let offset_y = (height as f32 - CAMERA_IMAGE_HEIGHT as f32 * scale) / 2.0;
+
+    for y in 0..CAMERA_IMAGE_HEIGHT {
+        for x in 0..CAMERA_IMAGE_WIDTH {
+            let source_x = ((offset_x + (x as f32 + 0.5) * scale) as usize).min(width  - 1);
+            let source_y = ((offset_y + (y as f32 + 0.5) * scale) as usize).min(height - 1);
+
+            image[y * CAMERA_IMAGE_WIDTH + x] = get_pixel(source_x, source_y);
+        }
+    }
+
+    image
+}
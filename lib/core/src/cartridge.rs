@@ -18,12 +18,14 @@
 use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io;
-use std::io::Read;
+use std::io::{Read, Write};
 use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::mmu::mbc::MemoryBankController;
 use crate::mmu::memory_data::{MemoryData, MemoryDataDynamic};
+use crate::mmu::rtc::{RealTimeClock, RTC_FOOTER_SIZE, RTC_FOOTER_SIZE_LEGACY};
 use crate::utils::{as_hex_digit, SerializableBuffer};
 
 
@@ -31,6 +33,71 @@ pub const FILE_EXT_GB:  &str = "gb";
 pub const FILE_EXT_GBC: &str = "gbc";
 pub const FILE_EXT_RAM: &str = "sav";
 
+/// The size of the RAM of MBC6 cartridges.
+pub const MBC6_RAM_SIZE: usize = 32 * 1024;
+
+/// The size of the flash memory of MBC6 cartridges, which is stored behind their RAM.
+pub const MBC6_FLASH_SIZE: usize = 1024 * 1024;
+
+/// The size of the 93LC56 EEPROM used by MBC7 cartridges.
+pub const MBC7_EEPROM_SIZE: usize = 256;
+
+/// The size of the RAM of TAMA5 cartridges.
+pub const TAMA5_RAM_SIZE: usize = 32;
+
+/// The size of a single ROM bank.
+pub const ROM_BANK_SIZE: usize = 16 * 1024;
+
+/// The minimum size of a ROM, which needs to contain at least the cartridge header.
+pub const ROM_MIN_SIZE: usize = 0x0150;
+
+/// The logo each cartridge header needs to contain in order to pass the boot ROM's check.
+pub const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+
+/// Errors which may occur when loading a cartridge.
+#[derive(Debug)]
+pub enum CartridgeError {
+    /// Failed to read the ROM or RAM file.
+    Io(io::Error),
+
+    /// The ROM is too small to contain a cartridge header.
+    TooSmall {
+        size: usize,
+    },
+
+    /// The cartridge type in the header refers to a memory bank controller,
+    /// which is not supported by the emulator.
+    UnsupportedMapper(u8),
+
+    /// The size of the RAM data does not match the RAM size of the cartridge.
+    RamSizeMismatch {
+        expected: usize,
+        found:    usize,
+    },
+}
+
+
+/// The result of validating the cartridge header.
+/// An invalid header does not prevent a cartridge from being loaded,
+/// but the boot ROM of a real device would refuse to start it,
+/// if the logo or header checksum are invalid.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct HeaderValidation {
+    /// Whether the header contains the Nintendo logo expected by the boot ROM.
+    pub logo_valid: bool,
+
+    /// Whether the checksum of the header matches, which is checked by the boot ROM.
+    pub header_checksum_valid: bool,
+
+    /// Whether the checksum of the whole ROM matches.
+    /// This is not checked by a real device.
+    pub global_checksum_valid: bool,
+}
 
 
 /// Type of game boy color support
@@ -65,6 +132,10 @@ pub enum LicenseeCode {
 #[derive(Clone)]
 pub struct RomData {
     data: Vec<u8>,
+
+    /// The size of the ROM chip, which is the size of the data rounded up
+    /// to the next power of two. Addresses beyond are mirrored.
+    chip_size: usize,
 }
 
 
@@ -81,6 +152,7 @@ pub struct Cartridge {
     title: String,
     rom: RomData,
     ram: MemoryDataDynamic,
+    rtc: Option<RealTimeClock>,
 
     manufacturer_code: String,
     licensee_code: LicenseeCode,
@@ -96,6 +168,8 @@ pub struct Cartridge {
     supports_cgb: GameBoyColorSupport,
     supports_sgb: bool,
 
+    header_validation: HeaderValidation,
+
     has_ram: bool,
     has_timer: bool,
     has_battery: bool,
@@ -108,6 +182,7 @@ pub struct Cartridge {
 struct CartridgeSerdeHelper {
     rom: SerializableBuffer<u8>,
     ram: Option<SerializableBuffer<u8>>,
+    rtc: Option<RealTimeClock>,
 }
 
 
@@ -123,18 +198,40 @@ pub const ROM_OFFSET_ROM_SIZE:              usize = 0x0148;
 pub const ROM_OFFSET_RAM_SIZE:              usize = 0x0149;
 pub const ROM_OFFSET_DESTINATION_CODE:      usize = 0x014A;
 pub const ROM_OFFSET_OLD_LICENSEE_CODE:     usize = 0x014B;
+pub const ROM_OFFSET_HEADER_CHECKSUM:       usize = 0x014D;
+pub const ROM_OFFSET_GLOBAL_CHECKSUM:       usize = 0x014E;
+
 
+impl HeaderValidation {
+    /// Checks whether all checks of the header did pass.
+    pub fn is_valid(&self) -> bool {
+        self.logo_valid && self.header_checksum_valid && self.global_checksum_valid
+    }
+}
 
 
 impl RomData {
+    /// Creates the ROM data object. The data is expected to be at least
+    /// large enough to contain the cartridge header.
+    fn new(data: Vec<u8>) -> Self {
+        let chip_size = data.len().div_ceil(ROM_BANK_SIZE).max(2).next_power_of_two() * ROM_BANK_SIZE;
+
+        Self {
+            data,
+            chip_size,
+        }
+    }
+
     /// Get the ROM data.
     pub fn get_data(&self) -> &Vec<u8> {
         &self.data
     }
 
     /// Get the ROM data on a particular address.
+    /// Addresses beyond the size of the ROM chip are mirrored, while the area
+    /// of the chip not being covered by the ROM data reads as 0xff.
     pub fn get_at(&self, address: usize) -> u8 {
-        self.data[address]
+        self.data.get(address % self.chip_size).copied().unwrap_or(0xff)
     }
 
     /// Get a data slice out of the ROM data.
@@ -182,6 +279,35 @@ impl RomData {
 
         return String::new();
     }
+
+
+    /// Checks the logo and checksums of the header at a particular offset.
+    pub fn validate_header(&self, header_offset: usize) -> HeaderValidation {
+        let logo = self.get_slice(header_offset + ROM_OFFSET_LOGO_BITMAP .. header_offset + ROM_OFFSET_LOGO_BITMAP + NINTENDO_LOGO.len());
+
+        let header_checksum = self.get_slice(header_offset + ROM_OFFSET_TITLE_STRING .. header_offset + ROM_OFFSET_HEADER_CHECKSUM)
+            .map(compute_header_checksum)
+        ;
+
+        // the global checksum covers all bytes of the ROM, except the checksum itself
+        let global_checksum_offset = header_offset + ROM_OFFSET_GLOBAL_CHECKSUM;
+        let global_checksum = self.data.iter()
+            .enumerate()
+            .filter(|(index, _)| *index != global_checksum_offset && *index != global_checksum_offset + 1)
+            .fold(0u16, |checksum, (_, b)| checksum.wrapping_add(*b as u16))
+        ;
+
+        let expected_global_checksum = u16::from_be_bytes([
+            self.get_at(global_checksum_offset),
+            self.get_at(global_checksum_offset + 1),
+        ]);
+
+        HeaderValidation {
+            logo_valid:            logo == Some(&NINTENDO_LOGO[..]),
+            header_checksum_valid: header_checksum == Some(self.get_at(header_offset + ROM_OFFSET_HEADER_CHECKSUM)),
+            global_checksum_valid: global_checksum == expected_global_checksum,
+        }
+    }
 }
 
 
@@ -197,6 +323,34 @@ pub fn compute_checksum(data: &[u8]) -> u8 {
 }
 
 
+/// Compute the header checksum of the bytes from the title up to the mask ROM version,
+/// as it is checked by the boot ROM.
+pub fn compute_header_checksum(data: &[u8]) -> u8 {
+    let mut checksum = 0u8;
+
+    for b in data {
+        checksum = checksum.wrapping_sub(*b).wrapping_sub(1);
+    }
+
+    checksum
+}
+
+
+/// Computes a 64 bit FNV-1a hash of a block of data.
+/// This is used to create a fingerprint of a ROM,
+/// which identifies a game more reliable than its title.
+pub fn compute_hash(data: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+
+    for b in data {
+        hash ^= *b as u64;
+        hash  = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+
+    hash
+}
+
+
 /// Load a file into a byte buffer.
 fn load_file(file_path: &Path) -> io::Result<Vec<u8>> {
     let mut file   = File::open(file_path)?;
@@ -209,12 +363,21 @@ fn load_file(file_path: &Path) -> io::Result<Vec<u8>> {
 }
 
 
+/// Get the current time as seconds since the unix epoch.
+pub(crate) fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+
 impl Cartridge {
     /// Load a cartridge from a ROM file.
     /// If a RAM file with the same name exists, it tries to load it as well.
     /// Failing to load the RAM file will cause an error, but if no RAM file
     /// exists, the cartridge will be loaded with uninitialized RAM.
-    pub fn load_files_with_default_ram(rom_file: &Path) -> io::Result<Cartridge> {
+    pub fn load_files_with_default_ram(rom_file: &Path) -> Result<Cartridge, CartridgeError> {
         let ram_file = rom_file.with_extension(FILE_EXT_RAM);
 
         Self::load_files(
@@ -232,22 +395,27 @@ impl Cartridge {
 
 
     /// Loads a cartridge from a ROM file.
-    pub fn load_file(rom_file: &Path) -> io::Result<Cartridge> {
+    pub fn load_file(rom_file: &Path) -> Result<Cartridge, CartridgeError> {
         Self::load_files(rom_file, None)
     }
 
 
     /// Loads a cartridge and it's RAM image from files.
-    pub fn load_files(rom_file: &Path, ram_file: Option<&Path>) -> io::Result<Cartridge> {
+    pub fn load_files(rom_file: &Path, ram_file: Option<&Path>) -> Result<Cartridge, CartridgeError> {
         // load the cartridge from the ROM file
         let rom_data      = load_file(rom_file)?;
         let mut cartridge = Self::load_from_bytes(rom_data, None)?;
 
-        // when the cartridge has battery powered RAM support, load the RAM file
-        if cartridge.has_ram && cartridge.has_battery {
+        // when the cartridge has battery powered RAM or clock, load the RAM file
+        if cartridge.has_battery {
             if let Some(ram_file) = ram_file {
-                let ram_data = load_file(ram_file)?;
-                cartridge.ram.read_from_bytes(ram_data.as_slice())?;
+                let ram_data  = load_file(ram_file)?;
+                let timestamp = cartridge.read_save_data(ram_data.as_slice())?;
+
+                // apply the time passed since the file was saved to the clock
+                if let (Some(rtc), Some(timestamp)) = (&mut cartridge.rtc, timestamp) {
+                    rtc.advance_seconds(current_timestamp().saturating_sub(timestamp));
+                }
             }
         }
 
@@ -259,10 +427,14 @@ impl Cartridge {
 
 
     /// Loads a cartridge and optionally its RAM from a byte buffer.
-    pub fn load_from_bytes(rom_data: Vec<u8>, ram_data: Option<Vec<u8>>) -> io::Result<Cartridge> {
-        let rom = RomData {
-            data: rom_data,
-        };
+    /// The ROM size in the header will be ignored, instead the ROM will be mirrored
+    /// like a ROM chip with the size of the data rounded up to the next power of two.
+    pub fn load_from_bytes(rom_data: Vec<u8>, ram_data: Option<Vec<u8>>) -> Result<Cartridge, CartridgeError> {
+        if rom_data.len() < ROM_MIN_SIZE {
+            return Err(CartridgeError::TooSmall { size: rom_data.len() });
+        }
+
+        let rom = RomData::new(rom_data);
 
         let cgb_flag_value = rom.data[ROM_OFFSET_FLAG_CGB];
         let supports_cgb = match cgb_flag_value {
@@ -274,12 +446,25 @@ impl Cartridge {
         let sgb_flag_value = rom.data[ROM_OFFSET_FLAG_SGB];
         let supports_sgb = sgb_flag_value == 0x03;
 
-        let rom_size_type  = rom.data[ROM_OFFSET_ROM_SIZE];
-        let rom_bank_count = 2 << rom_size_type;
-        let rom_size       = (16 * 1024) * rom_bank_count as usize;
+        // MMM01 multi cartridges start with a menu in the last 32kiB of the ROM,
+        // whose header describes the whole cartridge
+        let header_offset = Self::find_mmm01_menu_header(&rom).unwrap_or(0);
 
-        let ram_size_type = rom.data[ROM_OFFSET_RAM_SIZE];
+        let rom_size       = rom.chip_size;
+        let rom_bank_count = (rom_size / ROM_BANK_SIZE) as u32;
+
+        let rom_type      = rom.data[header_offset + ROM_OFFSET_ROM_TYPE];
+        let ram_size_type = rom.data[header_offset + ROM_OFFSET_RAM_SIZE];
         let (ram_bank_count, ram_size) = match ram_size_type {
+            // MBC6 stores its flash memory behind the RAM
+            _ if rom_type == 0x20 => (4, MBC6_RAM_SIZE + MBC6_FLASH_SIZE),
+
+            // MBC7 stores its data in a serial EEPROM instead of RAM
+            _ if rom_type == 0x22 => (1, MBC7_EEPROM_SIZE),
+
+            // TAMA5 has a fixed amount of RAM, which is not described by the header
+            _ if rom_type == 0xFD => (1, TAMA5_RAM_SIZE),
+
             0x00 => ( 0,   0),
             0x01 => ( 1,   2 * 1024),
             0x02 => ( 1,   8 * 1024),
@@ -289,8 +474,6 @@ impl Cartridge {
             _    => ( 0,   0),
         };
 
-        let rom_type = rom.data[ROM_OFFSET_ROM_TYPE];
-
         let mbc = match rom_type {
             0x01..=0x03 => if Self::check_is_mbc1m_multi_cart(&rom) {
                                 MemoryBankController::MBC1M
@@ -298,29 +481,38 @@ impl Cartridge {
                            else {
                                 MemoryBankController::MBC1
                            },
+            0x00        => MemoryBankController::None,
+            0x08..=0x09 => MemoryBankController::None,
             0x05..=0x06 => MemoryBankController::MBC2,
+            0x0B..=0x0D => MemoryBankController::MMM01,
             0x0F..=0x13 => MemoryBankController::MBC3,
             0x19..=0x1E => MemoryBankController::MBC5,
             0x20        => MemoryBankController::MBC6,
             0x22        => MemoryBankController::MBC7,
-            _           => MemoryBankController::None,
+            0xFC        => MemoryBankController::PocketCamera,
+            0xFD        => MemoryBankController::TAMA5,
+            0xFE        => MemoryBankController::HuC3,
+            0xFF        => MemoryBankController::HuC1,
+            _           => return Err(CartridgeError::UnsupportedMapper(rom_type)),
         };
 
-        let has_ram = match rom_type {
+        // cartridges without a RAM size in their header have no RAM chip
+        let has_ram = ram_size != 0 && match rom_type {
             0x02 | 0x03 | 0x08 | 0x09 | 0x0C | 0x0D => true,
             0x10 | 0x12 | 0x13 | 0x1A | 0x1B | 0x1D | 0x1E => true,
-            0x22 | 0xFF => true,
+            0x20 | 0x22 | 0xFC..=0xFF => true,
             _ => false,
         };
 
         let has_timer = match rom_type {
-            0x0F | 0x10 => true,
+            0x0F | 0x10 | 0xFD | 0xFE => true,
             _ => false,
         };
 
         let has_battery = match rom_type {
             0x03 | 0x06 | 0x09 | 0x0D | 0x0F => true,
-            0x10 | 0x13 | 0x1B | 0x1E | 0x22 | 0xFF => true,
+            0x10 | 0x13 | 0x1B | 0x1E | 0x20 | 0x22 => true,
+            0xFC..=0xFF => true,
             _ => false,
         };
 
@@ -330,15 +522,15 @@ impl Cartridge {
         };
 
         // allocate RAM banks for this cartridge
-        let mut ram = MemoryDataDynamic::alloc(ram_size);
+        let ram = MemoryDataDynamic::alloc(ram_size);
 
-        // if RAM is available and powered by a battery, it's persistent
-        // and we can try to load the RAM image from a file.
-        if has_ram && has_battery {
-            if let Some(ram_data_vec) = ram_data {
-                ram.read_from_bytes(&ram_data_vec)?;
-            }
+        // cartridges with a timer contain a real time clock chip
+        let rtc = if has_timer {
+            Some(RealTimeClock::new())
         }
+        else {
+            None
+        };
 
         let licensee_code_old = rom.data[ROM_OFFSET_OLD_LICENSEE_CODE];
 
@@ -358,7 +550,7 @@ impl Cartridge {
             }
         };
 
-        let cartridge = Cartridge {
+        let mut cartridge = Cartridge {
             source_file: None,
 
             title: rom.read_title(),
@@ -377,6 +569,8 @@ impl Cartridge {
             supports_cgb,
             supports_sgb,
 
+            header_validation: rom.validate_header(header_offset),
+
             has_ram,
             has_timer,
             has_battery,
@@ -384,12 +578,82 @@ impl Cartridge {
 
             rom,
             ram,
+            rtc,
         };
 
+        // if RAM or clock are powered by a battery, they are persistent
+        // and we can try to restore their state from the given data.
+        if has_battery {
+            if let Some(ram_data_vec) = ram_data {
+                cartridge.read_save_data(&ram_data_vec)?;
+            }
+        }
+
         Ok(cartridge)
     }
 
 
+    /// Restores the battery powered RAM and clock from the data of a save file.
+    /// The save file contains the RAM image, followed by the state of the real
+    /// time clock, if the cartridge has one. When the clock's state is present,
+    /// the timestamp when the file was saved will be returned.
+    fn read_save_data(&mut self, data: &[u8]) -> Result<Option<u64>, CartridgeError> {
+        let ram_size  = if self.has_ram { self.ram.size() } else { 0 };
+        let rtc_size  = data.len().saturating_sub(ram_size);
+        let mut timestamp = None;
+
+        if let Some(rtc) = &mut self.rtc {
+            match rtc_size {
+                RTC_FOOTER_SIZE | RTC_FOOTER_SIZE_LEGACY => {
+                    if let Some((loaded_rtc, saved_at)) = RealTimeClock::from_footer_bytes(&data[ram_size..]) {
+                        *rtc      = loaded_rtc;
+                        timestamp = Some(saved_at);
+                    }
+                }
+
+                // missing clock data will be accepted and leave the clock in its initial state
+                _ => { }
+            }
+        }
+
+        let ram_data = if timestamp.is_some() { &data[..ram_size] } else { data };
+
+        if self.has_ram {
+            if ram_data.len() != ram_size {
+                return Err(CartridgeError::RamSizeMismatch {
+                    expected:  ram_size,
+                    found:     ram_data.len(),
+                });
+            }
+
+            self.ram.read_from_bytes(ram_data)?;
+        }
+
+        Ok(timestamp)
+    }
+
+
+    /// Get the data to be stored in a save file, containing the battery powered
+    /// RAM image, followed by the state of the real time clock, if any.
+    /// The timestamp will be stored along with the clock, so the time passed
+    /// until the file will be loaded again can be applied to the clock.
+    /// For MBC6 cartridges, the RAM image is followed by the 1MiB flash memory,
+    /// so their save files contain [MBC6_RAM_SIZE] + [MBC6_FLASH_SIZE] bytes.
+    pub fn get_save_data(&self, timestamp: u64) -> Vec<u8> {
+        let mut data = Vec::new();
+
+        if self.has_ram {
+            data.extend_from_slice(self.ram.as_slice());
+        }
+
+        if let Some(rtc) = &self.rtc {
+            data.extend_from_slice(&rtc.to_footer_bytes(timestamp));
+        }
+
+        data
+    }
+
+
     /// Checks if a ROM is a MBC1 multi cart ROM
     fn check_is_mbc1m_multi_cart(rom: &RomData) -> bool {
         // A ROM will be considered as 'multi cartridge' if it contains a cartridge header with
@@ -405,6 +669,26 @@ impl Cartridge {
     }
 
 
+    /// Checks if a ROM is a MMM01 multi cart ROM and returns the offset of the menu's header.
+    fn find_mmm01_menu_header(rom: &RomData) -> Option<usize> {
+        // The menu of a MMM01 multi cartridge is located in the last 32kiB of the ROM,
+        // while the header at the beginning of the ROM usually belongs to the first game.
+        let menu_offset = rom.data.len().checked_sub(0x8000)?;
+        let rom_type    = *rom.data.get(menu_offset + ROM_OFFSET_ROM_TYPE)?;
+
+        match rom_type {
+            0x0B..=0x0D if menu_offset != 0 => Some(menu_offset),
+            _                               => None,
+        }
+    }
+
+
+    /// Get the result of validating the cartridge header.
+    pub fn get_header_validation(&self) -> &HeaderValidation {
+        &self.header_validation
+    }
+
+
     /// Get the source file of this cartridge, if any.
     /// If the cartridge was loaded from a file, this is the source file where it was loaded from.
     pub fn get_source_file(&self) -> Option<&PathBuf> {
@@ -426,12 +710,23 @@ impl Cartridge {
         &mut self.ram
     }
 
-    /// Saves the RAM to a file, if the cartridge has battery powered RAM.
+    /// Get the real time clock of this cartridge, if any.
+    pub fn get_rtc(&self) -> Option<&RealTimeClock> {
+        self.rtc.as_ref()
+    }
+
+    /// Get the mutable real time clock of this cartridge, if any.
+    pub fn get_rtc_mut(&mut self) -> Option<&mut RealTimeClock> {
+        self.rtc.as_mut()
+    }
+
+    /// Saves the RAM to a file, if the cartridge has battery powered RAM or clock.
     pub fn save_ram_to_file_if_any(&self) -> io::Result<()> {
-        if self.has_ram && self.has_battery {
+        if (self.has_ram || self.has_timer) && self.has_battery {
             if let Some(rom_file) = &self.source_file {
                 let ram_file = rom_file.with_extension(FILE_EXT_RAM);
-                self.get_ram().save_to_file(&ram_file)?;
+                let mut file = File::create(ram_file)?;
+                file.write_all(&self.get_save_data(current_timestamp()))?;
             }
             else {
                 return Err(io::Error::new(
@@ -461,6 +756,11 @@ impl Cartridge {
         }
     }
 
+    /// Computes a hash over the whole ROM data to identify the game.
+    pub fn compute_rom_hash(&self) -> u64 {
+        compute_hash(self.rom.get_data())
+    }
+
     /// get the game's manufacturer code
     pub fn get_manufacturer_code(&self) -> &String {
         &self.manufacturer_code
@@ -537,13 +837,20 @@ impl Cartridge {
 
 
 impl TryFrom<CartridgeSerdeHelper> for Cartridge {
-    type Error = io::Error;
+    type Error = CartridgeError;
 
     fn try_from(helper: CartridgeSerdeHelper) -> Result<Self, Self::Error> {
-        Cartridge::load_from_bytes(
+        let mut cartridge = Cartridge::load_from_bytes(
             helper.rom.into(), 
             helper.ram.map(|ram| ram.into())
-        )
+        )?;
+
+        // restore the clock state, if any
+        if let (Some(rtc), Some(helper_rtc)) = (&mut cartridge.rtc, helper.rtc) {
+            *rtc = helper_rtc;
+        }
+
+        Ok(cartridge)
     }
 }
 
@@ -557,12 +864,40 @@ impl From<Cartridge> for CartridgeSerdeHelper {
             }
             else {
                 None
-            }
+            },
+            rtc: cart.rtc,
+        }
+    }
+}
+
+
+impl Display for CartridgeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CartridgeError::Io(e) => write!(f, "I/O error: {e}"),
+            CartridgeError::TooSmall { size } => write!(f, "ROM is too small with only {size} bytes"),
+            CartridgeError::UnsupportedMapper(rom_type) => write!(f, "Unsupported cartridge type {rom_type:02x}"),
+
+            CartridgeError::RamSizeMismatch { expected, found } => write!(
+                f,
+                "RAM data has a size of {found} bytes, but the cartridge has {expected} bytes of RAM"
+            ),
         }
     }
 }
 
 
+impl std::error::Error for CartridgeError {
+}
+
+
+impl From<io::Error> for CartridgeError {
+    fn from(value: io::Error) -> Self {
+        CartridgeError::Io(value)
+    }
+}
+
+
 impl Display for LicenseeCode {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
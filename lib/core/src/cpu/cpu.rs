@@ -17,10 +17,12 @@
 
 use std::fmt::{Display, Formatter};
 
+use crate::cpu::interrupts::Interrupt;
 use crate::cpu::opcode::{Instruction, OpCode};
 use crate::cpu::opcodes::{OPCODE_TABLE, OPCODE_TABLE_EXTENDED};
 use crate::gameboy::Clock;
 use crate::mmu::mmu::Mmu;
+use crate::ppu::ppu::OamCorruption;
 use crate::utils::{change_bit, get_bit, to_u16, to_u8};
 
 
@@ -82,6 +84,10 @@ pub enum HaltState {
 
     /// The CPU was suspended by the HALT command.
     Halt,
+
+    /// The CPU was locked up by executing an illegal opcode
+    /// and won't resume until the device gets reset.
+    Locked,
 }
 
 /// An object representing the gameboy's CPU
@@ -109,6 +115,11 @@ pub struct Cpu {
 
     /// The state whether the CPU was suspended by the HALT command.
     halt: HaltState,
+
+    /// Set when HALT was executed while interrupts were disabled, but pending.
+    /// In this case the CPU fails to increment the instruction pointer after
+    /// reading the next opcode, so the same byte will be read twice.
+    halt_bug: bool,
 }
 
 
@@ -189,7 +200,8 @@ impl HaltState {
     pub fn is_cpu_running(&self) -> bool {
         match self {
             HaltState::Running => true,
-            HaltState::Halt => false,
+            HaltState::Halt    => false,
+            HaltState::Locked  => false,
         }
     }
 }
@@ -209,6 +221,8 @@ impl Cpu {
 
             ime:  ImeState::Disabled,
             halt: HaltState::Running,
+
+            halt_bug: false,
         }
     }
 
@@ -217,6 +231,22 @@ impl Cpu {
         self.halt.is_cpu_running()
     }
 
+    /// Checks whether the CPU was locked up by executing an illegal opcode.
+    pub fn is_locked(&self) -> bool {
+        matches!(self.halt, HaltState::Locked)
+    }
+
+    /// Get the current HALT state of the CPU.
+    pub fn get_halt_state(&self) -> HaltState {
+        self.halt
+    }
+
+    /// Sets the HALT state of the CPU directly, without any side effects
+    /// of the HALT instruction. Used when restoring the state of a device.
+    pub fn set_halt_state(&mut self, state: HaltState) {
+        self.halt = state;
+    }
+
     /// Let the CPU process their data.
     /// This function takes the amount of ticks to be processed.
     pub fn update(&mut self, cycles: Clock) {
@@ -239,7 +269,8 @@ impl Cpu {
     }
 
     /// Handles any pending interrupts.
-    pub fn handle_interrupts(&mut self) -> Option<Clock> {
+    /// Returns the interrupt being dispatched and the number of cycles consumed, if any.
+    pub fn handle_interrupts(&mut self) -> Option<(Interrupt, Clock)> {
         match self.ime {
             ImeState::Enabled => {
                 let cpu_state = &mut self.get_mmu_mut().get_peripherals_mut().interrupts;
@@ -252,7 +283,7 @@ impl Cpu {
                     self.call_addr(interrupt.address());
 
                     // stop handling other interrupts
-                    return Some(20);
+                    return Some((interrupt, 20));
                 }
             },
 
@@ -317,7 +348,14 @@ impl Cpu {
                 // and revert the program counter to repeat the HALT instruction
                 // after interrupts were handled.
                 self.ime = ImeState::Enabled;
-                self.instruction_pointer -= 1;
+                self.instruction_pointer = self.instruction_pointer.wrapping_sub(1);
+            }
+
+            // when interrupts are disabled, but already pending, HALT will be left
+            // immediately and the CPU fails to increment the instruction pointer
+            // when reading the next opcode.
+            ImeState::Disabled if self.get_mmu().get_peripherals().interrupts.has_interrupts_pending() => {
+                self.halt_bug = true;
             }
 
             _ => {
@@ -326,6 +364,12 @@ impl Cpu {
         }
     }
 
+    /// Locks up the CPU, like it happens when executing an illegal opcode.
+    /// The CPU will stop executing any instructions and can't be woken up by interrupts.
+    pub fn lock_up(&mut self) {
+        self.halt = HaltState::Locked;
+    }
+
 
     /// Get access to the memory unit linked to the CPU.
     pub fn get_mmu(&self) -> &Mmu {
@@ -341,7 +385,7 @@ impl Cpu {
     /// Fetches the next opcode on the current location of the instruction pointer.
     /// The instruction pointer will be forwarded to the next instruction.
     pub fn fetch_next_opcode(&mut self) -> &'static OpCode {
-        let opcode_byte = self.fetch_u8();
+        let opcode_byte = self.fetch_opcode_byte();
         if opcode_byte != 0xCB {
             &OPCODE_TABLE[opcode_byte as usize]
         }
@@ -351,6 +395,19 @@ impl Cpu {
         }
     }
 
+    /// Fetches the opcode byte on the current location of the instruction pointer.
+    /// After the HALT bug was triggered, the instruction pointer will not be
+    /// forwarded once, so the same byte will be read again afterwards.
+    fn fetch_opcode_byte(&mut self) -> u8 {
+        if self.halt_bug {
+            self.halt_bug = false;
+            self.get_next_byte()
+        }
+        else {
+            self.fetch_u8()
+        }
+    }
+
     /// Fetches the next instruction on the current location of the instruction pointer.
     /// The instruction pointer will be forwarded to the next instruction.
     pub fn fetch_next_instruction(&mut self) -> Instruction {
@@ -364,20 +421,20 @@ impl Cpu {
             opcode_id,
             opcode_address,
             arg: [
-                self.mmu.read_u8(self.instruction_pointer),
-                self.mmu.read_u8(self.instruction_pointer.wrapping_add(1))
+                self.mmu.peek_u8(self.instruction_pointer),
+                self.mmu.peek_u8(self.instruction_pointer.wrapping_add(1))
             ]
         }
     }
 
     /// Get the next byte on the current location of the instruction pointer, without moving it.
     pub fn get_next_byte(&self) -> u8 {
-        self.mmu.read_u8(self.instruction_pointer)
+        self.mmu.peek_u8(self.instruction_pointer)
     }
 
     /// Get the next byte relative to the current location of the instruction pointer, without moving it.
     pub fn get_next_byte_at(&self, offset: u16) -> u8 {
-        self.mmu.read_u8(self.instruction_pointer + offset)
+        self.mmu.peek_u8(self.instruction_pointer.wrapping_add(offset))
     }
 
     /// Get the next i8 value on the current location of the instruction pointer, without moving it.
@@ -401,7 +458,7 @@ impl Cpu {
     /// The instruction pointer will be forwarded to the next instruction.
     pub fn fetch_u8(&mut self) -> u8 {
         let value = self.get_next_byte();
-        self.instruction_pointer += 1;
+        self.instruction_pointer = self.instruction_pointer.wrapping_add(1);
         value
     }
 
@@ -427,10 +484,19 @@ impl Cpu {
 
     /// Pushes a 8bit value on the stack, moving the stack pointer.
     pub fn push_u8(&mut self, value: u8) {
+        self.trigger_oam_bug(self.stack_pointer, OamCorruption::Write);
         self.stack_pointer = self.stack_pointer.wrapping_sub(1);
         self.mmu.write_u8(self.stack_pointer, value);
     }
 
+    /// Triggers the OAM corruption bug when a 16 bit register containing
+    /// an address within OAM is incremented or decremented.
+    pub fn trigger_oam_bug(&mut self, address: u16, corruption: OamCorruption) {
+        if (0xfe00 ..= 0xfeff).contains(&address) {
+            self.mmu.get_peripherals_mut().ppu.trigger_oam_bug(corruption);
+        }
+    }
+
     /// Pushes a 16bit value on the stack, moving the stack pointer.
     pub fn push_u16(&mut self, value: u16) {
         let (high, low) = to_u8(value);
@@ -440,6 +506,7 @@ impl Cpu {
 
     /// Pops a 8bit value from the stack, moving the stack pointer.
     pub fn pop_u8(&mut self) -> u8 {
+        self.trigger_oam_bug(self.stack_pointer, OamCorruption::ReadWithIncDec);
         let value = self.mmu.read_u8(self.stack_pointer);
         self.stack_pointer = self.stack_pointer.wrapping_add(1);
         value
@@ -496,13 +563,9 @@ impl Cpu {
     }
 
     /// Moves the instruction pointer relative to it's current position.
+    /// Like on the real device, the address wraps around at the end of the address space.
     pub fn jump_relative(&mut self, offset: i16) {
-        if offset < 0 {
-            self.instruction_pointer -= (-offset) as u16;
-        }
-        else {
-            self.instruction_pointer += offset as u16;
-        }
+        self.instruction_pointer = self.instruction_pointer.wrapping_add_signed(offset);
     }
 
     /// Moves the instruction pointer to a fixed location.
@@ -299,6 +299,26 @@ impl Instruction {
     }
 
 
+    /// Get the address a jump or call instruction with an immediate target
+    /// will branch to, or `None` for any other instruction.
+    pub fn get_branch_target(&self) -> Option<u16> {
+        match self.opcode_id {
+            // JR with a relative offset from the following instruction
+            0x18 | 0x20 | 0x28 | 0x30 | 0x38 => {
+                let next_address = self.opcode_address.wrapping_add(self.get_instruction_length());
+                Some(next_address.wrapping_add_signed(self.arg[0] as i8 as i16))
+            }
+
+            // JP and CALL with an absolute address
+            0xc2 | 0xc3 | 0xca | 0xd2 | 0xda | 0xc4 | 0xcc | 0xcd | 0xd4 | 0xdc => {
+                Some(to_u16(self.arg[1], self.arg[0]))
+            }
+
+            _ => None,
+        }
+    }
+
+
     /// Get the replacement string for an argument placeholder.
     pub fn resolve_argument(&self, arg: &str) -> String {
         let arg0 = self.arg[0];
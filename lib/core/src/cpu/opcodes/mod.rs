@@ -20,7 +20,7 @@ mod opcodes_control;
 mod opcodes_jump;
 mod opcodes_ld;
 
-use crate::cpu::opcode::OpCode;
+use crate::cpu::opcode::{OpCode, OpCodeResult};
 
 use crate::cpu::opcodes::opcodes_arithmetic::*;
 use crate::cpu::opcodes::opcodes_arithmetic::inc::*;
@@ -43,25 +43,29 @@ use crate::cpu::opcodes::opcodes_ld::*;
 
 
 /// Represents an invalid opcode.
+/// Executing it locks up the CPU, like it happens on the real hardware.
 pub static OPCODE_INVALID: OpCode = OpCode {
     name: "[INVALID]",
     bytes: 1,
     cycles_ahead: 0,
-    cycles: 0,
-    proc: |_ctx, _gb| {
-        panic!();
+    cycles: 4,
+    proc: |gb, _ctx| {
+        gb.cpu.lock_up();
+        OpCodeResult::Done
     }
 };
 
 /// Represents an opcode which has no assigned functionality.
 /// It's not expected to find one of those in any ROM binary.
+/// Executing it locks up the CPU, like it happens on the real hardware.
 pub static OPCODE_UNASSIGNED: OpCode = OpCode {
     name: "[NOT ASSIGNED]",
     bytes: 1,
     cycles_ahead: 0,
-    cycles: 0,
-    proc: |_ctx, _gb| {
-        panic!();
+    cycles: 4,
+    proc: |gb, _ctx| {
+        gb.cpu.lock_up();
+        OpCodeResult::Done
     }
 };
 
@@ -16,6 +16,7 @@
  */
 
 use crate::cpu::cpu::{CpuFlag, RegisterR16, RegisterR8};
+use crate::ppu::ppu::OamCorruption;
 use crate::gameboy::GameBoy;
 use crate::utils::{carrying_add_u16, carrying_add_u8, carrying_sub_u8};
 use crate::cpu::opcode::{opcode, OpCodeContext, OpCodeResult};
@@ -71,6 +72,7 @@ pub mod inc {
     /// r16 <- r16 + 1
     fn increment_r16(gb: &mut GameBoy, r16: RegisterR16) {
         let value  = gb.cpu.get_r16(r16);
+        gb.cpu.trigger_oam_bug(value, OamCorruption::Write);
         let result = increment_u16v(gb, value);
         gb.cpu.set_r16(r16, result);
     }
@@ -121,6 +123,7 @@ pub mod inc {
     // INC SP
     opcode!(inc_sp, [gb] {
         let sp_old = gb.cpu.get_stack_pointer();
+        gb.cpu.trigger_oam_bug(sp_old, OamCorruption::Write);
         let sp_new = sp_old.wrapping_add(1);
         gb.cpu.set_stack_pointer(sp_new);
     });
@@ -161,6 +164,7 @@ pub mod dec {
     /// r16 <- r16 - 1
     fn decrement_r16(gb: &mut GameBoy, r16: RegisterR16) {
         let value  = gb.cpu.get_r16(r16);
+        gb.cpu.trigger_oam_bug(value, OamCorruption::Write);
         let result = decrement_u16v(gb, value);
         gb.cpu.set_r16(r16, result);
     }
@@ -211,6 +215,7 @@ pub mod dec {
     // DEC SP
     opcode!(dec_sp, [gb] {
         let sp_old = gb.cpu.get_stack_pointer();
+        gb.cpu.trigger_oam_bug(sp_old, OamCorruption::Write);
         let sp_new = sp_old.wrapping_sub(1);
         gb.cpu.set_stack_pointer(sp_new);
     });
@@ -1,5 +1,5 @@
 /*
- * Copyright (C) 2022-2023 by Christian Fischer
+ * Copyright (C) 2022-2024 by Christian Fischer
  *
  * This program is free software: you can redistribute it and/or modify
  * it under the terms of the GNU General Public License as published by
@@ -16,13 +16,34 @@
  */
 
 use crate::cpu::cpu::CpuFlag;
-use crate::gameboy::GameBoy;
-use crate::cpu::opcode::{opcode, OpCodeContext};
+use crate::gameboy::{Clock, GameBoy};
+use crate::cpu::opcode::{opcode, OpCodeContext, OpCodeResult};
+use crate::mmu::locations::MEMORY_LOCATION_REGISTER_DIV;
 use crate::utils::signed_overflow_add_u16;
 
+/// The time the CPU is paused while switching between normal and double speed mode.
+const SPEED_SWITCH_CYCLES: Clock = 2050 * 4;
+
 opcode!(nop, []);
 
-opcode!(stop, [] {
+opcode!(stop, [gb, ctx] {
+    match ctx.get_stage() {
+        0 => {
+            // on GBC, STOP performs a speed switch, if requested via KEY1 before
+            if gb.get_peripherals_mut().mem.perform_speed_switch() {
+                ctx.add_cycles(SPEED_SWITCH_CYCLES);
+                return OpCodeResult::StageDone(SPEED_SWITCH_CYCLES);
+            }
+
+            OpCodeResult::Done
+        }
+
+        _ => {
+            // the divider gets reset after the speed switch was completed
+            gb.get_mmu_mut().write_u8(MEMORY_LOCATION_REGISTER_DIV, 0x00);
+            OpCodeResult::Done
+        }
+    }
 });
 
 opcode!(halt, [gb] {
@@ -17,6 +17,7 @@
 
 use crate::cpu::cpu::{CpuFlag, RegisterR16, RegisterR8};
 use crate::gameboy::GameBoy;
+use crate::ppu::ppu::OamCorruption;
 use crate::cpu::opcode::{opcode, OpCodeContext};
 use crate::utils::signed_overflow_add_u16;
 
@@ -258,29 +259,33 @@ opcode!(ld_hlptr_l, [gb] ld_r16ptr_r8(gb, RegisterR16::HL, RegisterR8::L));
 // LD A, (HL+)
 opcode!(ld_a_hlptri, [gb] {
     let hl = gb.cpu.get_r16(RegisterR16::HL);
+    gb.cpu.trigger_oam_bug(hl, OamCorruption::ReadWithIncDec);
     ld_r8_addr(gb, RegisterR8::A, hl);
-    gb.cpu.set_r16(RegisterR16::HL, hl + 1);
+    gb.cpu.set_r16(RegisterR16::HL, hl.wrapping_add(1));
 });
 
 // LD A, (HL-)
 opcode!(ld_a_hlptrd, [gb] {
     let hl = gb.cpu.get_r16(RegisterR16::HL);
+    gb.cpu.trigger_oam_bug(hl, OamCorruption::ReadWithIncDec);
     ld_r8_addr(gb, RegisterR8::A, hl);
-    gb.cpu.set_r16(RegisterR16::HL, hl - 1);
+    gb.cpu.set_r16(RegisterR16::HL, hl.wrapping_sub(1));
 });
 
 // LD (HL+), A
 opcode!(ld_hlptri_a, [gb] {
     let hl = gb.cpu.get_r16(RegisterR16::HL);
+    gb.cpu.trigger_oam_bug(hl, OamCorruption::Write);
     ld_addr_r8(gb, hl, RegisterR8::A);
-    gb.cpu.set_r16(RegisterR16::HL, hl + 1);
+    gb.cpu.set_r16(RegisterR16::HL, hl.wrapping_add(1));
 });
 
 // LD (HL-), A
 opcode!(ld_hlptrd_a, [gb] {
     let hl = gb.cpu.get_r16(RegisterR16::HL);
+    gb.cpu.trigger_oam_bug(hl, OamCorruption::Write);
     ld_addr_r8(gb, hl, RegisterR8::A);
-    gb.cpu.set_r16(RegisterR16::HL, hl - 1);
+    gb.cpu.set_r16(RegisterR16::HL, hl.wrapping_sub(1));
 });
 
 // LD (r16), u8
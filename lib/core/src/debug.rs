@@ -15,6 +15,9 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
+pub mod breakpoints;
+pub mod symbols;
+
 use flagset::{flags, FlagSet};
 
 flags! {
@@ -28,6 +31,21 @@ flags! {
 
         /// The PPU completed rendering a frame.
         PpuFrameCompleted   = 0b_0000_0010,
+
+        /// The CPU locked up after executing an illegal opcode.
+        CpuLocked           = 0b_0000_0100,
+
+        /// A breakpoint or watchpoint was hit.
+        BreakpointHit       = 0b_0000_1000,
+
+        /// The rumble motor of the cartridge was turned on or off.
+        RumbleChanged       = 0b_0001_0000,
+
+        /// The CPU dispatched an interrupt and jumped into its handler.
+        InterruptDispatched = 0b_0010_0000,
+
+        /// The CPU was suspended by the HALT instruction.
+        CpuHalted           = 0b_0100_0000,
     }
 }
 
@@ -0,0 +1,447 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::cell::RefCell;
+
+use crate::cpu::interrupts::Interrupt;
+use crate::mmu::locations::MEMORY_LOCATION_INTERRUPTS_ENABLED;
+
+/// A unique identifier of a breakpoint, which is assigned when adding a breakpoint.
+pub type BreakpointId = u32;
+
+
+/// The kind of memory access to be observed by a watchpoint.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MemoryAccess {
+    /// Reading a value from memory.
+    Read,
+
+    /// Writing a value into memory.
+    Write,
+
+    /// Either reading or writing memory.
+    ReadWrite,
+}
+
+
+/// A condition on the value being read or written, which needs to
+/// be met for a watchpoint to be triggered.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ValueCondition {
+    /// Triggers on any value.
+    Any,
+
+    /// Triggers only if the value equals the given value.
+    Equals(u8),
+
+    /// Triggers only if the value is different to the given value.
+    NotEquals(u8),
+
+    /// Triggers only if the bits selected by the mask are equal to the given value.
+    Masked {
+        mask:  u8,
+        value: u8,
+    },
+}
+
+
+/// A condition to stop the emulator.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Breakpoint {
+    /// Stops before the instruction on the given address is executed.
+    /// For addresses in the cartridge ROM, the breakpoint may be limited
+    /// to a specific ROM bank, otherwise it applies to any bank.
+    Execute {
+        address: u16,
+        bank:    Option<u32>,
+    },
+
+    /// Stops after an instruction accessed any address within a memory range,
+    /// both addresses inclusive.
+    Memory {
+        begin:      u16,
+        end:        u16,
+        access:     MemoryAccess,
+        condition:  ValueCondition,
+    },
+
+    /// Stops after an instruction accessed an IO register.
+    IoRegister {
+        address:    u16,
+        access:     MemoryAccess,
+        condition:  ValueCondition,
+    },
+
+    /// Stops after the CPU dispatched an interrupt, before the
+    /// first instruction of the interrupt handler is executed.
+    Interrupt(Interrupt),
+}
+
+
+/// Details about a memory access, which triggered a watchpoint.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MemoryAccessEvent {
+    /// The address being accessed.
+    pub address: u16,
+
+    /// The value being read or written.
+    pub value: u8,
+
+    /// Whether the value was read or written.
+    pub access: MemoryAccess,
+}
+
+
+/// Reports a breakpoint being hit while running the emulator.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct BreakpointHit {
+    /// The ID of the breakpoint being hit.
+    pub id: BreakpointId,
+
+    /// The address of the instruction being executed when the breakpoint was hit.
+    /// For interrupts, this is the address the CPU will return to after handling the interrupt.
+    pub instruction_address: u16,
+
+    /// For watchpoints: Details about the memory access which triggered the watchpoint.
+    pub memory_access: Option<MemoryAccessEvent>,
+}
+
+
+/// A breakpoint registered in the [Breakpoints] list.
+#[derive(Clone, Debug)]
+pub struct BreakpointEntry {
+    /// The unique ID of this breakpoint.
+    pub id: BreakpointId,
+
+    /// The condition to stop the emulator.
+    pub breakpoint: Breakpoint,
+
+    /// Whether the breakpoint is currently enabled.
+    pub enabled: bool,
+
+    /// The number of times this breakpoint was hit.
+    pub hit_count: u32,
+}
+
+
+/// A list of breakpoints and watchpoints, which will be checked while
+/// the emulator is running. Any hits are reported via
+/// [crate::gameboy::EmulatorUpdateResults] and will stop
+/// [crate::gameboy::GameBoy::run_frame] after the instruction which hit the breakpoint.
+///
+/// Watchpoints only observe memory accesses by the CPU while running the emulator,
+/// but no accesses by DMA transfers or reads from a frontend.
+/// Breakpoints are not part of snapshots and need to be set up again after
+/// restoring an emulator instance.
+#[derive(Default)]
+pub struct Breakpoints {
+    /// All registered breakpoints.
+    entries: Vec<BreakpointEntry>,
+
+    /// The ID to be assigned to the next breakpoint.
+    next_id: BreakpointId,
+
+    /// Whether any enabled execution breakpoints exist.
+    has_execute_breakpoints: bool,
+
+    /// Whether any enabled watchpoints exist.
+    has_watchpoints: bool,
+
+    /// Whether any enabled interrupt breakpoints exist.
+    has_interrupt_breakpoints: bool,
+
+    /// Whether memory accesses are currently observed, which is
+    /// only the case while the emulator is running a step.
+    armed: bool,
+
+    /// The address of the instruction currently being executed.
+    instruction_address: u16,
+
+    /// After stopping on an execution breakpoint, the instruction on this
+    /// address will be executed without hitting the same breakpoint again.
+    resume_address: Option<u16>,
+
+    /// All hits since the current step started.
+    pending_hits: RefCell<Vec<BreakpointHit>>,
+}
+
+
+impl MemoryAccess {
+    /// Checks whether this kind of access includes another access.
+    fn includes(&self, access: MemoryAccess) -> bool {
+        matches!(
+            (self, access),
+                (MemoryAccess::ReadWrite, _)
+            |   (MemoryAccess::Read,  MemoryAccess::Read)
+            |   (MemoryAccess::Write, MemoryAccess::Write)
+        )
+    }
+}
+
+
+impl ValueCondition {
+    /// Checks whether a value meets this condition.
+    pub fn matches(&self, value: u8) -> bool {
+        match self {
+            ValueCondition::Any                 => true,
+            ValueCondition::Equals(expected)    => value == *expected,
+            ValueCondition::NotEquals(expected) => value != *expected,
+            ValueCondition::Masked { mask, value: expected } => (value & mask) == (expected & mask),
+        }
+    }
+}
+
+
+impl Breakpoint {
+    /// Checks whether an address is a valid IO register address to be observed.
+    pub fn is_io_register(address: u16) -> bool {
+        matches!(address, 0xff00 ..= 0xff7f | MEMORY_LOCATION_INTERRUPTS_ENABLED)
+    }
+
+
+    /// Checks whether this breakpoint is a watchpoint observing memory accesses.
+    pub fn is_watchpoint(&self) -> bool {
+        matches!(self, Breakpoint::Memory { .. } | Breakpoint::IoRegister { .. })
+    }
+
+
+    /// Checks whether this watchpoint is triggered by a memory access.
+    fn matches_memory_access(&self, address: u16, value: u8, access: MemoryAccess) -> bool {
+        match self {
+            Breakpoint::Memory { begin, end, access: expected_access, condition } => {
+                    (*begin ..= *end).contains(&address)
+                &&  expected_access.includes(access)
+                &&  condition.matches(value)
+            }
+
+            Breakpoint::IoRegister { address: register, access: expected_access, condition } => {
+                    *register == address
+                &&  expected_access.includes(access)
+                &&  condition.matches(value)
+            }
+
+            _ => false,
+        }
+    }
+}
+
+
+impl Breakpoints {
+    /// Adds a new breakpoint, which is enabled by default.
+    /// Returns the ID of the breakpoint, which can be used to modify or remove it.
+    pub fn add(&mut self, breakpoint: Breakpoint) -> Result<BreakpointId, String> {
+        if let Breakpoint::IoRegister { address, .. } = breakpoint {
+            if !Breakpoint::is_io_register(address) {
+                return Err(format!("Address {address:04x} is not an IO register"));
+            }
+        }
+
+        if let Breakpoint::Memory { begin, end, .. } = breakpoint {
+            if begin > end {
+                return Err(format!("Invalid memory range {begin:04x} - {end:04x}"));
+            }
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.entries.push(BreakpointEntry {
+            id,
+            breakpoint,
+            enabled:   true,
+            hit_count: 0,
+        });
+
+        self.update_flags();
+
+        Ok(id)
+    }
+
+
+    /// Removes a breakpoint.
+    /// Returns whether the breakpoint did exist.
+    pub fn remove(&mut self, id: BreakpointId) -> bool {
+        let count_before = self.entries.len();
+        self.entries.retain(|entry| entry.id != id);
+        self.update_flags();
+
+        self.entries.len() != count_before
+    }
+
+
+    /// Removes all breakpoints.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.update_flags();
+    }
+
+
+    /// Enables or disables a breakpoint.
+    /// Returns whether the breakpoint did exist.
+    pub fn set_enabled(&mut self, id: BreakpointId, enabled: bool) -> bool {
+        let found = match self.entries.iter_mut().find(|entry| entry.id == id) {
+            Some(entry) => {
+                entry.enabled = enabled;
+                true
+            }
+
+            None => false,
+        };
+
+        self.update_flags();
+
+        found
+    }
+
+
+    /// Get a breakpoint by its ID.
+    pub fn get(&self, id: BreakpointId) -> Option<&BreakpointEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+
+    /// Get all registered breakpoints.
+    pub fn get_entries(&self) -> &[BreakpointEntry] {
+        &self.entries
+    }
+
+
+    /// Checks whether there are no breakpoints registered.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+
+    /// Checks whether any breakpoints are enabled and need to be checked while running.
+    pub fn is_active(&self) -> bool {
+        self.has_execute_breakpoints || self.has_watchpoints || self.has_interrupt_breakpoints
+    }
+
+
+    /// Updates the flags about which kinds of breakpoints are enabled.
+    fn update_flags(&mut self) {
+        let enabled = || self.entries.iter().filter(|entry| entry.enabled);
+
+        self.has_execute_breakpoints   = enabled().any(|entry| matches!(entry.breakpoint, Breakpoint::Execute { .. }));
+        self.has_watchpoints           = enabled().any(|entry| entry.breakpoint.is_watchpoint());
+        self.has_interrupt_breakpoints = enabled().any(|entry| matches!(entry.breakpoint, Breakpoint::Interrupt(_)));
+    }
+
+
+    /// Starts observing memory accesses of the instruction on the given address.
+    pub(crate) fn begin_step(&mut self, instruction_address: u16) {
+        self.armed               = self.has_watchpoints;
+        self.instruction_address = instruction_address;
+    }
+
+
+    /// Stops observing memory accesses and returns all hits of the current step.
+    pub(crate) fn end_step(&mut self) -> Vec<BreakpointHit> {
+        self.armed = false;
+
+        let hits = self.pending_hits.take();
+
+        for hit in &hits {
+            if let Some(entry) = self.entries.iter_mut().find(|entry| entry.id == hit.id) {
+                entry.hit_count += 1;
+            }
+        }
+
+        hits
+    }
+
+
+    /// Checks whether the instruction on the given address hits an execution breakpoint.
+    /// `rom_bank` is the ROM bank mapped into the address, if the address refers to the cartridge ROM.
+    /// Returns `true` if the instruction should not be executed.
+    pub(crate) fn check_execute(&mut self, address: u16, rom_bank: Option<u32>) -> bool {
+        // continue after the emulator stopped on this instruction before
+        if self.resume_address.take() == Some(address) || !self.has_execute_breakpoints {
+            return false;
+        }
+
+        let mut hit = false;
+
+        for entry in self.entries.iter().filter(|entry| entry.enabled) {
+            if let Breakpoint::Execute { address: bp_address, bank } = entry.breakpoint {
+                let bank_matches = match (bank, rom_bank) {
+                    (Some(bank), Some(rom_bank)) => bank == rom_bank,
+                    _                            => true,
+                };
+
+                if bp_address == address && bank_matches {
+                    self.pending_hits.get_mut().push(BreakpointHit {
+                        id:                  entry.id,
+                        instruction_address: address,
+                        memory_access:       None,
+                    });
+
+                    hit = true;
+                }
+            }
+        }
+
+        if hit {
+            self.resume_address = Some(address);
+        }
+
+        hit
+    }
+
+
+    /// Checks whether a dispatched interrupt hits any interrupt breakpoint.
+    pub(crate) fn check_interrupt(&mut self, interrupt: Interrupt) {
+        if !self.has_interrupt_breakpoints {
+            return;
+        }
+
+        for entry in self.entries.iter().filter(|entry| entry.enabled) {
+            if entry.breakpoint == Breakpoint::Interrupt(interrupt) {
+                self.pending_hits.get_mut().push(BreakpointHit {
+                    id:                  entry.id,
+                    instruction_address: self.instruction_address,
+                    memory_access:       None,
+                });
+            }
+        }
+    }
+
+
+    /// Checks whether memory accesses are currently being observed.
+    #[inline]
+    pub(crate) fn is_watching(&self) -> bool {
+        self.armed
+    }
+
+
+    /// Checks whether a memory access by the CPU triggers any watchpoints.
+    pub(crate) fn check_memory_access(&self, address: u16, value: u8, access: MemoryAccess) {
+        for entry in self.entries.iter().filter(|entry| entry.enabled) {
+            if entry.breakpoint.matches_memory_access(address, value, access) {
+                self.pending_hits.borrow_mut().push(BreakpointHit {
+                    id:                  entry.id,
+                    instruction_address: self.instruction_address,
+                    memory_access:       Some(MemoryAccessEvent {
+                        address,
+                        value,
+                        access,
+                    }),
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,338 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The default file extension of symbol files.
+pub const FILE_EXT_SYM: &str = "sym";
+
+/// The default file extension of linker map files.
+pub const FILE_EXT_MAP: &str = "map";
+
+
+/// A named location in the GameBoy's address space.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Symbol {
+    /// The name of the symbol. Local labels are prefixed by their parent label, like `Main.loop`.
+    pub name: String,
+
+    /// The bank of the memory area the symbol is located in.
+    pub bank: u32,
+
+    /// The address of the symbol.
+    pub address: u16,
+}
+
+
+/// A collection of symbols, which can be looked up by their name or location.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "Vec<Symbol>", into = "Vec<Symbol>"))]
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable {
+    /// All symbols, ordered by their location.
+    symbols: Vec<Symbol>,
+
+    /// The index of each symbol by its name.
+    by_name: HashMap<String, usize>,
+}
+
+
+impl SymbolTable {
+    /// Creates an empty symbol table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+
+    /// Parses a symbol file in the format written by RGBDS or no$gmb.
+    /// Each line contains a symbol like `01:4000 Label`, comments start with `;`.
+    /// Lines which cannot be parsed are ignored.
+    pub fn parse_sym(text: &str) -> Self {
+        let mut symbols = Vec::new();
+
+        for line in text.lines() {
+            let line = strip_comment(line).trim();
+
+            let parsed = line.split_once(char::is_whitespace).and_then(|(location, name)| {
+                let (bank, address) = location.split_once(':')?;
+                let bank    = u32::from_str_radix(bank, 16).ok()?;
+                let address = u16::from_str_radix(address, 16).ok()?;
+
+                Some((bank, address, name.trim()))
+            });
+
+            if let Some((bank, address, name)) = parsed {
+                symbols.push(Symbol { name: name.to_string(), bank, address });
+            }
+        }
+
+        Self::from_symbols(symbols)
+    }
+
+
+    /// Parses a map file written by the RGBDS linker.
+    /// Symbols are listed below the section they are located in, like `$4000 = Label`,
+    /// while their bank is taken from the enclosing `ROMX bank #1:` header.
+    pub fn parse_map(text: &str) -> Self {
+        let mut symbols = Vec::new();
+        let mut bank    = 0;
+
+        for line in text.lines() {
+            let line = strip_comment(line).trim();
+
+            if let Some(header) = line.strip_suffix(':') {
+                if let Some((_, number)) = header.split_once(" bank #") {
+                    bank = number.trim().parse().unwrap_or(0);
+                }
+
+                continue;
+            }
+
+            let parsed = line.split_once('=').and_then(|(address, name)| {
+                let address = address.trim().strip_prefix('$')?;
+                let address = u16::from_str_radix(address, 16).ok()?;
+
+                Some((address, name.trim()))
+            });
+
+            if let Some((address, name)) = parsed {
+                symbols.push(Symbol { name: name.to_string(), bank, address });
+            }
+        }
+
+        Self::from_symbols(symbols)
+    }
+
+
+    /// Creates a symbol table from a list of symbols.
+    /// If there are multiple symbols with the same name, the last one will be kept.
+    pub fn from_symbols(mut symbols: Vec<Symbol>) -> Self {
+        let mut names = HashSet::new();
+        symbols.reverse();
+        symbols.retain(|symbol| names.insert(symbol.name.clone()));
+        symbols.reverse();
+        symbols.sort_by_key(|symbol| (symbol.bank, symbol.address));
+
+        let mut table = Self { symbols, by_name: HashMap::new() };
+        table.update_index();
+        table
+    }
+
+
+    /// Reads a symbol file. Files with the `.map` extension are parsed as linker map file,
+    /// any other file as symbol file.
+    pub fn read_from_file(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+
+        let is_map_file = path
+            .extension()
+            .map(|extension| extension.eq_ignore_ascii_case(FILE_EXT_MAP))
+            .unwrap_or(false)
+        ;
+
+        if is_map_file {
+            Ok(Self::parse_map(&text))
+        }
+        else {
+            Ok(Self::parse_sym(&text))
+        }
+    }
+
+
+    /// Adds a symbol. If a symbol with the same name already exists, it will be replaced.
+    pub fn add(&mut self, symbol: Symbol) {
+        if let Some(index) = self.by_name.get(&symbol.name) {
+            self.symbols.remove(*index);
+        }
+
+        let index = self.symbols.partition_point(|other| {
+            (other.bank, other.address) <= (symbol.bank, symbol.address)
+        });
+
+        self.symbols.insert(index, symbol);
+        self.update_index();
+    }
+
+
+    /// Writes all symbols into a symbol file, which can be read by [SymbolTable::read_from_file].
+    pub fn write_to_file(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_sym_string())
+    }
+
+
+    /// Formats all symbols in the format of a symbol file, like `01:4000 Label`.
+    pub fn to_sym_string(&self) -> String {
+        self.symbols.iter().fold(
+            String::new(),
+            |mut text, symbol| {
+                _ = writeln!(text, "{:02x}:{:04x} {}", symbol.bank, symbol.address, symbol.name);
+                text
+            }
+        )
+    }
+
+
+    /// Adds all symbols of another table, which are not yet part of this table.
+    pub fn merge(&mut self, other: SymbolTable) {
+        let mut symbols = other.symbols;
+        symbols.retain(|symbol| !self.by_name.contains_key(&symbol.name));
+
+        if !symbols.is_empty() {
+            symbols.append(&mut self.symbols);
+            *self = Self::from_symbols(symbols);
+        }
+    }
+
+
+    /// Changes the name of an existing symbol.
+    /// Fails if there is no symbol with the old name or the new name is already in use.
+    pub fn rename(&mut self, old_name: &str, new_name: &str) -> Result<(), String> {
+        if self.by_name.contains_key(new_name) {
+            return Err(format!("Symbol '{new_name}' already exists"));
+        }
+
+        let index = *self.by_name
+            .get(old_name)
+            .ok_or_else(|| format!("Symbol '{old_name}' not found"))
+            ?
+        ;
+
+        self.symbols[index].name = new_name.to_string();
+        self.update_index();
+
+        Ok(())
+    }
+
+
+    /// Removes a symbol by its name, if it exists.
+    pub fn remove(&mut self, name: &str) -> Option<Symbol> {
+        let index  = self.by_name.get(name).copied()?;
+        let symbol = self.symbols.remove(index);
+        self.update_index();
+
+        Some(symbol)
+    }
+
+
+    /// Get all symbols, ordered by their bank and address.
+    pub fn get_symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+
+    /// Checks whether the table does not contain any symbols.
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+
+    /// Finds a symbol by its name.
+    pub fn find_by_name(&self, name: &str) -> Option<&Symbol> {
+        self.by_name.get(name).map(|index| &self.symbols[*index])
+    }
+
+
+    /// Finds the symbol located exactly on an address.
+    /// If `bank` is `None`, symbols of any bank will match.
+    pub fn find_at(&self, address: u16, bank: Option<u32>) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .find(|symbol| symbol.address == address && bank.is_none_or(|bank| bank == symbol.bank))
+    }
+
+
+    /// Finds the symbol with the highest address lower or equal to the given address,
+    /// which is located within the same memory area, like the same ROM bank.
+    /// If `bank` is `None`, symbols of any bank will match.
+    pub fn find_nearest(&self, address: u16, bank: Option<u32>) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .filter(|symbol| bank.is_none_or(|bank| bank == symbol.bank))
+            .filter(|symbol| symbol.address <= address)
+            .filter(|symbol| get_memory_area(symbol.address) == get_memory_area(address))
+            .max_by_key(|symbol| symbol.address)
+    }
+
+
+    /// Formats an address as symbol name with an offset, like `Main+$4`.
+    /// Returns `None` if there is no symbol in front of the address.
+    pub fn format_address(&self, address: u16, bank: Option<u32>) -> Option<String> {
+        self.find_nearest(address, bank).map(|symbol| {
+            match address - symbol.address {
+                0      => symbol.name.clone(),
+                offset => format!("{}+${offset:x}", symbol.name),
+            }
+        })
+    }
+
+
+    /// Rebuilds the index of symbol names after the list of symbols was changed.
+    fn update_index(&mut self) {
+        self.by_name = self.symbols
+            .iter()
+            .enumerate()
+            .map(|(index, symbol)| (symbol.name.clone(), index))
+            .collect()
+        ;
+    }
+}
+
+
+impl From<Vec<Symbol>> for SymbolTable {
+    fn from(symbols: Vec<Symbol>) -> Self {
+        Self::from_symbols(symbols)
+    }
+}
+
+
+impl From<SymbolTable> for Vec<Symbol> {
+    fn from(table: SymbolTable) -> Self {
+        table.symbols
+    }
+}
+
+
+/// Removes a comment starting with `;` from a line.
+fn strip_comment(line: &str) -> &str {
+    match line.split_once(';') {
+        Some((content, _)) => content,
+        None               => line,
+    }
+}
+
+
+/// Get the start address of the memory area an address is located in.
+/// Symbols are only looked up within the same memory area.
+fn get_memory_area(address: u16) -> u16 {
+    match address {
+        0x0000 ..= 0x3fff => 0x0000,
+        0x4000 ..= 0x7fff => 0x4000,
+        0x8000 ..= 0x9fff => 0x8000,
+        0xa000 ..= 0xbfff => 0xa000,
+        0xc000 ..= 0xcfff => 0xc000,
+        0xd000 ..= 0xdfff => 0xd000,
+        0xe000 ..= 0xfdff => 0xe000,
+        0xfe00 ..= 0xfeff => 0xfe00,
+        0xff00 ..= 0xff7f => 0xff00,
+        0xff80 ..= 0xffff => 0xff80,
+    }
+}
@@ -28,7 +28,7 @@ pub mod abbreviations {
 
 
 /// The type of GameBoy device to be emulated.
-#[derive(Copy, Clone, Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DeviceType {
     /// The original GameBoy with monochrome 4 color display.
@@ -16,18 +16,22 @@
  */
 
 use crate::apu::apu::Apu;
-use crate::boot_rom::BootRom;
+use crate::boot_rom::{BootRom, BootRomLayout};
 use crate::cartridge::{Cartridge, GameBoyColorSupport, LicenseeCode};
 use crate::cpu::cpu::{Cpu, CpuFlag, RegisterR8, CPU_CLOCK_SPEED};
 use crate::cpu::interrupts::InterruptRegisters;
 use crate::cpu::opcode::{OpCodeContext, OpCodeResult};
 use crate::debug::{DebugEvent, DebugEvents};
+use crate::debug::breakpoints::{BreakpointHit, Breakpoints};
 // re-export some types
 pub use crate::device_type::{DeviceType, EmulationType};
 use crate::input::Input;
+use crate::mmu::locations::{MEMORY_LOCATION_HDMA5, MEMORY_LOCATION_OPRI};
+use crate::mmu::mbc::MbcImpl;
 use crate::mmu::memory::Memory;
 use crate::mmu::memory_bus::{MemoryBusConnection, MemoryBusSignals};
 use crate::mmu::mmu::Mmu;
+use crate::ppu::compat_palettes::{CompatibilityPalettes, ManualPaletteSelection};
 use crate::ppu::ppu::{Ppu, CPU_CYCLES_PER_FRAME};
 use crate::serial::SerialPort;
 use crate::timer::Timer;
@@ -50,6 +54,10 @@ pub struct DeviceConfig {
 
     /// Flag if opcodes should be printed
     pub print_opcodes: bool,
+
+    /// Palettes to be used for classic GameBoy games on a GameBoy Color
+    /// instead of the ones selected by the cartridge title.
+    pub dmg_compatibility_palette: Option<ManualPaletteSelection>,
 }
 
 
@@ -60,6 +68,7 @@ pub struct Builder {
     cartridge:     Option<Cartridge>,
     device_type:   Option<DeviceType>,
     print_opcodes: bool,
+    dmg_compatibility_palette: Option<ManualPaletteSelection>,
 }
 
 
@@ -91,10 +100,16 @@ pub struct Peripherals {
 #[derive(Default)]
 pub struct EmulatorUpdateResults {
     /// The number of cycles being processed.
+    /// This is measured in device cycles, so in double speed mode
+    /// the CPU will process twice as many cycles.
     pub cycles: Clock,
 
     /// Any debug events occurred during updating the emulator.
     pub events: DebugEvents,
+
+    /// All breakpoints being hit during updating the emulator.
+    /// If not empty, the events will contain [DebugEvent::BreakpointHit].
+    pub breakpoint_hits: Vec<BreakpointHit>,
 }
 
 
@@ -119,6 +134,7 @@ impl Builder {
             cartridge:     None,
             device_type:   None,
             print_opcodes: false,
+            dmg_compatibility_palette: None,
         }
     }
 
@@ -143,6 +159,13 @@ impl Builder {
         self.print_opcodes = print;
     }
 
+    /// Selects the palettes used for classic GameBoy games on a GameBoy Color,
+    /// like pressing a button combination during the GBC boot animation.
+    /// If not specified, the palettes will be selected by the cartridge title.
+    pub fn set_dmg_compatibility_palette(&mut self, selection: ManualPaletteSelection) {
+        self.dmg_compatibility_palette = Some(selection);
+    }
+
     /// Get the preferred device type, which is either specified explicitly
     /// or selected by the cartridge properties.
     pub fn select_preferred_device_type(&self) -> DeviceType {
@@ -151,6 +174,13 @@ impl Builder {
             return *device_type;
         }
 
+        // a GBC boot ROM requires a GBC device
+        if let Some(boot_rom) = &self.boot_rom {
+            if let BootRomLayout::Color = boot_rom.get_layout() {
+                return DeviceType::GameBoyColor;
+            }
+        }
+
         // determine the preferred device type by the cartridge properties
         if let Some(cartridge) = &self.cartridge {
             return match cartridge.get_cgb_support() {
@@ -166,7 +196,13 @@ impl Builder {
 
     /// Check the emulation type based on the selected device and GameBoyColor
     /// support of the selected cartridge.
+    /// With a boot ROM, a GBC device always starts in GBC mode and the boot ROM
+    /// selects the DMG compatibility mode via KEY0 after checking the cartridge.
     pub fn select_emulation_type(&self, device_type: &DeviceType) -> EmulationType {
+        if self.boot_rom.is_some() && device_type.has_gbc_support() {
+            return EmulationType::GBC;
+        }
+
         match device_type {
             DeviceType::GameBoyDmg => {}
             _ => {
@@ -191,7 +227,8 @@ impl Builder {
         let device_config = DeviceConfig {
             device: device_type,
             emulation: emulation_type,
-            print_opcodes: self.print_opcodes
+            print_opcodes: self.print_opcodes,
+            dmg_compatibility_palette: self.dmg_compatibility_palette,
         };
 
         // construct the GameBoy object
@@ -199,6 +236,13 @@ impl Builder {
 
         // set boot ROM, if any
         if let Some(boot_rom) = self.boot_rom.take() {
+            if boot_rom.get_layout() != BootRomLayout::for_device(device_type) {
+                return Err(format!(
+                    "The boot ROM does not match the device type {}",
+                    device_type.get_abbreviation()
+                ));
+            }
+
             gb.get_peripherals_mut().mem.set_boot_rom(boot_rom);
         }
 
@@ -226,13 +270,14 @@ impl GameBoy {
 
                 cpu: Cpu::new(
                     Mmu::new(
+                        device_config,
                         Peripherals {
                             apu:        Box::new(Apu::new(device_config)),
                             ppu:        Box::new(Ppu::new(device_config)),
                             mem:        Box::new(Memory::new(device_config)),
                             timer:      Box::new(Timer::new()),
                             input:      Box::new(Input::new()),
-                            serial:     Box::new(SerialPort::new()),
+                            serial:     Box::new(SerialPort::new(device_config)),
                             interrupts: Box::new(InterruptRegisters::new()),
                         }
                     )
@@ -400,7 +445,7 @@ impl GameBoy {
                 /* 10 */ 0x80, 0xbf, 0xf3, 0xff, 0xbf,    X, 0x3f, 0x00, 0xff, 0xbf, 0x7f, 0xff, 0x9f, 0xff, 0xbf,    X,
                 /* 20 */ 0xff, 0x00, 0x00, 0xbf, 0x77, 0xf3, 0xf1,    X,    X,    X,    X,    X,    X,    X,    X,    X,
                 /* 30 */    X,    X,    X,    X,    X,    X,    X,    X,    X,    X,    X,    X,    X,    X,    X,    X,
-                /* 40 */ 0x91, lcds, 0x00, 0x00,   ly, 0x00,  dma, 0xfc, 0x00, 0x00, 0x00, 0x00,    X, 0xff,    X,  vbk,
+                /* 40 */ 0x91, lcds, 0x00, 0x00,   ly, 0x00,  dma, 0xfc, 0x00, 0x00, 0x00, 0x00,    X, 0x7e,    X,  vbk,
                 /* 50 */    X, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,    X,    X,    X,    X,    X,    X,    X,    X,    X,
                 /* 60 */    X,    X,    X,    X,    X,    X,    X,    X, 0xff, 0xff, 0xff, 0xff, opri,    X,    X,    X,
                 /* 70 */ 0xff,    X,    X,    X,    X,    X,    X,    X,    X,    X,    X,    X,    X,    X,    X,    X,
@@ -416,11 +461,36 @@ impl GameBoy {
 
             // apply selected values
             for i in 0..=255 {
+                // skip HDMA5, which would start a VRAM DMA transfer
+                if 0xff00 + i == MEMORY_LOCATION_HDMA5 {
+                    continue;
+                }
+
+                // OPRI can only be written by the boot ROM, so it's set on the PPU directly
+                if 0xff00 + i == MEMORY_LOCATION_OPRI {
+                    self.get_peripherals_mut().ppu.on_write(MEMORY_LOCATION_OPRI, opri);
+                    continue;
+                }
+
                 self.get_mmu_mut().write_u8(0xff00 + i, io_reg_data[i as usize]);
             }
 
             self.get_peripherals_mut().timer.initialize_counter(timer_counter, tac);
         }
+
+        // without boot ROM, the compatibility palettes for classic games
+        // need to be selected like the GBC boot ROM would do
+        if self.device_config.device.has_gbc_support() && !self.device_config.is_gbc_enabled() {
+            let compat_palettes = match self.device_config.dmg_compatibility_palette {
+                Some(selection) => CompatibilityPalettes::for_manual_selection(selection),
+                None => match self.get_peripherals().mem.get_cartridge().as_ref() {
+                    Some(cartridge) => CompatibilityPalettes::for_cartridge(cartridge),
+                    None            => CompatibilityPalettes::default(),
+                },
+            };
+
+            compat_palettes.apply_to(self.get_peripherals_mut().ppu.get_palettes_mut());
+        }
     }
 
 
@@ -460,6 +530,34 @@ impl GameBoy {
     }
 
 
+    /// Get the breakpoints and watchpoints installed on this device.
+    pub fn get_breakpoints(&self) -> &Breakpoints {
+        self.get_mmu().get_breakpoints()
+    }
+
+
+    /// Get the breakpoints and watchpoints installed on this device.
+    pub fn get_breakpoints_mut(&mut self) -> &mut Breakpoints {
+        self.get_mmu_mut().get_breakpoints_mut()
+    }
+
+
+    /// Get the number of the cartridge ROM bank currently mapped into an address.
+    /// Returns `None` for addresses outside the cartridge ROM, while the boot ROM is mapped
+    /// or while the address maps other memory than ROM, like the flash memory of MBC6 cartridges.
+    pub fn get_mapped_rom_bank(&self, address: u16) -> Option<u32> {
+        let mbc = self.get_peripherals().mem.get_mbc();
+
+        match address {
+            0x0000 ..= 0x7fff if !self.get_peripherals().mem.is_boot_rom_mapped(address) && mbc.is_rom_mapped(address) => {
+                Some(mbc.get_rom_bank(address))
+            }
+
+            _ => None,
+        }
+    }
+
+
     /// Runs the emulator for a single step, either an instruction
     /// or to process a single HALT cycle.
     pub fn run_single_step(&mut self) -> EmulatorUpdateResults {
@@ -481,6 +579,11 @@ impl GameBoy {
                 break;
             }
 
+            // stop at the exact instruction where a breakpoint was hit
+            if results.events.contains(DebugEvent::BreakpointHit) {
+                break;
+            }
+
             // inc ase the screen was disabled, stop after the time of one frame has passed
             if results.cycles >= CPU_CYCLES_PER_FRAME {
                 break;
@@ -493,30 +596,80 @@ impl GameBoy {
 
     /// Continues processing the next pending operation.
     fn process_next(&mut self) -> EmulatorUpdateResults {
-        if self.cpu.is_running() {
-            if let Some(cycles) = self.cpu.handle_interrupts() {
+        let total_cycles_before = self.total_cycles;
+        let boot_rom_mapped     = self.get_peripherals().mem.has_boot_rom();
+
+        // only check for breakpoints if there are any enabled
+        let breakpoints_active = self.get_mmu().get_breakpoints().is_active();
+        if breakpoints_active {
+            let instruction_address = self.cpu.get_instruction_pointer();
+            self.get_mmu_mut().get_breakpoints_mut().begin_step(instruction_address);
+        }
+
+        let mut results = if self.cpu.is_running() && !self.get_mmu().is_cpu_stalled() {
+            if let Some((interrupt, cycles)) = self.cpu.handle_interrupts() {
                 let signals = self.update_components(cycles);
 
+                if breakpoints_active {
+                    self.get_mmu_mut().get_breakpoints_mut().check_interrupt(interrupt);
+                }
+
                 EmulatorUpdateResults {
                     cycles,
-                    events: signals.events,
+                    events: signals.events | DebugEvent::InterruptDispatched,
+                    .. EmulatorUpdateResults::default()
                 }
             }
+            else if breakpoints_active && self.check_execute_breakpoints() {
+                // stop before executing the instruction
+                EmulatorUpdateResults::default()
+            }
             else {
                 self.process_next_opcode()
             }
         }
         else {
-            // when in HALT state just pass 4 cycles
-            // where the CPU idles
+            // when in HALT state or stalled by a VRAM DMA transfer
+            // just pass 4 cycles where the CPU idles
             let halt_cycle = 4;
             let signals    = self.update_components(halt_cycle);
 
             EmulatorUpdateResults {
                 cycles: halt_cycle,
                 events: signals.events,
+                .. EmulatorUpdateResults::default()
+            }
+        };
+
+        // collect any breakpoints being hit during this step
+        if breakpoints_active {
+            let hits = self.get_mmu_mut().get_breakpoints_mut().end_step();
+
+            if !hits.is_empty() {
+                results.events |= DebugEvent::BreakpointHit;
+                results.breakpoint_hits = hits;
             }
         }
+
+        // the boot ROM may have switched a GBC into DMG compatibility mode when it got unmapped
+        if boot_rom_mapped && !self.get_peripherals().mem.has_boot_rom() {
+            self.device_config = *self.get_mmu().get_device_config();
+        }
+
+        // report the time passed in device cycles, which differs
+        // from CPU cycles when running in double speed mode
+        results.cycles = self.total_cycles - total_cycles_before;
+
+        results
+    }
+
+
+    /// Checks whether the next instruction hits an execution breakpoint.
+    fn check_execute_breakpoints(&mut self) -> bool {
+        let address  = self.cpu.get_instruction_pointer();
+        let rom_bank = self.get_mapped_rom_bank(address);
+
+        self.get_mmu_mut().get_breakpoints_mut().check_execute(address, rom_bank)
     }
 
 
@@ -560,6 +713,16 @@ impl GameBoy {
             }
         }
 
+        // report the CPU being locked up by an illegal opcode
+        if self.cpu.is_locked() {
+            signals.events |= DebugEvent::CpuLocked;
+        }
+
+        // report the CPU being suspended by the HALT instruction
+        else if !self.cpu.is_running() {
+            signals.events |= DebugEvent::CpuHalted;
+        }
+
         // print opcode and CPU state if enabled
         if self.device_config.print_opcodes {
             println!(
@@ -575,21 +738,43 @@ impl GameBoy {
         EmulatorUpdateResults {
             cycles: context.get_cycles_consumed(),
             events: signals.events,
+            .. EmulatorUpdateResults::default()
         }
     }
 
 
     /// Applies the time passed during CPU execution to other components as well.
+    /// In double speed mode, components running on the CPU clock (CPU, Timer, Serial
+    /// and OAM DMA) will process twice as many cycles as components running on the
+    /// device clock (PPU, APU and the cartridge).
     #[must_use]
     fn update_components(&mut self, cycles: Clock) -> MemoryBusSignals {
+        let device_cycles = if self.get_peripherals().mem.is_double_speed() {
+            cycles / 2
+        }
+        else {
+            cycles
+        };
+
         self.cpu.update(cycles);
         self.get_mmu_mut().update(cycles);
-        self.get_peripherals_mut().apu.update(cycles);
-        self.get_peripherals_mut().ppu.update(cycles);
+        self.get_peripherals_mut().mem.update(device_cycles);
+        self.get_peripherals_mut().apu.update(device_cycles);
+        self.get_peripherals_mut().ppu.update(device_cycles);
+        self.get_mmu_mut().update_hblank_dma();
         self.get_peripherals_mut().timer.update(cycles);
         self.get_peripherals_mut().serial.update(cycles);
         self.get_peripherals_mut().input.update();
 
+        // forward changes of the device's tilt into the cartridge's accelerometer, if any
+        if let Some((tilt_x, tilt_y)) = self.get_peripherals_mut().input.take_tilt_change() {
+            self.get_peripherals_mut().mem.set_tilt(tilt_x, tilt_y);
+        }
+
+        // report state changes of the cartridge's rumble motor, if any
+        let total_cycles = self.total_cycles;
+        self.get_peripherals_mut().mem.update_rumble(total_cycles);
+
         // collects all signals received from components
         let signals =
                 self.get_peripherals_mut().apu.take_signals()
@@ -597,13 +782,14 @@ impl GameBoy {
             |   self.get_peripherals_mut().timer.take_signals()
             |   self.get_peripherals_mut().serial.take_signals()
             |   self.get_peripherals_mut().input.take_signals()
+            |   self.get_peripherals_mut().mem.take_signals()
         ;
 
         // forward all requested interrupts into the Interrupts component.
         self.get_peripherals_mut().interrupts.request_interrupts(signals.interrupts);
 
-        // increment clock counters
-        self.total_cycles += cycles;
+        // increment clock counters, which are measured in device cycles
+        self.total_cycles += device_cycles;
 
         signals
     }
@@ -615,8 +801,9 @@ impl std::ops::Add for EmulatorUpdateResults {
 
     fn add(self, rhs: Self) -> Self::Output {
         Self {
-            cycles: self.cycles + rhs.cycles,
-            events: self.events | rhs.events,
+            cycles:          self.cycles + rhs.cycles,
+            events:          self.events | rhs.events,
+            breakpoint_hits: [self.breakpoint_hits, rhs.breakpoint_hits].concat(),
         }
     }
 }
@@ -626,5 +813,6 @@ impl std::ops::AddAssign for EmulatorUpdateResults {
     fn add_assign(&mut self, rhs: Self) {
         self.cycles += rhs.cycles;
         self.events |= rhs.events;
+        self.breakpoint_hits.extend(rhs.breakpoint_hits);
     }
 }
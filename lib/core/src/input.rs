@@ -53,6 +53,15 @@ pub struct Input {
     /// The pressed state of each button last time;
     /// used to detect changes in the pressed state of each button
     previous_button_states: u8,
+
+    /// The current tilt of the device on the X and Y axis in g,
+    /// which is used by cartridges with an accelerometer.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_since::<2, _, _>"))]
+    tilt: (f32, f32),
+
+    /// Whether the tilt was changed since it was last forwarded to the cartridge.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    tilt_changed: bool,
 }
 
 
@@ -79,6 +88,8 @@ impl Input {
             button_selection:       0x00,
             button_states:          0x00,
             previous_button_states: 0x00,
+            tilt:                   (0.0, 0.0),
+            tilt_changed:           false,
         }
     }
 
@@ -109,6 +120,38 @@ impl Input {
         get_bit(self.button_states, button as u8)
     }
 
+    /// Get the pressed state of all buttons, where each bit represents
+    /// the button with the according [InputButton] index.
+    pub fn get_button_states(&self) -> u8 {
+        self.button_states
+    }
+
+    /// Changes the 'pressed' state of all buttons at once, where each bit represents
+    /// the button with the according [InputButton] index.
+    pub fn set_button_states(&mut self, button_states: u8) {
+        self.button_states = button_states;
+    }
+
+    /// Sets the current tilt of the device, which is read by cartridges with an accelerometer.
+    /// `x` is the tilt to the right and `y` the tilt towards the bottom in g,
+    /// where 1.0 is a full 90° tilt and 0.0 is holding the device flat.
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        if self.tilt != (x, y) {
+            self.tilt         = (x, y);
+            self.tilt_changed = true;
+        }
+    }
+
+    /// Get the current tilt of the device on the X and Y axis in g.
+    pub fn get_tilt(&self) -> (f32, f32) {
+        self.tilt
+    }
+
+    /// Get the current tilt of the device, if it was changed since the last call.
+    pub(crate) fn take_tilt_change(&mut self) -> Option<(f32, f32)> {
+        take(&mut self.tilt_changed).then_some(self.tilt)
+    }
+
     /// Requests an interrupt to be fired.
     fn request_interrupt(&mut self, interrupt: Interrupt) {
         self.signals.interrupts |= interrupt;
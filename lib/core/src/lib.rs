@@ -17,6 +17,7 @@
 
 pub mod apu;
 pub mod boot_rom;
+pub mod camera;
 pub mod cartridge;
 pub mod cpu;
 pub mod debug;
@@ -24,7 +25,10 @@ pub mod device_type;
 pub mod gameboy;
 pub mod input;
 pub mod mmu;
+pub mod movie;
 pub mod ppu;
+pub mod printer;
+pub mod rewind;
 pub mod serial;
 pub mod snapshots;
 pub mod timer;
@@ -66,6 +66,8 @@ pub const MEMORY_LOCATION_PALETTE_OBP0:             u16 = 0xff48;
 pub const MEMORY_LOCATION_PALETTE_OBP1:             u16 = 0xff49;
 pub const MEMORY_LOCATION_WY:                       u16 = 0xff4a;
 pub const MEMORY_LOCATION_WX:                       u16 = 0xff4b;
+pub const MEMORY_LOCATION_KEY0:                     u16 = 0xff4c;
+pub const MEMORY_LOCATION_KEY1:                     u16 = 0xff4d;
 pub const MEMORY_LOCATION_VBK:                      u16 = 0xff4f;
 pub const MEMORY_LOCATION_BOOT_ROM_DISABLE:         u16 = 0xff50;
 pub const MEMORY_LOCATION_HDMA1:                    u16 = 0xff51;
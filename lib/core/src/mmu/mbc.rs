@@ -17,11 +17,21 @@
 
 use std::fmt::{Display, Formatter};
 
+use crate::camera::CameraImageSource;
 use crate::cartridge::Cartridge;
+use crate::gameboy::Clock;
+use crate::mmu::mbc::huc1::Huc1;
+use crate::mmu::mbc::huc3::Huc3;
 use crate::mmu::mbc::mbc1::Mbc1;
 use crate::mmu::mbc::mbc2::Mbc2;
+use crate::mmu::mbc::mbc3::Mbc3;
 use crate::mmu::mbc::mbc5::Mbc5;
+use crate::mmu::mbc::mbc6::Mbc6;
+use crate::mmu::mbc::mbc7::Mbc7;
 use crate::mmu::mbc::mbc_none::MbcNone;
+use crate::mmu::mbc::mmm01::Mmm01;
+use crate::mmu::mbc::pocket_camera::PocketCamera;
+use crate::mmu::mbc::tama5::Tama5;
 
 
 /// Type of memory bank controller to be used
@@ -35,6 +45,11 @@ pub enum MemoryBankController {
     MBC5,
     MBC6,
     MBC7,
+    MMM01,
+    HuC1,
+    HuC3,
+    TAMA5,
+    PocketCamera,
 }
 
 
@@ -45,32 +60,98 @@ pub enum Mbc {
     MBC1(Mbc1),
     MBC1M(Mbc1),
     MBC2(Mbc2),
-    MBC3(MbcNone),
+    MBC3(Mbc3),
     MBC5(Mbc5),
-    MBC6(MbcNone),
-    MBC7(MbcNone),
+    MBC6(
+        // before version 2, MBC6 and MBC7 were not emulated and did not store any state
+        #[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_since::<2, _, _>"))]
+        Mbc6
+    ),
+    MBC7(
+        #[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_since::<2, _, _>"))]
+        Mbc7
+    ),
+    MMM01(Mmm01),
+    HuC1(Huc1),
+    HuC3(Huc3),
+    TAMA5(Tama5),
+    PocketCamera(PocketCamera),
 }
 
 
 /// Trait for objects acting as memory bank controller.
 pub trait MbcImpl {
+    /// Initializes the memory bank controller for the cartridge it was created for.
+    fn init(&mut self, _cartridge: &Cartridge) {
+    }
+
     /// Read a single byte from the device memory.
     fn read_byte(&self, cartridge: &Cartridge, address: u16) -> u8;
 
     /// Write a single byte into the device memory.
     fn write_byte(&mut self, cartridge: &mut Cartridge, address: u16, value: u8);
+
+    /// Let the memory bank controller handle time based components
+    /// like a real time clock. 'cycles' gives the number of ticks
+    /// passed since the last call.
+    fn update(&mut self, _cartridge: &mut Cartridge, _cycles: Clock) {
+    }
+
+    /// Get a sequence of register writes, which restores the current
+    /// state of the memory bank controller when being replayed on a
+    /// freshly created instance.
+    fn get_register_writes(&self) -> Vec<(u16, u8)> {
+        Vec::new()
+    }
+
+    /// Get the number of the ROM bank currently mapped into a ROM address.
+    fn get_rom_bank(&self, address: u16) -> u32 {
+        if address < 0x4000 { 0 } else { 1 }
+    }
+
+    /// Checks whether a ROM address currently maps a ROM bank,
+    /// rather than other memory like the flash memory of MBC6 cartridges.
+    fn is_rom_mapped(&self, _address: u16) -> bool {
+        true
+    }
+
+    /// Set the current tilt of the device for cartridges with an accelerometer.
+    /// `x` and `y` are the acceleration on each axis in g.
+    fn set_tilt(&mut self, _x: f32, _y: f32) {
+    }
+
+    /// Connects the source of images seen by cartridges with a camera sensor.
+    fn set_camera_image_source(&mut self, _source: Box<dyn CameraImageSource>) {
+    }
+
+    /// Disconnects the source of images seen by cartridges with a camera sensor and returns it.
+    fn take_camera_image_source(&mut self) -> Option<Box<dyn CameraImageSource>> {
+        None
+    }
+
+    /// Checks whether the rumble motor of the cartridge is currently turned on.
+    fn is_rumble_active(&self, _cartridge: &Cartridge) -> bool {
+        false
+    }
 }
 
 
 /// Creates a memory bank controller object based on the type given.
 pub fn create_mbc(kind: &MemoryBankController) -> Mbc {
     match kind {
-        MemoryBankController::None  => Mbc::None(MbcNone::new()),
-        MemoryBankController::MBC1  => Mbc::MBC1(Mbc1::new()),
-        MemoryBankController::MBC1M => Mbc::MBC1(Mbc1::new_multicart()),
-        MemoryBankController::MBC2  => Mbc::MBC2(Mbc2::new()),
-        MemoryBankController::MBC5  => Mbc::MBC5(Mbc5::new()),
-        _                           => panic!("Not implemented {}", kind)
+        MemoryBankController::None         => Mbc::None(MbcNone::new()),
+        MemoryBankController::MBC1         => Mbc::MBC1(Mbc1::new()),
+        MemoryBankController::MBC1M        => Mbc::MBC1(Mbc1::new_multicart()),
+        MemoryBankController::MBC2         => Mbc::MBC2(Mbc2::new()),
+        MemoryBankController::MBC3         => Mbc::MBC3(Mbc3::new()),
+        MemoryBankController::MBC5         => Mbc::MBC5(Mbc5::new()),
+        MemoryBankController::MBC6         => Mbc::MBC6(Mbc6::new()),
+        MemoryBankController::MBC7         => Mbc::MBC7(Mbc7::new()),
+        MemoryBankController::MMM01        => Mbc::MMM01(Mmm01::new()),
+        MemoryBankController::HuC1         => Mbc::HuC1(Huc1::new()),
+        MemoryBankController::HuC3         => Mbc::HuC3(Huc3::new()),
+        MemoryBankController::TAMA5        => Mbc::TAMA5(Tama5::new()),
+        MemoryBankController::PocketCamera => Mbc::PocketCamera(PocketCamera::new()),
     }
 }
 
@@ -78,14 +159,19 @@ pub fn create_mbc(kind: &MemoryBankController) -> Mbc {
 impl Display for MemoryBankController {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let name = match *self {
-            MemoryBankController::None  => "None",
-            MemoryBankController::MBC1  => "MBC1",
-            MemoryBankController::MBC1M => "MBC1M",
-            MemoryBankController::MBC2  => "MBC2",
-            MemoryBankController::MBC3  => "MBC3",
-            MemoryBankController::MBC5  => "MBC5",
-            MemoryBankController::MBC6  => "MBC6",
-            MemoryBankController::MBC7  => "MBC7",
+            MemoryBankController::None         => "None",
+            MemoryBankController::MBC1         => "MBC1",
+            MemoryBankController::MBC1M        => "MBC1M",
+            MemoryBankController::MBC2         => "MBC2",
+            MemoryBankController::MBC3         => "MBC3",
+            MemoryBankController::MBC5         => "MBC5",
+            MemoryBankController::MBC6         => "MBC6",
+            MemoryBankController::MBC7         => "MBC7",
+            MemoryBankController::MMM01        => "MMM01",
+            MemoryBankController::HuC1         => "HuC1",
+            MemoryBankController::HuC3         => "HuC3",
+            MemoryBankController::TAMA5        => "TAMA5",
+            MemoryBankController::PocketCamera => "Pocket Camera",
         };
 
         write!(f, "{:}", name)
@@ -94,30 +180,211 @@ impl Display for MemoryBankController {
 
 
 impl MbcImpl for Mbc {
+    fn init(&mut self, cartridge: &Cartridge) {
+        match self {
+            Mbc::None(mbc_impl)         => mbc_impl.init(cartridge),
+            Mbc::MBC1(mbc_impl)         => mbc_impl.init(cartridge),
+            Mbc::MBC1M(mbc_impl)        => mbc_impl.init(cartridge),
+            Mbc::MBC2(mbc_impl)         => mbc_impl.init(cartridge),
+            Mbc::MBC3(mbc_impl)         => mbc_impl.init(cartridge),
+            Mbc::MBC5(mbc_impl)         => mbc_impl.init(cartridge),
+            Mbc::MBC6(mbc_impl)         => mbc_impl.init(cartridge),
+            Mbc::MBC7(mbc_impl)         => mbc_impl.init(cartridge),
+            Mbc::MMM01(mbc_impl)        => mbc_impl.init(cartridge),
+            Mbc::HuC1(mbc_impl)         => mbc_impl.init(cartridge),
+            Mbc::HuC3(mbc_impl)         => mbc_impl.init(cartridge),
+            Mbc::TAMA5(mbc_impl)        => mbc_impl.init(cartridge),
+            Mbc::PocketCamera(mbc_impl) => mbc_impl.init(cartridge),
+        }
+    }
+
+
     fn read_byte(&self, cartridge: &Cartridge, address: u16) -> u8 {
         match self {
-            Mbc::None(mbc_impl)  => mbc_impl.read_byte(cartridge, address),
-            Mbc::MBC1(mbc_impl)  => mbc_impl.read_byte(cartridge, address),
-            Mbc::MBC1M(mbc_impl) => mbc_impl.read_byte(cartridge, address),
-            Mbc::MBC2(mbc_impl)  => mbc_impl.read_byte(cartridge, address),
-            Mbc::MBC3(mbc_impl)  => mbc_impl.read_byte(cartridge, address),
-            Mbc::MBC5(mbc_impl)  => mbc_impl.read_byte(cartridge, address),
-            Mbc::MBC6(mbc_impl)  => mbc_impl.read_byte(cartridge, address),
-            Mbc::MBC7(mbc_impl)  => mbc_impl.read_byte(cartridge, address),
+            Mbc::None(mbc_impl)         => mbc_impl.read_byte(cartridge, address),
+            Mbc::MBC1(mbc_impl)         => mbc_impl.read_byte(cartridge, address),
+            Mbc::MBC1M(mbc_impl)        => mbc_impl.read_byte(cartridge, address),
+            Mbc::MBC2(mbc_impl)         => mbc_impl.read_byte(cartridge, address),
+            Mbc::MBC3(mbc_impl)         => mbc_impl.read_byte(cartridge, address),
+            Mbc::MBC5(mbc_impl)         => mbc_impl.read_byte(cartridge, address),
+            Mbc::MBC6(mbc_impl)         => mbc_impl.read_byte(cartridge, address),
+            Mbc::MBC7(mbc_impl)         => mbc_impl.read_byte(cartridge, address),
+            Mbc::MMM01(mbc_impl)        => mbc_impl.read_byte(cartridge, address),
+            Mbc::HuC1(mbc_impl)         => mbc_impl.read_byte(cartridge, address),
+            Mbc::HuC3(mbc_impl)         => mbc_impl.read_byte(cartridge, address),
+            Mbc::TAMA5(mbc_impl)        => mbc_impl.read_byte(cartridge, address),
+            Mbc::PocketCamera(mbc_impl) => mbc_impl.read_byte(cartridge, address),
         }
     }
 
 
     fn write_byte(&mut self, cartridge: &mut Cartridge, address: u16, value: u8) {
         match self {
-            Mbc::None(mbc_impl)  => mbc_impl.write_byte(cartridge, address, value),
-            Mbc::MBC1(mbc_impl)  => mbc_impl.write_byte(cartridge, address, value),
-            Mbc::MBC1M(mbc_impl) => mbc_impl.write_byte(cartridge, address, value),
-            Mbc::MBC2(mbc_impl)  => mbc_impl.write_byte(cartridge, address, value),
-            Mbc::MBC3(mbc_impl)  => mbc_impl.write_byte(cartridge, address, value),
-            Mbc::MBC5(mbc_impl)  => mbc_impl.write_byte(cartridge, address, value),
-            Mbc::MBC6(mbc_impl)  => mbc_impl.write_byte(cartridge, address, value),
-            Mbc::MBC7(mbc_impl)  => mbc_impl.write_byte(cartridge, address, value),
+            Mbc::None(mbc_impl)         => mbc_impl.write_byte(cartridge, address, value),
+            Mbc::MBC1(mbc_impl)         => mbc_impl.write_byte(cartridge, address, value),
+            Mbc::MBC1M(mbc_impl)        => mbc_impl.write_byte(cartridge, address, value),
+            Mbc::MBC2(mbc_impl)         => mbc_impl.write_byte(cartridge, address, value),
+            Mbc::MBC3(mbc_impl)         => mbc_impl.write_byte(cartridge, address, value),
+            Mbc::MBC5(mbc_impl)         => mbc_impl.write_byte(cartridge, address, value),
+            Mbc::MBC6(mbc_impl)         => mbc_impl.write_byte(cartridge, address, value),
+            Mbc::MBC7(mbc_impl)         => mbc_impl.write_byte(cartridge, address, value),
+            Mbc::MMM01(mbc_impl)        => mbc_impl.write_byte(cartridge, address, value),
+            Mbc::HuC1(mbc_impl)         => mbc_impl.write_byte(cartridge, address, value),
+            Mbc::HuC3(mbc_impl)         => mbc_impl.write_byte(cartridge, address, value),
+            Mbc::TAMA5(mbc_impl)        => mbc_impl.write_byte(cartridge, address, value),
+            Mbc::PocketCamera(mbc_impl) => mbc_impl.write_byte(cartridge, address, value),
+        }
+    }
+
+
+    fn update(&mut self, cartridge: &mut Cartridge, cycles: Clock) {
+        match self {
+            Mbc::None(mbc_impl)         => mbc_impl.update(cartridge, cycles),
+            Mbc::MBC1(mbc_impl)         => mbc_impl.update(cartridge, cycles),
+            Mbc::MBC1M(mbc_impl)        => mbc_impl.update(cartridge, cycles),
+            Mbc::MBC2(mbc_impl)         => mbc_impl.update(cartridge, cycles),
+            Mbc::MBC3(mbc_impl)         => mbc_impl.update(cartridge, cycles),
+            Mbc::MBC5(mbc_impl)         => mbc_impl.update(cartridge, cycles),
+            Mbc::MBC6(mbc_impl)         => mbc_impl.update(cartridge, cycles),
+            Mbc::MBC7(mbc_impl)         => mbc_impl.update(cartridge, cycles),
+            Mbc::MMM01(mbc_impl)        => mbc_impl.update(cartridge, cycles),
+            Mbc::HuC1(mbc_impl)         => mbc_impl.update(cartridge, cycles),
+            Mbc::HuC3(mbc_impl)         => mbc_impl.update(cartridge, cycles),
+            Mbc::TAMA5(mbc_impl)        => mbc_impl.update(cartridge, cycles),
+            Mbc::PocketCamera(mbc_impl) => mbc_impl.update(cartridge, cycles),
+        }
+    }
+
+
+    fn get_register_writes(&self) -> Vec<(u16, u8)> {
+        match self {
+            Mbc::None(mbc_impl)         => mbc_impl.get_register_writes(),
+            Mbc::MBC1(mbc_impl)         => mbc_impl.get_register_writes(),
+            Mbc::MBC1M(mbc_impl)        => mbc_impl.get_register_writes(),
+            Mbc::MBC2(mbc_impl)         => mbc_impl.get_register_writes(),
+            Mbc::MBC3(mbc_impl)         => mbc_impl.get_register_writes(),
+            Mbc::MBC5(mbc_impl)         => mbc_impl.get_register_writes(),
+            Mbc::MBC6(mbc_impl)         => mbc_impl.get_register_writes(),
+            Mbc::MBC7(mbc_impl)         => mbc_impl.get_register_writes(),
+            Mbc::MMM01(mbc_impl)        => mbc_impl.get_register_writes(),
+            Mbc::HuC1(mbc_impl)         => mbc_impl.get_register_writes(),
+            Mbc::HuC3(mbc_impl)         => mbc_impl.get_register_writes(),
+            Mbc::TAMA5(mbc_impl)        => mbc_impl.get_register_writes(),
+            Mbc::PocketCamera(mbc_impl) => mbc_impl.get_register_writes(),
+        }
+    }
+
+
+    fn get_rom_bank(&self, address: u16) -> u32 {
+        match self {
+            Mbc::None(mbc_impl)         => mbc_impl.get_rom_bank(address),
+            Mbc::MBC1(mbc_impl)         => mbc_impl.get_rom_bank(address),
+            Mbc::MBC1M(mbc_impl)        => mbc_impl.get_rom_bank(address),
+            Mbc::MBC2(mbc_impl)         => mbc_impl.get_rom_bank(address),
+            Mbc::MBC3(mbc_impl)         => mbc_impl.get_rom_bank(address),
+            Mbc::MBC5(mbc_impl)         => mbc_impl.get_rom_bank(address),
+            Mbc::MBC6(mbc_impl)         => mbc_impl.get_rom_bank(address),
+            Mbc::MBC7(mbc_impl)         => mbc_impl.get_rom_bank(address),
+            Mbc::MMM01(mbc_impl)        => mbc_impl.get_rom_bank(address),
+            Mbc::HuC1(mbc_impl)         => mbc_impl.get_rom_bank(address),
+            Mbc::HuC3(mbc_impl)         => mbc_impl.get_rom_bank(address),
+            Mbc::TAMA5(mbc_impl)        => mbc_impl.get_rom_bank(address),
+            Mbc::PocketCamera(mbc_impl) => mbc_impl.get_rom_bank(address),
+        }
+    }
+
+
+    fn is_rom_mapped(&self, address: u16) -> bool {
+        match self {
+            Mbc::None(mbc_impl)         => mbc_impl.is_rom_mapped(address),
+            Mbc::MBC1(mbc_impl)         => mbc_impl.is_rom_mapped(address),
+            Mbc::MBC1M(mbc_impl)        => mbc_impl.is_rom_mapped(address),
+            Mbc::MBC2(mbc_impl)         => mbc_impl.is_rom_mapped(address),
+            Mbc::MBC3(mbc_impl)         => mbc_impl.is_rom_mapped(address),
+            Mbc::MBC5(mbc_impl)         => mbc_impl.is_rom_mapped(address),
+            Mbc::MBC6(mbc_impl)         => mbc_impl.is_rom_mapped(address),
+            Mbc::MBC7(mbc_impl)         => mbc_impl.is_rom_mapped(address),
+            Mbc::MMM01(mbc_impl)        => mbc_impl.is_rom_mapped(address),
+            Mbc::HuC1(mbc_impl)         => mbc_impl.is_rom_mapped(address),
+            Mbc::HuC3(mbc_impl)         => mbc_impl.is_rom_mapped(address),
+            Mbc::TAMA5(mbc_impl)        => mbc_impl.is_rom_mapped(address),
+            Mbc::PocketCamera(mbc_impl) => mbc_impl.is_rom_mapped(address),
+        }
+    }
+
+
+    fn set_tilt(&mut self, x: f32, y: f32) {
+        match self {
+            Mbc::None(mbc_impl)         => mbc_impl.set_tilt(x, y),
+            Mbc::MBC1(mbc_impl)         => mbc_impl.set_tilt(x, y),
+            Mbc::MBC1M(mbc_impl)        => mbc_impl.set_tilt(x, y),
+            Mbc::MBC2(mbc_impl)         => mbc_impl.set_tilt(x, y),
+            Mbc::MBC3(mbc_impl)         => mbc_impl.set_tilt(x, y),
+            Mbc::MBC5(mbc_impl)         => mbc_impl.set_tilt(x, y),
+            Mbc::MBC6(mbc_impl)         => mbc_impl.set_tilt(x, y),
+            Mbc::MBC7(mbc_impl)         => mbc_impl.set_tilt(x, y),
+            Mbc::MMM01(mbc_impl)        => mbc_impl.set_tilt(x, y),
+            Mbc::HuC1(mbc_impl)         => mbc_impl.set_tilt(x, y),
+            Mbc::HuC3(mbc_impl)         => mbc_impl.set_tilt(x, y),
+            Mbc::TAMA5(mbc_impl)        => mbc_impl.set_tilt(x, y),
+            Mbc::PocketCamera(mbc_impl) => mbc_impl.set_tilt(x, y),
+        }
+    }
+
+
+    fn set_camera_image_source(&mut self, source: Box<dyn CameraImageSource>) {
+        match self {
+            Mbc::None(mbc_impl)         => mbc_impl.set_camera_image_source(source),
+            Mbc::MBC1(mbc_impl)         => mbc_impl.set_camera_image_source(source),
+            Mbc::MBC1M(mbc_impl)        => mbc_impl.set_camera_image_source(source),
+            Mbc::MBC2(mbc_impl)         => mbc_impl.set_camera_image_source(source),
+            Mbc::MBC3(mbc_impl)         => mbc_impl.set_camera_image_source(source),
+            Mbc::MBC5(mbc_impl)         => mbc_impl.set_camera_image_source(source),
+            Mbc::MBC6(mbc_impl)         => mbc_impl.set_camera_image_source(source),
+            Mbc::MBC7(mbc_impl)         => mbc_impl.set_camera_image_source(source),
+            Mbc::MMM01(mbc_impl)        => mbc_impl.set_camera_image_source(source),
+            Mbc::HuC1(mbc_impl)         => mbc_impl.set_camera_image_source(source),
+            Mbc::HuC3(mbc_impl)         => mbc_impl.set_camera_image_source(source),
+            Mbc::TAMA5(mbc_impl)        => mbc_impl.set_camera_image_source(source),
+            Mbc::PocketCamera(mbc_impl) => mbc_impl.set_camera_image_source(source),
+        }
+    }
+
+
+    fn take_camera_image_source(&mut self) -> Option<Box<dyn CameraImageSource>> {
+        match self {
+            Mbc::None(mbc_impl)        => mbc_impl.take_camera_image_source(),
+            Mbc::MBC1(mbc_impl)        => mbc_impl.take_camera_image_source(),
+            Mbc::MBC1M(mbc_impl)       => mbc_impl.take_camera_image_source(),
+            Mbc::MBC2(mbc_impl)        => mbc_impl.take_camera_image_source(),
+            Mbc::MBC3(mbc_impl)        => mbc_impl.take_camera_image_source(),
+            Mbc::MBC5(mbc_impl)        => mbc_impl.take_camera_image_source(),
+            Mbc::MBC6(mbc_impl)        => mbc_impl.take_camera_image_source(),
+            Mbc::MBC7(mbc_impl)        => mbc_impl.take_camera_image_source(),
+            Mbc::MMM01(mbc_impl)       => mbc_impl.take_camera_image_source(),
+            Mbc::HuC1(mbc_impl)        => mbc_impl.take_camera_image_source(),
+            Mbc::HuC3(mbc_impl)        => mbc_impl.take_camera_image_source(),
+            Mbc::TAMA5(mbc_impl)       => mbc_impl.take_camera_image_source(),
+            Mbc::PocketCamera(mbc_impl)=> mbc_impl.take_camera_image_source(),
+        }
+    }
+
+
+    fn is_rumble_active(&self, cartridge: &Cartridge) -> bool {
+        match self {
+            Mbc::None(mbc_impl)         => mbc_impl.is_rumble_active(cartridge),
+            Mbc::MBC1(mbc_impl)         => mbc_impl.is_rumble_active(cartridge),
+            Mbc::MBC1M(mbc_impl)        => mbc_impl.is_rumble_active(cartridge),
+            Mbc::MBC2(mbc_impl)         => mbc_impl.is_rumble_active(cartridge),
+            Mbc::MBC3(mbc_impl)         => mbc_impl.is_rumble_active(cartridge),
+            Mbc::MBC5(mbc_impl)         => mbc_impl.is_rumble_active(cartridge),
+            Mbc::MBC6(mbc_impl)         => mbc_impl.is_rumble_active(cartridge),
+            Mbc::MBC7(mbc_impl)         => mbc_impl.is_rumble_active(cartridge),
+            Mbc::MMM01(mbc_impl)        => mbc_impl.is_rumble_active(cartridge),
+            Mbc::HuC1(mbc_impl)         => mbc_impl.is_rumble_active(cartridge),
+            Mbc::HuC3(mbc_impl)         => mbc_impl.is_rumble_active(cartridge),
+            Mbc::TAMA5(mbc_impl)        => mbc_impl.is_rumble_active(cartridge),
+            Mbc::PocketCamera(mbc_impl) => mbc_impl.is_rumble_active(cartridge),
         }
     }
 }
@@ -384,6 +651,21 @@ mod mbc1 {
                 _ => unreachable!("Unexpected write to address {}", address),
             }
         }
+
+
+        fn get_register_writes(&self) -> Vec<(u16, u8)> {
+            vec![
+                (0x0000, if self.ram_enabled { 0x0a } else { 0x00 }),
+                (0x2000, self.bank_selection_0),
+                (0x4000, self.bank_selection_1),
+                (0x6000, self.mode),
+            ]
+        }
+
+
+        fn get_rom_bank(&self, address: u16) -> u32 {
+            if address < 0x4000 { self.rom_bank_0_selected } else { self.rom_bank_1_selected }
+        }
     }
 }
 
@@ -525,6 +807,226 @@ mod mbc2 {
                 _ => unreachable!("Unexpected write to address {}", address),
             }
         }
+
+
+        fn get_register_writes(&self) -> Vec<(u16, u8)> {
+            vec![
+                (0x0000, if self.ram_enabled { 0x0a } else { 0x00 }),
+                (0x0100, self.bank_selection_0),
+            ]
+        }
+
+
+        fn get_rom_bank(&self, address: u16) -> u32 {
+            if address < 0x4000 { 0 } else { self.rom_bank_selected }
+        }
+    }
+}
+
+
+mod mbc3 {
+    use super::*;
+    use crate::mmu::memory_data::MemoryData;
+    use crate::mmu::rtc::{RTC_REGISTER_DAYS_HIGH, RTC_REGISTER_SECONDS};
+
+
+    /// Type 3 Memory Bank Controller:
+    /// Supports up to 2MiB ROMs, up to 32kiB RAM and an optional real time clock.
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Mbc3 {
+        /// The value written into the ROM bank selection register.
+        rom_bank_selection: u8,
+
+        /// The value written into the RAM bank selection register.
+        /// Values 0x00 - 0x07 select a RAM bank, 0x08 - 0x0c map
+        /// a register of the real time clock into the RAM area.
+        ram_bank_selection: u8,
+
+        /// The selected ROM bank number.
+        rom_bank_selected: u32,
+
+        /// The offset added to the address the game wants to read from,
+        /// to get the real address within the ROM file.
+        rom_bank_offset: usize,
+
+        /// The offset added to the address the game wants to read/write,
+        /// to get the real address within the RAM image.
+        ram_bank_offset: usize,
+
+        /// Sets if the RAM bank and clock registers are enabled or not.
+        ram_enabled: bool,
+
+        /// The last value written into the latch clock register.
+        /// Writing 0x00 followed by 0x01 will latch the current time.
+        latch_register_value: u8,
+    }
+
+
+    impl Mbc3 {
+        pub fn new() -> Self {
+            Self {
+                rom_bank_selection: 0x00,
+                ram_bank_selection: 0x00,
+
+                rom_bank_selected: 1,
+                rom_bank_offset:   0x4000,
+
+                ram_bank_offset:   0x0000,
+
+                ram_enabled: false,
+
+                latch_register_value: 0xff,
+            }
+        }
+
+
+        /// After writing to one of the bank selection registers,
+        /// this function is used to calculate the actual RAM and ROM bank numbers
+        /// as well as the offsets to read and write inside the ROM and RAM images.
+        fn update_selected_banks(&mut self, cartridge: &Cartridge) {
+            // like on MBC1, selecting bank 0 will select bank 1 instead
+            let rom_bank = if self.rom_bank_selection != 0 {
+                self.rom_bank_selection as u32
+            }
+            else {
+                1
+            };
+
+            // store the rom bank and the offset to be added to all
+            // requested addresses, beginning with 0x4000
+            if cartridge.get_rom_bank_count() != 0 {
+                self.rom_bank_selected = rom_bank % cartridge.get_rom_bank_count();
+                self.rom_bank_offset   = (self.rom_bank_selected as usize) * 0x4000;
+            }
+
+            // store the ram bank and the offset to be added to all addresses,
+            // unless a clock register is selected
+            if cartridge.get_ram_bank_count() != 0 && self.ram_bank_selection < RTC_REGISTER_SECONDS {
+                let ram_bank = (self.ram_bank_selection as u32) % cartridge.get_ram_bank_count();
+                self.ram_bank_offset = (ram_bank as usize) * 0x2000;
+            }
+        }
+
+
+        /// Checks whether a clock register is mapped into the RAM area.
+        fn is_rtc_register_selected(&self) -> bool {
+            (RTC_REGISTER_SECONDS ..= RTC_REGISTER_DAYS_HIGH).contains(&self.ram_bank_selection)
+        }
+    }
+
+
+    impl MbcImpl for Mbc3 {
+        fn read_byte(&self, cartridge: &Cartridge, address: u16) -> u8 {
+            match address {
+                // read from fixed ROM bank, which is always bank 0.
+                0x0000 ..= 0x3fff => {
+                    let rom_address = address as usize;
+                    cartridge.get_rom().get_at(rom_address)
+                },
+
+                // read from the switchable ROM bank
+                0x4000 ..= 0x7fff => {
+                    let rom_address = (address as usize) + self.rom_bank_offset - 0x4000;
+                    cartridge.get_rom().get_at(rom_address)
+                },
+
+                // read from switchable RAM bank or clock register.
+                0xa000 ..= 0xbfff => {
+                    if !self.ram_enabled {
+                        0xff
+                    }
+                    else if self.is_rtc_register_selected() {
+                        match cartridge.get_rtc() {
+                            Some(rtc) => rtc.read_register(self.ram_bank_selection),
+                            None      => 0xff,
+                        }
+                    }
+                    else if cartridge.has_ram() && self.ram_bank_selection < RTC_REGISTER_SECONDS {
+                        let ram_address = (address as usize) + self.ram_bank_offset - 0xa000;
+                        cartridge.get_ram().get_at(ram_address)
+                    }
+                    else {
+                        0xff
+                    }
+                }
+
+                _ => unreachable!("Unexpected read from address {}", address),
+            }
+        }
+
+
+        fn write_byte(&mut self, cartridge: &mut Cartridge, address: u16, value: u8) {
+            match address {
+                // enable or disable RAM and clock registers
+                0x0000 ..= 0x1fff => {
+                    self.ram_enabled = (value & 0x0f) == 0x0a;
+                },
+
+                // ROM bank selection
+                0x2000 ..= 0x3fff => {
+                    self.rom_bank_selection = value & 0x7f;
+                    self.update_selected_banks(cartridge);
+                },
+
+                // RAM bank or clock register selection
+                0x4000 ..= 0x5fff => {
+                    self.ram_bank_selection = value & 0x0f;
+                    self.update_selected_banks(cartridge);
+                },
+
+                // latch clock data
+                0x6000 ..= 0x7fff => {
+                    if self.latch_register_value == 0x00 && value == 0x01 {
+                        if let Some(rtc) = cartridge.get_rtc_mut() {
+                            rtc.latch();
+                        }
+                    }
+
+                    self.latch_register_value = value;
+                },
+
+                // Cartridge RAM or clock register
+                0xa000 ..= 0xbfff => {
+                    if !self.ram_enabled {
+                        return;
+                    }
+
+                    if self.is_rtc_register_selected() {
+                        if let Some(rtc) = cartridge.get_rtc_mut() {
+                            rtc.write_register(self.ram_bank_selection, value);
+                        }
+                    }
+                    else if cartridge.has_ram() && self.ram_bank_selection < RTC_REGISTER_SECONDS {
+                        let ram_address = (address as usize) - 0xa000 + self.ram_bank_offset;
+                        cartridge.get_ram_mut().set_at(ram_address, value);
+                    }
+                },
+
+                _ => unreachable!("Unexpected write to address {}", address),
+            }
+        }
+
+
+        fn get_register_writes(&self) -> Vec<(u16, u8)> {
+            vec![
+                (0x0000, if self.ram_enabled { 0x0a } else { 0x00 }),
+                (0x2000, self.rom_bank_selection),
+                (0x4000, self.ram_bank_selection),
+                (0x6000, self.latch_register_value),
+            ]
+        }
+
+
+        fn get_rom_bank(&self, address: u16) -> u32 {
+            if address < 0x4000 { 0 } else { self.rom_bank_selected }
+        }
+
+
+        fn update(&mut self, cartridge: &mut Cartridge, cycles: Clock) {
+            if let Some(rtc) = cartridge.get_rtc_mut() {
+                rtc.update(cycles);
+            }
+        }
     }
 }
 
@@ -532,6 +1034,7 @@ mod mbc2 {
 mod mbc5 {
     use super::*;
     use crate::mmu::memory_data::MemoryData;
+    use crate::utils::get_bit;
 
 
     /// Type 5 Memory Bank Controller:
@@ -547,6 +1050,8 @@ mod mbc5 {
         rom_bank_selection_1: u8,
 
         /// The value written into the RAM bank selection register.
+        /// On cartridges with a rumble motor, bit 3 controls the motor
+        /// instead of selecting the RAM bank.
         ram_bank_selection_0: u8,
 
         /// The selected ROM bank number.
@@ -595,8 +1100,10 @@ mod mbc5 {
               | ((self.rom_bank_selection_1 as u32 & 0x01) << 8)
             ;
 
+            // rumble cartridges use bit 3 for the motor, so only 8 RAM banks can be selected
+            let ram_bank_mask = if cartridge.has_rumble() { 0x07 } else { 0x0f };
             let ram_bank =
-                self.ram_bank_selection_0 as u32
+                self.ram_bank_selection_0 as u32 & ram_bank_mask
             ;
 
             // store the rom bank and the offset to be added to all
@@ -609,7 +1116,7 @@ mod mbc5 {
             // store the ram bank and the offset to be added to all addresses
             if cartridge.get_ram_bank_count() != 0 {
                 self.ram_bank_selected = ram_bank % cartridge.get_ram_bank_count();
-                self.ram_bank_offset   = (self.ram_bank_offset as usize) * 0x2000;
+                self.ram_bank_offset   = (self.ram_bank_selected as usize) * 0x2000;
             }
         }
     }
@@ -686,5 +1193,2395 @@ mod mbc5 {
                 _ => unreachable!("Unexpected write to address {}", address),
             }
         }
+
+
+        fn get_register_writes(&self) -> Vec<(u16, u8)> {
+            vec![
+                (0x0000, if self.ram_enabled { 0x0a } else { 0x00 }),
+                (0x2000, self.rom_bank_selection_0),
+                (0x3000, self.rom_bank_selection_1),
+                (0x4000, self.ram_bank_selection_0),
+            ]
+        }
+
+
+        fn get_rom_bank(&self, address: u16) -> u32 {
+            if address < 0x4000 { 0 } else { self.rom_bank_selected }
+        }
+
+
+        fn is_rumble_active(&self, cartridge: &Cartridge) -> bool {
+            cartridge.has_rumble() && get_bit(self.ram_bank_selection_0, 3)
+        }
+    }
+}
+
+
+mod mbc7 {
+    use super::*;
+    use crate::mmu::memory_data::MemoryData;
+    use crate::utils::get_bit;
+
+    /// The value of the accelerometer while the device is held flat.
+    const ACCELEROMETER_CENTER: f32 = 0x81d0 as f32;
+
+    /// The change of the accelerometer value per 1g of acceleration.
+    const ACCELEROMETER_SCALE: f32 = 0x70 as f32;
+
+    /// The value of both accelerometer registers after erasing the latched values.
+    const ACCELEROMETER_ERASED: u16 = 0x8000;
+
+    /// The number of bits of an EEPROM command following the start bit,
+    /// which are two bits of the opcode and 8 bits of the address.
+    const EEPROM_COMMAND_BITS: u8 = 10;
+
+
+    /// Type 7 Memory Bank Controller:
+    /// Supports up to 2MiB ROMs, a 93LC56 serial EEPROM as persistent
+    /// storage and a two-axis accelerometer.
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Mbc7 {
+        /// The value written into the ROM bank selection register.
+        rom_bank_selection: u8,
+
+        /// The selected ROM bank number.
+        rom_bank_selected: u32,
+
+        /// The offset added to the address the game wants to read from,
+        /// to get the real address within the ROM file.
+        rom_bank_offset: usize,
+
+        /// Sets if the first RAM enable register was enabled.
+        ram_enabled_1: bool,
+
+        /// Sets if the second RAM enable register was enabled.
+        ram_enabled_2: bool,
+
+        /// The current tilt of the device on the X axis in g.
+        tilt_x: f32,
+
+        /// The current tilt of the device on the Y axis in g.
+        tilt_y: f32,
+
+        /// The latched value of the accelerometer on the X axis.
+        accelerometer_x: u16,
+
+        /// The latched value of the accelerometer on the Y axis.
+        accelerometer_y: u16,
+
+        /// Whether the latched values were erased, which is required to latch new values.
+        accelerometer_erased: bool,
+
+        /// The serial EEPROM, which stores its data in the cartridge RAM.
+        eeprom: Eeprom,
+    }
+
+
+    /// The state of the 93LC56 serial EEPROM, which is accessed via a three-wire interface.
+    /// Its data is organized in 16 bit words and stored in the cartridge RAM.
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct Eeprom {
+        /// The state of the chip select line.
+        cs: bool,
+
+        /// The state of the clock line.
+        clk: bool,
+
+        /// The state of the data input line.
+        di: bool,
+
+        /// The state of the data output line.
+        dout: bool,
+
+        /// Whether erase and write commands are enabled.
+        write_enabled: bool,
+
+        /// The current state of the command being transferred.
+        state: EepromState,
+    }
+
+
+    /// The state of a command being transferred to the EEPROM.
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    enum EepromState {
+        /// Waiting for the start bit of the next command.
+        Idle,
+
+        /// Receiving the opcode and address bits of a command.
+        Command { bits: u16, count: u8 },
+
+        /// Sending the data of a word being read.
+        Read { data: u16, remaining: u8 },
+
+        /// Receiving the data to be written into a single word or,
+        /// if no address is given, into all words.
+        Write { address: Option<usize>, data: u16, count: u8 },
+
+        /// The command was completed, waiting for the chip select line to be released.
+        Done,
+    }
+
+
+    impl Mbc7 {
+        pub fn new() -> Self {
+            Self {
+                rom_bank_selection: 0x01,
+
+                rom_bank_selected: 1,
+                rom_bank_offset:   0x4000,
+
+                ram_enabled_1: false,
+                ram_enabled_2: false,
+
+                tilt_x: 0.0,
+                tilt_y: 0.0,
+
+                accelerometer_x:      ACCELEROMETER_ERASED,
+                accelerometer_y:      ACCELEROMETER_ERASED,
+                accelerometer_erased: false,
+
+                eeprom: Eeprom::new(),
+            }
+        }
+
+
+        /// After writing to the bank selection register, this function is used to
+        /// calculate the actual ROM bank number and the offset within the ROM image.
+        fn update_selected_banks(&mut self, cartridge: &Cartridge) {
+            if cartridge.get_rom_bank_count() != 0 {
+                self.rom_bank_selected = (self.rom_bank_selection as u32) % cartridge.get_rom_bank_count();
+                self.rom_bank_offset   = (self.rom_bank_selected as usize) * 0x4000;
+            }
+        }
+
+
+        /// Checks whether the register area is enabled by both RAM enable registers.
+        fn is_ram_enabled(&self) -> bool {
+            self.ram_enabled_1 && self.ram_enabled_2
+        }
+
+
+        /// Converts the tilt of an axis into the value of the accelerometer.
+        fn to_accelerometer_value(tilt: f32) -> u16 {
+            (ACCELEROMETER_CENTER + tilt * ACCELEROMETER_SCALE).clamp(0.0, u16::MAX as f32) as u16
+        }
+    }
+
+
+    impl Default for Mbc7 {
+        /// Creates the state of an MBC7 after power on.
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+
+    impl Eeprom {
+        fn new() -> Self {
+            Self {
+                cs:             false,
+                clk:            false,
+                di:             false,
+                dout:           true,
+                write_enabled:  false,
+                state:          EepromState::Idle,
+            }
+        }
+
+
+        /// Get the state of all lines, as read from the EEPROM register.
+        fn read(&self) -> u8 {
+                (if self.cs   { 0x80 } else { 0x00 })
+            |   (if self.clk  { 0x40 } else { 0x00 })
+            |   (if self.di   { 0x02 } else { 0x00 })
+            |   (if self.dout { 0x01 } else { 0x00 })
+        }
+
+
+        /// Changes the state of the input lines by writing into the EEPROM register.
+        /// Data is transferred on each rising edge of the clock line.
+        fn write(&mut self, ram: &mut impl MemoryData, value: u8) {
+            let cs  = get_bit(value, 7);
+            let clk = get_bit(value, 6);
+            let di  = get_bit(value, 1);
+
+            let is_rising_edge = cs && clk && !self.clk;
+
+            self.cs  = cs;
+            self.clk = clk;
+            self.di  = di;
+
+            // releasing chip select cancels any command
+            if !cs {
+                self.state = EepromState::Idle;
+                self.dout  = true;
+                return;
+            }
+
+            if is_rising_edge {
+                self.on_clock(ram);
+            }
+        }
+
+
+        /// Handles a single bit being transferred on a rising edge of the clock line.
+        fn on_clock(&mut self, ram: &mut impl MemoryData) {
+            let bit = self.di as u16;
+
+            self.state = match self.state {
+                // a command starts with a '1' bit
+                EepromState::Idle if bit == 1 => {
+                    EepromState::Command { bits: 0, count: 0 }
+                }
+
+                EepromState::Idle => {
+                    EepromState::Idle
+                }
+
+                EepromState::Command { bits, count } => {
+                    let bits  = (bits << 1) | bit;
+                    let count = count + 1;
+
+                    if count == EEPROM_COMMAND_BITS {
+                        self.run_command(ram, bits)
+                    }
+                    else {
+                        EepromState::Command { bits, count }
+                    }
+                }
+
+                EepromState::Read { data, remaining } => {
+                    self.dout = (data & 0x8000) != 0;
+
+                    match remaining {
+                        1 => EepromState::Done,
+                        _ => EepromState::Read { data: data << 1, remaining: remaining - 1 },
+                    }
+                }
+
+                EepromState::Write { address, data, count } => {
+                    let data  = (data << 1) | bit;
+                    let count = count + 1;
+
+                    if count == 16 {
+                        if self.write_enabled {
+                            match address {
+                                Some(address) => write_word(ram, address, data),
+                                None          => (0..get_word_count(ram)).for_each(|address| write_word(ram, address, data)),
+                            }
+                        }
+
+                        // signals the write operation to be completed
+                        self.dout = true;
+
+                        EepromState::Done
+                    }
+                    else {
+                        EepromState::Write { address, data, count }
+                    }
+                }
+
+                EepromState::Done => {
+                    EepromState::Done
+                }
+            };
+        }
+
+
+        /// Executes a command after receiving its opcode and address bits.
+        fn run_command(&mut self, ram: &mut impl MemoryData, bits: u16) -> EepromState {
+            let opcode  = (bits >> 8) & 0x03;
+            let address = ((bits & 0xff) as usize) % get_word_count(ram).max(1);
+
+            match opcode {
+                // READ: sends a dummy '0' bit followed by the 16 bits of the word
+                0b10 => {
+                    self.dout = false;
+                    EepromState::Read { data: read_word(ram, address), remaining: 16 }
+                }
+
+                // WRITE: receives 16 bits to be written
+                0b01 => {
+                    EepromState::Write { address: Some(address), data: 0, count: 0 }
+                }
+
+                // ERASE: sets all bits of a word
+                0b11 => {
+                    if self.write_enabled {
+                        write_word(ram, address, 0xffff);
+                    }
+
+                    EepromState::Done
+                }
+
+                // extended commands are selected by the upper two address bits
+                _ => match (bits >> 6) & 0x03 {
+                    // EWDS: disables writing
+                    0b00 => {
+                        self.write_enabled = false;
+                        EepromState::Done
+                    }
+
+                    // WRAL: receives 16 bits to be written into all words
+                    0b01 => {
+                        EepromState::Write { address: None, data: 0, count: 0 }
+                    }
+
+                    // ERAL: sets all bits of all words
+                    0b10 => {
+                        if self.write_enabled {
+                            (0..get_word_count(ram)).for_each(|address| write_word(ram, address, 0xffff));
+                        }
+
+                        EepromState::Done
+                    }
+
+                    // EWEN: enables writing
+                    _ => {
+                        self.write_enabled = true;
+                        EepromState::Done
+                    }
+                },
+            }
+        }
+    }
+
+
+    /// Get the number of 16 bit words stored in the EEPROM.
+    fn get_word_count(ram: &impl MemoryData) -> usize {
+        ram.size() / 2
+    }
+
+
+    /// Reads a 16 bit word of the EEPROM, which is stored in little endian order.
+    fn read_word(ram: &impl MemoryData, address: usize) -> u16 {
+        if address < get_word_count(ram) {
+            u16::from_le_bytes([ram.get_at(address * 2), ram.get_at(address * 2 + 1)])
+        }
+        else {
+            0xffff
+        }
+    }
+
+
+    /// Writes a 16 bit word of the EEPROM, which is stored in little endian order.
+    fn write_word(ram: &mut impl MemoryData, address: usize, value: u16) {
+        if address < get_word_count(ram) {
+            let [low, high] = value.to_le_bytes();
+            ram.set_at(address * 2,     low);
+            ram.set_at(address * 2 + 1, high);
+        }
+    }
+
+
+    impl MbcImpl for Mbc7 {
+        fn read_byte(&self, cartridge: &Cartridge, address: u16) -> u8 {
+            match address {
+                // read from fixed ROM bank, which is always bank 0.
+                0x0000 ..= 0x3fff => {
+                    let rom_address = address as usize;
+                    cartridge.get_rom().get_at(rom_address)
+                },
+
+                // read from the switchable ROM bank
+                0x4000 ..= 0x7fff => {
+                    let rom_address = (address as usize) + self.rom_bank_offset - 0x4000;
+                    cartridge.get_rom().get_at(rom_address)
+                },
+
+                // registers of the accelerometer and EEPROM, selected by bits 4-7 of the address
+                0xa000 ..= 0xafff if self.is_ram_enabled() => {
+                    let [x_low, x_high] = self.accelerometer_x.to_le_bytes();
+                    let [y_low, y_high] = self.accelerometer_y.to_le_bytes();
+
+                    match (address >> 4) & 0x0f {
+                        0x02 => x_low,
+                        0x03 => x_high,
+                        0x04 => y_low,
+                        0x05 => y_high,
+                        0x06 => 0x00,
+                        0x08 => self.eeprom.read(),
+                        _    => 0xff,
+                    }
+                }
+
+                // unused area or RAM disabled
+                0xa000 ..= 0xbfff => {
+                    0xff
+                }
+
+                _ => unreachable!("Unexpected read from address {}", address),
+            }
+        }
+
+
+        fn write_byte(&mut self, cartridge: &mut Cartridge, address: u16, value: u8) {
+            match address {
+                // first RAM enable register
+                0x0000 ..= 0x1fff => {
+                    self.ram_enabled_1 = value == 0x0a;
+                },
+
+                // ROM bank selection
+                0x2000 ..= 0x3fff => {
+                    self.rom_bank_selection = value & 0x7f;
+                    self.update_selected_banks(cartridge);
+                },
+
+                // second RAM enable register
+                0x4000 ..= 0x5fff => {
+                    self.ram_enabled_2 = value == 0x40;
+                },
+
+                // invalid address
+                0x6000 ..= 0x7fff => {
+                },
+
+                // registers of the accelerometer and EEPROM, selected by bits 4-7 of the address
+                0xa000 ..= 0xafff if self.is_ram_enabled() => {
+                    match (address >> 4) & 0x0f {
+                        // erase the latched accelerometer values
+                        0x00 if value == 0x55 => {
+                            self.accelerometer_x      = ACCELEROMETER_ERASED;
+                            self.accelerometer_y      = ACCELEROMETER_ERASED;
+                            self.accelerometer_erased = true;
+                        }
+
+                        // latch the current accelerometer values
+                        0x01 if value == 0xaa && self.accelerometer_erased => {
+                            self.accelerometer_x      = Self::to_accelerometer_value(self.tilt_x);
+                            self.accelerometer_y      = Self::to_accelerometer_value(self.tilt_y);
+                            self.accelerometer_erased = false;
+                        }
+
+                        0x08 => {
+                            self.eeprom.write(cartridge.get_ram_mut(), value);
+                        }
+
+                        _ => { }
+                    }
+                },
+
+                // unused area or RAM disabled
+                0xa000 ..= 0xbfff => {
+                },
+
+                _ => unreachable!("Unexpected write to address {}", address),
+            }
+        }
+
+
+        fn get_register_writes(&self) -> Vec<(u16, u8)> {
+            vec![
+                (0x0000, if self.ram_enabled_1 { 0x0a } else { 0x00 }),
+                (0x2000, self.rom_bank_selection),
+                (0x4000, if self.ram_enabled_2 { 0x40 } else { 0x00 }),
+            ]
+        }
+
+
+        fn get_rom_bank(&self, address: u16) -> u32 {
+            if address < 0x4000 { 0 } else { self.rom_bank_selected }
+        }
+
+
+        fn set_tilt(&mut self, x: f32, y: f32) {
+            self.tilt_x = x;
+            self.tilt_y = y;
+        }
+    }
+}
+
+
+mod mbc6 {
+    use super::*;
+    use crate::cartridge::{MBC6_FLASH_SIZE, MBC6_RAM_SIZE};
+    use crate::mmu::memory_data::MemoryData;
+    use crate::utils::get_bit;
+
+    /// The size of each switchable ROM or flash bank.
+    const ROM_BANK_SIZE: usize = 0x2000;
+
+    /// The size of each switchable RAM bank.
+    const RAM_BANK_SIZE: usize = 0x1000;
+
+    /// The size of a flash sector, which is the unit being erased by a sector erase command.
+    const FLASH_SECTOR_SIZE: usize = 0x20000;
+
+    /// The manufacturer and device ID reported by the flash chip in ID mode.
+    const FLASH_ID: [u8; 2] = [0xc2, 0x81];
+
+    /// The flash addresses used to send the unlock sequence in front of each command.
+    const FLASH_UNLOCK_ADDRESS_1: usize = 0x5555;
+    const FLASH_UNLOCK_ADDRESS_2: usize = 0x2aaa;
+
+
+    /// Type 6 Memory Bank Controller:
+    /// Supports up to 2MiB ROMs, 32kiB RAM and 1MiB of flash memory.
+    /// ROM and flash are mapped in two switchable 8kiB slots, RAM in two 4kiB slots.
+    /// The flash memory is stored behind the RAM within the cartridge RAM image,
+    /// so both are persisted together.
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Mbc6 {
+        /// The values written into the ROM or flash bank selection registers of both slots.
+        rom_bank_selection: [u8; 2],
+
+        /// Whether the slots map a bank of the flash memory instead of a ROM bank.
+        flash_selected: [bool; 2],
+
+        /// The values written into the RAM bank selection registers of both slots.
+        ram_bank_selection: [u8; 2],
+
+        /// Sets if the RAM banks are enabled or not.
+        ram_enabled: bool,
+
+        /// Sets if the flash memory is enabled or not.
+        flash_enabled: bool,
+
+        /// Sets if program and erase commands of the flash memory are enabled.
+        flash_write_enabled: bool,
+
+        /// The current state of the command sequence sent to the flash memory.
+        flash_state: FlashState,
+
+        /// Whether the flash memory reports its ID instead of its data.
+        flash_id_mode: bool,
+    }
+
+
+    /// The state of the command sequence sent to the flash memory.
+    /// Each command needs to be preceded by an unlock sequence.
+    #[derive(Copy, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    enum FlashState {
+        /// Waiting for the first byte of the unlock sequence.
+        Idle,
+
+        /// Received the first byte of the unlock sequence.
+        Unlock1,
+
+        /// Received the full unlock sequence, waiting for a command.
+        Unlock2,
+
+        /// Received the erase command, waiting for another unlock sequence.
+        EraseSetup,
+
+        /// Received the first byte of the unlock sequence after the erase command.
+        EraseUnlock1,
+
+        /// Waiting for the chip or sector erase command.
+        EraseUnlock2,
+
+        /// Waiting for the byte to be programmed.
+        Program,
+    }
+
+
+    impl Mbc6 {
+        pub fn new() -> Self {
+            Self {
+                rom_bank_selection: [0x00; 2],
+                flash_selected:     [false; 2],
+                ram_bank_selection: [0x00; 2],
+
+                ram_enabled:         false,
+                flash_enabled:       false,
+                flash_write_enabled: false,
+
+                flash_state:   FlashState::Idle,
+                flash_id_mode: false,
+            }
+        }
+
+
+        /// Get the slot of the ROM or RAM area an address belongs to.
+        fn get_slot(address: u16) -> usize {
+            match address {
+                0x4000 ..= 0x5fff | 0xa000 ..= 0xafff => 0,
+                _                                     => 1,
+            }
+        }
+
+
+        /// Get the address within the ROM image for an address within a switchable ROM slot.
+        fn get_rom_address(&self, cartridge: &Cartridge, address: u16) -> usize {
+            let slot       = Self::get_slot(address);
+            let bank_count = (cartridge.get_rom_size() / ROM_BANK_SIZE).max(1);
+            let bank       = (self.rom_bank_selection[slot] as usize) % bank_count;
+
+            bank * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1))
+        }
+
+
+        /// Get the address within the flash memory for an address within a switchable ROM slot.
+        fn get_flash_address(&self, address: u16) -> usize {
+            let slot = Self::get_slot(address);
+            let bank = (self.rom_bank_selection[slot] as usize) % (MBC6_FLASH_SIZE / ROM_BANK_SIZE);
+
+            bank * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1))
+        }
+
+
+        /// Get the address within the cartridge RAM image for an address within a RAM slot.
+        fn get_ram_address(&self, address: u16) -> usize {
+            let slot = Self::get_slot(address);
+            let bank = (self.ram_bank_selection[slot] as usize) % (MBC6_RAM_SIZE / RAM_BANK_SIZE);
+
+            bank * RAM_BANK_SIZE + (address as usize & (RAM_BANK_SIZE - 1))
+        }
+
+
+        /// Reads a byte from the flash memory.
+        fn read_flash(&self, cartridge: &Cartridge, flash_address: usize) -> u8 {
+            if self.flash_id_mode {
+                FLASH_ID[flash_address & 0x01]
+            }
+            else {
+                cartridge.get_ram().get_at(MBC6_RAM_SIZE + flash_address)
+            }
+        }
+
+
+        /// Handles a byte written into the flash memory, which is either
+        /// part of a command sequence or a byte to be programmed.
+        fn write_flash(&mut self, cartridge: &mut Cartridge, flash_address: usize, value: u8) {
+            let command_address = flash_address & 0x7fff;
+
+            self.flash_state = match (self.flash_state, command_address, value) {
+                // the reset command can be sent at any time
+                (_, _, 0xf0) => {
+                    self.flash_id_mode = false;
+                    FlashState::Idle
+                }
+
+                // programming a byte can only clear bits, which were not erased before
+                (FlashState::Program, _, _) => {
+                    if self.flash_write_enabled {
+                        let ram     = cartridge.get_ram_mut();
+                        let current = ram.get_at(MBC6_RAM_SIZE + flash_address);
+                        ram.set_at(MBC6_RAM_SIZE + flash_address, current & value);
+                    }
+
+                    FlashState::Idle
+                }
+
+                // unlock sequence
+                (FlashState::Idle,         FLASH_UNLOCK_ADDRESS_1, 0xaa) => FlashState::Unlock1,
+                (FlashState::Unlock1,      FLASH_UNLOCK_ADDRESS_2, 0x55) => FlashState::Unlock2,
+                (FlashState::EraseSetup,   FLASH_UNLOCK_ADDRESS_1, 0xaa) => FlashState::EraseUnlock1,
+                (FlashState::EraseUnlock1, FLASH_UNLOCK_ADDRESS_2, 0x55) => FlashState::EraseUnlock2,
+
+                // commands following the unlock sequence
+                (FlashState::Unlock2, FLASH_UNLOCK_ADDRESS_1, 0x90) => {
+                    self.flash_id_mode = true;
+                    FlashState::Idle
+                }
+
+                (FlashState::Unlock2, FLASH_UNLOCK_ADDRESS_1, 0xa0) => FlashState::Program,
+                (FlashState::Unlock2, FLASH_UNLOCK_ADDRESS_1, 0x80) => FlashState::EraseSetup,
+
+                // chip erase
+                (FlashState::EraseUnlock2, FLASH_UNLOCK_ADDRESS_1, 0x10) => {
+                    if self.flash_write_enabled {
+                        let ram = cartridge.get_ram_mut();
+                        for address in MBC6_RAM_SIZE .. MBC6_RAM_SIZE + MBC6_FLASH_SIZE {
+                            ram.set_at(address, 0xff);
+                        }
+                    }
+
+                    FlashState::Idle
+                }
+
+                // sector erase on the sector containing the address written to
+                (FlashState::EraseUnlock2, _, 0x30) => {
+                    if self.flash_write_enabled {
+                        let ram          = cartridge.get_ram_mut();
+                        let sector_start = flash_address & !(FLASH_SECTOR_SIZE - 1);
+                        for address in sector_start .. sector_start + FLASH_SECTOR_SIZE {
+                            ram.set_at(MBC6_RAM_SIZE + address, 0xff);
+                        }
+                    }
+
+                    FlashState::Idle
+                }
+
+                // any unexpected write cancels the command sequence
+                _ => FlashState::Idle,
+            };
+        }
+    }
+
+
+    impl Default for Mbc6 {
+        /// Creates the state of an MBC6 after power on.
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+
+    impl MbcImpl for Mbc6 {
+        fn read_byte(&self, cartridge: &Cartridge, address: u16) -> u8 {
+            match address {
+                // read from fixed ROM bank, which is always bank 0.
+                0x0000 ..= 0x3fff => {
+                    let rom_address = address as usize;
+                    cartridge.get_rom().get_at(rom_address)
+                },
+
+                // read from one of the switchable ROM or flash slots
+                0x4000 ..= 0x7fff => {
+                    if !self.flash_selected[Self::get_slot(address)] {
+                        let rom_address = self.get_rom_address(cartridge, address);
+                        cartridge.get_rom().get_at(rom_address)
+                    }
+                    else if self.flash_enabled && cartridge.has_ram() {
+                        self.read_flash(cartridge, self.get_flash_address(address))
+                    }
+                    else {
+                        0xff
+                    }
+                },
+
+                // read from one of the switchable RAM slots
+                0xa000 ..= 0xbfff => {
+                    if cartridge.has_ram() && self.ram_enabled {
+                        let ram_address = self.get_ram_address(address);
+                        cartridge.get_ram().get_at(ram_address)
+                    }
+                    else {
+                        0xff
+                    }
+                }
+
+                _ => unreachable!("Unexpected read from address {}", address),
+            }
+        }
+
+
+        fn write_byte(&mut self, cartridge: &mut Cartridge, address: u16, value: u8) {
+            match address {
+                // enable or disable RAM
+                0x0000 ..= 0x03ff => {
+                    self.ram_enabled = (value & 0x0f) == 0x0a;
+                },
+
+                // RAM bank selection for both slots
+                0x0400 ..= 0x07ff => {
+                    self.ram_bank_selection[0] = value & 0x07;
+                },
+
+                0x0800 ..= 0x0bff => {
+                    self.ram_bank_selection[1] = value & 0x07;
+                },
+
+                // enable or disable flash memory
+                0x0c00 ..= 0x0fff => {
+                    self.flash_enabled = get_bit(value, 0);
+                },
+
+                // enable or disable writing into the flash memory
+                0x1000 ..= 0x1fff => {
+                    self.flash_write_enabled = get_bit(value, 0);
+                },
+
+                // ROM or flash bank selection for both slots
+                0x2000 ..= 0x27ff => {
+                    self.rom_bank_selection[0] = value & 0x7f;
+                },
+
+                0x2800 ..= 0x2fff => {
+                    self.flash_selected[0] = get_bit(value, 3);
+                },
+
+                0x3000 ..= 0x37ff => {
+                    self.rom_bank_selection[1] = value & 0x7f;
+                },
+
+                0x3800 ..= 0x3fff => {
+                    self.flash_selected[1] = get_bit(value, 3);
+                },
+
+                // commands or data written into the flash memory
+                0x4000 ..= 0x7fff => {
+                    if self.flash_selected[Self::get_slot(address)] && self.flash_enabled && cartridge.has_ram() {
+                        let flash_address = self.get_flash_address(address);
+                        self.write_flash(cartridge, flash_address, value);
+                    }
+                },
+
+                // Cartridge RAM
+                0xa000 ..= 0xbfff => {
+                    if cartridge.has_ram() && self.ram_enabled {
+                        let ram_address = self.get_ram_address(address);
+                        cartridge.get_ram_mut().set_at(ram_address, value);
+                    }
+                },
+
+                _ => unreachable!("Unexpected write to address {}", address),
+            }
+        }
+
+
+        fn get_register_writes(&self) -> Vec<(u16, u8)> {
+            vec![
+                (0x0000, if self.ram_enabled { 0x0a } else { 0x00 }),
+                (0x0400, self.ram_bank_selection[0]),
+                (0x0800, self.ram_bank_selection[1]),
+                (0x0c00, self.flash_enabled as u8),
+                (0x1000, self.flash_write_enabled as u8),
+                (0x2000, self.rom_bank_selection[0]),
+                (0x2800, if self.flash_selected[0] { 0x08 } else { 0x00 }),
+                (0x3000, self.rom_bank_selection[1]),
+                (0x3800, if self.flash_selected[1] { 0x08 } else { 0x00 }),
+            ]
+        }
+
+
+        fn get_rom_bank(&self, address: u16) -> u32 {
+            if address < 0x4000 {
+                0
+            }
+            else {
+                // the number of the 16kiB bank containing the 8kiB bank being mapped
+                (self.rom_bank_selection[Self::get_slot(address)] / 2) as u32
+            }
+        }
+
+
+        fn is_rom_mapped(&self, address: u16) -> bool {
+            address < 0x4000 || !self.flash_selected[Self::get_slot(address)]
+        }
+    }
+}
+
+
+mod mmm01 {
+    use super::*;
+    use crate::mmu::memory_data::MemoryData;
+    use crate::utils::get_bit;
+
+
+    /// MMM01 Memory Bank Controller:
+    /// Used by multi game cartridges, which start with a menu located in
+    /// the last 32kiB of the ROM. The menu selects the game by setting the
+    /// upper bits of the ROM and RAM bank numbers and then locks them, so
+    /// the game can use the controller like a MBC1 within its own area.
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Mmm01 {
+        /// Whether the game was mapped, which locks the outer bank registers.
+        mapped: bool,
+
+        /// Mode switch like on MBC1, which enables RAM banking in mode 1.
+        mode: u8,
+
+        /// Whether writing the mode register was disabled by the menu.
+        mode_locked: bool,
+
+        /// Bits 0-4 of the ROM bank number.
+        rom_bank_low: u8,
+
+        /// Bits 5-6 of the ROM bank number, only writable before mapping.
+        rom_bank_mid: u8,
+
+        /// Bits 7-8 of the ROM bank number, only writable before mapping.
+        rom_bank_high: u8,
+
+        /// Bits 1-4 of the ROM bank number, which cannot be changed by the game once mapped.
+        rom_bank_mask: u8,
+
+        /// Bits 0-1 of the RAM bank number.
+        ram_bank_low: u8,
+
+        /// Bits 2-3 of the RAM bank number, only writable before mapping.
+        ram_bank_high: u8,
+
+        /// Bits 0-1 of the RAM bank number, which cannot be changed by the game once mapped.
+        ram_bank_mask: u8,
+
+        /// The selected ROM bank slot #0 number.
+        rom_bank_0_selected: u32,
+
+        /// The offset added to the address the game wants to read from,
+        /// to get the real address within the ROM file.
+        rom_bank_0_offset: usize,
+
+        /// The selected ROM bank slot #1 number.
+        rom_bank_1_selected: u32,
+
+        /// The offset added to the address the game wants to read from,
+        /// to get the real address within the ROM file.
+        rom_bank_1_offset: usize,
+
+        /// The offset added to the address the game wants to read/write,
+        /// to get the real address within the RAM image.
+        ram_bank_offset: usize,
+
+        /// Sets if the RAM bank is enabled or not.
+        ram_enabled: bool,
+    }
+
+
+    impl Mmm01 {
+        pub fn new() -> Self {
+            Self {
+                mapped: false,
+                mode: 0,
+                mode_locked: false,
+
+                rom_bank_low:  0x00,
+                rom_bank_mid:  0x00,
+                rom_bank_high: 0x00,
+                rom_bank_mask: 0x00,
+
+                ram_bank_low:  0x00,
+                ram_bank_high: 0x00,
+                ram_bank_mask: 0x00,
+
+                rom_bank_0_selected: 0,
+                rom_bank_0_offset:   0x0000,
+
+                rom_bank_1_selected: 1,
+                rom_bank_1_offset:   0x4000,
+
+                ram_bank_offset: 0x0000,
+
+                ram_enabled: false,
+            }
+        }
+
+
+        /// After writing to one of the bank selection registers,
+        /// this function is used to calculate the actual RAM and ROM bank numbers
+        /// as well as the offsets to read and write inside the ROM and RAM images.
+        fn update_selected_banks(&mut self, cartridge: &Cartridge) {
+            let rom_bank_count = cartridge.get_rom_bank_count();
+
+            let (rom_bank_0, rom_bank_1) = if self.mapped {
+                // the bits covered by the mask are fixed like the outer bank bits
+                let mask       = (self.rom_bank_mask as u32) << 1;
+                let outer_bank =
+                        ((self.rom_bank_high as u32) << 7)
+                    |   ((self.rom_bank_mid  as u32) << 5)
+                    |   ((self.rom_bank_low  as u32) & mask)
+                ;
+
+                // like on MBC1, selecting bank 0 within the game's area selects bank 1 instead
+                let inner_bank = match (self.rom_bank_low as u32) & !mask & 0x1f {
+                    0    => 1,
+                    bank => bank,
+                };
+
+                (outer_bank, outer_bank | inner_bank)
+            }
+            else {
+                // before mapping a game, the last 32kiB of the ROM containing the menu are mapped
+                (rom_bank_count.saturating_sub(2), rom_bank_count.saturating_sub(1))
+            };
+
+            // store the rom bank and the offset to be added to all requested addresses
+            if rom_bank_count != 0 {
+                self.rom_bank_0_selected = rom_bank_0 % rom_bank_count;
+                self.rom_bank_0_offset   = (self.rom_bank_0_selected as usize) * 0x4000;
+
+                self.rom_bank_1_selected = rom_bank_1 % rom_bank_count;
+                self.rom_bank_1_offset   = (self.rom_bank_1_selected as usize) * 0x4000;
+            }
+
+            // like on MBC1, the lower RAM bank bits are only used in mode 1
+            let ram_bank_low = if self.mode == 1 {
+                self.ram_bank_low
+            }
+            else {
+                self.ram_bank_low & self.ram_bank_mask
+            };
+
+            let ram_bank = ((self.ram_bank_high as u32) << 2) | (ram_bank_low as u32);
+
+            // store the ram bank and the offset to be added to all addresses
+            if cartridge.get_ram_bank_count() != 0 {
+                self.ram_bank_offset = ((ram_bank % cartridge.get_ram_bank_count()) as usize) * 0x2000;
+            }
+        }
+    }
+
+
+    impl MbcImpl for Mmm01 {
+        fn read_byte(&self, cartridge: &Cartridge, address: u16) -> u8 {
+            match address {
+                // read from the first ROM bank slot
+                0x0000 ..= 0x3fff => {
+                    let rom_address = (address as usize) + self.rom_bank_0_offset;
+                    cartridge.get_rom().get_at(rom_address)
+                },
+
+                // read from the switchable ROM bank
+                0x4000 ..= 0x7fff => {
+                    let rom_address = (address as usize) + self.rom_bank_1_offset - 0x4000;
+                    cartridge.get_rom().get_at(rom_address)
+                },
+
+                // read from switchable RAM bank.
+                0xa000 ..= 0xbfff => {
+                    if cartridge.has_ram() && self.ram_enabled {
+                        let ram_address = (address as usize) - 0xa000 + self.ram_bank_offset;
+                        cartridge.get_ram().get_at(ram_address)
+                    }
+                    else {
+                        0xff
+                    }
+                }
+
+                _ => unreachable!("Unexpected read from address {}", address),
+            }
+        }
+
+
+        fn write_byte(&mut self, cartridge: &mut Cartridge, address: u16, value: u8) {
+            match address {
+                // enable or disable RAM, before mapping also the RAM bank mask and map enable
+                0x0000 ..= 0x1fff => {
+                    self.ram_enabled = (value & 0x0f) == 0x0a;
+
+                    if !self.mapped {
+                        self.ram_bank_mask = (value >> 4) & 0x03;
+                        self.mapped        = get_bit(value, 6);
+                    }
+
+                    self.update_selected_banks(cartridge);
+                },
+
+                // ROM bank selection, before mapping also the middle ROM bank bits
+                0x2000 ..= 0x3fff => {
+                    if self.mapped {
+                        let mask = self.rom_bank_mask << 1;
+                        self.rom_bank_low = (self.rom_bank_low & mask) | (value & 0x1f & !mask);
+                    }
+                    else {
+                        self.rom_bank_low = value & 0x1f;
+                        self.rom_bank_mid = (value >> 5) & 0x03;
+                    }
+
+                    self.update_selected_banks(cartridge);
+                },
+
+                // RAM bank selection, before mapping also the upper RAM and ROM bank bits
+                0x4000 ..= 0x5fff => {
+                    if self.mapped {
+                        let mask = self.ram_bank_mask;
+                        self.ram_bank_low = (self.ram_bank_low & mask) | (value & 0x03 & !mask);
+                    }
+                    else {
+                        self.ram_bank_low  = value & 0x03;
+                        self.ram_bank_high = (value >> 2) & 0x03;
+                        self.rom_bank_high = (value >> 4) & 0x03;
+                        self.mode_locked   = get_bit(value, 6);
+                    }
+
+                    self.update_selected_banks(cartridge);
+                },
+
+                // mode selection, before mapping also the ROM bank mask
+                0x6000 ..= 0x7fff => {
+                    if !self.mode_locked {
+                        self.mode = value & 0x01;
+                    }
+
+                    if !self.mapped {
+                        self.rom_bank_mask = (value >> 2) & 0x0f;
+                    }
+
+                    self.update_selected_banks(cartridge);
+                },
+
+                // Cartridge RAM
+                0xa000 ..= 0xbfff => {
+                    if cartridge.has_ram() && self.ram_enabled {
+                        let ram_address = (address as usize) - 0xa000 + self.ram_bank_offset;
+                        cartridge.get_ram_mut().set_at(ram_address, value);
+                    }
+                },
+
+                _ => unreachable!("Unexpected write to address {}", address),
+            }
+        }
+
+
+        fn init(&mut self, cartridge: &Cartridge) {
+            self.update_selected_banks(cartridge);
+        }
+
+
+        fn get_register_writes(&self) -> Vec<(u16, u8)> {
+            // restore the registers of the menu first, before mapping the game
+            vec![
+                (0x2000, self.rom_bank_low | (self.rom_bank_mid << 5)),
+                (0x4000, self.ram_bank_low | (self.ram_bank_high << 2) | (self.rom_bank_high << 4)),
+                (0x6000, self.mode | (self.rom_bank_mask << 2)),
+                (0x4000, self.ram_bank_low | (self.ram_bank_high << 2) | (self.rom_bank_high << 4) | ((self.mode_locked as u8) << 6)),
+                (
+                    0x0000,
+                        (if self.ram_enabled { 0x0a } else { 0x00 })
+                    |   (self.ram_bank_mask << 4)
+                    |   ((self.mapped as u8) << 6)
+                ),
+            ]
+        }
+
+
+        fn get_rom_bank(&self, address: u16) -> u32 {
+            if address < 0x4000 { self.rom_bank_0_selected } else { self.rom_bank_1_selected }
+        }
+    }
+}
+
+
+mod huc1 {
+    use super::*;
+    use crate::mmu::memory_data::MemoryData;
+    use crate::utils::get_bit;
+
+    /// The value read from the infrared receiver while no light is received.
+    const IR_NO_LIGHT: u8 = 0xc0;
+
+
+    /// Hudson HuC1 Memory Bank Controller:
+    /// Supports up to 1MiB ROMs, up to 32kiB RAM and an infrared transceiver,
+    /// which can be mapped into the RAM area.
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Huc1 {
+        /// The value written into the ROM bank selection register.
+        rom_bank_selection: u8,
+
+        /// The value written into the RAM bank selection register.
+        ram_bank_selection: u8,
+
+        /// The selected ROM bank number.
+        rom_bank_selected: u32,
+
+        /// The offset added to the address the game wants to read from,
+        /// to get the real address within the ROM file.
+        rom_bank_offset: usize,
+
+        /// The offset added to the address the game wants to read/write,
+        /// to get the real address within the RAM image.
+        ram_bank_offset: usize,
+
+        /// Whether the infrared transceiver is mapped into the RAM area instead of RAM.
+        ir_mode: bool,
+
+        /// The state of the infrared LED.
+        ir_led: bool,
+    }
+
+
+    impl Huc1 {
+        pub fn new() -> Self {
+            Self {
+                rom_bank_selection: 0x00,
+                ram_bank_selection: 0x00,
+
+                rom_bank_selected: 1,
+                rom_bank_offset:   0x4000,
+
+                ram_bank_offset:   0x0000,
+
+                ir_mode: false,
+                ir_led:  false,
+            }
+        }
+
+
+        /// After writing to one of the bank selection registers,
+        /// this function is used to calculate the actual RAM and ROM bank numbers
+        /// as well as the offsets to read and write inside the ROM and RAM images.
+        fn update_selected_banks(&mut self, cartridge: &Cartridge) {
+            // like on MBC1, selecting bank 0 will select bank 1 instead
+            let rom_bank = if self.rom_bank_selection != 0 {
+                self.rom_bank_selection as u32
+            }
+            else {
+                1
+            };
+
+            if cartridge.get_rom_bank_count() != 0 {
+                self.rom_bank_selected = rom_bank % cartridge.get_rom_bank_count();
+                self.rom_bank_offset   = (self.rom_bank_selected as usize) * 0x4000;
+            }
+
+            if cartridge.get_ram_bank_count() != 0 {
+                let ram_bank = (self.ram_bank_selection as u32) % cartridge.get_ram_bank_count();
+                self.ram_bank_offset = (ram_bank as usize) * 0x2000;
+            }
+        }
+
+
+        /// Checks whether the infrared LED is currently turned on.
+        pub fn is_ir_led_on(&self) -> bool {
+            self.ir_led
+        }
+    }
+
+
+    impl MbcImpl for Huc1 {
+        fn read_byte(&self, cartridge: &Cartridge, address: u16) -> u8 {
+            match address {
+                // read from fixed ROM bank, which is always bank 0.
+                0x0000 ..= 0x3fff => {
+                    let rom_address = address as usize;
+                    cartridge.get_rom().get_at(rom_address)
+                },
+
+                // read from the switchable ROM bank
+                0x4000 ..= 0x7fff => {
+                    let rom_address = (address as usize) + self.rom_bank_offset - 0x4000;
+                    cartridge.get_rom().get_at(rom_address)
+                },
+
+                // read from the infrared receiver or switchable RAM bank
+                0xa000 ..= 0xbfff => {
+                    if self.ir_mode {
+                        IR_NO_LIGHT
+                    }
+                    else if cartridge.has_ram() {
+                        let ram_address = (address as usize) + self.ram_bank_offset - 0xa000;
+                        cartridge.get_ram().get_at(ram_address)
+                    }
+                    else {
+                        0xff
+                    }
+                }
+
+                _ => unreachable!("Unexpected read from address {}", address),
+            }
+        }
+
+
+        fn write_byte(&mut self, cartridge: &mut Cartridge, address: u16, value: u8) {
+            match address {
+                // switch between RAM and infrared mode
+                0x0000 ..= 0x1fff => {
+                    self.ir_mode = value == 0x0e;
+                },
+
+                // ROM bank selection
+                0x2000 ..= 0x3fff => {
+                    self.rom_bank_selection = value & 0x3f;
+                    self.update_selected_banks(cartridge);
+                },
+
+                // RAM bank selection
+                0x4000 ..= 0x5fff => {
+                    self.ram_bank_selection = value & 0x03;
+                    self.update_selected_banks(cartridge);
+                },
+
+                // invalid address
+                0x6000 ..= 0x7fff => {
+                },
+
+                // infrared LED or Cartridge RAM
+                0xa000 ..= 0xbfff => {
+                    if self.ir_mode {
+                        self.ir_led = get_bit(value, 0);
+                    }
+                    else if cartridge.has_ram() {
+                        let ram_address = (address as usize) - 0xa000 + self.ram_bank_offset;
+                        cartridge.get_ram_mut().set_at(ram_address, value);
+                    }
+                },
+
+                _ => unreachable!("Unexpected write to address {}", address),
+            }
+        }
+
+
+        fn get_register_writes(&self) -> Vec<(u16, u8)> {
+            vec![
+                (0x0000, if self.ir_mode { 0x0e } else { 0x00 }),
+                (0x2000, self.rom_bank_selection),
+                (0x4000, self.ram_bank_selection),
+            ]
+        }
+
+
+        fn get_rom_bank(&self, address: u16) -> u32 {
+            if address < 0x4000 { 0 } else { self.rom_bank_selected }
+        }
+    }
+}
+
+
+mod huc3 {
+    use super::*;
+    use crate::mmu::memory_data::MemoryData;
+    use crate::mmu::rtc::{RealTimeClock, RTC_REGISTER_DAYS_HIGH, RTC_REGISTER_DAYS_LOW, RTC_REGISTER_HOURS, RTC_REGISTER_MINUTES};
+    use crate::utils::get_bit;
+
+    /// The value read from the infrared receiver while no light is received.
+    const IR_NO_LIGHT: u8 = 0xc0;
+
+    /// The number of minutes of a day, after which the day counter gets incremented.
+    const MINUTES_PER_DAY: u16 = 24 * 60;
+
+    /// The mask of the 12 bit day counter of the HuC3 clock.
+    const DAYS_MASK: u16 = 0x0fff;
+
+    /// The mask of the 9 bit day counter of the real time clock.
+    const RTC_DAYS_MASK: u16 = 0x01ff;
+
+    /// Modes selecting what gets mapped into the RAM area.
+    const MODE_RAM_READ:        u8 = 0x00;
+    const MODE_RAM_READ_WRITE:  u8 = 0x0a;
+    const MODE_RTC_COMMAND:     u8 = 0x0b;
+    const MODE_RTC_RESPONSE:    u8 = 0x0c;
+    const MODE_RTC_SEMAPHORE:   u8 = 0x0d;
+    const MODE_IR:              u8 = 0x0e;
+
+
+    /// Hudson HuC3 Memory Bank Controller:
+    /// Supports up to 2MiB ROMs, up to 32kiB RAM, an infrared transceiver
+    /// and a clock chip with a tone generator. The clock is accessed via
+    /// commands, which read and write the time as 4 bit values.
+    /// The time is kept by the cartridge's real time clock, so it's stored
+    /// in save files like the clock of MBC3 cartridges.
+    /// Since the real time clock only has a 9 bit day counter, the 12 bit
+    /// day counter of the HuC3 is kept separately and advanced with the days
+    /// passed on the real time clock. Save files only keep its lower 9 bits.
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Huc3 {
+        /// The value written into the ROM bank selection register.
+        rom_bank_selection: u8,
+
+        /// The value written into the RAM bank selection register.
+        ram_bank_selection: u8,
+
+        /// The selected ROM bank number.
+        rom_bank_selected: u32,
+
+        /// The offset added to the address the game wants to read from,
+        /// to get the real address within the ROM file.
+        rom_bank_offset: usize,
+
+        /// The offset added to the address the game wants to read/write,
+        /// to get the real address within the RAM image.
+        ram_bank_offset: usize,
+
+        /// The mode selecting what gets mapped into the RAM area.
+        mode: u8,
+
+        /// The last command written to the clock chip, which is executed
+        /// when the semaphore gets cleared.
+        command: u8,
+
+        /// The 4 bit response of the last command executed.
+        response: u8,
+
+        /// The index of the clock value accessed by read and write commands.
+        access_index: u8,
+
+        /// Whether the tone generator is currently playing.
+        tone_enabled: bool,
+
+        /// The state of the infrared LED.
+        ir_led: bool,
+
+        /// The 12 bit day counter of the clock.
+        days: u16,
+
+        /// The day counter of the real time clock when [Self::days] was last updated.
+        rtc_days: u16,
+    }
+
+
+    impl Huc3 {
+        pub fn new() -> Self {
+            Self {
+                rom_bank_selection: 0x00,
+                ram_bank_selection: 0x00,
+
+                rom_bank_selected: 1,
+                rom_bank_offset:   0x4000,
+
+                ram_bank_offset:   0x0000,
+
+                mode:         MODE_RAM_READ,
+                command:      0x00,
+                response:     0x00,
+                access_index: 0x00,
+
+                tone_enabled: false,
+                ir_led:       false,
+
+                days:         0,
+                rtc_days:     0,
+            }
+        }
+
+
+        /// After writing to one of the bank selection registers,
+        /// this function is used to calculate the actual RAM and ROM bank numbers
+        /// as well as the offsets to read and write inside the ROM and RAM images.
+        fn update_selected_banks(&mut self, cartridge: &Cartridge) {
+            // like on MBC1, selecting bank 0 will select bank 1 instead
+            let rom_bank = if self.rom_bank_selection != 0 {
+                self.rom_bank_selection as u32
+            }
+            else {
+                1
+            };
+
+            if cartridge.get_rom_bank_count() != 0 {
+                self.rom_bank_selected = rom_bank % cartridge.get_rom_bank_count();
+                self.rom_bank_offset   = (self.rom_bank_selected as usize) * 0x4000;
+            }
+
+            if cartridge.get_ram_bank_count() != 0 {
+                let ram_bank = (self.ram_bank_selection as u32) % cartridge.get_ram_bank_count();
+                self.ram_bank_offset = (ram_bank as usize) * 0x2000;
+            }
+        }
+
+
+        /// Executes the last command written to the clock chip.
+        fn run_command(&mut self, cartridge: &mut Cartridge) {
+            let argument = self.command & 0x0f;
+
+            match (self.command >> 4) & 0x07 {
+                // read a 4 bit value of the clock and advance the access index
+                0x01 => {
+                    self.response = match cartridge.get_rtc() {
+                        Some(rtc) => self.read_clock_value(rtc, self.access_index),
+                        None      => 0x00,
+                    };
+
+                    self.access_index = self.access_index.wrapping_add(1);
+                }
+
+                // write a 4 bit value of the clock, optionally advancing the access index
+                0x02 | 0x03 => {
+                    if let Some(rtc) = cartridge.get_rtc_mut() {
+                        self.write_clock_value(rtc, self.access_index, argument);
+                    }
+
+                    if (self.command >> 4) & 0x07 == 0x03 {
+                        self.access_index = self.access_index.wrapping_add(1);
+                    }
+                }
+
+                // set the lower or upper 4 bits of the access index
+                0x04 => {
+                    self.access_index = (self.access_index & 0xf0) | argument;
+                }
+
+                0x05 => {
+                    self.access_index = (self.access_index & 0x0f) | (argument << 4);
+                }
+
+                // extended commands
+                0x06 => {
+                    // the status command always reports the clock to be running
+                    self.response = if argument == 0x02 { 0x01 } else { 0x00 };
+
+                    // the tone generator plays until any other extended command is sent
+                    self.tone_enabled = argument == 0x0e;
+                }
+
+                _ => { }
+            }
+        }
+
+
+        /// Advances the day counter by the number of days passed on the real time clock.
+        fn update_days(&mut self, rtc: &RealTimeClock) {
+            let rtc_days = rtc.get_current().get_days();
+            let elapsed  = rtc_days.wrapping_sub(self.rtc_days) & RTC_DAYS_MASK;
+
+            self.days     = (self.days + elapsed) & DAYS_MASK;
+            self.rtc_days = rtc_days;
+        }
+
+
+        /// Reads a 4 bit value of the clock. Index 0-2 contain the minutes of the
+        /// current day, index 3-5 the number of days.
+        fn read_clock_value(&mut self, rtc: &RealTimeClock, index: u8) -> u8 {
+            self.update_days(rtc);
+
+            match index {
+                0 ..= 2 => ((get_clock_minutes(rtc) >> (index * 4)) & 0x0f) as u8,
+                3 ..= 5 => ((self.days >> ((index - 3) * 4)) & 0x0f) as u8,
+                _       => 0x00,
+            }
+        }
+
+
+        /// Writes a 4 bit value of the clock. Index 0-2 contain the minutes of the
+        /// current day, index 3-5 the number of days.
+        fn write_clock_value(&mut self, rtc: &mut RealTimeClock, index: u8, value: u8) {
+            self.update_days(rtc);
+
+            match index {
+                0 ..= 2 => {
+                    let shift   = index * 4;
+                    let minutes = get_clock_minutes(rtc);
+                    let minutes = ((minutes & !(0x0f << shift)) | ((value as u16) << shift)) % MINUTES_PER_DAY;
+
+                    rtc.write_register(RTC_REGISTER_HOURS,   (minutes / 60) as u8);
+                    rtc.write_register(RTC_REGISTER_MINUTES, (minutes % 60) as u8);
+                }
+
+                3 ..= 5 => {
+                    let shift = (index - 3) * 4;
+                    self.days = (self.days & !(0x0f << shift)) | ((value as u16) << shift);
+
+                    // the real time clock keeps the lower 9 bits to be stored in save files
+                    let rtc_days  = self.days & RTC_DAYS_MASK;
+                    let days_high = (rtc.get_current().days_high & !0x01) | ((rtc_days >> 8) as u8);
+
+                    rtc.write_register(RTC_REGISTER_DAYS_LOW,  (rtc_days & 0xff) as u8);
+                    rtc.write_register(RTC_REGISTER_DAYS_HIGH, days_high);
+                    self.rtc_days = rtc_days;
+                }
+
+                _ => { }
+            }
+        }
+
+
+        /// Checks whether the tone generator is currently playing.
+        pub fn is_tone_enabled(&self) -> bool {
+            self.tone_enabled
+        }
+
+
+        /// Checks whether the infrared LED is currently turned on.
+        pub fn is_ir_led_on(&self) -> bool {
+            self.ir_led
+        }
+    }
+
+
+    /// Get the time of the clock as minutes of the current day.
+    fn get_clock_minutes(rtc: &RealTimeClock) -> u16 {
+        let current = rtc.get_current();
+        (current.hours as u16) * 60 + (current.minutes as u16)
+    }
+
+
+    impl MbcImpl for Huc3 {
+        fn read_byte(&self, cartridge: &Cartridge, address: u16) -> u8 {
+            match address {
+                // read from fixed ROM bank, which is always bank 0.
+                0x0000 ..= 0x3fff => {
+                    let rom_address = address as usize;
+                    cartridge.get_rom().get_at(rom_address)
+                },
+
+                // read from the switchable ROM bank
+                0x4000 ..= 0x7fff => {
+                    let rom_address = (address as usize) + self.rom_bank_offset - 0x4000;
+                    cartridge.get_rom().get_at(rom_address)
+                },
+
+                // RAM area, depending on the selected mode
+                0xa000 ..= 0xbfff => {
+                    match self.mode {
+                        MODE_RAM_READ | MODE_RAM_READ_WRITE if cartridge.has_ram() => {
+                            let ram_address = (address as usize) + self.ram_bank_offset - 0xa000;
+                            cartridge.get_ram().get_at(ram_address)
+                        }
+
+                        MODE_RTC_RESPONSE   => (self.command & 0xf0) | self.response,
+
+                        // commands are executed immediately, so the clock is always ready
+                        MODE_RTC_SEMAPHORE  => 0xff,
+
+                        MODE_IR             => IR_NO_LIGHT,
+
+                        _                   => 0xff,
+                    }
+                }
+
+                _ => unreachable!("Unexpected read from address {}", address),
+            }
+        }
+
+
+        fn write_byte(&mut self, cartridge: &mut Cartridge, address: u16, value: u8) {
+            match address {
+                // select what gets mapped into the RAM area
+                0x0000 ..= 0x1fff => {
+                    self.mode = value & 0x0f;
+                },
+
+                // ROM bank selection
+                0x2000 ..= 0x3fff => {
+                    self.rom_bank_selection = value & 0x7f;
+                    self.update_selected_banks(cartridge);
+                },
+
+                // RAM bank selection
+                0x4000 ..= 0x5fff => {
+                    self.ram_bank_selection = value & 0x03;
+                    self.update_selected_banks(cartridge);
+                },
+
+                // invalid address
+                0x6000 ..= 0x7fff => {
+                },
+
+                // RAM area, depending on the selected mode
+                0xa000 ..= 0xbfff => {
+                    match self.mode {
+                        MODE_RAM_READ_WRITE if cartridge.has_ram() => {
+                            let ram_address = (address as usize) - 0xa000 + self.ram_bank_offset;
+                            cartridge.get_ram_mut().set_at(ram_address, value);
+                        }
+
+                        MODE_RTC_COMMAND => {
+                            self.command = value & 0x7f;
+                        }
+
+                        // clearing the semaphore executes the last command
+                        MODE_RTC_SEMAPHORE if !get_bit(value, 0) => {
+                            self.run_command(cartridge);
+                        }
+
+                        MODE_IR => {
+                            self.ir_led = get_bit(value, 0);
+                        }
+
+                        _ => { }
+                    }
+                },
+
+                _ => unreachable!("Unexpected write to address {}", address),
+            }
+        }
+
+
+        fn get_register_writes(&self) -> Vec<(u16, u8)> {
+            vec![
+                (0x0000, self.mode),
+                (0x2000, self.rom_bank_selection),
+                (0x4000, self.ram_bank_selection),
+            ]
+        }
+
+
+        fn get_rom_bank(&self, address: u16) -> u32 {
+            if address < 0x4000 { 0 } else { self.rom_bank_selected }
+        }
+
+
+        fn update(&mut self, cartridge: &mut Cartridge, cycles: Clock) {
+            if let Some(rtc) = cartridge.get_rtc_mut() {
+                rtc.update(cycles);
+            }
+        }
+    }
+}
+
+
+mod tama5 {
+    use super::*;
+    use crate::cartridge::TAMA5_RAM_SIZE;
+    use crate::mmu::memory_data::MemoryData;
+    use crate::mmu::rtc::{RealTimeClock, RTC_REGISTER_HOURS, RTC_REGISTER_MINUTES, RTC_REGISTER_SECONDS};
+
+    /// Registers selected by writing into 0xa001.
+    const REGISTER_ROM_BANK_LOW:   u8 = 0x00;
+    const REGISTER_ROM_BANK_HIGH:  u8 = 0x01;
+    const REGISTER_DATA_LOW:       u8 = 0x04;
+    const REGISTER_DATA_HIGH:      u8 = 0x05;
+    const REGISTER_ADDRESS_HIGH:   u8 = 0x06;
+    const REGISTER_ADDRESS_LOW:    u8 = 0x07;
+    const REGISTER_STATUS:         u8 = 0x0a;
+    const REGISTER_READ_LOW:       u8 = 0x0c;
+    const REGISTER_READ_HIGH:      u8 = 0x0d;
+
+    /// Commands selected by bits 1-3 of the upper address register.
+    const COMMAND_RAM_WRITE:   u8 = 0x00;
+    const COMMAND_RAM_READ:    u8 = 0x01;
+    const COMMAND_CLOCK:       u8 = 0x02;
+
+
+    /// Bandai TAMA5 Memory Bank Controller:
+    /// Supports up to 512kiB ROMs, 32 bytes of persistent RAM and a clock chip.
+    /// All functions are accessed via 4 bit registers, which are selected
+    /// by writing into 0xa001 and then written via 0xa000. RAM and clock are
+    /// accessed via commands, which get executed when writing the lower
+    /// address register. The time is kept by the cartridge's real time clock,
+    /// so it's stored in save files like the clock of MBC3 cartridges.
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Tama5 {
+        /// The register selected to be accessed via 0xa000.
+        register_selection: u8,
+
+        /// The values written into each register.
+        registers: [u8; 16],
+
+        /// The selected ROM bank number.
+        rom_bank_selected: u32,
+
+        /// The offset added to the address the game wants to read from,
+        /// to get the real address within the ROM file.
+        rom_bank_offset: usize,
+    }
+
+
+    impl Tama5 {
+        pub fn new() -> Self {
+            Self {
+                register_selection: 0x00,
+                registers: [0x00; 16],
+
+                rom_bank_selected: 1,
+                rom_bank_offset:   0x4000,
+            }
+        }
+
+
+        /// After writing to one of the bank selection registers, this function is used to
+        /// calculate the actual ROM bank number and the offset within the ROM image.
+        fn update_selected_banks(&mut self, cartridge: &Cartridge) {
+            let rom_bank =
+                    (self.registers[REGISTER_ROM_BANK_LOW as usize] as u32)
+                |   ((self.registers[REGISTER_ROM_BANK_HIGH as usize] as u32 & 0x01) << 4)
+            ;
+
+            if cartridge.get_rom_bank_count() != 0 {
+                self.rom_bank_selected = rom_bank % cartridge.get_rom_bank_count();
+                self.rom_bank_offset   = (self.rom_bank_selected as usize) * 0x4000;
+            }
+        }
+
+
+        /// Get the command selected by the upper address register.
+        fn get_command(&self) -> u8 {
+            self.registers[REGISTER_ADDRESS_HIGH as usize] >> 1
+        }
+
+
+        /// Get the 5 bit address from both address registers.
+        fn get_address(&self) -> usize {
+            let high = (self.registers[REGISTER_ADDRESS_HIGH as usize] & 0x01) as usize;
+            let low  = self.registers[REGISTER_ADDRESS_LOW as usize] as usize;
+
+            (high << 4) | low
+        }
+
+
+        /// Get the 8 bit value from both data registers.
+        fn get_data(&self) -> u8 {
+            (self.registers[REGISTER_DATA_HIGH as usize] << 4) | self.registers[REGISTER_DATA_LOW as usize]
+        }
+
+
+        /// Executes the command selected by the upper address register.
+        fn run_command(&mut self, cartridge: &mut Cartridge) {
+            let address = self.get_address();
+            let data    = self.get_data();
+
+            match self.get_command() {
+                COMMAND_RAM_WRITE if cartridge.has_ram() => {
+                    cartridge.get_ram_mut().set_at(address % TAMA5_RAM_SIZE, data);
+                }
+
+                COMMAND_CLOCK => {
+                    if let Some(rtc) = cartridge.get_rtc_mut() {
+                        write_clock_digit(rtc, address, data & 0x0f);
+                    }
+                }
+
+                _ => { }
+            }
+        }
+
+
+        /// Get the result of the command selected by the upper address register.
+        fn get_command_result(&self, cartridge: &Cartridge) -> u8 {
+            match self.get_command() {
+                COMMAND_RAM_READ if cartridge.has_ram() => {
+                    cartridge.get_ram().get_at(self.get_address() % TAMA5_RAM_SIZE)
+                }
+
+                COMMAND_CLOCK => {
+                    match cartridge.get_rtc() {
+                        Some(rtc) => read_clock_digit(rtc, self.get_address()),
+                        None      => 0x00,
+                    }
+                }
+
+                _ => 0x00,
+            }
+        }
+    }
+
+
+    /// Reads a single decimal digit of the clock. Address 0-5 contain the ones and tens
+    /// of seconds, minutes and hours, address 7-8 the ones and tens of the day counter.
+    fn read_clock_digit(rtc: &RealTimeClock, address: usize) -> u8 {
+        let current = rtc.get_current();
+        let days    = (current.get_days() % 100) as u8;
+
+        match address {
+            0x00 => current.seconds % 10,
+            0x01 => current.seconds / 10,
+            0x02 => current.minutes % 10,
+            0x03 => current.minutes / 10,
+            0x04 => current.hours % 10,
+            0x05 => current.hours / 10,
+            0x07 => days % 10,
+            0x08 => days / 10,
+            _    => 0x00,
+        }
+    }
+
+
+    /// Writes a single decimal digit of the clock's seconds, minutes or hours.
+    /// Address 0-5 contain the ones and tens of each value.
+    fn write_clock_digit(rtc: &mut RealTimeClock, address: usize, digit: u8) {
+        let register = match address / 2 {
+            0 => RTC_REGISTER_SECONDS,
+            1 => RTC_REGISTER_MINUTES,
+            2 => RTC_REGISTER_HOURS,
+            _ => return,
+        };
+
+        let value = rtc.get_current().get(register);
+        let value = match address % 2 {
+            0 => (value / 10) * 10 + digit,
+            _ => (value % 10) + digit * 10,
+        };
+
+        rtc.write_register(register, value);
+    }
+
+
+    impl MbcImpl for Tama5 {
+        fn read_byte(&self, cartridge: &Cartridge, address: u16) -> u8 {
+            match address {
+                // read from fixed ROM bank, which is always bank 0.
+                0x0000 ..= 0x3fff => {
+                    let rom_address = address as usize;
+                    cartridge.get_rom().get_at(rom_address)
+                },
+
+                // read from the switchable ROM bank
+                0x4000 ..= 0x7fff => {
+                    let rom_address = (address as usize) + self.rom_bank_offset - 0x4000;
+                    cartridge.get_rom().get_at(rom_address)
+                },
+
+                // read the selected register
+                0xa000 => {
+                    match self.register_selection {
+                        REGISTER_STATUS     => 0xf1,
+                        REGISTER_READ_LOW   => 0xf0 | (self.get_command_result(cartridge) & 0x0f),
+                        REGISTER_READ_HIGH  => 0xf0 | (self.get_command_result(cartridge) >> 4),
+                        _                   => 0xff,
+                    }
+                },
+
+                // unused area
+                0xa001 ..= 0xbfff => {
+                    0xff
+                },
+
+                _ => unreachable!("Unexpected read from address {}", address),
+            }
+        }
+
+
+        fn write_byte(&mut self, cartridge: &mut Cartridge, address: u16, value: u8) {
+            match address {
+                // not used by TAMA5
+                0x0000 ..= 0x7fff => {
+                },
+
+                // write into the selected register
+                0xa000 => {
+                    let register = self.register_selection & 0x0f;
+                    self.registers[register as usize] = value & 0x0f;
+
+                    match register {
+                        REGISTER_ROM_BANK_LOW | REGISTER_ROM_BANK_HIGH => {
+                            self.update_selected_banks(cartridge);
+                        }
+
+                        REGISTER_ADDRESS_LOW => {
+                            self.run_command(cartridge);
+                        }
+
+                        _ => { }
+                    }
+                },
+
+                // select the register to be accessed
+                0xa001 => {
+                    self.register_selection = value & 0x0f;
+                },
+
+                // unused area
+                0xa002 ..= 0xbfff => {
+                },
+
+                _ => unreachable!("Unexpected write to address {}", address),
+            }
+        }
+
+
+        fn get_register_writes(&self) -> Vec<(u16, u8)> {
+            let mut writes = Vec::new();
+
+            // write each register, but restore the lower address register while
+            // a read command is selected, so no command gets executed
+            let read_command = COMMAND_RAM_READ << 1;
+            for (register, value) in [
+                (REGISTER_ROM_BANK_LOW,  self.registers[REGISTER_ROM_BANK_LOW as usize]),
+                (REGISTER_ROM_BANK_HIGH, self.registers[REGISTER_ROM_BANK_HIGH as usize]),
+                (REGISTER_DATA_LOW,      self.registers[REGISTER_DATA_LOW as usize]),
+                (REGISTER_DATA_HIGH,     self.registers[REGISTER_DATA_HIGH as usize]),
+                (REGISTER_ADDRESS_HIGH,  read_command),
+                (REGISTER_ADDRESS_LOW,   self.registers[REGISTER_ADDRESS_LOW as usize]),
+                (REGISTER_ADDRESS_HIGH,  self.registers[REGISTER_ADDRESS_HIGH as usize]),
+            ] {
+                writes.push((0xa001, register));
+                writes.push((0xa000, value));
+            }
+
+            writes.push((0xa001, self.register_selection));
+
+            writes
+        }
+
+
+        fn get_rom_bank(&self, address: u16) -> u32 {
+            if address < 0x4000 { 0 } else { self.rom_bank_selected }
+        }
+
+
+        fn update(&mut self, cartridge: &mut Cartridge, cycles: Clock) {
+            if let Some(rtc) = cartridge.get_rtc_mut() {
+                rtc.update(cycles);
+            }
+        }
+    }
+}
+
+
+mod pocket_camera {
+    use super::*;
+    use crate::camera::{CameraImage, TestPatternImageSource, CAMERA_IMAGE_HEIGHT, CAMERA_IMAGE_WIDTH};
+    use crate::mmu::memory_data::MemoryData;
+    use crate::utils::get_bit;
+
+    /// The number of cycles a capture takes, without the exposure time.
+    const CAPTURE_CYCLES_BASE: Clock = 129_792;
+
+    /// Additional cycles a capture takes, unless the N flag is set.
+    const CAPTURE_CYCLES_WITHOUT_N: Clock = 2_048;
+
+    /// The number of cycles per unit of the exposure time register.
+    const CAPTURE_CYCLES_PER_EXPOSURE: Clock = 64;
+
+    /// The exposure time, at which the brightness of the sensor is not amplified.
+    const EXPOSURE_NEUTRAL: i32 = 0x1000;
+
+    /// The location of the captured image within the first RAM bank.
+    const IMAGE_RAM_OFFSET: usize = 0x0100;
+
+    /// The number of tiles per row of the captured image.
+    const IMAGE_TILES_PER_ROW: usize = CAMERA_IMAGE_WIDTH / 8;
+
+    /// The ratio of the edge enhancement, selected by bits 4-6 of register 4.
+    const EDGE_ENHANCEMENT_RATIOS: [f32; 8] = [0.5, 0.75, 1.0, 1.25, 2.0, 3.0, 4.0, 5.0];
+
+    /// Selecting this RAM bank maps the camera registers into the RAM area.
+    const RAM_BANK_REGISTERS: u8 = 0x10;
+
+    /// Numbers of the camera registers.
+    const REGISTER_CAPTURE:         u8 = 0x00;
+    const REGISTER_GAIN:            u8 = 0x01;
+    const REGISTER_EXPOSURE_HIGH:   u8 = 0x02;
+    const REGISTER_EXPOSURE_LOW:    u8 = 0x03;
+    const REGISTER_EDGE:            u8 = 0x04;
+    const REGISTER_VOLTAGE:         u8 = 0x05;
+    const REGISTER_DITHERING_FIRST: u8 = 0x06;
+    const REGISTER_DITHERING_LAST:  u8 = 0x35;
+
+
+    /// Game Boy Camera (Pocket Camera) Memory Bank Controller:
+    /// Supports up to 1MiB ROMs, 128kiB RAM and an image sensor, whose registers
+    /// can be mapped into the RAM area. When taking a picture, the image provided
+    /// by the connected [CameraImageSource] is processed by the sensor's exposure,
+    /// edge enhancement, invert and dithering settings and then written into the first
+    /// RAM bank as 16x14 tiles. The gain and voltage registers are stored, but their
+    /// effect on the analog signal of the sensor is not emulated.
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct PocketCamera {
+        /// The value written into the ROM bank selection register.
+        rom_bank_selection: u8,
+
+        /// The value written into the RAM bank selection register.
+        /// Setting bit 4 maps the camera registers into the RAM area.
+        ram_bank_selection: u8,
+
+        /// The selected ROM bank number.
+        rom_bank_selected: u32,
+
+        /// The offset added to the address the game wants to read from,
+        /// to get the real address within the ROM file.
+        rom_bank_offset: usize,
+
+        /// The offset added to the address the game wants to read/write,
+        /// to get the real address within the RAM image.
+        ram_bank_offset: usize,
+
+        /// Sets if writing into RAM is enabled or not.
+        ram_enabled: bool,
+
+        /// Register 0: The edge enhancement mode, without the capture bit.
+        capture_flags: u8,
+
+        /// Register 1: The N and VH flags and the gain of the sensor.
+        /// Only the N flag is used, which affects the duration of a capture.
+        gain: u8,
+
+        /// Register 2 and 3: The exposure time.
+        exposure: u16,
+
+        /// Register 4: The edge enhancement ratio, invert flag and output voltage.
+        /// The output voltage is not emulated.
+        edge: u8,
+
+        /// Register 5: The zero point calibration of the sensor, which is not emulated.
+        voltage: u8,
+
+        /// Register 6-53: The thresholds of the three shades for each pixel of a 4x4 dithering matrix.
+        dithering_matrix: [[u8; 3]; 16],
+
+        /// The remaining number of cycles until the current capture is completed.
+        capture_cycles_remaining: Clock,
+
+        /// The source of the images seen by the sensor.
+        #[cfg_attr(feature = "serde", serde(skip))]
+        image_source: Option<Box<dyn CameraImageSource>>,
+    }
+
+
+    impl PocketCamera {
+        pub fn new() -> Self {
+            Self {
+                rom_bank_selection: 0x01,
+                ram_bank_selection: 0x00,
+
+                rom_bank_selected: 1,
+                rom_bank_offset:   0x4000,
+
+                ram_bank_offset:   0x0000,
+
+                ram_enabled: false,
+
+                capture_flags:    0x00,
+                gain:             0x00,
+                exposure:         0x0000,
+                edge:             0x00,
+                voltage:          0x00,
+                dithering_matrix: [[0x00; 3]; 16],
+
+                capture_cycles_remaining: 0,
+
+                image_source: None,
+            }
+        }
+
+
+        /// After writing to one of the bank selection registers,
+        /// this function is used to calculate the actual RAM and ROM bank numbers
+        /// as well as the offsets to read and write inside the ROM and RAM images.
+        fn update_selected_banks(&mut self, cartridge: &Cartridge) {
+            // unlike MBC1, bank 0 can be mapped into the switchable ROM area
+            if cartridge.get_rom_bank_count() != 0 {
+                self.rom_bank_selected = (self.rom_bank_selection as u32) % cartridge.get_rom_bank_count();
+                self.rom_bank_offset   = (self.rom_bank_selected as usize) * 0x4000;
+            }
+
+            if cartridge.get_ram_bank_count() != 0 && !self.is_register_area_selected() {
+                let ram_bank = (self.ram_bank_selection as u32) % cartridge.get_ram_bank_count();
+                self.ram_bank_offset = (ram_bank as usize) * 0x2000;
+            }
+        }
+
+
+        /// Checks whether the camera registers are mapped into the RAM area.
+        fn is_register_area_selected(&self) -> bool {
+            (self.ram_bank_selection & RAM_BANK_REGISTERS) != 0
+        }
+
+
+        /// Checks whether the sensor is currently taking a picture.
+        pub fn is_capturing(&self) -> bool {
+            self.capture_cycles_remaining != 0
+        }
+
+
+        /// Writes into one of the camera registers.
+        fn write_register(&mut self, register: u8, value: u8) {
+            match register {
+                REGISTER_CAPTURE => {
+                    // setting bit 0 starts a new capture, clearing it cancels the current one
+                    if get_bit(value, 0) && !self.is_capturing() {
+                        self.capture_cycles_remaining = self.get_capture_cycles();
+                    }
+                    else if !get_bit(value, 0) {
+                        self.capture_cycles_remaining = 0;
+                    }
+
+                    // the busy state is stored in the remaining capture cycles instead of bit 0
+                    self.capture_flags = value & 0x06;
+                }
+
+                REGISTER_GAIN           => self.gain     = value,
+                REGISTER_EXPOSURE_HIGH  => self.exposure = (self.exposure & 0x00ff) | ((value as u16) << 8),
+                REGISTER_EXPOSURE_LOW   => self.exposure = (self.exposure & 0xff00) |  (value as u16),
+                REGISTER_EDGE           => self.edge     = value,
+                REGISTER_VOLTAGE        => self.voltage  = value,
+
+                REGISTER_DITHERING_FIRST ..= REGISTER_DITHERING_LAST => {
+                    let index = (register - REGISTER_DITHERING_FIRST) as usize;
+                    self.dithering_matrix[index / 3][index % 3] = value;
+                }
+
+                _ => { }
+            }
+        }
+
+
+        /// Get the number of cycles a capture takes with the current settings.
+        fn get_capture_cycles(&self) -> Clock {
+            let n_cycles = if get_bit(self.gain, 7) { 0 } else { CAPTURE_CYCLES_WITHOUT_N };
+
+            CAPTURE_CYCLES_BASE + n_cycles + (self.exposure as Clock) * CAPTURE_CYCLES_PER_EXPOSURE
+        }
+
+
+        /// Takes a picture from the image source and stores the processed image in RAM.
+        fn capture(&mut self, cartridge: &mut Cartridge) {
+            let mut sensor = [0u8; CAMERA_IMAGE_WIDTH * CAMERA_IMAGE_HEIGHT];
+
+            match &mut self.image_source {
+                Some(image_source) => image_source.capture(&mut sensor),
+                None               => TestPatternImageSource.capture(&mut sensor),
+            }
+
+            if !cartridge.has_ram() {
+                return;
+            }
+
+            let ram = cartridge.get_ram_mut();
+
+            for y in 0..CAMERA_IMAGE_HEIGHT {
+                for x in 0..CAMERA_IMAGE_WIDTH {
+                    let shade = self.get_pixel_shade(&sensor, x, y);
+
+                    // store the pixel in 2bpp tile format
+                    let tile    = (y / 8) * IMAGE_TILES_PER_ROW + (x / 8);
+                    let address = IMAGE_RAM_OFFSET + tile * 16 + (y % 8) * 2;
+                    let bit     = 7 - (x % 8);
+
+                    for plane in 0..2 {
+                        let mask  = 1 << bit;
+                        let value = ram.get_at(address + plane) & !mask;
+                        let set   = if get_bit(shade, plane as u8) { mask } else { 0 };
+                        ram.set_at(address + plane, value | set);
+                    }
+                }
+            }
+        }
+
+
+        /// Get the brightness of a single pixel after applying the exposure time.
+        /// Coordinates outside of the image are clamped to its borders.
+        fn get_exposed_pixel(&self, sensor: &CameraImage, x: isize, y: isize) -> f32 {
+            let x = x.clamp(0, CAMERA_IMAGE_WIDTH  as isize - 1) as usize;
+            let y = y.clamp(0, CAMERA_IMAGE_HEIGHT as isize - 1) as usize;
+
+            let brightness = sensor[y * CAMERA_IMAGE_WIDTH + x] as i32;
+            let exposed    = brightness * (self.exposure as i32) / EXPOSURE_NEUTRAL;
+
+            exposed.min(0xff) as f32
+        }
+
+
+        /// Get the shade of a single pixel, from 0 (white) to 3 (black),
+        /// after applying edge enhancement, inversion and dithering.
+        fn get_pixel_shade(&self, sensor: &CameraImage, x: usize, y: usize) -> u8 {
+            let (ix, iy)  = (x as isize, y as isize);
+            let mut value = self.get_exposed_pixel(sensor, ix, iy);
+
+            // edge enhancement in both directions
+            if (self.capture_flags & 0x06) == 0x06 {
+                let ratio = EDGE_ENHANCEMENT_RATIOS[((self.edge >> 4) & 0x07) as usize];
+                let neighbours =
+                        self.get_exposed_pixel(sensor, ix - 1, iy)
+                    +   self.get_exposed_pixel(sensor, ix + 1, iy)
+                    +   self.get_exposed_pixel(sensor, ix, iy - 1)
+                    +   self.get_exposed_pixel(sensor, ix, iy + 1)
+                ;
+
+                value += (value * 4.0 - neighbours) * ratio;
+            }
+
+            // invert the output signal
+            if get_bit(self.edge, 3) {
+                value = 255.0 - value.clamp(0.0, 255.0);
+            }
+
+            // compare with the thresholds of the matrix entry for this pixel
+            let thresholds = &self.dithering_matrix[(y % 4) * 4 + (x % 4)];
+            let value      = value.clamp(0.0, 255.0) as u8;
+
+            match thresholds.iter().position(|threshold| value < *threshold) {
+                Some(index) => 3 - index as u8,
+                None        => 0,
+            }
+        }
+    }
+
+
+    impl MbcImpl for PocketCamera {
+        fn read_byte(&self, cartridge: &Cartridge, address: u16) -> u8 {
+            match address {
+                // read from fixed ROM bank, which is always bank 0.
+                0x0000 ..= 0x3fff => {
+                    let rom_address = address as usize;
+                    cartridge.get_rom().get_at(rom_address)
+                },
+
+                // read from the switchable ROM bank
+                0x4000 ..= 0x7fff => {
+                    let rom_address = (address as usize) + self.rom_bank_offset - 0x4000;
+                    cartridge.get_rom().get_at(rom_address)
+                },
+
+                // camera registers, of which only the capture register can be read
+                0xa000 ..= 0xbfff if self.is_register_area_selected() => {
+                    match (address & 0x007f) as u8 {
+                        REGISTER_CAPTURE => self.capture_flags | (self.is_capturing() as u8),
+                        _                => 0x00,
+                    }
+                }
+
+                // RAM can be read without being enabled, but not while taking a picture
+                0xa000 ..= 0xbfff => {
+                    if self.is_capturing() {
+                        0x00
+                    }
+                    else if cartridge.has_ram() {
+                        let ram_address = (address as usize) + self.ram_bank_offset - 0xa000;
+                        cartridge.get_ram().get_at(ram_address)
+                    }
+                    else {
+                        0xff
+                    }
+                }
+
+                _ => unreachable!("Unexpected read from address {}", address),
+            }
+        }
+
+
+        fn write_byte(&mut self, cartridge: &mut Cartridge, address: u16, value: u8) {
+            match address {
+                // enable or disable writing into RAM
+                0x0000 ..= 0x1fff => {
+                    self.ram_enabled = (value & 0x0f) == 0x0a;
+                },
+
+                // ROM bank selection
+                0x2000 ..= 0x3fff => {
+                    self.rom_bank_selection = value & 0x3f;
+                    self.update_selected_banks(cartridge);
+                },
+
+                // RAM bank or camera register selection
+                0x4000 ..= 0x5fff => {
+                    self.ram_bank_selection = value & 0x1f;
+                    self.update_selected_banks(cartridge);
+                },
+
+                // invalid address
+                0x6000 ..= 0x7fff => {
+                },
+
+                // camera registers
+                0xa000 ..= 0xbfff if self.is_register_area_selected() => {
+                    self.write_register((address & 0x007f) as u8, value);
+                },
+
+                // Cartridge RAM
+                0xa000 ..= 0xbfff => {
+                    if cartridge.has_ram() && self.ram_enabled && !self.is_capturing() {
+                        let ram_address = (address as usize) - 0xa000 + self.ram_bank_offset;
+                        cartridge.get_ram_mut().set_at(ram_address, value);
+                    }
+                },
+
+                _ => unreachable!("Unexpected write to address {}", address),
+            }
+        }
+
+
+        fn get_register_writes(&self) -> Vec<(u16, u8)> {
+            let mut writes = vec![
+                (0x0000, if self.ram_enabled { 0x0a } else { 0x00 }),
+                (0x2000, self.rom_bank_selection),
+
+                // restore the camera registers, without starting a capture
+                (0x4000, RAM_BANK_REGISTERS),
+                (0xa000, self.capture_flags),
+                (0xa001, self.gain),
+                (0xa002, (self.exposure >> 8) as u8),
+                (0xa003, (self.exposure & 0xff) as u8),
+                (0xa004, self.edge),
+                (0xa005, self.voltage),
+            ];
+
+            for (index, value) in self.dithering_matrix.iter().flatten().enumerate() {
+                writes.push((0xa000 + REGISTER_DITHERING_FIRST as u16 + index as u16, *value));
+            }
+
+            writes.push((0x4000, self.ram_bank_selection));
+
+            writes
+        }
+
+
+        fn get_rom_bank(&self, address: u16) -> u32 {
+            if address < 0x4000 { 0 } else { self.rom_bank_selected }
+        }
+
+
+        fn update(&mut self, cartridge: &mut Cartridge, cycles: Clock) {
+            if self.is_capturing() {
+                self.capture_cycles_remaining = self.capture_cycles_remaining.saturating_sub(cycles);
+
+                if !self.is_capturing() {
+                    self.capture(cartridge);
+                }
+            }
+        }
+
+
+        fn set_camera_image_source(&mut self, source: Box<dyn CameraImageSource>) {
+            self.image_source = Some(source);
+        }
+
+
+        fn take_camera_image_source(&mut self) -> Option<Box<dyn CameraImageSource>> {
+            self.image_source.take()
+        }
     }
 }
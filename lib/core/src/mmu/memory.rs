@@ -17,14 +17,18 @@
 
 use std::cmp::max;
 use std::io;
+use std::mem::take;
 
 use crate::boot_rom::BootRom;
+use crate::camera::CameraImageSource;
 use crate::cartridge::Cartridge;
-use crate::gameboy::{DeviceConfig, EmulationType};
+use crate::debug::DebugEvent;
+use crate::gameboy::{Clock, DeviceConfig, EmulationType};
 use crate::mmu::locations::*;
 use crate::mmu::mbc::{create_mbc, Mbc, MbcImpl, MemoryBankController};
-use crate::mmu::memory_bus::{memory_map, MemoryBusConnection};
+use crate::mmu::memory_bus::{memory_map, MemoryBusConnection, MemoryBusSignals};
 use crate::mmu::memory_data::{MemoryData, MemoryDataFixedSize};
+use crate::utils::get_bit;
 
 
 /// Stores the information of an active OAM DMA transfer
@@ -57,6 +61,38 @@ pub enum DmaTransferState {
 }
 
 
+/// Stores the state of a GBC VRAM DMA transfer, which copies data from ROM or RAM
+/// into the active VRAM bank in blocks of 16 bytes. The transfer may either be
+/// performed all at once (general purpose DMA) or one block on each HBlank.
+#[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VramDmaTransferInfo {
+    /// The address where to copy the next block from.
+    pub source: u16,
+
+    /// The offset within VRAM where to copy the next block to.
+    pub destination: u16,
+
+    /// The number of blocks remaining to be transferred.
+    pub remaining_blocks: u8,
+
+    /// Whether a HBlank DMA transfer is currently in progress.
+    pub hblank_active: bool,
+}
+
+
+/// A change of the state of the cartridge's rumble motor.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RumbleEvent {
+    /// The total number of device cycles processed by the emulator when the state changed.
+    pub cycle: Clock,
+
+    /// Whether the motor was turned on or off.
+    pub active: bool,
+}
+
+
 pub type WRamBank = MemoryDataFixedSize<4096>;
 pub type HRamBank = MemoryDataFixedSize<127>;
 
@@ -78,9 +114,38 @@ pub struct Memory {
     /// High RAM
     hram: HRamBank,
 
+    /// GBC only: the CPU is running in double speed mode.
+    double_speed: bool,
+
+    /// GBC only: a speed switch was requested via KEY1 and will
+    /// be performed on the next STOP instruction.
+    speed_switch_armed: bool,
+
+    /// GBC only: the value written into KEY0 by the boot ROM, which selects
+    /// the DMG compatibility mode once the boot ROM gets unmapped.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_since::<2, _, _>"))]
+    key0: u8,
+
     /// MemoryBankController implementation.
     mbc: Mbc,
 
+    /// The state of the cartridge's rumble motor when it was checked the last time.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_since::<2, _, _>"))]
+    rumble_active: bool,
+
+    /// All changes of the rumble motor state not yet taken by the frontend.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    rumble_events: Vec<RumbleEvent>,
+
+    /// A flag to enable or disable recording rumble events.
+    /// This is a setting of the frontend and not part of snapshots.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    rumble_events_enabled: bool,
+
+    /// Pending output to be sent back through the memory bus.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    signals: MemoryBusSignals,
+
     boot_rom:   Option<BootRom>,
     cartridge:  Option<Cartridge>,
 }
@@ -103,8 +168,18 @@ impl Memory {
 
             hram: HRamBank::new(),
 
+            double_speed:       false,
+            speed_switch_armed: false,
+            key0:               0x00,
+
             mbc: create_mbc(&MemoryBankController::None),
 
+            rumble_active:          false,
+            rumble_events:          Vec::new(),
+            rumble_events_enabled:  false,
+
+            signals: MemoryBusSignals::default(),
+
             boot_rom:   None,
             cartridge:  None,
         }
@@ -119,6 +194,14 @@ impl Memory {
         }
     }
 
+    /// Checks whether the boot rom is currently mapped on the given address.
+    pub fn is_boot_rom_mapped(&self, address: u16) -> bool {
+        match &self.boot_rom {
+            Some(boot_rom) => boot_rom.is_mapped(address),
+            None           => false,
+        }
+    }
+
     /// Load a boot ROM into the memory.
     pub fn set_boot_rom(&mut self, boot_rom: BootRom) {
         self.boot_rom = Some(boot_rom)
@@ -127,6 +210,7 @@ impl Memory {
     /// Load ROM data from a cartridge into the memory.
     pub fn set_cartridge(&mut self, cartridge: Cartridge) {
         self.mbc       = create_mbc(cartridge.get_mbc());
+        self.mbc.init(&cartridge);
         self.cartridge = Some(cartridge);
     }
 
@@ -135,6 +219,163 @@ impl Memory {
         self.cartridge.as_ref()
     }
 
+    /// Get a mutable reference to the currently assigned cartridge, if any.
+    pub fn get_cartridge_mut(&mut self) -> Option<&mut Cartridge> {
+        self.cartridge.as_mut()
+    }
+
+    /// Get the memory bank controller handling the cartridge.
+    pub fn get_mbc(&self) -> &Mbc {
+        &self.mbc
+    }
+
+    /// Get all work RAM banks (DMG = 2 banks, GBC = 8 banks).
+    pub fn get_wram_banks(&self) -> &[WRamBank] {
+        &self.wram_banks
+    }
+
+    /// Get all work RAM banks (DMG = 2 banks, GBC = 8 banks).
+    pub fn get_wram_banks_mut(&mut self) -> &mut [WRamBank] {
+        &mut self.wram_banks
+    }
+
+    /// Get the high RAM.
+    pub fn get_hram(&self) -> &HRamBank {
+        &self.hram
+    }
+
+    /// Get the high RAM.
+    pub fn get_hram_mut(&mut self) -> &mut HRamBank {
+        &mut self.hram
+    }
+
+    /// Let the memory handle time based components on the cartridge.
+    /// 'cycles' gives the number of ticks passed since the last call.
+    pub fn update(&mut self, cycles: Clock) {
+        if let Some(cartridge) = &mut self.cartridge {
+            self.mbc.update(cartridge, cycles);
+        }
+    }
+
+    /// Forwards the current tilt of the device to the cartridge's accelerometer, if any.
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        self.mbc.set_tilt(x, y);
+    }
+
+    /// Connects the source of images seen by the cartridge's camera sensor, if any.
+    /// This needs to be done after the cartridge was loaded.
+    pub fn set_camera_image_source(&mut self, source: Box<dyn CameraImageSource>) {
+        self.mbc.set_camera_image_source(source);
+    }
+
+
+    /// Disconnects the source of images seen by the cartridge's camera sensor and returns it.
+    /// The image source is not part of snapshots, so frontends can use this to carry it
+    /// over to an emulator instance restored from a snapshot.
+    pub fn take_camera_image_source(&mut self) -> Option<Box<dyn CameraImageSource>> {
+        self.mbc.take_camera_image_source()
+    }
+
+    /// Takes over the settings of the frontend, which are not part of snapshots,
+    /// from a previous instance. This is the camera's image source and whether
+    /// rumble events are recorded. Used after restoring a snapshot or rewinding.
+    pub fn take_frontend_state_from(&mut self, previous: &mut Memory) {
+        self.enable_rumble_events(previous.rumble_events_enabled);
+
+        if let Some(image_source) = previous.take_camera_image_source() {
+            self.set_camera_image_source(image_source);
+        }
+    }
+
+    /// Checks whether the state of the cartridge's rumble motor has changed
+    /// and records the change with the current time of the emulator.
+    /// A change will be reported via [DebugEvent::RumbleChanged].
+    pub fn update_rumble(&mut self, total_cycles: Clock) {
+        let active = match &self.cartridge {
+            Some(cartridge) => self.mbc.is_rumble_active(cartridge),
+            None            => false,
+        };
+
+        if active != self.rumble_active {
+            self.rumble_active   = active;
+            self.signals.events |= DebugEvent::RumbleChanged;
+
+            if self.rumble_events_enabled {
+                self.rumble_events.push(RumbleEvent {
+                    cycle: total_cycles,
+                    active,
+                });
+            }
+        }
+    }
+
+    /// Checks whether the rumble motor of the cartridge is currently turned on.
+    pub fn is_rumble_active(&self) -> bool {
+        self.rumble_active
+    }
+
+    /// Enables or disables recording the changes of the rumble motor's state.
+    /// Frontends need to collect them via [Memory::take_rumble_events] regularly.
+    pub fn enable_rumble_events(&mut self, enabled: bool) {
+        self.rumble_events_enabled = enabled;
+
+        if !enabled {
+            self.rumble_events.clear();
+        }
+    }
+
+    /// Takes all changes of the rumble motor's state since the last call.
+    pub fn take_rumble_events(&mut self) -> Vec<RumbleEvent> {
+        take(&mut self.rumble_events)
+    }
+
+    /// Checks whether the boot ROM selected the DMG compatibility mode via KEY0.
+    pub fn is_dmg_compatibility_selected(&self) -> bool {
+        self.device_config.device.has_gbc_support() && get_bit(self.key0, 2)
+    }
+
+    /// Switches the memory of a GameBoy Color into DMG compatibility mode,
+    /// which disables switching WRAM banks and the CPU speed.
+    pub fn enter_dmg_compatibility_mode(&mut self) {
+        self.device_config.emulation = EmulationType::DMG;
+        self.wram_active_bank_1      = 1;
+        self.double_speed            = false;
+        self.speed_switch_armed      = false;
+    }
+
+    /// Checks whether the CPU is currently running in double speed mode.
+    pub fn is_double_speed(&self) -> bool {
+        self.double_speed
+    }
+
+    /// Sets the CPU speed directly, without the need of a speed switch.
+    /// Used when restoring the state of a device.
+    pub fn set_double_speed(&mut self, double_speed: bool) {
+        if let EmulationType::GBC = self.device_config.emulation {
+            self.double_speed       = double_speed;
+            self.speed_switch_armed = false;
+        }
+    }
+
+    /// Checks whether a speed switch was requested, which will be performed
+    /// when the CPU executes the next STOP instruction.
+    pub fn is_speed_switch_armed(&self) -> bool {
+        self.speed_switch_armed
+    }
+
+    /// Toggles between normal and double speed mode, if a speed switch was requested before.
+    /// Returns whether the speed was switched or not.
+    pub fn perform_speed_switch(&mut self) -> bool {
+        if self.speed_switch_armed {
+            self.speed_switch_armed = false;
+            self.double_speed       = !self.double_speed;
+
+            return true;
+        }
+
+        false
+    }
+
     /// Save the cartridge RAM, if any.
     pub fn save_cartridge_ram_to_file_if_any(&self) -> io::Result<()> {
         if let Some(cartridge) = &self.cartridge {
@@ -147,10 +388,12 @@ impl Memory {
 
 
 impl Memory {
-    /// Reads data from the boot rom, if any, otherwise from the cartridge.
+    /// Reads data from the boot rom, if any and mapped on this address, otherwise from the cartridge.
     fn read_boot_rom_or_cartridge(&self, address: u16) -> u8 {
         if let Some(boot_rom) = &self.boot_rom {
-            return boot_rom.read(address);
+            if boot_rom.is_mapped(address) {
+                return boot_rom.read(address);
+            }
         }
 
         self.read_from_cartridge(address)
@@ -180,8 +423,8 @@ impl MemoryBusConnection for Memory {
     fn on_read(&self, address: u16) -> u8 {
         memory_map!(
             address => {
-                0x0000 ..= 0x00ff => [] self.read_boot_rom_or_cartridge(address),
-                0x0100 ..= 0x7fff => [] self.read_from_cartridge(address),
+                0x0000 ..= 0x08ff => [] self.read_boot_rom_or_cartridge(address),
+                0x0900 ..= 0x7fff => [] self.read_from_cartridge(address),
                 0xa000 ..= 0xbfff => [] self.read_from_cartridge(address),
 
                 0xc000 ..= 0xcfff => [mapped_address] {
@@ -218,6 +461,18 @@ impl MemoryBusConnection for Memory {
                             }
                         },
 
+                        MEMORY_LOCATION_KEY1 => {
+                            // on GBC: current speed & speed switch armed
+                            if let EmulationType::GBC = self.device_config.emulation {
+                                    0b_0111_1110
+                                |   if self.double_speed       { 0b_1000_0000 } else { 0 }
+                                |   if self.speed_switch_armed { 0b_0000_0001 } else { 0 }
+                            }
+                            else {
+                                0xff
+                            }
+                        },
+
                         MEMORY_LOCATION_SVBK => {
                             // on GBC: WRAM bank #1
                             if let EmulationType::GBC = self.device_config.emulation {
@@ -272,6 +527,20 @@ impl MemoryBusConnection for Memory {
                             }
                         },
 
+                        MEMORY_LOCATION_KEY0 => {
+                            // on GBC: only writable by the boot ROM
+                            if self.device_config.device.has_gbc_support() && self.boot_rom.is_some() {
+                                self.key0 = value;
+                            }
+                        },
+
+                        MEMORY_LOCATION_KEY1 => {
+                            // on GBC: arm speed switch, only bit 0 is writable
+                            if let EmulationType::GBC = self.device_config.emulation {
+                                self.speed_switch_armed = (value & 0x01) != 0;
+                            }
+                        },
+
                         MEMORY_LOCATION_SVBK => {
                             // on GBC: switch WRAM bank #1
                             if let EmulationType::GBC = self.device_config.emulation {
@@ -286,4 +555,9 @@ impl MemoryBusConnection for Memory {
             }
         )
     }
+
+
+    fn take_signals(&mut self) -> MemoryBusSignals {
+        take(&mut self.signals)
+    }
 }
@@ -65,6 +65,13 @@ pub trait MemoryBusConnection {
     /// A request to write to a memory address in the components accountability.
     fn on_write(&mut self, address: u16, value: u8);
 
+    /// Get the raw value of a register in the components accountability, including
+    /// write-only bits, which cannot be seen via [MemoryBusConnection::on_read].
+    /// Writing this value back restores the state of the register.
+    fn get_register_value(&self, address: u16) -> u8 {
+        self.on_read(address)
+    }
+
     /// Takes the signals sent from a component since the last call.
     /// After calling this, the pending signals of this component are expected to be cleared.
     fn take_signals(&mut self) -> MemoryBusSignals {
@@ -106,6 +113,15 @@ pub trait MemoryBus<TRootType, TMemoryMapper>
     }
 
 
+    /// Get the raw value of a register via this memory bus,
+    /// see [MemoryBusConnection::get_register_value].
+    fn read_register_value(&self, address: u16) -> u8 {
+        let root       = self.get_root();
+        let connection = TMemoryMapper::map(address, root);
+        connection.get_register_value(address)
+    }
+
+
     /// Send a single byte to an address via this memory bus.
     /// The memory bus will forward the data to the according component.
     fn write(&mut self, address: u16, value: u8) {
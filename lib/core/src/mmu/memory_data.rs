@@ -84,6 +84,8 @@ mod dynamic_size {
     use crate::mmu::memory_data::MemoryData;
 
     /// A data object storing data of variable size.
+    /// Accessing addresses beyond the size of the data will be mirrored,
+    /// like a RAM chip being smaller than the address space it is mapped into.
     #[derive(Clone)]
     pub struct MemoryDataDynamic {
         data: Vec<u8>,
@@ -106,11 +108,18 @@ mod dynamic_size {
         }
 
         fn get_at(&self, address: usize) -> u8 {
-            self.data[address]
+            match self.data.len() {
+                0    => 0xff,
+                size => self.data[address % size],
+            }
         }
 
         fn set_at(&mut self, address: usize, value: u8) {
-            self.data[address] = value;
+            let size = self.data.len();
+
+            if size != 0 {
+                self.data[address % size] = value;
+            }
         }
 
         fn as_slice(&self) -> &[u8] {
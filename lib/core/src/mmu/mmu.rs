@@ -17,12 +17,22 @@
 
 use std::cmp::min;
 
-use crate::gameboy::{Clock, Peripherals};
+use crate::debug::breakpoints::{Breakpoints, MemoryAccess};
+use crate::gameboy::{Clock, DeviceConfig, EmulationType, Peripherals};
 use crate::mmu::locations::*;
-use crate::mmu::memory::{DmaTransferInfo, DmaTransferState};
+use crate::mmu::memory::{DmaTransferInfo, DmaTransferState, VramDmaTransferInfo};
 use crate::mmu::memory_bus::{impl_memory_mapper, MemoryBus, MemoryBusConnection, MemoryMapper};
 use crate::mmu::memory_data::MemoryData;
-use crate::utils::{to_u16, to_u8};
+use crate::ppu::flags::LcdControlFlag;
+use crate::utils::{get_bit, to_u16, to_u8};
+
+
+/// Size of a single block transferred by VRAM DMA.
+const VRAM_DMA_BLOCK_SIZE: u16 = 16;
+
+/// The time the CPU gets stalled while transferring a single block via VRAM DMA.
+/// In double speed mode, this will take twice as many CPU cycles.
+const VRAM_DMA_CYCLES_PER_BLOCK: Clock = 32;
 
 /// The memory management unit, which provides an interface to read and write the device memory.
 /// IO operations are performed via memory bus, which maps memory addresses to their according
@@ -30,6 +40,10 @@ use crate::utils::{to_u16, to_u8};
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mmu {
     internal: MmuInternal,
+
+    /// Breakpoints and watchpoints observing memory accesses by the CPU.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    breakpoints: Breakpoints,
 }
 
 
@@ -37,28 +51,51 @@ pub struct Mmu {
 /// IO operations to their actual components.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MmuInternal {
+    /// The configuration of the running device
+    device_config: DeviceConfig,
+
     peripherals: Peripherals,
 
     dma: DmaTransferState,
 
     /// Stores the value written into the DMA register.
     dma_register_value: u8,
+
+    /// GBC only: state of the VRAM DMA transfer.
+    vram_dma: VramDmaTransferInfo,
+
+    /// The number of cycles the CPU is stalled by a VRAM DMA transfer.
+    vram_dma_stall_cycles: Clock,
 }
 
 
 impl Mmu {
-    pub fn new(peripherals: Peripherals) -> Self {
+    pub fn new(device_config: DeviceConfig, peripherals: Peripherals) -> Self {
         Self {
             internal: MmuInternal {
+                device_config,
+
                 peripherals,
 
                 dma: DmaTransferState::Disabled,
                 dma_register_value: 0xff,
-            }
+
+                vram_dma: VramDmaTransferInfo::default(),
+                vram_dma_stall_cycles: 0,
+            },
+
+            breakpoints: Breakpoints::default(),
         }
     }
 
 
+    /// Get the configuration of the running device.
+    /// On GBC devices, the emulation type may be changed by the boot ROM.
+    pub fn get_device_config(&self) -> &DeviceConfig {
+        &self.internal.device_config
+    }
+
+
     /// Get the peripherals connected to the memory bus.
     pub fn get_peripherals(&self) -> &Peripherals {
         &self.internal.peripherals
@@ -71,12 +108,56 @@ impl Mmu {
     }
 
 
+    /// Get the breakpoints and watchpoints to be checked while running the emulator.
+    pub fn get_breakpoints(&self) -> &Breakpoints {
+        &self.breakpoints
+    }
+
+
+    /// Get the breakpoints and watchpoints to be checked while running the emulator.
+    pub fn get_breakpoints_mut(&mut self) -> &mut Breakpoints {
+        &mut self.breakpoints
+    }
+
+
     /// Reads a single byte value from the memory bus on a given address.
+    /// While the emulator is running, this may trigger watchpoints.
     pub fn read_u8(&self, address: u16) -> u8 {
+        let value = self.internal.read(address);
+
+        if self.breakpoints.is_watching() {
+            self.breakpoints.check_memory_access(address, value, MemoryAccess::Read);
+        }
+
+        value
+    }
+
+
+    /// Reads a single byte value from the memory bus on a given address
+    /// without triggering any watchpoints, like when fetching instructions.
+    pub fn peek_u8(&self, address: u16) -> u8 {
         self.internal.read(address)
     }
 
 
+    /// Reads a single byte value from the memory bus for debugging purposes.
+    /// Unlike [Self::peek_u8], this bypasses the VRAM and OAM locks of the PPU,
+    /// so debugger views always see the actual memory contents.
+    pub fn debug_read_u8(&self, address: u16) -> u8 {
+        match address {
+            0x8000 ..= 0x9fff | 0xfe00 ..= 0xfe9f => self.internal.peripherals.ppu.debug_read(address),
+            _ => self.internal.read(address),
+        }
+    }
+
+
+    /// Get the raw value of an IO register, including write-only bits,
+    /// which would be masked when reading it via [Self::read_u8].
+    pub fn get_register_value(&self, address: u16) -> u8 {
+        self.internal.read_register_value(address)
+    }
+
+
     /// Reads two bytes into a 16 bit integer from the memory bus on a given address.
     pub fn read_u16(&self, address: u16) -> u16 {
         let l = self.read_u8(address.wrapping_add(0));
@@ -86,8 +167,13 @@ impl Mmu {
 
 
     /// Writes a single byte value to the memory bus on a given address.
+    /// While the emulator is running, this may trigger watchpoints.
     pub fn write_u8(&mut self, address: u16, value: u8) {
         self.internal.write(address, value);
+
+        if self.breakpoints.is_watching() {
+            self.breakpoints.check_memory_access(address, value, MemoryAccess::Write);
+        }
     }
 
 
@@ -104,6 +190,21 @@ impl Mmu {
     /// the last call.
     pub fn update(&mut self, cycles: Clock) {
         self.internal.handle_dma_transfer(cycles);
+        self.internal.handle_vram_dma_transfer(cycles);
+    }
+
+
+    /// Transfers the next block of an active HBlank DMA, if the PPU just entered HBlank.
+    /// This needs to be called after the PPU was updated, so the transfer
+    /// starts on the same cycle the PPU enters HBlank.
+    pub fn update_hblank_dma(&mut self) {
+        self.internal.handle_hblank_dma_transfer();
+    }
+
+
+    /// Checks whether the CPU is currently stalled by a VRAM DMA transfer.
+    pub fn is_cpu_stalled(&self) -> bool {
+        self.internal.vram_dma_stall_cycles > 0
     }
 }
 
@@ -141,6 +242,95 @@ impl MmuInternal {
             }
         }
     }
+
+
+    /// Handles the time passed during a VRAM DMA transfer.
+    fn handle_vram_dma_transfer(&mut self, cycles: Clock) {
+        self.vram_dma_stall_cycles = self.vram_dma_stall_cycles.saturating_sub(cycles);
+    }
+
+
+    /// Transfers the next block of a HBlank DMA once the PPU entered HBlank.
+    fn handle_hblank_dma_transfer(&mut self) {
+        // always take the PPU's HBlank state, so it won't trigger a transfer started later
+        let hblank_started = self.peripherals.ppu.take_hblank_started();
+
+        if self.vram_dma.hblank_active && hblank_started {
+            self.transfer_vram_dma_block();
+        }
+    }
+
+
+    /// Starts a new VRAM DMA transfer or cancels an active HBlank DMA
+    /// after the HDMA5 register was written.
+    fn start_vram_dma_transfer(&mut self, value: u8) {
+        let hblank_mode = get_bit(value, 7);
+
+        // writing a zero into bit 7 during an HBlank DMA cancels the transfer
+        if self.vram_dma.hblank_active && !hblank_mode {
+            self.vram_dma.hblank_active = false;
+            return;
+        }
+
+        self.vram_dma.remaining_blocks = (value & 0x7f) + 1;
+
+        if hblank_mode {
+            self.vram_dma.hblank_active = true;
+
+            // with the LCD being disabled, there is no HBlank to wait for,
+            // so the first block will be transferred immediately
+            if !self.peripherals.ppu.check_lcdc(LcdControlFlag::LcdEnabled) {
+                self.transfer_vram_dma_block();
+            }
+        }
+        else {
+            // general purpose DMA transfers all data at once
+            while self.vram_dma.remaining_blocks > 0 {
+                self.transfer_vram_dma_block();
+            }
+        }
+    }
+
+
+    /// Switches all components of a GameBoy Color into DMG compatibility mode,
+    /// after the boot ROM detected a cartridge without GBC support.
+    fn enter_dmg_compatibility_mode(&mut self) {
+        self.device_config.emulation = EmulationType::DMG;
+        self.vram_dma = VramDmaTransferInfo::default();
+
+        self.peripherals.apu.enter_dmg_compatibility_mode();
+        self.peripherals.ppu.enter_dmg_compatibility_mode();
+        self.peripherals.mem.enter_dmg_compatibility_mode();
+        self.peripherals.serial.enter_dmg_compatibility_mode();
+    }
+
+
+    /// Copies a single block of data from the source address into the active VRAM bank.
+    fn transfer_vram_dma_block(&mut self) {
+        for b in 0 .. VRAM_DMA_BLOCK_SIZE {
+            let src = self.vram_dma.source.wrapping_add(b);
+            let dst = 0x8000 | ((self.vram_dma.destination + b) & 0x1fff);
+
+            let val = self.read(src);
+            self.write(dst, val);
+        }
+
+        self.vram_dma.source      = self.vram_dma.source.wrapping_add(VRAM_DMA_BLOCK_SIZE);
+        self.vram_dma.destination = (self.vram_dma.destination + VRAM_DMA_BLOCK_SIZE) & 0x1ff0;
+
+        self.vram_dma.remaining_blocks -= 1;
+        if self.vram_dma.remaining_blocks == 0 {
+            self.vram_dma.hblank_active = false;
+        }
+
+        // the CPU gets stalled while the block is being transferred
+        self.vram_dma_stall_cycles += if self.peripherals.mem.is_double_speed() {
+            VRAM_DMA_CYCLES_PER_BLOCK * 2
+        }
+        else {
+            VRAM_DMA_CYCLES_PER_BLOCK
+        };
+    }
 }
 
 
@@ -159,11 +349,60 @@ impl MemoryBusConnection for MmuInternal {
     fn on_read(&self, address: u16) -> u8 {
         match address {
             MEMORY_LOCATION_DMA_ADDRESS => self.dma_register_value,
+
+            MEMORY_LOCATION_HDMA5 => {
+                // on GBC: the number of remaining blocks and bit 7 cleared while a HBlank DMA is active
+                match self.device_config.emulation {
+                    EmulationType::DMG => 0xff,
+                    EmulationType::GBC => {
+                        let remaining = self.vram_dma.remaining_blocks.wrapping_sub(1) & 0x7f;
+                        let inactive  = if self.vram_dma.hblank_active { 0x00 } else { 0x80 };
+
+                        inactive | remaining
+                    }
+                }
+            },
+
+            MEMORY_LOCATION_BOOT_ROM_DISABLE => self.peripherals.mem.on_read(address),
+
+            MEMORY_LOCATION_OPRI => {
+                // on GBC: the object priority mode selected by the boot ROM
+                if self.device_config.device.has_gbc_support() {
+                    self.peripherals.ppu.on_read(address)
+                }
+                else {
+                    0xff
+                }
+            },
+
             _ => 0xff,
         }
     }
 
+    fn get_register_value(&self, address: u16) -> u8 {
+        match (&self.device_config.emulation, address) {
+            // on GBC: the write-only source and destination of the VRAM DMA
+            (EmulationType::GBC, MEMORY_LOCATION_HDMA1) => to_u8(self.vram_dma.source).0,
+            (EmulationType::GBC, MEMORY_LOCATION_HDMA2) => to_u8(self.vram_dma.source).1,
+            (EmulationType::GBC, MEMORY_LOCATION_HDMA3) => to_u8(self.vram_dma.destination).0,
+            (EmulationType::GBC, MEMORY_LOCATION_HDMA4) => to_u8(self.vram_dma.destination).1,
+
+            _ => self.on_read(address),
+        }
+    }
+
     fn on_write(&mut self, address: u16, value: u8) {
+        if let EmulationType::GBC = self.device_config.emulation {
+            match address {
+                MEMORY_LOCATION_HDMA1 => self.vram_dma.source      = (self.vram_dma.source      & 0x00ff) | ((value as u16) << 8),
+                MEMORY_LOCATION_HDMA2 => self.vram_dma.source      = (self.vram_dma.source      & 0xff00) | ((value & 0xf0) as u16),
+                MEMORY_LOCATION_HDMA3 => self.vram_dma.destination = (self.vram_dma.destination & 0x00ff) | (((value & 0x1f) as u16) << 8),
+                MEMORY_LOCATION_HDMA4 => self.vram_dma.destination = (self.vram_dma.destination & 0xff00) | ((value & 0xf0) as u16),
+                MEMORY_LOCATION_HDMA5 => self.start_vram_dma_transfer(value),
+                _ => { }
+            }
+        }
+
         match address {
             MEMORY_LOCATION_DMA_ADDRESS => {
                 self.dma_register_value = value;
@@ -175,6 +414,23 @@ impl MemoryBusConnection for MmuInternal {
                 });
             },
 
+            MEMORY_LOCATION_BOOT_ROM_DISABLE => {
+                let boot_rom_mapped = self.peripherals.mem.has_boot_rom();
+                self.peripherals.mem.on_write(address, value);
+
+                // the mode selected by the boot ROM via KEY0 gets locked once the boot ROM is unmapped
+                if boot_rom_mapped && !self.peripherals.mem.has_boot_rom() && self.peripherals.mem.is_dmg_compatibility_selected() {
+                    self.enter_dmg_compatibility_mode();
+                }
+            },
+
+            MEMORY_LOCATION_OPRI => {
+                // on GBC: the object priority mode can only be selected by the boot ROM
+                if self.device_config.device.has_gbc_support() && self.peripherals.mem.has_boot_rom() {
+                    self.peripherals.ppu.on_write(address, value);
+                }
+            },
+
             _ => { }
         }
     }
@@ -213,6 +469,10 @@ impl_memory_mapper!(
         // APU registers
         0xff10 ..= 0xff3f => *root.peripherals.apu,
 
+        // GBC mode and speed switch registers
+        MEMORY_LOCATION_KEY0 => *root.peripherals.mem,
+        MEMORY_LOCATION_KEY1 => *root.peripherals.mem,
+
         // PPU registers
         0xff40 ..= 0xff45 => *root.peripherals.ppu,
         0xff47 ..= 0xff4f => *root.peripherals.ppu,
@@ -220,6 +480,13 @@ impl_memory_mapper!(
 
         MEMORY_LOCATION_DMA_ADDRESS => *root,
 
+        // boot ROM and GBC object priority registers, which select the DMG compatibility mode
+        MEMORY_LOCATION_BOOT_ROM_DISABLE => *root,
+        MEMORY_LOCATION_OPRI => *root,
+
+        // GBC VRAM DMA registers
+        0xff51 ..= 0xff55 => *root,
+
         MEMORY_LOCATION_INTERRUPTS_FLAGGED => *root.peripherals.interrupts,
         MEMORY_LOCATION_INTERRUPTS_ENABLED => *root.peripherals.interrupts,
 
@@ -21,3 +21,4 @@ pub mod memory;
 pub mod memory_bus;
 pub mod memory_data;
 pub mod mmu;
+pub mod rtc;
@@ -0,0 +1,320 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::cpu::cpu::CPU_CLOCK_SPEED;
+use crate::gameboy::Clock;
+use crate::utils::{change_bit, get_bit};
+
+
+/// Size of the RTC footer appended to the cartridge RAM in save files,
+/// using a 64 bit timestamp.
+pub const RTC_FOOTER_SIZE: usize = 48;
+
+/// Size of the legacy RTC footer using a 32 bit timestamp.
+pub const RTC_FOOTER_SIZE_LEGACY: usize = 44;
+
+pub const RTC_REGISTER_SECONDS:     u8 = 0x08;
+pub const RTC_REGISTER_MINUTES:     u8 = 0x09;
+pub const RTC_REGISTER_HOURS:       u8 = 0x0a;
+pub const RTC_REGISTER_DAYS_LOW:    u8 = 0x0b;
+pub const RTC_REGISTER_DAYS_HIGH:   u8 = 0x0c;
+
+/// Bit in the DH register containing the upper bit of the day counter.
+const DAYS_HIGH_BIT_DAY:    u8 = 0;
+
+/// Bit in the DH register to halt the clock.
+const DAYS_HIGH_BIT_HALT:   u8 = 6;
+
+/// Bit in the DH register which gets set when the day counter overflows.
+const DAYS_HIGH_BIT_CARRY:  u8 = 7;
+
+/// Bitmasks of the bits actually stored in each RTC register.
+/// Unused bits will always be read as '1'.
+const RTC_REGISTER_MASKS: [u8; 5] = [0x3f, 0x3f, 0x1f, 0xff, 0xc1];
+
+
+/// The set of registers provided by the real time clock.
+#[derive(Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RtcRegisters {
+    pub seconds:    u8,
+    pub minutes:    u8,
+    pub hours:      u8,
+    pub days_low:   u8,
+    pub days_high:  u8,
+}
+
+
+/// The real time clock chip found on MBC3 cartridges.
+/// The clock will be driven by the number of cycles emulated,
+/// so it stays in sync with the emulated device. When loading
+/// the clock's state from a save file, the time passed since
+/// the file was saved will be applied as well.
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RealTimeClock {
+    /// The registers of the running clock.
+    current: RtcRegisters,
+
+    /// The registers latched by the MBC and being visible to the program.
+    latched: RtcRegisters,
+
+    /// The number of cycles passed since the last increment of the seconds register.
+    sub_second_cycles: Clock,
+}
+
+
+impl RtcRegisters {
+    /// Get the value of a register by its number (0x08 - 0x0c).
+    pub fn get(&self, register: u8) -> u8 {
+        match register {
+            RTC_REGISTER_SECONDS    => self.seconds,
+            RTC_REGISTER_MINUTES    => self.minutes,
+            RTC_REGISTER_HOURS      => self.hours,
+            RTC_REGISTER_DAYS_LOW   => self.days_low,
+            RTC_REGISTER_DAYS_HIGH  => self.days_high,
+            _                       => 0xff,
+        }
+    }
+
+    /// Set the value of a register by its number (0x08 - 0x0c).
+    pub fn set(&mut self, register: u8, value: u8) {
+        match register {
+            RTC_REGISTER_SECONDS    => self.seconds   = value,
+            RTC_REGISTER_MINUTES    => self.minutes   = value,
+            RTC_REGISTER_HOURS      => self.hours     = value,
+            RTC_REGISTER_DAYS_LOW   => self.days_low  = value,
+            RTC_REGISTER_DAYS_HIGH  => self.days_high = value,
+            _                       => { }
+        }
+    }
+
+    /// Get the 9 bit day counter.
+    pub fn get_days(&self) -> u16 {
+        (self.days_low as u16) | ((get_bit(self.days_high, DAYS_HIGH_BIT_DAY) as u16) << 8)
+    }
+
+    /// Set the 9 bit day counter.
+    fn set_days(&mut self, days: u16) {
+        self.days_low  = (days & 0xff) as u8;
+        self.days_high = change_bit(self.days_high, DAYS_HIGH_BIT_DAY, (days & 0x100) != 0);
+    }
+
+    /// Writes the registers as 32 bit little endian values, as used by the RTC save file footer.
+    fn write_to(&self, buffer: &mut Vec<u8>) {
+        for value in [self.seconds, self.minutes, self.hours, self.days_low, self.days_high] {
+            buffer.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+    }
+
+    /// Reads the registers from 32 bit little endian values, as used by the RTC save file footer.
+    fn read_from(data: &[u8]) -> Self {
+        let get = |index: usize| -> u8 {
+            let offset = index * 4;
+            let bytes  = [data[offset], data[offset + 1], data[offset + 2], data[offset + 3]];
+            (u32::from_le_bytes(bytes) as u8) & RTC_REGISTER_MASKS[index]
+        };
+
+        Self {
+            seconds:    get(0),
+            minutes:    get(1),
+            hours:      get(2),
+            days_low:   get(3),
+            days_high:  get(4),
+        }
+    }
+}
+
+
+impl RealTimeClock {
+    /// Creates a new clock with all registers set to zero.
+    pub fn new() -> Self {
+        Self {
+            current:            RtcRegisters::default(),
+            latched:            RtcRegisters::default(),
+            sub_second_cycles:  0,
+        }
+    }
+
+
+    /// Lets time pass on the clock, unless it is halted.
+    pub fn update(&mut self, cycles: Clock) {
+        if self.is_halted() {
+            return;
+        }
+
+        self.sub_second_cycles += cycles;
+
+        while self.sub_second_cycles >= CPU_CLOCK_SPEED {
+            self.sub_second_cycles -= CPU_CLOCK_SPEED;
+            self.tick_second();
+        }
+    }
+
+
+    /// Advances the clock by a number of seconds, unless it is halted.
+    /// Used to apply the time passed while the emulator was not running.
+    pub fn advance_seconds(&mut self, mut seconds: u64) {
+        if self.is_halted() {
+            return;
+        }
+
+        // a full cycle of the day counter only affects the carry flag,
+        // so skip as many full cycles as possible
+        const SECONDS_PER_DAY_CYCLE: u64 = 512 * 24 * 60 * 60;
+        if seconds >= SECONDS_PER_DAY_CYCLE {
+            self.current.days_high = change_bit(self.current.days_high, DAYS_HIGH_BIT_CARRY, true);
+            seconds %= SECONDS_PER_DAY_CYCLE;
+        }
+
+        for _ in 0..seconds {
+            self.tick_second();
+        }
+    }
+
+
+    /// Increments the seconds register and all following registers on overflow.
+    /// Registers containing invalid values will overflow when reaching the
+    /// limit of their bit width, without incrementing the next register.
+    fn tick_second(&mut self) {
+        let regs = &mut self.current;
+
+        regs.seconds = (regs.seconds + 1) & RTC_REGISTER_MASKS[0];
+        if regs.seconds != 60 {
+            return;
+        }
+
+        regs.seconds = 0;
+        regs.minutes = (regs.minutes + 1) & RTC_REGISTER_MASKS[1];
+        if regs.minutes != 60 {
+            return;
+        }
+
+        regs.minutes = 0;
+        regs.hours   = (regs.hours + 1) & RTC_REGISTER_MASKS[2];
+        if regs.hours != 24 {
+            return;
+        }
+
+        regs.hours = 0;
+
+        let days = regs.get_days() + 1;
+        if days > 0x1ff {
+            regs.set_days(0);
+            regs.days_high = change_bit(regs.days_high, DAYS_HIGH_BIT_CARRY, true);
+        }
+        else {
+            regs.set_days(days);
+        }
+    }
+
+
+    /// Copies the current time into the latched registers.
+    pub fn latch(&mut self) {
+        self.latched = self.current;
+    }
+
+
+    /// Reads a latched register visible to the program.
+    pub fn read_register(&self, register: u8) -> u8 {
+        match register {
+            RTC_REGISTER_SECONDS ..= RTC_REGISTER_DAYS_HIGH => {
+                let mask = RTC_REGISTER_MASKS[(register - RTC_REGISTER_SECONDS) as usize];
+                self.latched.get(register) | !mask
+            }
+
+            _ => 0xff,
+        }
+    }
+
+
+    /// Writes into a clock register.
+    /// Writing to the seconds register also resets the sub-second counter.
+    pub fn write_register(&mut self, register: u8, value: u8) {
+        if let RTC_REGISTER_SECONDS ..= RTC_REGISTER_DAYS_HIGH = register {
+            let mask  = RTC_REGISTER_MASKS[(register - RTC_REGISTER_SECONDS) as usize];
+            let value = value & mask;
+
+            if register == RTC_REGISTER_SECONDS {
+                self.sub_second_cycles = 0;
+            }
+
+            self.current.set(register, value);
+            self.latched.set(register, value);
+        }
+    }
+
+
+    /// Checks whether the clock was halted via DH register.
+    pub fn is_halted(&self) -> bool {
+        get_bit(self.current.days_high, DAYS_HIGH_BIT_HALT)
+    }
+
+
+    /// Get the registers of the running clock.
+    pub fn get_current(&self) -> &RtcRegisters {
+        &self.current
+    }
+
+
+    /// Get the latched registers visible to the program.
+    pub fn get_latched(&self) -> &RtcRegisters {
+        &self.latched
+    }
+
+
+    /// Serializes the clock state into the 48 byte footer, which is appended
+    /// to the cartridge RAM in save files and used by most other emulators.
+    pub fn to_footer_bytes(&self, timestamp: u64) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(RTC_FOOTER_SIZE);
+        self.current.write_to(&mut buffer);
+        self.latched.write_to(&mut buffer);
+        buffer.extend_from_slice(&timestamp.to_le_bytes());
+
+        buffer
+    }
+
+
+    /// Reads the clock state from a save file footer.
+    /// Accepts both the 48 byte footer and the legacy 44 byte footer with a
+    /// 32 bit timestamp. Returns the clock and the timestamp when it was saved.
+    pub fn from_footer_bytes(data: &[u8]) -> Option<(Self, u64)> {
+        let timestamp = match data.len() {
+            RTC_FOOTER_SIZE => {
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(&data[40..48]);
+                u64::from_le_bytes(bytes)
+            }
+
+            RTC_FOOTER_SIZE_LEGACY => {
+                let mut bytes = [0u8; 4];
+                bytes.copy_from_slice(&data[40..44]);
+                u32::from_le_bytes(bytes) as u64
+            }
+
+            _ => return None,
+        };
+
+        let rtc = Self {
+            current:            RtcRegisters::read_from(&data[0..20]),
+            latched:            RtcRegisters::read_from(&data[20..40]),
+            sub_second_cycles:  0,
+        };
+
+        Some((rtc, timestamp))
+    }
+}
@@ -0,0 +1,905 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::convert::Infallible;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+use crate::cartridge::compute_hash;
+use crate::cpu::cpu::RegisterR16;
+use crate::debug::DebugEvent;
+use crate::device_type::DeviceType;
+use crate::gameboy::{Clock, EmulatorUpdateResults, GameBoy};
+use crate::mmu::memory_data::MemoryData;
+use crate::ppu::ppu::CPU_CYCLES_PER_FRAME;
+
+#[cfg(feature = "snapshots")]
+use crate::snapshots::{Snapshot, SnapshotError};
+
+/// The magic number at the beginning of each movie file.
+pub const MOVIE_MAGIC: [u8; 8] = *b"GEMIMOVI";
+
+/// The current version of the movie file format.
+pub const MOVIE_FORMAT_VERSION: u16 = 1;
+
+
+/// Errors which may occur when recording, loading or playing a movie.
+#[derive(Debug)]
+pub enum MovieError {
+    /// Failed to read or write the movie data.
+    Io(io::Error),
+
+    /// The data is not a valid movie or is corrupted.
+    InvalidData(String),
+
+    /// The movie was created with a newer version of the format than supported.
+    UnsupportedVersion {
+        found:     u16,
+        supported: u16,
+    },
+
+    /// The movie was recorded with a different ROM.
+    RomMismatch {
+        expected:  u64,
+        found:     u64,
+    },
+
+    /// The movie was recorded on a different device type.
+    DeviceMismatch {
+        expected:  DeviceType,
+        found:     DeviceType,
+    },
+
+    /// The movie starts from power-on, but the emulator already did run.
+    NotAtPowerOn,
+
+    /// Failed to create or restore the snapshot the movie starts from.
+    #[cfg(feature = "snapshots")]
+    Snapshot(SnapshotError),
+
+    /// The state of the emulator differs from the state while recording,
+    /// which means the playback is no longer in sync with the recording.
+    Desync {
+        /// The frame on which the different state was detected.
+        frame: u32,
+    },
+}
+
+
+/// The granularity on which input changes are recorded.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MovieGranularity {
+    /// The button states are sampled once at the beginning of each frame.
+    PerFrame,
+
+    /// The button states are sampled before each step of the emulator,
+    /// which allows to record input changes in the middle of a frame.
+    PerCycle,
+}
+
+
+/// The point where the playback of a movie starts.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MovieStart {
+    /// The movie starts with a freshly initialized device.
+    PowerOn,
+
+    /// The movie starts from a snapshot embedded into the movie.
+    Snapshot(Vec<u8>),
+}
+
+
+/// A change of the button states.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MovieEvent {
+    /// The frame in which the button states were changed.
+    pub frame: u32,
+
+    /// The number of cycles since the start of the movie
+    /// when the button states were changed.
+    pub cycle: Clock,
+
+    /// The pressed state of all buttons, where each bit represents
+    /// the button with the according [crate::input::InputButton] index.
+    pub buttons: u8,
+}
+
+
+/// A hash of the emulator state, which was taken periodically while
+/// recording to detect when the playback gets out of sync.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MovieCheckpoint {
+    /// The number of frames completed when the hash was taken.
+    pub frame: u32,
+
+    /// The number of cycles since the start of the movie when the hash was taken.
+    pub cycle: Clock,
+
+    /// The hash of the emulator state, see [compute_state_hash].
+    pub hash: u64,
+}
+
+
+/// Configures how a [MovieRecorder] records a movie.
+#[derive(Copy, Clone, Debug)]
+pub struct MovieConfig {
+    /// The granularity on which input changes are recorded.
+    pub granularity: MovieGranularity,
+
+    /// The number of frames between two checkpoints.
+    /// No checkpoints will be stored if zero.
+    pub checkpoint_interval: u32,
+}
+
+
+/// A recording of all input changes while running a ROM, which can be
+/// replayed to reproduce the exact same session.
+///
+/// # File format
+/// All numbers are stored in little endian byte order.
+///
+/// | Type           | Content                                                      |
+/// |----------------|--------------------------------------------------------------|
+/// | `[u8; 8]`      | The magic number `GEMIMOVI`                                  |
+/// | `u16`          | The format version                                           |
+/// | `u64`          | The hash of the ROM the movie was recorded with              |
+/// | `u8` + `[u8]`  | The length and abbreviation of the device type, like `dmg`   |
+/// | `u8`           | The granularity; 0 = per frame, 1 = per cycle                |
+/// | `u32`          | The number of frames between two checkpoints                 |
+/// | `u32`          | The length of the movie in frames                            |
+/// | `u64`          | The length of the movie in cycles                            |
+/// | `u8`           | The start; 0 = power-on, 1 = snapshot                        |
+/// | `u32` + `[u8]` | Only for snapshot starts: The length and data of a snapshot  |
+/// | `u32`          | The number of events, followed by each event:                |
+/// | - `u32`        | The frame of the event                                       |
+/// | - `u64`        | The cycle of the event, relative to the start of the movie   |
+/// | - `u8`         | The button states, see [crate::input::Input::get_button_states] |
+/// | `u32`          | The number of checkpoints, followed by each checkpoint:      |
+/// | - `u32`        | The frame of the checkpoint                                  |
+/// | - `u64`        | The cycle of the checkpoint, relative to the start of the movie |
+/// | - `u64`        | The state hash, see [compute_state_hash]                     |
+#[derive(Clone, Debug)]
+pub struct Movie {
+    /// The hash of the ROM the movie was recorded with.
+    rom_hash: u64,
+
+    /// The device type the movie was recorded on.
+    device: DeviceType,
+
+    /// The granularity on which input changes were recorded.
+    granularity: MovieGranularity,
+
+    /// The number of frames between two checkpoints.
+    checkpoint_interval: u32,
+
+    /// The number of frames completed while recording.
+    length_frames: u32,
+
+    /// The number of cycles processed while recording.
+    length_cycles: Clock,
+
+    /// The point where the playback starts.
+    start: MovieStart,
+
+    /// All changes of the button states, ordered by their cycle.
+    events: Vec<MovieEvent>,
+
+    /// All checkpoints, ordered by their frame.
+    checkpoints: Vec<MovieCheckpoint>,
+}
+
+
+/// Records the input of an emulator instance into a [Movie].
+/// Instead of running the emulator directly, the frontend needs to run
+/// it through the recorder, so each input change can be captured.
+pub struct MovieRecorder {
+    /// The movie being recorded.
+    movie: Movie,
+
+    /// The number of cycles processed by the emulator when the recording was started.
+    start_cycles: Clock,
+
+    /// Whether the next step of the emulator is the first one of a frame.
+    at_frame_start: bool,
+
+    /// The button states recorded last, if any.
+    last_button_states: Option<u8>,
+}
+
+
+/// Replays a [Movie] on an emulator instance.
+/// While playing, the frontend should not change the button states,
+/// because they will be overridden by the movie.
+pub struct MoviePlayer {
+    /// The movie being played.
+    movie: Movie,
+
+    /// The number of cycles processed by the emulator when the playback was started.
+    start_cycles: Clock,
+
+    /// The number of frames completed since the playback was started.
+    frame: u32,
+
+    /// The index of the next event to be applied.
+    next_event: usize,
+
+    /// The index of the next checkpoint to be checked.
+    next_checkpoint: usize,
+}
+
+
+impl Display for MovieError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MovieError::Io(e) => write!(f, "I/O error: {e}"),
+            MovieError::InvalidData(message) => write!(f, "Invalid movie data: {message}"),
+
+            MovieError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "Movie format version {found} is not supported, latest supported version is {supported}"
+            ),
+
+            MovieError::RomMismatch { expected, found } => write!(
+                f,
+                "Movie was recorded with ROM {found:016x}, but ROM {expected:016x} is loaded"
+            ),
+
+            MovieError::DeviceMismatch { expected, found } => write!(
+                f,
+                "Movie was recorded on device {}, but {} is running",
+                found.get_abbreviation(),
+                expected.get_abbreviation()
+            ),
+
+            MovieError::NotAtPowerOn => write!(f, "Movie starts at power-on, but the emulator is already running"),
+
+            #[cfg(feature = "snapshots")]
+            MovieError::Snapshot(e) => write!(f, "Snapshot error: {e}"),
+
+            MovieError::Desync { frame } => write!(f, "Movie playback desynced on frame {frame}"),
+        }
+    }
+}
+
+
+impl std::error::Error for MovieError {
+}
+
+
+impl From<io::Error> for MovieError {
+    fn from(value: io::Error) -> Self {
+        MovieError::Io(value)
+    }
+}
+
+
+#[cfg(feature = "snapshots")]
+impl From<SnapshotError> for MovieError {
+    fn from(value: SnapshotError) -> Self {
+        MovieError::Snapshot(value)
+    }
+}
+
+
+impl Default for MovieConfig {
+    fn default() -> Self {
+        Self {
+            granularity:            MovieGranularity::PerFrame,
+            checkpoint_interval:    60,
+        }
+    }
+}
+
+
+impl Movie {
+    /// Creates an empty movie for the ROM and device of an emulator instance.
+    fn create_for(gb: &GameBoy, config: &MovieConfig, start: MovieStart) -> Self {
+        Self {
+            rom_hash:               get_rom_hash(gb),
+            device:                 gb.get_config().device,
+            granularity:            config.granularity,
+            checkpoint_interval:    config.checkpoint_interval,
+            length_frames:          0,
+            length_cycles:          0,
+            start,
+            events:                 Vec::new(),
+            checkpoints:            Vec::new(),
+        }
+    }
+
+
+    /// Get the hash of the ROM the movie was recorded with.
+    pub fn get_rom_hash(&self) -> u64 {
+        self.rom_hash
+    }
+
+
+    /// Get the device type the movie was recorded on.
+    pub fn get_device(&self) -> DeviceType {
+        self.device
+    }
+
+
+    /// Get the granularity on which input changes were recorded.
+    pub fn get_granularity(&self) -> MovieGranularity {
+        self.granularity
+    }
+
+
+    /// Get the point where the playback starts.
+    pub fn get_start(&self) -> &MovieStart {
+        &self.start
+    }
+
+
+    /// Get the number of frames completed while recording.
+    pub fn get_length_frames(&self) -> u32 {
+        self.length_frames
+    }
+
+
+    /// Get the number of cycles processed while recording.
+    pub fn get_length_cycles(&self) -> Clock {
+        self.length_cycles
+    }
+
+
+    /// Get all changes of the button states, ordered by their cycle.
+    pub fn get_events(&self) -> &[MovieEvent] {
+        &self.events
+    }
+
+
+    /// Get all checkpoints, ordered by their frame.
+    pub fn get_checkpoints(&self) -> &[MovieCheckpoint] {
+        &self.checkpoints
+    }
+
+
+    /// Checks whether the movie was recorded with the same ROM and
+    /// on the same device as a currently running emulator instance.
+    pub fn check_compatible(&self, gb: &GameBoy) -> Result<(), MovieError> {
+        let rom_hash = get_rom_hash(gb);
+        if rom_hash != self.rom_hash {
+            return Err(MovieError::RomMismatch {
+                expected: rom_hash,
+                found:    self.rom_hash,
+            });
+        }
+
+        let device = gb.get_config().device;
+        if device != self.device {
+            return Err(MovieError::DeviceMismatch {
+                expected: device,
+                found:    self.device,
+            });
+        }
+
+        Ok(())
+    }
+
+
+    /// Parses a movie from its binary representation.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MovieError> {
+        let mut reader = ByteReader::new(bytes);
+
+        if reader.read_bytes(MOVIE_MAGIC.len())? != MOVIE_MAGIC {
+            return Err(MovieError::InvalidData("Invalid magic number".to_string()));
+        }
+
+        let version = reader.read_u16()?;
+        if version > MOVIE_FORMAT_VERSION {
+            return Err(MovieError::UnsupportedVersion {
+                found:     version,
+                supported: MOVIE_FORMAT_VERSION,
+            });
+        }
+
+        let rom_hash = reader.read_u64()?;
+
+        let device_length = reader.read_u8()? as usize;
+        let device_abbreviation = String::from_utf8_lossy(reader.read_bytes(device_length)?).to_string();
+        let device = DeviceType::from_abbreviation(&device_abbreviation)
+            .ok_or_else(|| MovieError::InvalidData(format!("Unknown device type '{device_abbreviation}'")))
+            ?;
+
+        let granularity = match reader.read_u8()? {
+            0 => MovieGranularity::PerFrame,
+            1 => MovieGranularity::PerCycle,
+            n => return Err(MovieError::InvalidData(format!("Unknown granularity {n}"))),
+        };
+
+        let checkpoint_interval = reader.read_u32()?;
+        let length_frames       = reader.read_u32()?;
+        let length_cycles       = reader.read_u64()?;
+
+        let start = match reader.read_u8()? {
+            0 => MovieStart::PowerOn,
+            1 => {
+                let length = reader.read_u32()? as usize;
+                MovieStart::Snapshot(reader.read_bytes(length)?.to_vec())
+            },
+            n => return Err(MovieError::InvalidData(format!("Unknown start type {n}"))),
+        };
+
+        let event_count = reader.read_u32()? as usize;
+        let mut events  = Vec::with_capacity(event_count.min(reader.remaining()));
+        for _ in 0..event_count {
+            events.push(MovieEvent {
+                frame:   reader.read_u32()?,
+                cycle:   reader.read_u64()?,
+                buttons: reader.read_u8()?,
+            });
+        }
+
+        let checkpoint_count = reader.read_u32()? as usize;
+        let mut checkpoints  = Vec::with_capacity(checkpoint_count.min(reader.remaining()));
+        for _ in 0..checkpoint_count {
+            checkpoints.push(MovieCheckpoint {
+                frame: reader.read_u32()?,
+                cycle: reader.read_u64()?,
+                hash:  reader.read_u64()?,
+            });
+        }
+
+        Ok(Self {
+            rom_hash,
+            device,
+            granularity,
+            checkpoint_interval,
+            length_frames,
+            length_cycles,
+            start,
+            events,
+            checkpoints,
+        })
+    }
+
+
+    /// Converts the movie into its binary representation.
+    /// See [Movie] for a description of the file format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(&MOVIE_MAGIC);
+        bytes.extend_from_slice(&MOVIE_FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.rom_hash.to_le_bytes());
+
+        let device_abbreviation = self.device.get_abbreviation();
+        bytes.push(device_abbreviation.len() as u8);
+        bytes.extend_from_slice(device_abbreviation.as_bytes());
+
+        bytes.push(match self.granularity {
+            MovieGranularity::PerFrame => 0,
+            MovieGranularity::PerCycle => 1,
+        });
+
+        bytes.extend_from_slice(&self.checkpoint_interval.to_le_bytes());
+        bytes.extend_from_slice(&self.length_frames.to_le_bytes());
+        bytes.extend_from_slice(&self.length_cycles.to_le_bytes());
+
+        match &self.start {
+            MovieStart::PowerOn => {
+                bytes.push(0);
+            },
+
+            MovieStart::Snapshot(snapshot) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&(snapshot.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(snapshot);
+            },
+        }
+
+        bytes.extend_from_slice(&(self.events.len() as u32).to_le_bytes());
+        for event in &self.events {
+            bytes.extend_from_slice(&event.frame.to_le_bytes());
+            bytes.extend_from_slice(&event.cycle.to_le_bytes());
+            bytes.push(event.buttons);
+        }
+
+        bytes.extend_from_slice(&(self.checkpoints.len() as u32).to_le_bytes());
+        for checkpoint in &self.checkpoints {
+            bytes.extend_from_slice(&checkpoint.frame.to_le_bytes());
+            bytes.extend_from_slice(&checkpoint.cycle.to_le_bytes());
+            bytes.extend_from_slice(&checkpoint.hash.to_le_bytes());
+        }
+
+        bytes
+    }
+
+
+    /// Reads a movie from a file path.
+    pub fn read_from_file(filepath: &Path) -> Result<Self, MovieError> {
+        let mut file = File::open(filepath)?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+
+        Self::from_bytes(&data)
+    }
+
+
+    /// Saves a movie into a file path.
+    pub fn save_to_file(&self, filepath: &Path) -> Result<(), MovieError> {
+        let mut file = File::create(filepath)?;
+        file.write_all(&self.to_bytes())?;
+
+        Ok(())
+    }
+}
+
+
+impl MovieRecorder {
+    /// Starts recording a movie on a freshly initialized emulator instance,
+    /// before the first frame was processed.
+    pub fn new(gb: &GameBoy, config: MovieConfig) -> Result<Self, MovieError> {
+        if gb.get_total_cycles_processed() != 0 {
+            return Err(MovieError::NotAtPowerOn);
+        }
+
+        Ok(Self::with_start(gb, config, MovieStart::PowerOn))
+    }
+
+
+    /// Starts recording a movie from the current state of an emulator instance,
+    /// which will be embedded as a snapshot into the movie.
+    #[cfg(feature = "snapshots")]
+    pub fn from_snapshot(gb: &GameBoy, config: MovieConfig) -> Result<Self, MovieError> {
+        let mut snapshot = Snapshot::create_from(gb)?;
+        snapshot.remove_thumbnail();
+
+        Ok(Self::with_start(gb, config, MovieStart::Snapshot(snapshot.to_bytes()?)))
+    }
+
+
+    /// Creates the recorder with a given start.
+    fn with_start(gb: &GameBoy, config: MovieConfig, start: MovieStart) -> Self {
+        Self {
+            movie:              Movie::create_for(gb, &config, start),
+            start_cycles:       gb.get_total_cycles_processed(),
+            at_frame_start:     true,
+            last_button_states: None,
+        }
+    }
+
+
+    /// Get the movie recorded so far.
+    pub fn get_movie(&self) -> &Movie {
+        &self.movie
+    }
+
+
+    /// Stops recording and returns the recorded movie.
+    pub fn finish(self) -> Movie {
+        self.movie
+    }
+
+
+    /// Runs the emulator until the PPU has completed one single frame,
+    /// like [GameBoy::run_frame], while recording any input changes.
+    pub fn run_frame(&mut self, gb: &mut GameBoy) -> EmulatorUpdateResults {
+        self.at_frame_start = true;
+
+        let Ok(results) = run_frame_with(gb, |gb| Ok::<_, Infallible>(self.run_single_step(gb)));
+
+        // the frame ended without the PPU, like when the LCD is disabled
+        if !results.events.contains(DebugEvent::PpuFrameCompleted) {
+            self.on_frame_completed(gb);
+        }
+
+        results
+    }
+
+
+    /// Runs the emulator for a single step, like [GameBoy::run_single_step],
+    /// while recording any input changes.
+    pub fn run_single_step(&mut self, gb: &mut GameBoy) -> EmulatorUpdateResults {
+        if self.at_frame_start || self.movie.granularity == MovieGranularity::PerCycle {
+            self.record_input(gb);
+        }
+
+        let results = gb.run_single_step();
+
+        self.at_frame_start      = false;
+        self.movie.length_cycles = gb.get_total_cycles_processed() - self.start_cycles;
+
+        if results.events.contains(DebugEvent::PpuFrameCompleted) {
+            self.on_frame_completed(gb);
+        }
+
+        results
+    }
+
+
+    /// Counts a completed frame and stores a checkpoint, if required.
+    fn on_frame_completed(&mut self, gb: &GameBoy) {
+        self.at_frame_start       = true;
+        self.movie.length_frames += 1;
+
+        let frame    = self.movie.length_frames;
+        let interval = self.movie.checkpoint_interval;
+        if interval != 0 && frame.is_multiple_of(interval) {
+            self.movie.checkpoints.push(MovieCheckpoint {
+                frame,
+                cycle: self.movie.length_cycles,
+                hash:  compute_state_hash(gb),
+            });
+        }
+    }
+
+
+    /// Records the current button states, if they were changed since the last event.
+    fn record_input(&mut self, gb: &GameBoy) {
+        let buttons = gb.get_peripherals().input.get_button_states();
+
+        if self.last_button_states != Some(buttons) {
+            self.movie.events.push(MovieEvent {
+                frame: self.movie.length_frames,
+                cycle: gb.get_total_cycles_processed() - self.start_cycles,
+                buttons,
+            });
+
+            self.last_button_states = Some(buttons);
+        }
+    }
+}
+
+
+impl MoviePlayer {
+    /// Prepares an emulator instance to play a movie.
+    /// For movies starting at power-on, the emulator needs to be freshly initialized,
+    /// for movies starting from a snapshot, the snapshot will be restored into the emulator.
+    pub fn new(movie: Movie, gb: &mut GameBoy) -> Result<Self, MovieError> {
+        movie.check_compatible(gb)?;
+
+        match &movie.start {
+            MovieStart::PowerOn => {
+                if gb.get_total_cycles_processed() != 0 {
+                    return Err(MovieError::NotAtPowerOn);
+                }
+            },
+
+            #[cfg(feature = "snapshots")]
+            MovieStart::Snapshot(data) => {
+                let mut restored = Snapshot::from_bytes(data)?.restore_for(gb)?;
+                restored.get_peripherals_mut().mem.take_frontend_state_from(&mut gb.get_peripherals_mut().mem);
+                *gb = restored;
+            },
+
+            #[cfg(not(feature = "snapshots"))]
+            MovieStart::Snapshot(_) => {
+                return Err(MovieError::InvalidData("Movies starting from a snapshot are not supported".to_string()));
+            },
+        }
+
+        Ok(Self {
+            movie,
+            start_cycles:       gb.get_total_cycles_processed(),
+            frame:              0,
+            next_event:         0,
+            next_checkpoint:    0,
+        })
+    }
+
+
+    /// Get the movie being played.
+    pub fn get_movie(&self) -> &Movie {
+        &self.movie
+    }
+
+
+    /// Get the number of frames completed since the playback was started.
+    pub fn get_current_frame(&self) -> u32 {
+        self.frame
+    }
+
+
+    /// Checks whether the whole movie was played.
+    /// The emulator may continue running with the last button states of the movie.
+    pub fn is_finished(&self, gb: &GameBoy) -> bool {
+        self.get_movie_cycle(gb) >= self.movie.length_cycles
+    }
+
+
+    /// Runs the emulator until the PPU has completed one single frame,
+    /// like [GameBoy::run_frame], while applying the input of the movie.
+    /// Fails if the emulator state differs from the state during recording,
+    /// in which case the emulator stops on the step where the difference was detected.
+    pub fn run_frame(&mut self, gb: &mut GameBoy) -> Result<EmulatorUpdateResults, MovieError> {
+        let results = run_frame_with(gb, |gb| self.run_single_step(gb))?;
+
+        // the frame ended without the PPU, like when the LCD is disabled
+        if !results.events.contains(DebugEvent::PpuFrameCompleted) {
+            self.frame += 1;
+        }
+
+        Ok(results)
+    }
+
+
+    /// Runs the emulator for a single step, like [GameBoy::run_single_step],
+    /// while applying the input of the movie.
+    /// Fails if the emulator state differs from the state during recording.
+    pub fn run_single_step(&mut self, gb: &mut GameBoy) -> Result<EmulatorUpdateResults, MovieError> {
+        let cycle = self.get_movie_cycle(gb);
+
+        while let Some(event) = self.movie.events.get(self.next_event) {
+            if event.cycle > cycle {
+                break;
+            }
+
+            gb.get_peripherals_mut().input.set_button_states(event.buttons);
+            self.next_event += 1;
+        }
+
+        let results = gb.run_single_step();
+
+        if results.events.contains(DebugEvent::PpuFrameCompleted) {
+            self.frame += 1;
+        }
+
+        self.check_checkpoints(gb)?;
+
+        Ok(results)
+    }
+
+
+    /// Compares the emulator state with all checkpoints which were reached.
+    /// Since the emulator runs the same steps as while recording, it has to
+    /// hit each checkpoint on the exact same cycle.
+    fn check_checkpoints(&mut self, gb: &GameBoy) -> Result<(), MovieError> {
+        let cycle = self.get_movie_cycle(gb);
+
+        while let Some(checkpoint) = self.movie.checkpoints.get(self.next_checkpoint) {
+            if checkpoint.cycle > cycle {
+                break;
+            }
+
+            self.next_checkpoint += 1;
+
+            if checkpoint.cycle != cycle || checkpoint.hash != compute_state_hash(gb) {
+                return Err(MovieError::Desync { frame: checkpoint.frame });
+            }
+        }
+
+        Ok(())
+    }
+
+
+    /// Get the number of cycles processed since the playback was started.
+    fn get_movie_cycle(&self, gb: &GameBoy) -> Clock {
+        gb.get_total_cycles_processed() - self.start_cycles
+    }
+}
+
+
+/// Computes a hash of the emulator state, which covers the CPU registers
+/// and all RAM areas, to detect whether two emulator instances did run
+/// into the same state.
+pub fn compute_state_hash(gb: &GameBoy) -> u64 {
+    let mut data = Vec::new();
+
+    for register in [RegisterR16::AF, RegisterR16::BC, RegisterR16::DE, RegisterR16::HL] {
+        data.extend_from_slice(&gb.cpu.get_r16(register).to_le_bytes());
+    }
+
+    data.extend_from_slice(&gb.cpu.get_stack_pointer().to_le_bytes());
+    data.extend_from_slice(&gb.cpu.get_instruction_pointer().to_le_bytes());
+
+    let mem = &gb.get_peripherals().mem;
+    for wram_bank in mem.get_wram_banks() {
+        data.extend_from_slice(wram_bank.as_slice());
+    }
+
+    data.extend_from_slice(mem.get_hram().as_slice());
+
+    if let Some(cartridge) = mem.get_cartridge() {
+        data.extend_from_slice(cartridge.get_ram().as_slice());
+    }
+
+    compute_hash(&data)
+}
+
+
+/// Get the hash of the ROM loaded into an emulator instance.
+fn get_rom_hash(gb: &GameBoy) -> u64 {
+    match gb.get_peripherals().mem.get_cartridge() {
+        Some(cartridge) => cartridge.compute_rom_hash(),
+        None            => 0,
+    }
+}
+
+
+/// Runs single steps until the PPU has completed one single frame
+/// or the time of one frame has passed, like [GameBoy::run_frame].
+/// Stops on the first step returning an error.
+fn run_frame_with<E>(
+        gb: &mut GameBoy,
+        mut step: impl FnMut(&mut GameBoy) -> Result<EmulatorUpdateResults, E>
+) -> Result<EmulatorUpdateResults, E> {
+    let mut results = EmulatorUpdateResults::default();
+
+    loop {
+        results += step(gb)?;
+
+        if results.events.contains(DebugEvent::PpuFrameCompleted) {
+            break;
+        }
+
+        if results.cycles >= CPU_CYCLES_PER_FRAME {
+            break;
+        }
+    }
+
+    Ok(results)
+}
+
+
+/// A helper to read numbers from a byte array.
+struct ByteReader<'a> {
+    data:     &'a [u8],
+    position: usize,
+}
+
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+        }
+    }
+
+
+    /// Get the number of bytes not read yet.
+    fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], MovieError> {
+        if length > self.remaining() {
+            return Err(MovieError::InvalidData("Unexpected end of data".to_string()));
+        }
+
+        let bytes = &self.data[self.position .. self.position + length];
+        self.position += length;
+
+        Ok(bytes)
+    }
+
+
+    fn read_u8(&mut self) -> Result<u8, MovieError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+
+    fn read_u16(&mut self) -> Result<u16, MovieError> {
+        Ok(u16::from_le_bytes(self.read_bytes(2)?.try_into().unwrap()))
+    }
+
+
+    fn read_u32(&mut self) -> Result<u32, MovieError> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+
+    fn read_u64(&mut self) -> Result<u64, MovieError> {
+        Ok(u64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+}
@@ -0,0 +1,297 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::cartridge::{Cartridge, LicenseeCode, ROM_OFFSET_TITLE_STRING};
+use crate::ppu::graphic_data::GbcPaletteData;
+use crate::ppu::video_memory::Palettes;
+
+
+/// All colors used by the GBC boot ROM to colorize classic GameBoy games.
+/// The colors are organized in palettes of four colors each, but palette
+/// combinations may also start in the middle of a palette.
+const PALETTE_COLORS: [u16; 120] = [
+    0x7fff, 0x32bf, 0x00d0, 0x0000,
+    0x639f, 0x4279, 0x15b0, 0x04cb,
+    0x7fff, 0x6e31, 0x454a, 0x0000,
+    0x7fff, 0x1bef, 0x0200, 0x0000,
+    0x7fff, 0x421f, 0x1cf2, 0x0000,
+    0x7fff, 0x5294, 0x294a, 0x0000,
+    0x7fff, 0x03ff, 0x012f, 0x0000,
+    0x7fff, 0x03ef, 0x01d6, 0x0000,
+    0x7fff, 0x42b5, 0x3dc8, 0x0000,
+    0x7e74, 0x03ff, 0x0180, 0x0000,
+    0x67ff, 0x77ac, 0x1a13, 0x2d6b,
+    0x7ed6, 0x4bff, 0x2175, 0x0000,
+    0x53ff, 0x4a5f, 0x7e52, 0x0000,
+    0x4fff, 0x7ed2, 0x3a4c, 0x1ce0,
+    0x03ed, 0x7fff, 0x255f, 0x0000,
+    0x036a, 0x021f, 0x03ff, 0x7fff,
+    0x7fff, 0x01df, 0x0112, 0x0000,
+    0x231f, 0x035f, 0x00f2, 0x0009,
+    0x7fff, 0x03ea, 0x011f, 0x0000,
+    0x299f, 0x001a, 0x000c, 0x0000,
+    0x7fff, 0x027f, 0x001f, 0x0000,
+    0x7fff, 0x03e0, 0x0206, 0x0120,
+    0x7fff, 0x7eeb, 0x001f, 0x7c00,
+    0x7fff, 0x3fff, 0x7e00, 0x001f,
+    0x7fff, 0x03ff, 0x001f, 0x0000,
+    0x03ff, 0x001f, 0x000c, 0x0000,
+    0x7fff, 0x033f, 0x0193, 0x0000,
+    0x0000, 0x4200, 0x037f, 0x7fff,
+    0x7fff, 0x7e8c, 0x7c00, 0x0000,
+    0x7fff, 0x1bef, 0x6180, 0x0000,
+];
+
+
+/// Get the offset of the first color of a palette within [PALETTE_COLORS].
+const fn p(palette: usize) -> usize {
+    palette * 4
+}
+
+
+/// Combinations of palettes for OBJ0, OBJ1 and BG, given as offset into [PALETTE_COLORS].
+const PALETTE_COMBINATIONS: [(usize, usize, usize); 51] = [
+    /*  0 */ (p( 4), p( 4), p(29)),
+    /*  1 */ (p(18), p(18), p(18)),
+    /*  2 */ (p(20), p(20), p(20)),
+    /*  3 */ (p(24), p(24), p(24)),
+    /*  4 */ (p( 9), p( 9), p( 9)),
+    /*  5 */ (p( 0), p( 0), p( 0)),
+    /*  6 */ (p(27), p(27), p(27)),
+    /*  7 */ (p( 5), p( 5), p( 5)),
+    /*  8 */ (p(12), p(12), p(12)),
+    /*  9 */ (p(26), p(26), p(26)),
+    /* 10 */ (p(16), p( 8), p( 8)),
+    /* 11 */ (p( 4), p(28), p(28)),
+    /* 12 */ (p( 4), p( 2), p( 2)),
+    /* 13 */ (p( 3), p( 4), p( 4)),
+    /* 14 */ (p( 4), p(29), p(29)),
+    /* 15 */ (p(28), p( 4), p(28)),
+    /* 16 */ (p( 2), p(17), p( 2)),
+    /* 17 */ (p(16), p(16), p( 8)),
+    /* 18 */ (p( 4), p( 4), p( 7)),
+    /* 19 */ (p( 4), p( 4), p(18)),
+    /* 20 */ (p( 4), p( 4), p(20)),
+    /* 21 */ (p(19), p(19), p( 9)),
+    /* 22 */ (p( 4) - 1, p( 4) - 1, p(11)),
+    /* 23 */ (p(17), p(17), p( 2)),
+    /* 24 */ (p( 4), p( 4), p( 2)),
+    /* 25 */ (p( 4), p( 4), p( 3)),
+    /* 26 */ (p(28), p(28), p( 0)),
+    /* 27 */ (p( 3), p( 3), p( 0)),
+    /* 28 */ (p( 0), p( 0), p( 1)),
+    /* 29 */ (p(18), p(22), p(18)),
+    /* 30 */ (p(20), p(22), p(20)),
+    /* 31 */ (p(24), p(22), p(24)),
+    /* 32 */ (p(16), p(22), p( 8)),
+    /* 33 */ (p(17), p( 4), p(13)),
+    /* 34 */ (p(28) - 1, p( 0), p(14)),
+    /* 35 */ (p(28) - 1, p( 4), p(15)),
+    /* 36 */ (p(19), p(22), p( 9)),
+    /* 37 */ (p(16), p(28), p(10)),
+    /* 38 */ (p( 4), p(23), p(28)),
+    /* 39 */ (p(17), p(22), p( 2)),
+    /* 40 */ (p( 4), p( 0), p( 2)),
+    /* 41 */ (p( 4), p(28), p( 3)),
+    /* 42 */ (p(28), p( 3), p( 0)),
+    /* 43 */ (p( 3), p(28), p( 4)),
+    /* 44 */ (p(21), p(28), p( 4)),
+    /* 45 */ (p( 3), p(28), p( 0)),
+    /* 46 */ (p(25), p( 3), p(28)),
+    /* 47 */ (p( 0), p(28), p( 8)),
+    /* 48 */ (p( 4), p( 3), p(28)),
+    /* 49 */ (p(28), p( 3), p( 6)),
+    /* 50 */ (p( 4), p(28), p(29)),
+];
+
+
+/// Checksums of the cartridge titles which get a dedicated palette combination.
+const TITLE_CHECKSUMS: [u8; 94] = [
+    0x00, 0x88, 0x16, 0x36, 0xd1, 0xdb, 0xf2, 0x3c, 0x8c, 0x92, 0x3d, 0x5c, 0x58, 0xc9, 0x3e, 0x70,
+    0x1d, 0x59, 0x69, 0x19, 0x35, 0xa8, 0x14, 0xaa, 0x75, 0x95, 0x99, 0x34, 0x6f, 0x15, 0xff, 0x97,
+    0x4b, 0x90, 0x17, 0x10, 0x39, 0xf7, 0xf6, 0xa2, 0x49, 0x4e, 0x43, 0x68, 0xe0, 0x8b, 0xf0, 0xce,
+    0x0c, 0x29, 0xe8, 0xb7, 0x86, 0x9a, 0x52, 0x01, 0x9d, 0x71, 0x9c, 0xbd, 0x5d, 0x6d, 0x67, 0x3f,
+    0x6b,
+
+    // checksums which are ambiguous and need to be checked against the 4th letter of the title
+    0xb3, 0x46, 0x28, 0xa5, 0xc6, 0xd3, 0x27, 0x61, 0x18, 0x66, 0x6a, 0xbf, 0x0d, 0xf4, 0xb3,
+    0x46, 0x28, 0xa5, 0xc6, 0xd3, 0x27, 0x61, 0x18, 0x66, 0x6a, 0xbf, 0x0d, 0xf4, 0xb3,
+];
+
+
+/// Index of the first entry in [TITLE_CHECKSUMS] which needs to be checked against the 4th letter.
+const FIRST_CHECKSUM_WITH_DUPLICATE: usize = 65;
+
+
+/// The 4th letter of the title for each ambiguous checksum.
+const DUPLICATES_4TH_LETTER: &[u8; 29] = b"BEFAARBEKEK R-URAR INAILICE R";
+
+
+/// The palette combination for each entry of [TITLE_CHECKSUMS].
+const PALETTE_PER_CHECKSUM: [u8; 94] = [
+     0,  4,  5, 35, 34,  3, 31, 15, 10,  5, 19, 36,  7, 37, 30, 44,
+    21, 32, 31, 20,  5, 33, 13, 14,  5, 29,  5, 18,  9,  3,  2, 26,
+    25, 25, 41, 42, 26, 45, 42, 45, 36, 38, 26, 42, 30, 41, 34, 34,
+     5, 42,  6,  5, 33, 25, 42, 42, 40,  2, 16, 25, 42, 42,  5,  0,
+    39,
+
+    36, 22, 25,  6, 32, 12, 36, 11, 39, 18, 39, 24, 31, 50, 17,
+    46,  6, 27,  0, 47, 41, 41,  0,  0, 19, 34, 23, 18, 29,
+];
+
+
+/// The palette combination used for cartridges not being recognized.
+const DEFAULT_PALETTE_COMBINATION: usize = 0;
+
+
+/// The button combinations which can be pressed during the GBC boot animation
+/// to select a palette for classic GameBoy games manually.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ManualPaletteSelection {
+    Up,
+    UpA,
+    UpB,
+    Left,
+    LeftA,
+    LeftB,
+    Down,
+    DownA,
+    DownB,
+    Right,
+    RightA,
+    RightB,
+}
+
+
+/// A set of palettes used to colorize a classic GameBoy game on the GameBoy Color.
+#[derive(Copy, Clone)]
+pub struct CompatibilityPalettes {
+    /// The palette used for background and window.
+    pub background: GbcPaletteData,
+
+    /// The palettes replacing OBP0 and OBP1.
+    pub objects: [GbcPaletteData; 2],
+}
+
+
+impl ManualPaletteSelection {
+    /// Get the index of the palette combination selected by this button combination.
+    fn get_palette_combination(&self) -> usize {
+        match self {
+            ManualPaletteSelection::Up      =>  5,
+            ManualPaletteSelection::UpA     => 43,
+            ManualPaletteSelection::UpB     => 28,
+            ManualPaletteSelection::Left    => 48,
+            ManualPaletteSelection::LeftA   => 40,
+            ManualPaletteSelection::LeftB   =>  7,
+            ManualPaletteSelection::Down    =>  8,
+            ManualPaletteSelection::DownA   =>  3,
+            ManualPaletteSelection::DownB   => 49,
+            ManualPaletteSelection::Right   =>  1,
+            ManualPaletteSelection::RightA  =>  0,
+            ManualPaletteSelection::RightB  =>  6,
+        }
+    }
+}
+
+
+impl CompatibilityPalettes {
+    /// Selects the palettes the GBC boot ROM would choose for a classic GameBoy cartridge.
+    /// Only games licensed by Nintendo are recognized by their title checksum,
+    /// any other game gets the default palettes.
+    pub fn for_cartridge(cartridge: &Cartridge) -> Self {
+        let combination = match cartridge.get_licensee_code() {
+            LicenseeCode::Old(1) | LicenseeCode::New(1) => {
+                let checksum    = cartridge.compute_title_checksum();
+                let fourth_char = cartridge.get_rom().get_at(ROM_OFFSET_TITLE_STRING + 3);
+
+                find_palette_combination(checksum, fourth_char)
+            }
+
+            _ => DEFAULT_PALETTE_COMBINATION,
+        };
+
+        Self::from_combination(combination)
+    }
+
+
+    /// Get the palettes selected by a button combination during the boot animation.
+    pub fn for_manual_selection(selection: ManualPaletteSelection) -> Self {
+        Self::from_combination(selection.get_palette_combination())
+    }
+
+
+    /// Creates the palettes of a palette combination.
+    fn from_combination(combination: usize) -> Self {
+        let (obj0, obj1, bg) = PALETTE_COMBINATIONS[combination];
+
+        Self {
+            background: palette_at(bg),
+            objects:    [palette_at(obj0), palette_at(obj1)],
+        }
+    }
+
+
+    /// Writes the palettes into the GBC palette memory, where they
+    /// are used when running in DMG compatibility mode.
+    pub fn apply_to(&self, palettes: &mut Palettes) {
+        palettes.gbc_background_palette.get_mut()[0] = self.background;
+        palettes.gbc_object_palette.get_mut()[0]     = self.objects[0];
+        palettes.gbc_object_palette.get_mut()[1]     = self.objects[1];
+    }
+}
+
+
+impl Default for CompatibilityPalettes {
+    /// The palettes used for cartridges not being recognized.
+    fn default() -> Self {
+        Self::from_combination(DEFAULT_PALETTE_COMBINATION)
+    }
+}
+
+
+/// Searches the palette combination for a title checksum.
+/// Ambiguous checksums are resolved by the 4th letter of the title.
+fn find_palette_combination(checksum: u8, fourth_char: u8) -> usize {
+    for (index, entry) in TITLE_CHECKSUMS.iter().enumerate() {
+        if *entry != checksum {
+            continue;
+        }
+
+        if index >= FIRST_CHECKSUM_WITH_DUPLICATE
+            && DUPLICATES_4TH_LETTER[index - FIRST_CHECKSUM_WITH_DUPLICATE] != fourth_char
+        {
+            continue;
+        }
+
+        return PALETTE_PER_CHECKSUM[index] as usize;
+    }
+
+    DEFAULT_PALETTE_COMBINATION
+}
+
+
+/// Reads a palette of four colors beginning at the given offset of [PALETTE_COLORS].
+fn palette_at(offset: usize) -> GbcPaletteData {
+    GbcPaletteData::from_rgb_555([
+        PALETTE_COLORS[offset],
+        PALETTE_COLORS[offset + 1],
+        PALETTE_COLORS[offset + 2],
+        PALETTE_COLORS[offset + 3],
+    ])
+}
@@ -313,10 +313,23 @@ impl GbcPaletteData {
         }
     }
 
+    /// Creates a palette from four RGB555 color values.
+    pub fn from_rgb_555(palette: [u16; 4]) -> Self {
+        Self {
+            palette
+        }
+    }
+
     /// Get the RGBA color for a specific pixel value.
     pub fn get_color(&self, pixel: &SpritePixelValue) -> Color {
         Color::from_rgb_555(self.palette[pixel.0 as usize])
     }
+
+    /// Get the RGBA color for a DMG LCD pixel, which is used
+    /// to colorize classic GameBoy games on the GameBoy Color.
+    pub fn get_dmg_color(&self, pixel: &DmgLcdPixel) -> Color {
+        Color::from_rgb_555(self.palette[pixel.0 as usize])
+    }
 }
 
 
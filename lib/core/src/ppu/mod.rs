@@ -15,8 +15,10 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
+pub mod compat_palettes;
 pub mod flags;
 pub mod graphic_data;
+pub mod pixel_fifo;
 pub mod ppu;
 pub mod sprite_image;
 pub mod video_memory;
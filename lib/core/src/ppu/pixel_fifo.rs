@@ -0,0 +1,241 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::ppu::ppu::{PixelFetchResult, TileFetchProperties};
+
+
+/// The number of pixels fetched at once, which is the width of a single tile.
+pub const FETCHER_PIXELS: usize = 8;
+
+/// The number of dots each step of the background fetcher takes,
+/// except for pushing pixels into the FIFO.
+pub const DOTS_PER_FETCHER_STEP: u8 = 2;
+
+/// The number of dots needed to fetch the data of an object.
+pub const DOTS_PER_OBJECT_FETCH: u8 = 6;
+
+
+/// A FIFO queue storing pixels to be shifted out to the LCD.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PixelFifo {
+    /// Storage of all pixels, used as a ring buffer.
+    pixels: [PixelFetchResult; FETCHER_PIXELS],
+
+    /// The index of the next pixel to be taken from the FIFO.
+    head: u8,
+
+    /// The number of pixels currently stored.
+    length: u8,
+}
+
+
+/// The steps the background fetcher is running through to fetch a row of 8 pixels.
+#[derive(Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FetcherStep {
+    /// Reads the tile number from the tile map.
+    GetTile,
+
+    /// Reads the lower byte of the tile data.
+    GetTileDataLow,
+
+    /// Reads the upper byte of the tile data.
+    GetTileDataHigh,
+
+    /// Waits until the pixels can be pushed into the background FIFO.
+    Push,
+}
+
+
+/// The fetcher reading pixels of the background or window layer
+/// and pushing them into the background FIFO.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BackgroundFetcher {
+    /// The step currently being processed.
+    pub step: FetcherStep,
+
+    /// The number of dots spent in the current step.
+    pub step_dots: u8,
+
+    /// The column of the next tile to be fetched, relative to the
+    /// scrolled background or the beginning of the window.
+    pub tile_x: u8,
+
+    /// Whether the fetcher is reading tiles of the window instead of the background.
+    pub fetching_window: bool,
+
+    /// The first tile of each scanline gets fetched twice, so the
+    /// result of the first fetch will be discarded.
+    pub discard_next_fetch: bool,
+
+    /// The location of the tile being fetched, read during [FetcherStep::GetTile].
+    pub tile: Option<TileFetchProperties>,
+
+    /// The pixels fetched, waiting to be pushed into the FIFO.
+    pub pixels: [PixelFetchResult; FETCHER_PIXELS],
+}
+
+
+/// The state of the pixel pipeline while the PPU is drawing a scanline.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DrawLineState {
+    /// The FIFO containing background and window pixels.
+    pub background_fifo: PixelFifo,
+
+    /// The FIFO containing pixels of objects, which will be mixed with the background.
+    pub object_fifo: PixelFifo,
+
+    /// The fetcher reading background and window tiles.
+    pub fetcher: BackgroundFetcher,
+
+    /// The number of pixels to be shifted out without being displayed.
+    /// This is used to apply the fine scrolling of SCX and window
+    /// positions left of the screen.
+    pub pixels_to_discard: u8,
+
+    /// The scanline sprite currently being fetched, if any.
+    pub object_fetch: Option<u8>,
+
+    /// The number of dots remaining until the current object fetch completes.
+    pub object_fetch_dots: u8,
+
+    /// A bitmask of all scanline sprites, which were already fetched.
+    pub objects_fetched: u16,
+}
+
+
+impl PixelFifo {
+    /// Get the number of pixels stored in the FIFO.
+    pub fn len(&self) -> usize {
+        self.length as usize
+    }
+
+    /// Checks whether the FIFO does not contain any pixels.
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Removes all pixels from the FIFO.
+    pub fn clear(&mut self) {
+        self.head   = 0;
+        self.length = 0;
+    }
+
+    /// Appends a pixel at the end of the FIFO.
+    /// Pixels exceeding the capacity of the FIFO are dropped.
+    pub fn push(&mut self, pixel: PixelFetchResult) {
+        if self.len() < FETCHER_PIXELS {
+            let index = (self.head as usize + self.len()) % FETCHER_PIXELS;
+            self.pixels[index] = pixel;
+            self.length += 1;
+        }
+    }
+
+    /// Takes the next pixel from the FIFO, if any.
+    pub fn pop(&mut self) -> Option<PixelFetchResult> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let pixel = self.pixels[self.head as usize];
+        self.head    = ((self.head as usize + 1) % FETCHER_PIXELS) as u8;
+        self.length -= 1;
+
+        Some(pixel)
+    }
+
+    /// Get a mutable reference to a pixel stored in the FIFO,
+    /// where index 0 refers to the next pixel to be taken.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut PixelFetchResult> {
+        if index < self.len() {
+            Some(&mut self.pixels[(self.head as usize + index) % FETCHER_PIXELS])
+        }
+        else {
+            None
+        }
+    }
+}
+
+
+impl BackgroundFetcher {
+    /// Restarts the fetcher to read tiles from the window.
+    pub fn start_window(&mut self) {
+        self.step            = FetcherStep::GetTile;
+        self.step_dots       = 0;
+        self.tile_x          = 0;
+        self.fetching_window = true;
+        self.tile            = None;
+    }
+}
+
+
+impl DrawLineState {
+    /// Checks whether a scanline sprite was already fetched.
+    pub fn is_object_fetched(&self, index: u8) -> bool {
+        (self.objects_fetched & (1 << index)) != 0
+    }
+
+    /// Marks a scanline sprite as being fetched.
+    pub fn set_object_fetched(&mut self, index: u8) {
+        self.objects_fetched |= 1 << index;
+    }
+}
+
+
+impl Default for PixelFifo {
+    /// Creates an empty FIFO.
+    fn default() -> Self {
+        Self {
+            pixels: [PixelFetchResult::none(); FETCHER_PIXELS],
+            head:   0,
+            length: 0,
+        }
+    }
+}
+
+
+impl Default for BackgroundFetcher {
+    /// Creates a fetcher starting with the first tile of a scanline.
+    fn default() -> Self {
+        Self {
+            step:               FetcherStep::GetTile,
+            step_dots:          0,
+            tile_x:             0,
+            fetching_window:    false,
+            discard_next_fetch: true,
+            tile:               None,
+            pixels:             [PixelFetchResult::none(); FETCHER_PIXELS],
+        }
+    }
+}
+
+
+impl Default for DrawLineState {
+    /// Creates the state for a new scanline to be drawn.
+    fn default() -> Self {
+        Self {
+            background_fifo:    PixelFifo::default(),
+            object_fifo:        PixelFifo::default(),
+            fetcher:            BackgroundFetcher::default(),
+            pixels_to_discard:  0,
+            object_fetch:       None,
+            object_fetch_dots:  0,
+            objects_fetched:    0,
+        }
+    }
+}
@@ -15,7 +15,6 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
-use std::cmp::min;
 use std::mem::take;
 
 use crate::cpu::interrupts::Interrupt;
@@ -28,9 +27,10 @@ use crate::mmu::memory_data::mapped::MemoryDataMapped;
 use crate::mmu::memory_data::MemoryData;
 use crate::ppu::flags::{LcdControl, LcdControlFlag, LcdInterruptFlag, LcdInterruptFlags};
 use crate::ppu::graphic_data::*;
+use crate::ppu::pixel_fifo::{DrawLineState, FetcherStep, DOTS_PER_FETCHER_STEP, DOTS_PER_OBJECT_FETCH, FETCHER_PIXELS};
 use crate::ppu::sprite_image::SpriteImage;
 use crate::ppu::video_memory::{OamRam, OamRamBank, Palettes, VideoMemory};
-use crate::utils::{get_bit, SerializableArray};
+use crate::utils::{get_bit, to_u16, to_u8, SerializableArray};
 
 pub const SCREEN_W: u32 = 160;
 pub const SCREEN_H: u32 = 144;
@@ -45,6 +45,12 @@ pub const CPU_CYCLES_DRAW_MAX:                  Clock =    289;
 pub const CPU_CYCLES_HBLANK_MIN:                Clock =     87;
 pub const CPU_CYCLES_HBLANK_MAX:                Clock =    204;
 
+/// The number of rows in OAM, each containing two objects.
+const OAM_ROWS:                                 usize =     20;
+
+/// The size of a single OAM row in bytes.
+const OAM_ROW_SIZE:                             usize =      8;
+
 pub const TILE_ATTR_BIT_VRAM_BANK:                  u8 = 3;
 pub const TILE_ATTR_BIT_H_FLIP:                     u8 = 5;
 pub const TILE_ATTR_BIT_V_FLIP:                     u8 = 6;
@@ -71,6 +77,18 @@ pub enum Mode {
 }
 
 
+/// The kind of CPU operation triggering the OAM corruption bug on DMG devices.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum OamCorruption {
+    /// A write access or a 16 bit increment or decrement of an address within OAM.
+    Write,
+
+    /// A read access, where the address gets incremented or decremented at the same time,
+    /// like on POP or LD A, (HL+)
+    ReadWithIncDec,
+}
+
+
 /// Defines the state whether the PPU is enabled or not.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LcdState {
@@ -85,6 +103,18 @@ pub enum LcdState {
 }
 
 
+/// A sprite found by the OAM scan to be displayed within a scanline.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanlineSprite {
+    /// The index of the sprite within the OAM table.
+    oam_index: u8,
+
+    /// The sprite data read from OAM.
+    sprite: Sprite,
+}
+
+
 /// An object storing data of any scanline to be processed by the PPU.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ScanlineData {
@@ -92,7 +122,7 @@ pub struct ScanlineData {
     line: u8,
 
     /// Stores the sprites to be displayed within the current scanline.
-    sprites: [Sprite; 10],
+    sprites: [ScanlineSprite; 10],
 
     /// The number of sprites found.
     sprites_found: u8,
@@ -104,6 +134,8 @@ pub struct ScanlineData {
 
 /// The result of fetching a pixel from either background / window
 /// or OAM list.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PixelFetchResult {
     /// The color value from the tile being displayed.
     /// This value is not a final color but needs to be
@@ -137,6 +169,8 @@ pub struct PixelFetchResultWithPalette<'a> {
 /// Contains information where to read tile data.
 /// This information can be obtained by reading tile
 /// information from the PPU tile map.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TileFetchProperties {
     /// The TileMap where to read from
     pub tilemap: TileMap,
@@ -209,6 +243,9 @@ pub struct Ppu {
     /// The PPU's current mode.
     mode: Mode,
 
+    /// Set when the PPU entered HBlank, which triggers the next block of a HBlank DMA transfer.
+    hblank_started: bool,
+
     /// Several memory units connected to the PPU.
     memory: VideoMemory,
 
@@ -227,11 +264,18 @@ pub struct Ppu {
     /// The cached data of the currently processed scanline.
     current_scanline: ScanlineData,
 
+    /// The state of the pixel FIFOs and fetchers while drawing the current scanline.
+    draw_state: DrawLineState,
+
     /// Stores the current line being processed for a window.
     /// This in independent of the frame line counter (LY) and just updated
     /// when window pixels were drawn for the current scanline.
     window_line: u8,
 
+    /// Set when LY matched WY within the current frame,
+    /// which allows the window to be displayed.
+    window_y_triggered: bool,
+
     /// If in DMG mode, a set of RGB colors to translate the LCD intensity values
     /// into RGB colors to be displayed on color screens.
     dmg_display_palette: DmgDisplayPalette,
@@ -309,11 +353,22 @@ impl LcdBuffer {
 }
 
 
+impl ScanlineSprite {
+    /// Creates an empty entry.
+    pub fn empty() -> Self {
+        Self {
+            oam_index: 0,
+            sprite:    Sprite::empty(),
+        }
+    }
+}
+
+
 impl ScanlineData {
     pub fn new() -> ScanlineData {
         ScanlineData {
             line: 0,
-            sprites: [Sprite::empty(); 10],
+            sprites: [ScanlineSprite::empty(); 10],
             sprites_found: 0,
             window_enabled: false,
         }
@@ -338,19 +393,30 @@ impl Ppu {
             lcd_state: LcdState::On,
             is_first_frame: true,
             mode: Mode::OamScan,
+            hblank_started: false,
             memory: VideoMemory::new(device_config),
             registers: PpuRegisters::default(),
             current_line: 0,
             current_line_pixel: 0,
             current_line_cycles: 0,
             current_scanline: ScanlineData::new(),
+            draw_state: DrawLineState::default(),
             window_line: 0,
+            window_y_triggered: false,
             dmg_display_palette,
             lcd_buffer: LcdBuffer::allow_with_color(blank_color),
         }
     }
     
     
+    /// Switches the PPU of a GameBoy Color into DMG compatibility mode.
+    /// The GBC palettes written so far will be used to colorize the classic GameBoy palettes.
+    pub fn enter_dmg_compatibility_mode(&mut self) {
+        self.device_config.emulation = EmulationType::DMG;
+        self.memory.vram_active_bank = 0;
+    }
+
+
     /// Get the blank color for a disabled screen.
     fn get_blank_color(device_config: &DeviceConfig, dmg_display_palette: &DmgDisplayPalette) -> Color {
         match device_config.emulation {
@@ -369,11 +435,21 @@ impl Ppu {
             LcdState::On => {
                 self.clock += cycles;
 
-                match self.mode {
-                    Mode::OamScan  => self.process_oam_scan(),
-                    Mode::DrawLine => self.process_draw_line(),
-                    Mode::HBlank   => self.process_hblank(),
-                    Mode::VBlank   => self.process_vblank(),
+                // the time passed may cover multiple modes, like while the CPU
+                // is paused during a speed switch, so process until the time is consumed
+                loop {
+                    let clock_before = self.clock;
+
+                    match self.mode {
+                        Mode::OamScan  => self.process_oam_scan(),
+                        Mode::DrawLine => self.process_draw_line(),
+                        Mode::HBlank   => self.process_hblank(),
+                        Mode::VBlank   => self.process_vblank(),
+                    }
+
+                    if self.clock == 0 || self.clock == clock_before {
+                        break;
+                    }
                 }
             }
 
@@ -423,174 +499,411 @@ impl Ppu {
             self.current_line_pixel  = 0;
             self.current_line_cycles = 80;
 
+            // the window can only be displayed after LY matched WY once per frame
+            if self.current_line == self.registers.window_y {
+                self.window_y_triggered = true;
+            }
+
+            // reset the pixel pipeline; the fine scroll of SCX will be
+            // applied by discarding pixels at the beginning of the scanline
+            self.draw_state = DrawLineState::default();
+            self.draw_state.pixels_to_discard = self.registers.scroll_x & 0x07;
+
             self.enter_mode(Mode::DrawLine);
         }
     }
 
 
     /// Draws pixels of the current scanline into the LCD buffer.
+    /// Each dot runs the fetchers and shifts out one pixel, as long as the
+    /// pixel pipeline is not stalled, so the duration of this mode depends
+    /// on scrolling, the window and the number of objects on this line.
     /// Enters Mode::HBlank after the drawing was completed.
     fn process_draw_line(&mut self) {
-        let pixels_remaining = SCREEN_W - (self.current_line_pixel as u32);
+        while self.clock > 0 {
+            self.clock               -= 1;
+            self.current_line_cycles += 1;
 
-        if pixels_remaining > 0 {
-            // how many pixels could be drawn
-            let pixels_to_update = min(self.clock, pixels_remaining as u64);
+            self.process_draw_line_dot();
 
-            // update clock
-            let cycles = pixels_to_update;
-            self.current_line_cycles += cycles;
-            self.clock -= cycles;
+            if self.current_line_pixel as u32 >= SCREEN_W {
+                self.enter_mode(Mode::HBlank);
+                break;
+            }
+        }
+    }
 
-            // process the pixels
-            self.process_draw_line_pixels(pixels_to_update);
+
+    /// Process a single dot of the pixel pipeline.
+    fn process_draw_line_dot(&mut self) {
+        // an object fetch stalls both the background fetcher and the pixel output
+        if self.draw_state.object_fetch.is_none() {
+            self.check_object_fetch();
         }
-        else {
-            self.current_line_cycles += self.clock;
-            self.clock = 0;
 
-            // wait for the minimum time mode 2+3 could take
-            if self.current_line_cycles >= CPU_CYCLES_OAMSCAN + CPU_CYCLES_DRAW_MIN {
-                self.enter_mode(Mode::HBlank);
+        if let Some(scanline_sprite_index) = self.draw_state.object_fetch {
+            self.draw_state.object_fetch_dots -= 1;
+
+            if self.draw_state.object_fetch_dots == 0 {
+                self.fetch_object_pixels(scanline_sprite_index);
+                self.draw_state.set_object_fetched(scanline_sprite_index);
+                self.draw_state.object_fetch = None;
             }
+
+            return;
         }
+
+        self.check_window_start();
+        self.process_background_fetcher();
+        self.shift_out_pixel();
     }
 
 
-    /// Process a number of pixels within the current scanline.
-    fn process_draw_line_pixels(&mut self, pixels_to_update: Clock) {
-        let window_enabled   = self.check_lcdc(LcdControlFlag::WindowEnabled);
-        let palette_bg       = &self.memory.palettes.bgp;
-        let palette_obp      = &self.memory.palettes.obp;
-        let palettes_gbc_bg  = &self.memory.palettes.gbc_background_palette.get();
-        let palettes_gbc_obj = &self.memory.palettes.gbc_object_palette.get();
-        let wx               = self.registers.window_x;
-        let wy               = self.registers.window_y;
-
-        for _ in 0..pixels_to_update {
-            // check if the window is enabled and the current screen pixel is inside the area covered by wx/wy
-            if !self.current_scanline.window_enabled && window_enabled {
-                if (self.current_line_pixel+7 >= wx) && ((wy as u32) < SCREEN_H) && (wy <= self.current_line) {
-                    self.current_scanline.window_enabled = true;
+    /// Checks whether an object starts at the current pixel and starts fetching it, if so.
+    /// The object fetch needs to wait for the background fetcher to complete the tile
+    /// currently being fetched, which adds up to 5 dots to the time needed.
+    fn check_object_fetch(&mut self) {
+        if !self.check_lcdc(LcdControlFlag::SpritesEnabled) || self.draw_state.pixels_to_discard != 0 {
+            return;
+        }
+
+        for index in 0..self.current_scanline.sprites_found {
+            if self.draw_state.is_object_fetched(index) {
+                continue;
+            }
+
+            // objects partially left of the screen are fetched on the first pixel
+            let sprite   = &self.current_scanline.sprites[index as usize].sprite;
+            let screen_x = sprite.pos_x.saturating_sub(8);
+
+            if screen_x == self.current_line_pixel {
+                // the pixels shifted out of the current tile determine how long
+                // the background fetcher needs to complete the tile
+                let fifo_length      = self.draw_state.background_fifo.len();
+                let position_in_tile = if fifo_length == 0 { 0 } else { FETCHER_PIXELS - fifo_length };
+                let wait_for_fetcher = 5u8.saturating_sub(position_in_tile as u8);
+
+                self.draw_state.object_fetch      = Some(index);
+                self.draw_state.object_fetch_dots = DOTS_PER_OBJECT_FETCH + wait_for_fetcher;
+
+                return;
+            }
+        }
+    }
+
+
+    /// Checks whether the window begins on the current pixel and restarts
+    /// the background fetcher to fetch window tiles, if so.
+    fn check_window_start(&mut self) {
+        let wx = self.registers.window_x;
+
+        if
+                !self.draw_state.fetcher.fetching_window
+            &&  self.window_y_triggered
+            &&  self.check_lcdc(LcdControlFlag::WindowEnabled)
+            &&  self.current_line_pixel + 7 >= wx
+        {
+            self.current_scanline.window_enabled = true;
+
+            self.draw_state.background_fifo.clear();
+            self.draw_state.fetcher.start_window();
+
+            // window pixels left of the screen are not displayed
+            if self.current_line_pixel == 0 {
+                self.draw_state.pixels_to_discard = 7u8.saturating_sub(wx);
+            }
+        }
+    }
+
+
+    /// Runs a single dot of the background fetcher.
+    /// Each step of the fetcher takes two dots, while the fetched pixels
+    /// are pushed into the FIFO as soon as it is empty.
+    fn process_background_fetcher(&mut self) {
+        let fetcher = &mut self.draw_state.fetcher;
+
+        if fetcher.step == FetcherStep::Push {
+            if self.draw_state.background_fifo.is_empty() {
+                for pixel in fetcher.pixels {
+                    self.draw_state.background_fifo.push(pixel);
                 }
+
+                fetcher.tile_x = fetcher.tile_x.wrapping_add(1);
+                fetcher.step   = FetcherStep::GetTile;
             }
 
-            // fetch background and foreground pixels, if any
-            let fetched_pixel_background = self.fetch_background_pixel();
-            let fetched_pixel_foreground = self.fetch_foreground_pixel();
+            return;
+        }
 
-            // select palettes for background pixel
-            let pixel_background = PixelFetchResultWithPalette {
-                data: &fetched_pixel_background,
-                palette_dmg: &palette_bg,
-                palette_gbc: &palettes_gbc_bg[fetched_pixel_background.palette_gbc as usize]
-            };
+        fetcher.step_dots += 1;
+        if fetcher.step_dots < DOTS_PER_FETCHER_STEP {
+            return;
+        }
 
-            // select palettes for foreground pixel
-            let pixel_foreground = PixelFetchResultWithPalette {
-                data: &fetched_pixel_foreground,
-                palette_dmg: &palette_obp[fetched_pixel_foreground.palette_dmg as usize],
-                palette_gbc: &palettes_gbc_obj[fetched_pixel_foreground.palette_gbc as usize]
-            };
+        fetcher.step_dots = 0;
+
+        match fetcher.step {
+            FetcherStep::GetTile => {
+                self.draw_state.fetcher.tile = Some(self.read_fetcher_tile());
+                self.draw_state.fetcher.step = FetcherStep::GetTileDataLow;
+            }
+
+            FetcherStep::GetTileDataLow => {
+                fetcher.step = FetcherStep::GetTileDataHigh;
+            }
+
+            FetcherStep::GetTileDataHigh => {
+                if let Some(tile) = fetcher.tile {
+                    self.draw_state.fetcher.pixels = self.read_fetcher_pixels(&tile);
+                }
 
-            // select pixel to be displayed
-            let pixel = self.mix_pixels(
-                    &pixel_background,
-                    &pixel_foreground
+                // the first fetch on each scanline is repeated
+                let fetcher = &mut self.draw_state.fetcher;
+                if fetcher.discard_next_fetch {
+                    fetcher.discard_next_fetch = false;
+                    fetcher.step = FetcherStep::GetTile;
+                }
+                else {
+                    fetcher.step = FetcherStep::Push;
+                }
+            }
+
+            FetcherStep::Push => unreachable!(),
+        }
+    }
+
+
+    /// Get the location of the tile to be fetched next by the background fetcher.
+    fn read_fetcher_tile(&self) -> TileFetchProperties {
+        let tileset_select = self.check_lcdc(LcdControlFlag::TileDataSelect);
+        let tileset        = TileSet::by_select_bit(tileset_select);
+        let fetcher        = &self.draw_state.fetcher;
+
+        if fetcher.fetching_window {
+            let window_tilemap_select = self.check_lcdc(LcdControlFlag::WindowTileMapSelect);
+            let window_tilemap        = TileMap::by_select_bit(window_tilemap_select);
+
+            self.read_tilemap_properties(
+                window_tilemap,
+                tileset,
+                fetcher.tile_x.wrapping_mul(8),
+                self.window_line
+            )
+        }
+        else {
+            let bg_tilemap_select = self.check_lcdc(LcdControlFlag::BackgroundTileMapSelect);
+            let bg_tilemap        = TileMap::by_select_bit(bg_tilemap_select);
+
+            // the fine scroll is applied when shifting out pixels,
+            // so the fetcher just reads whole tiles
+            let (background_x, background_y) = self.screen_to_background(
+                fetcher.tile_x.wrapping_mul(8),
+                self.current_line
             );
 
-            // the first frame does not draw pixels
-            if !self.is_first_frame {
-                // resolve pixel color using the according palette
-                let pixel_color = match self.device_config.emulation {
-                    EmulationType::DMG => {
-                        let lcd_pixel = pixel.palette_dmg.get_color(&pixel.data.value);
-                        *self.translate_dmg_color_index(&lcd_pixel)
-                    }
+            self.read_tilemap_properties(
+                bg_tilemap,
+                tileset,
+                background_x & 0xf8,
+                background_y
+            )
+        }
+    }
+
+
+    /// Reads a row of 8 pixels from the tile located by the background fetcher.
+    fn read_fetcher_pixels(&self, tile: &TileFetchProperties) -> [PixelFetchResult; FETCHER_PIXELS] {
+        let mut pixels = [PixelFetchResult::none(); FETCHER_PIXELS];
+
+        for (x, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = self.read_tile_pixel(&TileFetchProperties {
+                tile_pixel_x: x as u8,
+                .. *tile
+            });
+        }
+
+        pixels
+    }
+
+
+    /// Reads the pixels of a scanline sprite and merges them into the object FIFO.
+    /// Pixels of objects being already in the FIFO are only replaced by
+    /// pixels of objects with a higher priority.
+    fn fetch_object_pixels(&mut self, scanline_sprite_index: u8) {
+        let scanline_sprite = self.current_scanline.sprites[scanline_sprite_index as usize];
+        let sprite          = &scanline_sprite.sprite;
+
+        let big_sprites = self.check_lcdc(LcdControlFlag::SpritesSize);
+        let sprite_h    = if big_sprites { 16 } else { 8 };
+        let sprite_w    = 8;
+
+        // when big sprites are enabled, the top sprite always has the least significant bit
+        // set to 0, and the bottom sprite is using the same sprite number with the LSB set to 1
+        // so we're just eliminating the LSB and continue reading with sprite data behind the
+        // top sprite memory location.
+        let sprite_mask = if big_sprites { 0xfe } else { 0xff };
+
+        // on GBC the OAM index takes priority, unless the object priority mode was changed
+        let oam_index_priority = self.device_config.is_gbc_enabled() && !self.registers.object_priority;
+
+        // calculate the row inside the sprite including y flip
+        let screen_y       = self.current_line + 16;
+        let sprite_pixel_y = flipped_if(screen_y.wrapping_sub(sprite.pos_y) & (sprite_h - 1), sprite_h, sprite.is_flip_y());
+
+        // if in GBC mode, check the sprite properties for the VRAM bank index
+        // where to read the pixel data from
+        let vram_bank_index = if self.device_config.is_gbc_enabled() {
+            sprite.get_gbc_vram_bank()
+        }
+        else {
+            0
+        };
+
+        // pixels of objects partially left of the screen are skipped
+        let first_pixel = 8u8.saturating_sub(sprite.pos_x);
+
+        for sprite_pixel in first_pixel .. sprite_w {
+            let sprite_pixel_x = flipped_if(sprite_pixel, sprite_w, sprite.is_flip_x());
+
+            let value = self.read_sprite_pixel(
+                TileSet::H8000,
+                sprite.tile & sprite_mask,
+                vram_bank_index,
+                sprite_pixel_x,
+                sprite_pixel_y
+            );
+
+            let pixel = PixelFetchResult {
+                value,
+                palette_dmg: sprite.get_dmg_palette(),
+                palette_gbc: sprite.get_color_palette(),
+                sprite_priority: scanline_sprite.oam_index,
+                background_priority: sprite.is_bg_priority(),
+            };
+
+            let fifo_index = (sprite_pixel - first_pixel) as usize;
+            match self.draw_state.object_fifo.get_mut(fifo_index) {
+                Some(existing) => {
+                    let replace =
+                            existing.value.is_transparent()
+                        ||  (oam_index_priority && pixel.value.is_opaque() && pixel.sprite_priority < existing.sprite_priority)
+                    ;
 
-                    EmulationType::GBC => {
-                        pixel.palette_gbc.get_color(&pixel.data.value)
+                    if replace {
+                        *existing = pixel;
                     }
-                };
+                }
 
-                // write pixel into LCD buffer
-                self.lcd_buffer.set_pixel(
-                    self.current_line_pixel as u32,
-                    self.current_line as u32,
-                    pixel_color
-                );
+                None => {
+                    self.draw_state.object_fifo.push(pixel);
+                }
             }
+        }
+    }
+
+
+    /// Shifts out the next pixel of the background FIFO, mixes it with the
+    /// according object pixel, if any, and draws it into the LCD buffer.
+    fn shift_out_pixel(&mut self) {
+        let Some(fetched_pixel_background) = self.draw_state.background_fifo.pop() else {
+            return;
+        };
 
-            // set next pixel to compute
-            self.current_line_pixel += 1;
+        // discard pixels scrolled out of the screen
+        if self.draw_state.pixels_to_discard != 0 {
+            self.draw_state.pixels_to_discard -= 1;
+            return;
         }
+
+        let fetched_pixel_foreground = self.draw_state.object_fifo.pop()
+            .unwrap_or_else(PixelFetchResult::none)
+        ;
+
+        self.draw_pixel(&fetched_pixel_background, &fetched_pixel_foreground);
+
+        // set next pixel to compute
+        self.current_line_pixel += 1;
     }
 
 
-    /// Fetch the data of the background or window layer on the current position in the active scanline.
-    fn fetch_background_pixel(&self) -> PixelFetchResult {
-        let bg_enabled      = self.check_lcdc(LcdControlFlag::BackgroundAndWindowEnabled);
-        let tileset_select  = self.check_lcdc(LcdControlFlag::TileDataSelect);
-        let tileset         = TileSet::by_select_bit(tileset_select);
+    /// Resolves the color of a pixel and writes it into the LCD buffer.
+    fn draw_pixel(&mut self, fetched_pixel_background: &PixelFetchResult, fetched_pixel_foreground: &PixelFetchResult) {
+        let palette_bg       = &self.memory.palettes.bgp;
+        let palette_obp      = &self.memory.palettes.obp;
+        let palettes_gbc_bg  = &self.memory.palettes.gbc_background_palette.get();
+        let palettes_gbc_obj = &self.memory.palettes.gbc_object_palette.get();
 
         // check if the flag for window/background is enabled
         // on CGB the background is always active, but their priority
         // disabled by clearing the LCDC bit 0
-        if bg_enabled || self.device_config.is_gbc_enabled() {
-            // process window pixels instead of background, if the window was enabled for this scanline
-            let tile_info = if self.current_scanline.window_enabled {
-                let window_tilemap_select = self.check_lcdc(LcdControlFlag::WindowTileMapSelect);
-                let window_tilemap        = TileMap::by_select_bit(window_tilemap_select);
-                let position_in_window_x  = self.current_line_pixel+7 - self.registers.window_x;
-                let position_in_window_y  = self.window_line;
-
-                self.read_tilemap_properties(
-                    window_tilemap,
-                    tileset,
-                    position_in_window_x,
-                    position_in_window_y
-                )
-            }
-            else {
-                // otherwise just handle the normal background
-
-                let bg_tilemap_select = self.check_lcdc(LcdControlFlag::BackgroundTileMapSelect);
-                let bg_tilemap        = TileMap::by_select_bit(bg_tilemap_select);
-
-                let (background_x, background_y) = self.screen_to_background(
-                    self.current_line_pixel,
-                    self.current_line
-                );
-
-                self.read_tilemap_properties(
-                    bg_tilemap,
-                    tileset,
-                    background_x,
-                    background_y
-                )
-            };
+        let bg_enabled =
+                self.check_lcdc(LcdControlFlag::BackgroundAndWindowEnabled)
+            ||  self.device_config.is_gbc_enabled()
+        ;
+
+        let fetched_pixel_background = if bg_enabled { *fetched_pixel_background } else { PixelFetchResult::none() };
 
-            self.read_tile_pixel(&tile_info)
+        // objects may be disabled while already being fetched
+        let fetched_pixel_foreground = if self.check_lcdc(LcdControlFlag::SpritesEnabled) {
+            *fetched_pixel_foreground
         }
         else {
             PixelFetchResult::none()
-        }
-    }
+        };
+
+        // select palettes for background pixel
+        let pixel_background = PixelFetchResultWithPalette {
+            data: &fetched_pixel_background,
+            palette_dmg: palette_bg,
+            palette_gbc: &palettes_gbc_bg[fetched_pixel_background.palette_gbc as usize]
+        };
 
+        // in DMG compatibility mode objects use the GBC palette selected by their DMG palette bit
+        let foreground_palette_gbc = match self.device_config.emulation {
+            EmulationType::DMG => fetched_pixel_foreground.palette_dmg,
+            EmulationType::GBC => fetched_pixel_foreground.palette_gbc,
+        };
 
-    /// Fetch the foreground pixel by reading the color of any sprite on the current
-    /// position within the active scanline
-    pub fn fetch_foreground_pixel(&self) -> PixelFetchResult {
-        let sprites_enabled = self.check_lcdc(LcdControlFlag::SpritesEnabled);
+        // select palettes for foreground pixel
+        let pixel_foreground = PixelFetchResultWithPalette {
+            data: &fetched_pixel_foreground,
+            palette_dmg: &palette_obp[fetched_pixel_foreground.palette_dmg as usize],
+            palette_gbc: &palettes_gbc_obj[foreground_palette_gbc as usize]
+        };
 
-        if sprites_enabled {
-            self.read_scanline_sprite_pixel(
-                &self.current_scanline,
-                self.current_line_pixel
-            )
-        }
-        else {
-            PixelFetchResult::none()
+        // select pixel to be displayed
+        let pixel = self.mix_pixels(
+                &pixel_background,
+                &pixel_foreground
+        );
+
+        // the first frame does not draw pixels
+        if !self.is_first_frame {
+            // resolve pixel color using the according palette
+            let pixel_color = match self.device_config.emulation {
+                EmulationType::DMG => {
+                    let lcd_pixel = pixel.palette_dmg.get_color(&pixel.data.value);
+
+                    // a GBC device colorizes classic games with its compatibility palettes
+                    if self.device_config.device.has_gbc_support() {
+                        pixel.palette_gbc.get_dmg_color(&lcd_pixel)
+                    }
+                    else {
+                        *self.translate_dmg_color_index(&lcd_pixel)
+                    }
+                }
+
+                EmulationType::GBC => {
+                    pixel.palette_gbc.get_color(&pixel.data.value)
+                }
+            };
+
+            // write pixel into LCD buffer
+            self.lcd_buffer.set_pixel(
+                self.current_line_pixel as u32,
+                self.current_line as u32,
+                pixel_color
+            );
         }
     }
 
@@ -664,6 +977,8 @@ impl Ppu {
         // request interrupt when entering VBlank
         match mode {
             Mode::HBlank => {
+                self.hblank_started = true;
+
                 if self.is_interrupt_enabled(LcdInterruptFlag::InterruptByHBlank) {
                     self.request_interrupt(Interrupt::LcdStat);
                 }
@@ -757,8 +1072,9 @@ impl Ppu {
 
     /// Callback to reset data when starting a new frame
     fn on_new_frame(&mut self) {
-        self.window_line    = 0;
-        self.is_first_frame = false;
+        self.window_line        = 0;
+        self.window_y_triggered = false;
+        self.is_first_frame     = false;
     }
 
 
@@ -818,6 +1134,11 @@ impl Ppu {
         self.get_dmg_display_palette().get_color(pixel)
     }
 
+    /// Takes the flag whether the PPU entered HBlank since the last call.
+    pub fn take_hblank_started(&mut self) -> bool {
+        take(&mut self.hblank_started)
+    }
+
     /// Get the index of the line currently being drawn.
     pub fn get_current_line(&self) -> u8 {
         self.current_line
@@ -843,11 +1164,107 @@ impl Ppu {
         self.memory.oam.get_mut()
     }
 
+    /// Get the OAM table.
+    pub fn get_oam_bank(&self) -> &OamRamBank {
+        &self.memory.oam
+    }
+
     /// Get the OAM table.
     pub fn get_oam_bank_mut(&mut self) -> &mut OamRamBank {
         &mut self.memory.oam
     }
 
+    /// Checks whether the CPU is able to access VRAM.
+    /// VRAM is locked while the PPU is drawing a scanline.
+    pub fn is_vram_accessible(&self) -> bool {
+        !matches!(self.mode, Mode::DrawLine)
+    }
+
+    /// Checks whether the CPU is able to access OAM.
+    /// OAM is locked during OAM scan and while the PPU is drawing a scanline.
+    pub fn is_oam_accessible(&self) -> bool {
+        !matches!(self.mode, Mode::OamScan | Mode::DrawLine)
+    }
+
+    /// Reads from VRAM or OAM without checking whether they're currently accessible by the CPU.
+    /// Addresses outside of VRAM and OAM are read via the regular memory bus access.
+    pub fn debug_read(&self, address: u16) -> u8 {
+        match address {
+            0x8000 ..= 0x9fff => self.memory.vram_banks[self.memory.vram_active_bank as usize].get_at((address - 0x8000) as usize),
+            0xfe00 ..= 0xfe9f => self.memory.oam.get_at((address - 0xfe00) as usize),
+            _ => self.on_read(address),
+        }
+    }
+
+    /// Applies the OAM corruption bug of DMG devices, which gets triggered when
+    /// the CPU accesses OAM while the PPU is scanning it in mode 2.
+    /// The OAM row currently being read by the PPU gets overwritten with
+    /// a mix of its own data and the data of the preceding rows.
+    pub fn trigger_oam_bug(&mut self, corruption: OamCorruption) {
+        if self.device_config.device.has_gbc_support() {
+            return;
+        }
+
+        if !matches!(self.lcd_state, LcdState::On) || !matches!(self.mode, Mode::OamScan) {
+            return;
+        }
+
+        // the PPU reads one row of two objects each M-cycle,
+        // the first row never gets corrupted
+        let row = (self.clock / 4) as usize;
+        if row == 0 || row >= OAM_ROWS {
+            return;
+        }
+
+        if corruption == OamCorruption::ReadWithIncDec && (4 .. OAM_ROWS - 1).contains(&row) {
+            let a = self.read_oam_word(row - 2, 0);
+            let b = self.read_oam_word(row - 1, 0);
+            let c = self.read_oam_word(row,     0);
+            let d = self.read_oam_word(row - 1, 2);
+
+            self.write_oam_word(row - 1, 0, (b & (a | c | d)) | (a & c & d));
+            self.copy_oam_row(row - 1, row);
+            self.copy_oam_row(row - 1, row - 2);
+        }
+
+        let a = self.read_oam_word(row,     0);
+        let b = self.read_oam_word(row - 1, 0);
+        let c = self.read_oam_word(row - 1, 2);
+
+        let first_word = match corruption {
+            OamCorruption::Write => ((a ^ c) & (b ^ c)) ^ c,
+            OamCorruption::ReadWithIncDec => b | (a & c),
+        };
+
+        self.copy_oam_row(row - 1, row);
+        self.write_oam_word(row, 0, first_word);
+    }
+
+    /// Reads a 16 bit word from an OAM row.
+    fn read_oam_word(&self, row: usize, word: usize) -> u16 {
+        let address = row * OAM_ROW_SIZE + word * 2;
+        let low     = self.memory.oam.get_at(address);
+        let high    = self.memory.oam.get_at(address + 1);
+
+        to_u16(high, low)
+    }
+
+    /// Writes a 16 bit word into an OAM row.
+    fn write_oam_word(&mut self, row: usize, word: usize, value: u16) {
+        let address     = row * OAM_ROW_SIZE + word * 2;
+        let (high, low) = to_u8(value);
+        self.memory.oam.set_at(address,     low);
+        self.memory.oam.set_at(address + 1, high);
+    }
+
+    /// Copies the content of an OAM row into another one.
+    fn copy_oam_row(&mut self, from: usize, to: usize) {
+        for offset in 0 .. OAM_ROW_SIZE {
+            let value = self.memory.oam.get_at(from * OAM_ROW_SIZE + offset);
+            self.memory.oam.set_at(to * OAM_ROW_SIZE + offset, value);
+        }
+    }
+
     /// Get a VRAM memory bank by its index.
     pub fn get_vram(&self, bank: usize) -> &[u8] {
         self.memory.vram_banks[bank].as_slice()
@@ -910,7 +1327,10 @@ impl Ppu {
                 &&  ly_plus_16 >= sprite.pos_y
                 &&  ly_plus_16 < (sprite.pos_y + sprite_h)
             {
-                scanline.sprites[scanline.sprites_found as usize] = sprite;
+                scanline.sprites[scanline.sprites_found as usize] = ScanlineSprite {
+                    oam_index: oam_entry,
+                    sprite,
+                };
                 scanline.sprites_found += 1;
 
                 if scanline.sprites_found >= 10 {
@@ -928,8 +1348,8 @@ impl Ppu {
         if !self.device_config.is_gbc_enabled() || self.registers.object_priority {
             scanline.sprites[0 .. scanline.sprites_found as usize].sort_by(
                 |a, b| {
-                    let ax = a.pos_x;
-                    let bx = b.pos_x;
+                    let ax = a.sprite.pos_x;
+                    let bx = b.sprite.pos_x;
                     ax.cmp(&bx)
                 }
             );
@@ -938,70 +1358,6 @@ impl Ppu {
         scanline
     }
 
-    /// Reads a pixel from the current scanline sprite data on a given x position.
-    pub fn read_scanline_sprite_pixel(&self, scanline: &ScanlineData, x: u8) -> PixelFetchResult {
-        // screen position considering the border offset of -8 / -16
-        let screen_x = x + 8;
-        let screen_y = scanline.line + 16;
-
-        let big_sprites = self.check_lcdc(LcdControlFlag::SpritesSize);
-        let sprite_h    = if big_sprites { 16 } else { 8 };
-        let sprite_w    = 8;
-
-        // when big sprites are enabled, the top sprite always has the least significant bit
-        // set to 0, and the bottom sprite is using the same sprite number with the LSB set to 1
-        // so we're just eliminating the LSB and continue reading with sprite data behind the
-        // top sprite memory location.
-        let sprite_mask = if big_sprites { 0xfe } else { 0xff };
-
-        // iterate over all sprite previously found by the OAM scan
-        for sprite_index in 0..scanline.sprites_found {
-            let sprite = &(scanline.sprites[sprite_index as usize]);
-
-            // check if the sprite overlaps the current scanline pixel
-            if screen_x < sprite.pos_x || x >= sprite.pos_x {
-                continue;
-            }
-
-            // calculate the position inside the sprite including x and y flip
-            let sprite_pixel_x = flipped_if(screen_x - sprite.pos_x, sprite_w, sprite.is_flip_x());
-            let sprite_pixel_y = flipped_if(screen_y - sprite.pos_y, sprite_h, sprite.is_flip_y());
-
-            // if in GBC mode, check the sprite properties for the VRAM bank index
-            // where to read the pixel data from
-            let vram_bank_index = if self.device_config.is_gbc_enabled() {
-                sprite.get_gbc_vram_bank()
-            }
-            else {
-                0
-            };
-
-            // read the sprite pixel value
-            let pixel = self.read_sprite_pixel(
-                TileSet::H8000,
-                sprite.tile & sprite_mask,
-                vram_bank_index,
-                sprite_pixel_x,
-                sprite_pixel_y
-            );
-
-            // skip transparent pixels
-            if pixel.is_transparent() {
-                continue;
-            }
-
-            return PixelFetchResult {
-                value: pixel,
-                palette_dmg: sprite.get_dmg_palette(),
-                palette_gbc: sprite.get_color_palette(),
-                sprite_priority: 0,
-                background_priority: sprite.is_bg_priority(),
-            };
-        }
-
-        PixelFetchResult::none()
-    }
-
     /// Reads a single pixel from the tilemap.
     pub fn read_tilemap_pixel(&self, tilemap: TileMap, tileset: TileSet, tilemap_x: u8, tilemap_y: u8) -> PixelFetchResult {
         let tile = self.read_tilemap_properties(tilemap, tileset, tilemap_x, tilemap_y);
@@ -1118,13 +1474,23 @@ impl MemoryBusConnection for Ppu {
         memory_map!(address => {
             // Video RAM
             0x8000 ..= 0x9fff => [mapped_address] {
-                let bank = &self.memory.vram_banks[self.memory.vram_active_bank as usize];
-                bank.get_at(mapped_address)
+                if self.is_vram_accessible() {
+                    let bank = &self.memory.vram_banks[self.memory.vram_active_bank as usize];
+                    bank.get_at(mapped_address)
+                }
+                else {
+                    0xff
+                }
             },
 
             // OAM memory
             0xfe00 ..= 0xfe9f => [mapped_address] {
-                self.memory.oam.get_at(mapped_address)
+                if self.is_oam_accessible() {
+                    self.memory.oam.get_at(mapped_address)
+                }
+                else {
+                    0xff
+                }
             },
 
             // IO Registers
@@ -1197,7 +1563,7 @@ impl MemoryBusConnection for Ppu {
                     }
 
                     MEMORY_LOCATION_OPRI => {
-                        self.registers.object_priority as u8
+                        0b_1111_1110 | (self.registers.object_priority as u8)
                     }
 
                     _ => 0xff,
@@ -1210,13 +1576,17 @@ impl MemoryBusConnection for Ppu {
         memory_map!(address => {
             // Video RAM
             0x8000 ..= 0x9fff => [mapped_address] {
-                let bank = &mut self.memory.vram_banks[self.memory.vram_active_bank as usize];
-                bank.set_at(mapped_address, value)
+                if self.is_vram_accessible() {
+                    let bank = &mut self.memory.vram_banks[self.memory.vram_active_bank as usize];
+                    bank.set_at(mapped_address, value)
+                }
             },
 
             // OAM memory
             0xfe00 ..= 0xfe9f => [mapped_address] {
-                self.memory.oam.set_at(mapped_address, value)
+                if self.is_oam_accessible() {
+                    self.memory.oam.set_at(mapped_address, value)
+                }
             },
 
             // IO registers
@@ -1238,7 +1608,7 @@ impl MemoryBusConnection for Ppu {
                     MEMORY_LOCATION_LCD_STATUS      => self.registers.lcd_interrupts    = LcdInterruptFlags::new_truncated(value),
                     MEMORY_LOCATION_SCY             => self.registers.scroll_y          = value,
                     MEMORY_LOCATION_SCX             => self.registers.scroll_x          = value,
-                    MEMORY_LOCATION_LY              => self.current_line                = value,
+                    MEMORY_LOCATION_LY              => { /* read-only */ },
                     MEMORY_LOCATION_LYC             => self.registers.line_compare      = value,
                     MEMORY_LOCATION_WY              => self.registers.window_y          = value,
                     MEMORY_LOCATION_WX              => self.registers.window_x          = value,
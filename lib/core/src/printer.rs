@@ -0,0 +1,519 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::mem::take;
+use std::sync::{Arc, Mutex};
+
+use crate::ppu::graphic_data::{Color, DmgDisplayPalette};
+use crate::ppu::ppu::SCREEN_W;
+use crate::serial::SerialLinkEndpoint;
+use crate::utils::{as_bit_flag, get_bit, to_u16};
+
+/// The first byte of each packet sent to the printer.
+const MAGIC_BYTE_1:                     u8 = 0x88;
+
+/// The second byte of each packet sent to the printer.
+const MAGIC_BYTE_2:                     u8 = 0x33;
+
+/// The value sent by the printer after receiving the checksum of a packet.
+const PRINTER_ALIVE:                    u8 = 0x81;
+
+/// Command to initialize the printer and clear its image buffer.
+const COMMAND_INITIALIZE:               u8 = 0x01;
+
+/// Command to start printing the content of the image buffer.
+const COMMAND_PRINT:                    u8 = 0x02;
+
+/// Command to append data to the image buffer.
+const COMMAND_DATA:                     u8 = 0x04;
+
+/// Command to query the status of the printer.
+const COMMAND_STATUS:                   u8 = 0x0f;
+
+/// Status flag: the checksum of the last packet did not match.
+pub const STATUS_CHECKSUM_ERROR:        u8 = 0x01;
+
+/// Status flag: the printer is currently printing.
+pub const STATUS_PRINTER_BUSY:          u8 = 0x02;
+
+/// Status flag: the image buffer is full.
+pub const STATUS_IMAGE_DATA_FULL:       u8 = 0x04;
+
+/// Status flag: the image buffer contains data, which was not printed yet.
+pub const STATUS_UNPROCESSED_DATA:      u8 = 0x08;
+
+/// The number of tiles in each row of the printed image.
+const TILES_PER_ROW:                    usize = 20;
+
+/// The number of bytes of a single 2bpp tile.
+const BYTES_PER_TILE:                   usize = 16;
+
+/// The number of bytes of a single band of 160x16 pixels, which is the
+/// maximum amount of data sent with a single data packet.
+const BYTES_PER_BAND:                   usize = TILES_PER_ROW * BYTES_PER_TILE * 2;
+
+/// The size of the printer's image buffer, which can store up to 9 bands.
+const IMAGE_BUFFER_SIZE:                usize = BYTES_PER_BAND * 9;
+
+/// The number of pixel rows the paper gets fed for each unit of a margin.
+const PIXEL_ROWS_PER_MARGIN_UNIT:       usize = 16;
+
+/// The number of status requests the printer remains busy after a print command.
+const STATUS_REQUESTS_WHILE_BUSY:       u8 = 4;
+
+
+/// The state of the printer while receiving a packet.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum PacketState {
+    /// Waiting for the first magic byte.
+    MagicByte1,
+
+    /// Waiting for the second magic byte.
+    MagicByte2,
+
+    /// Receiving the command byte.
+    Command,
+
+    /// Receiving the flag whether the data is compressed.
+    Compression,
+
+    /// Receiving the lower byte of the data length.
+    LengthLow,
+
+    /// Receiving the upper byte of the data length.
+    LengthHigh,
+
+    /// Receiving the packet data.
+    Data,
+
+    /// Receiving the lower byte of the checksum.
+    ChecksumLow,
+
+    /// Receiving the upper byte of the checksum.
+    ChecksumHigh,
+
+    /// Receiving the first byte after the packet, while sending the 'alive' byte.
+    Alive,
+
+    /// Receiving the second byte after the packet, while sending the printer status.
+    Status,
+}
+
+
+/// An image printed by the [Printer], which is always 160 pixels wide.
+pub struct PrintedImage {
+    /// The height of the image in pixels.
+    height: u32,
+
+    /// The pixels of the image row by row.
+    pixels: Vec<Color>,
+}
+
+
+/// The internal state of the printer, shared by all handles of a [Printer].
+struct PrinterDevice {
+    /// The byte currently being received.
+    byte_received: u8,
+
+    /// The number of bits received of the current byte.
+    bits_received: u8,
+
+    /// The byte currently being sent back.
+    byte_to_send: u8,
+
+    /// The state of the packet currently being received.
+    packet_state: PacketState,
+
+    /// The command of the current packet.
+    command: u8,
+
+    /// Whether the data of the current packet is compressed.
+    compressed: bool,
+
+    /// The length of the data of the current packet.
+    length: u16,
+
+    /// The data of the current packet.
+    data: Vec<u8>,
+
+    /// The checksum calculated from the bytes received.
+    checksum: u16,
+
+    /// The checksum sent with the current packet.
+    checksum_received: u16,
+
+    /// The status flags reported to the GameBoy.
+    status: u8,
+
+    /// The number of status requests until the printer finishes printing.
+    busy_counter: u8,
+
+    /// The tile data received to be printed.
+    image_buffer: Vec<u8>,
+
+    /// The palette to convert the printed shades into colors.
+    display_palette: DmgDisplayPalette,
+
+    /// The pixels printed on the paper, which was not yet fed out of the printer.
+    paper: Vec<Color>,
+
+    /// A list of all finished printouts.
+    printouts: Vec<PrintedImage>,
+}
+
+
+/// An emulation of the GameBoy Printer, which can be connected to the [crate::serial::SerialPort].
+/// The printer receives packets from the GameBoy, collects the image data sent
+/// and creates a [PrintedImage] each time the paper gets fed out after printing.
+///
+/// Any clone of the printer refers to the same device, so one handle can be connected
+/// to the serial port, while another one is used to collect the printouts.
+#[derive(Clone)]
+pub struct Printer {
+    device: Arc<Mutex<PrinterDevice>>,
+}
+
+
+impl PrintedImage {
+    /// Get the width of the image in pixels.
+    pub fn get_width(&self) -> u32 {
+        SCREEN_W
+    }
+
+    /// Get the height of the image in pixels.
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the value of a specific pixel.
+    pub fn get_pixel(&self, x: u32, y: u32) -> &Color {
+        &self.pixels[(x + y * SCREEN_W) as usize]
+    }
+
+    /// Get all pixels of the image row by row.
+    pub fn get_pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+}
+
+
+impl PrinterDevice {
+    /// Processes a single byte received from the GameBoy and
+    /// selects the byte to be sent back with the next transfer.
+    fn on_byte_received(&mut self, byte: u8) {
+        let mut byte_to_send = 0x00;
+
+        self.packet_state = match self.packet_state {
+            PacketState::MagicByte1 => {
+                if byte == MAGIC_BYTE_1 { PacketState::MagicByte2 } else { PacketState::MagicByte1 }
+            }
+
+            PacketState::MagicByte2 => {
+                match byte {
+                    MAGIC_BYTE_2 => {
+                        self.checksum = 0;
+                        self.data.clear();
+                        PacketState::Command
+                    }
+
+                    MAGIC_BYTE_1 => PacketState::MagicByte2,
+                    _            => PacketState::MagicByte1,
+                }
+            }
+
+            PacketState::Command => {
+                self.command   = byte;
+                self.checksum  = self.checksum.wrapping_add(byte as u16);
+                PacketState::Compression
+            }
+
+            PacketState::Compression => {
+                self.compressed = get_bit(byte, 0);
+                self.checksum   = self.checksum.wrapping_add(byte as u16);
+                PacketState::LengthLow
+            }
+
+            PacketState::LengthLow => {
+                self.length   = byte as u16;
+                self.checksum = self.checksum.wrapping_add(byte as u16);
+                PacketState::LengthHigh
+            }
+
+            PacketState::LengthHigh => {
+                self.length   = to_u16(byte, self.length as u8);
+                self.checksum = self.checksum.wrapping_add(byte as u16);
+
+                if self.length == 0 { PacketState::ChecksumLow } else { PacketState::Data }
+            }
+
+            PacketState::Data => {
+                self.data.push(byte);
+                self.checksum = self.checksum.wrapping_add(byte as u16);
+
+                if self.data.len() >= self.length as usize { PacketState::ChecksumLow } else { PacketState::Data }
+            }
+
+            PacketState::ChecksumLow => {
+                self.checksum_received = byte as u16;
+                PacketState::ChecksumHigh
+            }
+
+            PacketState::ChecksumHigh => {
+                self.checksum_received = to_u16(byte, self.checksum_received as u8);
+                byte_to_send = PRINTER_ALIVE;
+
+                if self.checksum_received == self.checksum {
+                    self.status &= !STATUS_CHECKSUM_ERROR;
+                    self.execute_command();
+                }
+                else {
+                    self.status |= STATUS_CHECKSUM_ERROR;
+                }
+
+                PacketState::Alive
+            }
+
+            PacketState::Alive => {
+                byte_to_send = self.status;
+                PacketState::Status
+            }
+
+            PacketState::Status => {
+                PacketState::MagicByte1
+            }
+        };
+
+        self.byte_to_send = byte_to_send;
+    }
+
+
+    /// Executes the command of a packet after it was received completely.
+    fn execute_command(&mut self) {
+        match self.command {
+            COMMAND_INITIALIZE => {
+                self.image_buffer.clear();
+                self.status       = 0x00;
+                self.busy_counter = 0;
+            }
+
+            COMMAND_PRINT if self.data.len() >= 4 => {
+                self.print(self.data[0], self.data[1], self.data[2]);
+            }
+
+            COMMAND_DATA => {
+                let data = take(&mut self.data);
+                self.append_image_data(&data);
+            }
+
+            COMMAND_STATUS if self.busy_counter > 0 => {
+                self.busy_counter -= 1;
+
+                if self.busy_counter == 0 {
+                    self.status &= !(STATUS_PRINTER_BUSY | STATUS_IMAGE_DATA_FULL);
+                }
+            }
+
+            _ => { }
+        }
+    }
+
+
+    /// Appends the data of a data packet into the image buffer.
+    /// Compressed data uses a run length encoding, where each block starts with a control byte.
+    /// If bit #7 is set, the following byte is repeated (n & 0x7f) + 2 times,
+    /// otherwise the following (n + 1) bytes are copied.
+    fn append_image_data(&mut self, data: &[u8]) {
+        if self.compressed {
+            let mut index = 0;
+
+            while index < data.len() {
+                let control = data[index];
+                index += 1;
+
+                if get_bit(control, 7) {
+                    let count = (control & 0x7f) as usize + 2;
+                    let value = data.get(index).copied().unwrap_or(0x00);
+                    self.image_buffer.resize(self.image_buffer.len() + count, value);
+                    index += 1;
+                }
+                else {
+                    let end = usize::min(index + control as usize + 1, data.len());
+                    self.image_buffer.extend_from_slice(&data[index .. end]);
+                    index = end;
+                }
+            }
+        }
+        else {
+            self.image_buffer.extend_from_slice(data);
+        }
+
+        self.image_buffer.truncate(IMAGE_BUFFER_SIZE);
+
+        if !self.image_buffer.is_empty() {
+            self.status |= STATUS_UNPROCESSED_DATA;
+        }
+
+        if self.image_buffer.len() >= IMAGE_BUFFER_SIZE {
+            self.status |= STATUS_IMAGE_DATA_FULL;
+        }
+    }
+
+
+    /// Prints the content of the image buffer.
+    /// The upper nibble of the margins contains the number of units fed before printing,
+    /// the lower nibble the number of units fed after printing, which also finishes the printout.
+    fn print(&mut self, sheets: u8, margins: u8, palette: u8) {
+        // a palette of 0x00 is treated like the default palette
+        let palette        = if palette == 0x00 { 0b_11_10_01_00 } else { palette };
+        let margin_before  = (margins >> 4) as usize;
+        let margin_after   = (margins & 0x0f) as usize;
+
+        self.feed_paper(margin_before * PIXEL_ROWS_PER_MARGIN_UNIT);
+
+        for _ in 0 .. sheets {
+            self.print_image_buffer(palette);
+        }
+
+        self.feed_paper(margin_after * PIXEL_ROWS_PER_MARGIN_UNIT);
+
+        if margin_after > 0 {
+            self.finish_printout();
+        }
+
+        self.image_buffer.clear();
+        self.status       = (self.status & !STATUS_UNPROCESSED_DATA) | STATUS_PRINTER_BUSY | STATUS_IMAGE_DATA_FULL;
+        self.busy_counter = STATUS_REQUESTS_WHILE_BUSY;
+    }
+
+
+    /// Decodes the 2bpp tiles in the image buffer and prints them on the paper.
+    fn print_image_buffer(&mut self, palette: u8) {
+        let tile_rows = self.image_buffer.len() / (TILES_PER_ROW * BYTES_PER_TILE);
+        let colors    = *self.display_palette.get_colors();
+
+        for tile_row in 0 .. tile_rows {
+            for line in 0 .. 8 {
+                for tile_column in 0 .. TILES_PER_ROW {
+                    let tile_address = (tile_row * TILES_PER_ROW + tile_column) * BYTES_PER_TILE;
+                    let low          = self.image_buffer[tile_address + line * 2];
+                    let high         = self.image_buffer[tile_address + line * 2 + 1];
+
+                    for bit in (0 .. 8).rev() {
+                        let value = as_bit_flag(get_bit(high, bit), 1) | as_bit_flag(get_bit(low, bit), 0);
+                        let shade = (palette >> (value * 2)) & 0x03;
+                        self.paper.push(colors[shade as usize]);
+                    }
+                }
+            }
+        }
+    }
+
+
+    /// Feeds a number of blank pixel rows.
+    fn feed_paper(&mut self, rows: usize) {
+        let blank = self.display_palette.get_colors()[0];
+        self.paper.resize(self.paper.len() + rows * SCREEN_W as usize, blank);
+    }
+
+
+    /// Stores the current paper as a finished printout.
+    fn finish_printout(&mut self) {
+        if !self.paper.is_empty() {
+            let pixels = take(&mut self.paper);
+
+            self.printouts.push(PrintedImage {
+                height: (pixels.len() / SCREEN_W as usize) as u32,
+                pixels,
+            });
+        }
+    }
+}
+
+
+impl Printer {
+    /// Sets the palette used to convert printed shades into colors.
+    pub fn set_display_palette(&self, palette: DmgDisplayPalette) {
+        self.lock().display_palette = palette;
+    }
+
+    /// Get the current status flags of the printer.
+    pub fn get_status(&self) -> u8 {
+        self.lock().status
+    }
+
+    /// Finishes the current printout, if the paper was not fed out yet.
+    /// This may be used to get images printed by games which don't use margins.
+    pub fn tear_off(&self) {
+        self.lock().finish_printout();
+    }
+
+    /// Takes all finished printouts.
+    pub fn take_printouts(&self) -> Vec<PrintedImage> {
+        take(&mut self.lock().printouts)
+    }
+
+    /// Get access to the internal state of the printer.
+    fn lock(&self) -> std::sync::MutexGuard<'_, PrinterDevice> {
+        self.device.lock().unwrap()
+    }
+}
+
+
+impl Default for Printer {
+    /// Creates a new printer without any paper printed.
+    fn default() -> Self {
+        Self {
+            device: Arc::new(Mutex::new(PrinterDevice {
+                byte_received:      0x00,
+                bits_received:      0,
+                byte_to_send:       0x00,
+                packet_state:       PacketState::MagicByte1,
+                command:            0x00,
+                compressed:         false,
+                length:             0,
+                data:               Vec::new(),
+                checksum:           0,
+                checksum_received:  0,
+                status:             0x00,
+                busy_counter:       0,
+                image_buffer:       Vec::with_capacity(IMAGE_BUFFER_SIZE),
+                display_palette:    DmgDisplayPalette::new_gray(),
+                paper:              Vec::new(),
+                printouts:          Vec::new(),
+            })),
+        }
+    }
+}
+
+
+impl SerialLinkEndpoint for Printer {
+    fn exchange_bit(&mut self, bit: bool) -> bool {
+        let mut device = self.lock();
+
+        let bit_out           = get_bit(device.byte_to_send, 7 - device.bits_received);
+        device.byte_received  = (device.byte_received << 1) | as_bit_flag(bit, 0);
+        device.bits_received += 1;
+
+        if device.bits_received == 8 {
+            let byte = device.byte_received;
+            device.bits_received = 0;
+            device.on_byte_received(byte);
+        }
+
+        bit_out
+    }
+}
@@ -0,0 +1,353 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+
+#[cfg(feature = "snapshots")]
+pub use with_rewind::*;
+
+#[cfg(feature = "snapshots")]
+mod with_rewind {
+    use crate::gameboy::{Clock, GameBoy};
+    use crate::snapshots::{decode_state, encode_state, SnapshotError};
+    use crate::utils::with_compression_disabled;
+    use std::collections::VecDeque;
+
+    /// The compression level used to compress keyframes.
+    const KEYFRAME_COMPRESSION_LEVEL: i32 = 1;
+
+    /// The compression level used to compress the delta of each state.
+    const DELTA_COMPRESSION_LEVEL: i32 = 1;
+
+
+    /// Configures how many states are stored by a [RewindBuffer].
+    #[derive(Copy, Clone, Debug)]
+    pub struct RewindConfig {
+        /// The number of frames between two captured states.
+        pub frames_per_state: u32,
+
+        /// The maximum number of states being stored.
+        /// When exceeded, the oldest states will be dropped.
+        pub capacity: usize,
+
+        /// The number of states being delta encoded against the same keyframe.
+        pub states_per_keyframe: usize,
+    }
+
+
+    /// A single state captured by the rewind buffer, which is stored
+    /// as the compressed difference against the keyframe of its group.
+    struct RewindState {
+        /// The number of cycles processed when the state was captured.
+        cycles: Clock,
+
+        /// The size of the uncompressed state.
+        size: usize,
+
+        /// The state compressed with the keyframe as dictionary, so only
+        /// the data which differs from the keyframe needs to be stored.
+        delta: Vec<u8>,
+    }
+
+
+    /// A group of states sharing the same keyframe.
+    struct KeyframeGroup {
+        /// The compressed full state all states of this group are compared against.
+        keyframe: Vec<u8>,
+
+        /// The size of the uncompressed keyframe.
+        keyframe_size: usize,
+
+        /// All states of this group, ordered from oldest to newest.
+        states: VecDeque<RewindState>,
+
+        /// The number of states added to this group, including states
+        /// which were already dropped because of the buffer's capacity.
+        states_added: usize,
+    }
+
+
+    /// A bounded ring buffer of emulator states to step back in time.
+    /// States are captured every N frames and delta encoded against a
+    /// keyframe to keep the memory usage low.
+    pub struct RewindBuffer {
+        /// The configuration of this buffer.
+        config: RewindConfig,
+
+        /// The number of frames remaining until the next state will be captured.
+        frames_until_capture: u32,
+
+        /// All keyframe groups, ordered from oldest to newest.
+        groups: VecDeque<KeyframeGroup>,
+
+        /// The uncompressed keyframe of the newest group, if already known.
+        current_keyframe: Option<Vec<u8>>,
+
+        /// The total number of states stored in all groups.
+        len: usize,
+    }
+
+
+    impl Default for RewindConfig {
+        fn default() -> Self {
+            Self {
+                frames_per_state:       2,
+                capacity:               600,
+                states_per_keyframe:    60,
+            }
+        }
+    }
+
+
+    impl Default for RewindBuffer {
+        fn default() -> Self {
+            Self::new(RewindConfig::default())
+        }
+    }
+
+
+    impl RewindBuffer {
+        /// Creates a new empty rewind buffer with the given configuration.
+        pub fn new(config: RewindConfig) -> Self {
+            Self {
+                config,
+                frames_until_capture:   0,
+                groups:                 VecDeque::new(),
+                current_keyframe:       None,
+                len:                    0,
+            }
+        }
+
+
+        /// Get the configuration of this buffer.
+        pub fn get_config(&self) -> &RewindConfig {
+            &self.config
+        }
+
+
+        /// Get the number of states currently stored.
+        pub fn len(&self) -> usize {
+            self.len
+        }
+
+
+        /// Checks whether there are any states stored.
+        pub fn is_empty(&self) -> bool {
+            self.len == 0
+        }
+
+
+        /// Removes all stored states.
+        pub fn clear(&mut self) {
+            self.groups.clear();
+            self.current_keyframe     = None;
+            self.len                  = 0;
+            self.frames_until_capture = 0;
+        }
+
+
+        /// Get the number of bytes used to store all states.
+        pub fn get_memory_usage(&self) -> usize {
+            self.groups.iter()
+                .map(|group| group.keyframe.len() + group.states.iter().map(|state| state.delta.len()).sum::<usize>())
+                .sum()
+        }
+
+
+        /// Get the cycle counts of the oldest and the newest state stored, if any.
+        pub fn get_cycles_range(&self) -> Option<(Clock, Clock)> {
+            let oldest = self.groups.front()?.states.front()?;
+            let newest = self.groups.back()?.states.back()?;
+
+            Some((oldest.cycles, newest.cycles))
+        }
+
+
+        /// To be called after each frame completed by the emulator.
+        /// Captures the emulator's state every N frames, as configured.
+        /// Returns whether a state was captured.
+        pub fn on_frame_completed(&mut self, gb: &GameBoy) -> Result<bool, SnapshotError> {
+            if self.frames_until_capture > 1 {
+                self.frames_until_capture -= 1;
+                return Ok(false);
+            }
+
+            self.capture(gb)?;
+            self.frames_until_capture = self.config.frames_per_state;
+
+            Ok(true)
+        }
+
+
+        /// Captures the current state of the emulator.
+        pub fn capture(&mut self, gb: &GameBoy) -> Result<(), SnapshotError> {
+            // serialize without compression, so the data can be compared against the keyframe
+            let state = with_compression_disabled(|| encode_state(gb))?;
+
+            // start a new group, when the current one is full
+            let needs_keyframe = match self.groups.back() {
+                Some(group) => group.states_added >= self.config.states_per_keyframe,
+                None        => true,
+            };
+
+            if needs_keyframe {
+                self.groups.push_back(KeyframeGroup {
+                    keyframe:       zstd::bulk::compress(&state, KEYFRAME_COMPRESSION_LEVEL)?,
+                    keyframe_size:  state.len(),
+                    states:         VecDeque::new(),
+                    states_added:   0,
+                });
+
+                self.current_keyframe = Some(state.clone());
+            }
+
+            if let Some(group) = self.groups.back_mut() {
+                let keyframe = match self.current_keyframe.take() {
+                    Some(keyframe) => keyframe,
+                    None           => decompress_keyframe(group)?,
+                };
+
+                let delta = zstd::bulk::Compressor::with_dictionary(DELTA_COMPRESSION_LEVEL, &keyframe)?
+                    .compress(&state)?
+                ;
+
+                group.states.push_back(RewindState {
+                    cycles: gb.get_total_cycles_processed(),
+                    size:   state.len(),
+                    delta,
+                });
+
+                group.states_added    += 1;
+                self.len              += 1;
+                self.current_keyframe  = Some(keyframe);
+            }
+
+            // drop the oldest states when exceeding the capacity
+            while self.len > self.config.capacity {
+                self.drop_oldest_state();
+            }
+
+            Ok(())
+        }
+
+
+        /// Removes the newest state from the buffer and restores it.
+        /// Returns `None` if the buffer is empty.
+        pub fn step_back(&mut self) -> Result<Option<GameBoy>, SnapshotError> {
+            let group = match self.groups.back_mut() {
+                Some(group) => group,
+                None        => return Ok(None),
+            };
+
+            let result = match group.states.pop_back() {
+                Some(state) => Some(restore_state(group, &state)?),
+                None        => None,
+            };
+
+            group.states_added = group.states_added.saturating_sub(1);
+            self.len          -= result.is_some() as usize;
+
+            if group.states.is_empty() {
+                self.groups.pop_back();
+                self.current_keyframe = None;
+            }
+
+            // start counting frames from the restored state
+            self.frames_until_capture = self.config.frames_per_state;
+
+            Ok(result)
+        }
+
+
+        /// Restores the emulator state at a given cycle count.
+        /// This restores the newest state captured before the requested cycle count and
+        /// runs the emulator until reaching it. All states captured after the requested
+        /// cycle count will be removed. Returns `None` if there's no state old enough.
+        pub fn seek_to_cycles(&mut self, cycles: Clock) -> Result<Option<GameBoy>, SnapshotError> {
+            match self.get_cycles_range() {
+                Some((oldest, _)) if oldest <= cycles => { }
+                _ => return Ok(None),
+            }
+
+            // remove all states newer than the requested cycle count
+            while let Some(group) = self.groups.back_mut() {
+                while group.states.back().is_some_and(|state| state.cycles > cycles) {
+                    group.states.pop_back();
+                    group.states_added = group.states_added.saturating_sub(1);
+                    self.len -= 1;
+                }
+
+                if !group.states.is_empty() {
+                    break;
+                }
+
+                self.groups.pop_back();
+                self.current_keyframe = None;
+            }
+
+            let group = match self.groups.back() {
+                Some(group) => group,
+                None        => return Ok(None),
+            };
+
+            let state  = group.states.back().unwrap();
+            let mut gb = restore_state(group, state)?;
+
+            while gb.get_total_cycles_processed() < cycles {
+                gb.run_single_step();
+            }
+
+            self.frames_until_capture = self.config.frames_per_state;
+
+            Ok(Some(gb))
+        }
+
+
+        /// Drops the oldest state and its keyframe, if no longer needed.
+        fn drop_oldest_state(&mut self) {
+            if let Some(group) = self.groups.front_mut() {
+                if group.states.pop_front().is_some() {
+                    self.len -= 1;
+                }
+
+                if group.states.is_empty() {
+                    self.groups.pop_front();
+
+                    if self.groups.is_empty() {
+                        self.current_keyframe = None;
+                    }
+                }
+            }
+        }
+    }
+
+
+    /// Decompresses the keyframe of a group.
+    fn decompress_keyframe(group: &KeyframeGroup) -> Result<Vec<u8>, SnapshotError> {
+        Ok(zstd::bulk::decompress(&group.keyframe, group.keyframe_size)?)
+    }
+
+
+    /// Restores an emulator instance from a state stored as delta against the keyframe of its group.
+    fn restore_state(group: &KeyframeGroup, state: &RewindState) -> Result<GameBoy, SnapshotError> {
+        let keyframe = decompress_keyframe(group)?;
+        let data     = zstd::bulk::Decompressor::with_dictionary(&keyframe)?
+            .decompress(&state.delta, state.size)?
+        ;
+
+        with_compression_disabled(|| decode_state(&data))
+    }
+}
@@ -15,28 +15,68 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
+use std::collections::VecDeque;
 use std::mem::take;
+use std::sync::{Arc, Mutex};
 
 use crate::cpu::interrupts::Interrupt;
-use crate::gameboy::Clock;
+use crate::debug::DebugEvent;
+use crate::gameboy::{Clock, DeviceConfig, EmulationType, EmulatorUpdateResults, GameBoy};
 use crate::mmu::locations::{MEMORY_LOCATION_SB, MEMORY_LOCATION_SC};
 use crate::mmu::memory_bus::{MemoryBusConnection, MemoryBusSignals};
+use crate::ppu::ppu::CPU_CYCLES_PER_FRAME;
 use crate::utils::{as_bit_flag, get_bit};
 
-const UPDATE_TIME_SERIAL_TRANSFER:      Clock = 4096;
+/// The number of CPU cycles to transfer a single bit using the internal clock with 8192 Hz.
+const CPU_CYCLES_PER_BIT_NORMAL:        Clock = 512;
+
+/// The number of CPU cycles to transfer a single bit using the fast internal clock
+/// with 262144 Hz, which is only available on GameBoy Color.
+const CPU_CYCLES_PER_BIT_FAST:          Clock = 16;
+
+/// The number of bits in a single transfer.
+const BITS_PER_TRANSFER:                u8 = 8;
+
+
+/// The remote end of a link cable connected to the serial port.
+///
+/// A device using the internal clock drives the transfer by calling [SerialLinkEndpoint::exchange_bit]
+/// for each bit, while a device using the external clock waits for clock pulses
+/// of the remote device, which are polled via [SerialLinkEndpoint::poll_external_clock].
+pub trait SerialLinkEndpoint : Send {
+    /// Called when the local device generates a clock pulse and shifts out a single bit.
+    /// Returns the bit shifted out by the remote device in exchange.
+    /// If no remote device is listening, the line stays high.
+    fn exchange_bit(&mut self, bit: bool) -> bool;
+
+    /// Polls the next clock pulse generated by the remote device, while the local
+    /// device is waiting for a transfer using the external clock.
+    /// Returns the bit sent by the remote device, if any clock pulse is pending.
+    fn poll_external_clock(&mut self) -> Option<bool> {
+        None
+    }
+
+    /// Notifies the remote end about the current state of the local device.
+    /// This provides the content of the local shift register and the number of bits
+    /// the local device is still waiting for when using the external clock.
+    fn set_local_state(&mut self, _shift_register: u8, _bits_pending: u8) {
+    }
+}
 
 
 /// An implementation of the GameBoy's serial port.
-/// This is a simplified implementation with the main
-/// purpose of receiving data from test ROMs like Blargg's.
-/// It currently does not provide precise timing, receiving
-/// input or double speed mode.
+/// Data is transferred bit by bit, either driven by the internal clock
+/// or by clock pulses of a remote device connected via [SerialLinkEndpoint].
+/// Without any remote device connected, all bits received will be 1.
 ///
 /// The output queue is disabled by default and needs to be
 /// enabled in order to store data sent.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SerialPort {
-    /// The SerialPort's clock to measure time between the transfer of each byte.
+    /// Whether GameBoy Color features like the fast clock speed are available.
+    gbc_enabled: bool,
+
+    /// The SerialPort's clock to measure time between the transfer of each bit.
     clock: Clock,
 
     /// Pending output to be sent back through the memory bus.
@@ -45,51 +85,183 @@ pub struct SerialPort {
     /// The flag written by SC register to enable or disable serial data transfer.
     transfer_enabled: bool,
 
-    /// The current byte written via SB register to be transferred.
+    /// Whether the transfer is driven by the internal clock (SC bit #0).
+    internal_clock: bool,
+
+    /// Whether the fast clock speed is selected (SC bit #1, GBC only).
+    fast_clock: bool,
+
+    /// The shift register accessible via SB register.
+    /// Bits are shifted out starting with the most significant bit,
+    /// while received bits are shifted in at the lowest bit.
     transfer_byte: u8,
 
+    /// The byte being sent with the current transfer.
+    sent_byte: u8,
+
+    /// The number of bits already transferred in the current transfer.
+    bits_transferred: u8,
+
     /// A queue of all bytes sent by the device.
     output_queue: Vec<u8>,
 
     /// A flag to enable or disable the output queue.
     output_queue_enabled: bool,
+
+    /// The remote end of the link cable, if any.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    link: Option<Box<dyn SerialLinkEndpoint>>,
+}
+
+
+/// The state of one end of an in-process link cable.
+#[derive(Default)]
+struct LinkWirePort {
+    /// The shift register of the device connected to this end.
+    shift_register: u8,
+
+    /// The number of bits the device is still waiting for using the external clock.
+    bits_pending: u8,
+
+    /// Clock pulses generated by the remote device, not yet processed by the local device.
+    pulses: VecDeque<bool>,
+}
+
+
+/// One end of an in-process link cable, which connects to the serial port of a [GameBoy].
+/// Both ends share a common state, so the instance driving the clock is able to exchange
+/// data with the other instance, which processes the clock pulses received on its next update.
+pub struct LinkCableEndpoint {
+    /// The state of both ends of the cable.
+    wire: Arc<Mutex<[LinkWirePort; 2]>>,
+
+    /// The index of the end this endpoint is connected to.
+    side: usize,
+}
+
+
+/// A link cable connecting two [GameBoy] instances running in the same process.
+/// Both devices are run in lockstep, so data can be exchanged at the same time
+/// it would be on real hardware.
+pub struct LinkCable {
+    devices: [GameBoy; 2],
 }
 
 
 impl SerialPort {
     /// Constructs a new instance of the SerialPort.
-    pub fn new() -> SerialPort {
+    pub fn new(device_config: DeviceConfig) -> SerialPort {
         SerialPort {
+            gbc_enabled:            matches!(device_config.emulation, EmulationType::GBC),
             clock:                  0,
             signals:                MemoryBusSignals::default(),
             transfer_enabled:       false,
+            internal_clock:         false,
+            fast_clock:             false,
             transfer_byte:          0x00,
+            sent_byte:              0x00,
+            bits_transferred:       0,
             output_queue:           vec![],
             output_queue_enabled:   false,
+            link:                   None,
         }
     }
 
 
+    /// Switches the serial port of a GameBoy Color into DMG compatibility mode,
+    /// which disables the fast clock.
+    pub fn enter_dmg_compatibility_mode(&mut self) {
+        self.gbc_enabled = false;
+        self.fast_clock  = false;
+    }
+
+
     /// Updates the SerialPort, perform data transfer if any data is pending.
     pub fn update(&mut self, cycles: Clock) {
-        self.clock += cycles;
+        if !self.transfer_enabled {
+            return;
+        }
 
-        if self.clock >= UPDATE_TIME_SERIAL_TRANSFER {
-            if self.transfer_enabled {
-                // store the data only if the output queue is enabled
-                if self.output_queue_enabled {
-                    self.output_queue.push(self.transfer_byte);
+        if self.internal_clock {
+            let cycles_per_bit = if self.fast_clock {
+                CPU_CYCLES_PER_BIT_FAST
+            }
+            else {
+                CPU_CYCLES_PER_BIT_NORMAL
+            };
+
+            self.clock += cycles;
+
+            while self.transfer_enabled && self.clock >= cycles_per_bit {
+                self.clock -= cycles_per_bit;
+
+                let bit_out = get_bit(self.transfer_byte, 7);
+                let bit_in  = match self.link {
+                    Some(ref mut link) => link.exchange_bit(bit_out),
+                    None => true,
+                };
+
+                self.shift_bit(bit_in);
+            }
+        }
+        else {
+            while self.transfer_enabled {
+                let bit_in = match self.link {
+                    Some(ref mut link) => link.poll_external_clock(),
+                    None => None,
+                };
+
+                match bit_in {
+                    Some(bit_in) => self.shift_bit(bit_in),
+                    None => break,
                 }
+            }
+        }
+    }
 
-                // after transfer completion, disable the transfer status bit
-                self.transfer_enabled = false;
 
-                // ..  and raise serial transfer interrupt
-                self.request_interrupt(Interrupt::Serial);
+    /// Shifts a single bit into the shift register and completes
+    /// the transfer after all bits were transferred.
+    fn shift_bit(&mut self, bit_in: bool) {
+        self.transfer_byte     = (self.transfer_byte << 1) | as_bit_flag(bit_in, 0);
+        self.bits_transferred += 1;
+
+        if self.bits_transferred >= BITS_PER_TRANSFER {
+            // store the data only if the output queue is enabled
+            if self.output_queue_enabled {
+                self.output_queue.push(self.sent_byte);
             }
 
+            // after transfer completion, disable the transfer status bit
+            self.transfer_enabled = false;
 
-            self.clock -= UPDATE_TIME_SERIAL_TRANSFER;
+            // ..  and raise serial transfer interrupt
+            self.request_interrupt(Interrupt::Serial);
+        }
+
+        self.update_link_state();
+    }
+
+
+    /// Starts a new transfer after the transfer flag was set in SC register.
+    fn start_transfer(&mut self) {
+        self.clock            = 0;
+        self.sent_byte        = self.transfer_byte;
+        self.bits_transferred = 0;
+    }
+
+
+    /// Provides the current state of the shift register to the remote end of the link cable.
+    fn update_link_state(&mut self) {
+        let bits_pending = if self.transfer_enabled && !self.internal_clock {
+            BITS_PER_TRANSFER - self.bits_transferred
+        }
+        else {
+            0
+        };
+
+        if let Some(ref mut link) = self.link {
+            link.set_local_state(self.transfer_byte, bits_pending);
         }
     }
 
@@ -100,6 +272,26 @@ impl SerialPort {
     }
 
 
+    /// Connects a remote device to the serial port.
+    /// Any previously connected device will be disconnected.
+    pub fn connect(&mut self, link: Box<dyn SerialLinkEndpoint>) {
+        self.link = Some(link);
+        self.update_link_state();
+    }
+
+
+    /// Disconnects the remote device currently connected, if any.
+    pub fn disconnect(&mut self) -> Option<Box<dyn SerialLinkEndpoint>> {
+        self.link.take()
+    }
+
+
+    /// Checks whether a remote device is connected to the serial port.
+    pub fn is_connected(&self) -> bool {
+        self.link.is_some()
+    }
+
+
     /// Enables the output queue to store data sent by the program.
     pub fn enable_output_queue(&mut self, enabled: bool) {
         self.output_queue_enabled = enabled;
@@ -154,7 +346,17 @@ impl MemoryBusConnection for SerialPort {
     fn on_read(&self, address: u16) -> u8 {
         match address {
             MEMORY_LOCATION_SB => self.transfer_byte,
-            MEMORY_LOCATION_SC => 0b_0111_1111 | as_bit_flag(self.transfer_enabled, 7),
+
+            MEMORY_LOCATION_SC => {
+                // the clock speed bit is only available on GBC
+                let unused_bits = if self.gbc_enabled { 0b_0111_1100 } else { 0b_0111_1110 };
+
+                    unused_bits
+                |   as_bit_flag(self.transfer_enabled, 7)
+                |   as_bit_flag(self.fast_clock, 1)
+                |   as_bit_flag(self.internal_clock, 0)
+            },
+
             _ => 0xff
         }
     }
@@ -162,10 +364,23 @@ impl MemoryBusConnection for SerialPort {
 
     fn on_write(&mut self, address: u16, value: u8) {
         match address {
-            MEMORY_LOCATION_SB => self.transfer_byte    = value,
-            MEMORY_LOCATION_SC => self.transfer_enabled = get_bit(value, 7),
+            MEMORY_LOCATION_SB => self.transfer_byte = value,
+
+            MEMORY_LOCATION_SC => {
+                let was_enabled       = self.transfer_enabled;
+                self.transfer_enabled = get_bit(value, 7);
+                self.internal_clock   = get_bit(value, 0);
+                self.fast_clock       = self.gbc_enabled && get_bit(value, 1);
+
+                if self.transfer_enabled && !was_enabled {
+                    self.start_transfer();
+                }
+            },
+
             _ => { }
         };
+
+        self.update_link_state();
     }
 
 
@@ -173,3 +388,133 @@ impl MemoryBusConnection for SerialPort {
         take(&mut self.signals)
     }
 }
+
+
+impl LinkCableEndpoint {
+    /// Creates a pair of endpoints, representing both ends of a link cable.
+    pub fn create_pair() -> (LinkCableEndpoint, LinkCableEndpoint) {
+        let wire = Arc::new(Mutex::new([LinkWirePort::default(), LinkWirePort::default()]));
+
+        (
+            LinkCableEndpoint { wire: wire.clone(), side: 0 },
+            LinkCableEndpoint { wire,               side: 1 },
+        )
+    }
+}
+
+
+impl SerialLinkEndpoint for LinkCableEndpoint {
+    fn exchange_bit(&mut self, bit: bool) -> bool {
+        let mut wire = self.wire.lock().unwrap();
+        let remote   = &mut wire[1 - self.side];
+
+        // pulses not yet processed by the remote device already took
+        // the upper bits of its shift register
+        let pulses_pending = remote.pulses.len() as u8;
+        if pulses_pending >= remote.bits_pending {
+            return true;
+        }
+
+        remote.pulses.push_back(bit);
+        get_bit(remote.shift_register, 7 - pulses_pending)
+    }
+
+
+    fn poll_external_clock(&mut self) -> Option<bool> {
+        let mut wire = self.wire.lock().unwrap();
+        wire[self.side].pulses.pop_front()
+    }
+
+
+    fn set_local_state(&mut self, shift_register: u8, bits_pending: u8) {
+        let mut wire = self.wire.lock().unwrap();
+        let local    = &mut wire[self.side];
+
+        local.shift_register = shift_register;
+        local.bits_pending   = bits_pending;
+
+        if bits_pending == 0 {
+            local.pulses.clear();
+        }
+    }
+}
+
+
+impl LinkCable {
+    /// Connects two [GameBoy] instances with a link cable.
+    pub fn connect(mut first: GameBoy, mut second: GameBoy) -> LinkCable {
+        let (first_end, second_end) = LinkCableEndpoint::create_pair();
+        first.get_peripherals_mut().serial.connect(Box::new(first_end));
+        second.get_peripherals_mut().serial.connect(Box::new(second_end));
+
+        LinkCable {
+            devices: [first, second],
+        }
+    }
+
+
+    /// Disconnects both devices and returns them.
+    pub fn disconnect(self) -> (GameBoy, GameBoy) {
+        let [mut first, mut second] = self.devices;
+        first.get_peripherals_mut().serial.disconnect();
+        second.get_peripherals_mut().serial.disconnect();
+
+        (first, second)
+    }
+
+
+    /// Get one of the connected devices by its index.
+    pub fn get_device(&self, index: usize) -> &GameBoy {
+        &self.devices[index]
+    }
+
+
+    /// Get one of the connected devices by its index.
+    pub fn get_device_mut(&mut self, index: usize) -> &mut GameBoy {
+        &mut self.devices[index]
+    }
+
+
+    /// Runs a single step on the device which is behind in time,
+    /// so both devices keep running in lockstep.
+    /// Returns the index of the device being updated and its results.
+    pub fn run_single_step(&mut self) -> (usize, EmulatorUpdateResults) {
+        let index = if self.devices[1].get_total_cycles_processed() < self.devices[0].get_total_cycles_processed() {
+            1
+        }
+        else {
+            0
+        };
+
+        (index, self.devices[index].run_single_step())
+    }
+
+
+    /// Continues running both devices, until the first one has completed a single frame.
+    /// Returns the results of both devices.
+    pub fn run_frame(&mut self) -> [EmulatorUpdateResults; 2] {
+        let mut results = [EmulatorUpdateResults::default(), EmulatorUpdateResults::default()];
+
+        loop {
+            let (index, step_results) = self.run_single_step();
+            results[index] += step_results;
+
+            // stop after completing one frame
+            if results[0].events.contains(DebugEvent::PpuFrameCompleted) {
+                break;
+            }
+
+            // stop at the exact instruction where a breakpoint was hit on either device
+            if results[index].events.contains(DebugEvent::BreakpointHit) {
+                break;
+            }
+
+            // in case the screen was disabled, stop after the time of one frame has passed
+            if results[0].cycles >= CPU_CYCLES_PER_FRAME {
+                break;
+            }
+        }
+
+        results
+    }
+}
@@ -16,73 +16,399 @@
  */
 
 #[cfg(feature = "snapshots")]
-pub use with_snapshots::Snapshot as Snapshot;
+pub use with_snapshots::*;
+
+#[cfg(feature = "snapshots")]
+pub use bess::{export_bess, import_bess, is_bess, BESS_MAGIC, BESS_VERSION_MAJOR, BESS_VERSION_MINOR};
+
+#[cfg(feature = "snapshots")]
+mod bess;
 
 #[cfg(feature = "snapshots")]
 mod with_snapshots {
-    use crate::gameboy::GameBoy;
-    use crate::utils::SerializableBuffer;
+    use crate::device_type::DeviceType;
+    use crate::gameboy::{Clock, GameBoy};
+    use crate::utils::{with_format_version, SerializableBuffer};
+    use std::fmt::{Display, Formatter};
     use std::fs::File;
     use std::io;
     use std::io::{Read, Write};
     use std::path::Path;
 
+    /// The magic number at the beginning of each snapshot file.
+    pub const SNAPSHOT_MAGIC: [u8; 8] = *b"GEMISNAP";
+
+    /// The current version of the snapshot format.
+    /// Version 0 refers to the legacy format, which was a plain dump
+    /// of the emulator state without any header and cannot be read anymore.
+    /// Version 2 added the state of the cartridge's rumble motor, the KEY0 register,
+    /// the tilt of the device and the state of the MBC6 and MBC7 controllers.
+    ///
+    /// Any change to the layout of the serialized emulator state requires this
+    /// version to be increased, with new fields being read depending on the version
+    /// of the snapshot.
+    pub const SNAPSHOT_FORMAT_VERSION: u16 = 2;
+
+    /// A function to migrate the payload of a snapshot from one version of the format into the next one.
+    type MigrationFn = fn(&[u8]) -> Result<Vec<u8>, SnapshotError>;
+
+    /// All migration hooks, where the entry at index N migrates a snapshot
+    /// from version N + 1 into version N + 2.
+    /// Fields added to the emulator state are not migrated here, but are read
+    /// depending on the version of the snapshot, see [upgrade_state].
+    const MIGRATIONS: [MigrationFn; SNAPSHOT_FORMAT_VERSION as usize - 1] = [
+        migrate_unchanged,
+    ];
+
+
+    /// Errors which may occur when creating, loading or restoring a snapshot.
+    #[derive(Debug)]
+    pub enum SnapshotError {
+        /// Failed to read or write the snapshot data.
+        Io(io::Error),
+
+        /// The data is not a valid snapshot or is corrupted.
+        InvalidData(String),
+
+        /// The data is a legacy snapshot without any header, which is not supported anymore.
+        LegacyFormat,
+
+        /// The snapshot was created with a newer version of the format than supported.
+        UnsupportedVersion {
+            found:     u16,
+            supported: u16,
+        },
+
+        /// The snapshot was created for a different ROM.
+        RomMismatch {
+            expected:  String,
+            found:     String,
+        },
+
+        /// The snapshot was created on a different device type.
+        DeviceMismatch {
+            expected:  DeviceType,
+            found:     DeviceType,
+        },
+    }
+
+
+    /// A thumbnail image of the emulator's screen when the snapshot was taken.
+    #[derive(Clone, serde::Serialize, serde::Deserialize)]
+    pub struct SnapshotThumbnail {
+        /// The width of the image in pixels.
+        pub width: u32,
+
+        /// The height of the image in pixels.
+        pub height: u32,
+
+        /// The image data as RGBA bytes.
+        pub pixels: SerializableBuffer<u8>,
+    }
+
+
+    /// Describes the content of a snapshot without the need to restore it.
+    #[derive(Clone, serde::Serialize, serde::Deserialize)]
+    pub struct SnapshotHeader {
+        /// The version of the emulator, which created the snapshot.
+        pub emulator_version: String,
+
+        /// The title of the ROM, from which the snapshot was taken.
+        pub rom_title: String,
+
+        /// A hash of the ROM data, from which the snapshot was taken.
+        pub rom_hash: u64,
+
+        /// The device type the snapshot was created on.
+        pub device: DeviceType,
+
+        /// The number of cycles processed until the snapshot was taken.
+        pub created_at_cycles: Clock,
+
+        /// An image of the emulator's screen, if any.
+        pub thumbnail: Option<SnapshotThumbnail>,
+    }
+
+
     /// Contains a full snapshot of an emulator instance,
     /// including the whole ROM and RAM.
     /// The snapshot can be used to restore the emulator
     /// instance.
     #[derive(serde::Serialize, serde::Deserialize)]
     pub struct Snapshot {
+        /// Information about the emulator instance stored in this snapshot.
+        header: SnapshotHeader,
+
         /// Binary form of the serialized emulator state.
         data: SerializableBuffer<u8>,
     }
 
 
+    impl Display for SnapshotError {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                SnapshotError::Io(e) => write!(f, "I/O error: {e}"),
+                SnapshotError::InvalidData(message) => write!(f, "Invalid snapshot data: {message}"),
+                SnapshotError::LegacyFormat => write!(f, "Snapshots without a header are not supported anymore"),
+
+                SnapshotError::UnsupportedVersion { found, supported } => write!(
+                    f,
+                    "Snapshot format version {found} is not supported, latest supported version is {supported}"
+                ),
+
+                SnapshotError::RomMismatch { expected, found } => write!(
+                    f,
+                    "Snapshot was created for ROM '{found}', but '{expected}' is loaded"
+                ),
+
+                SnapshotError::DeviceMismatch { expected, found } => write!(
+                    f,
+                    "Snapshot was created on device {}, but {} is running",
+                    found.get_abbreviation(),
+                    expected.get_abbreviation()
+                ),
+            }
+        }
+    }
+
+
+    impl std::error::Error for SnapshotError {
+    }
+
+
+    impl From<io::Error> for SnapshotError {
+        fn from(value: io::Error) -> Self {
+            SnapshotError::Io(value)
+        }
+    }
+
+
+    impl From<SnapshotError> for io::Error {
+        fn from(value: SnapshotError) -> Self {
+            match value {
+                SnapshotError::Io(e) => e,
+                _ => io::Error::new(io::ErrorKind::InvalidData, value),
+            }
+        }
+    }
+
+
+    impl SnapshotHeader {
+        /// Creates the header describing an emulator instance.
+        fn create_from(gb: &GameBoy) -> Self {
+            let (rom_title, rom_hash) = match gb.get_peripherals().mem.get_cartridge() {
+                Some(cartridge) => (cartridge.get_title().clone(), cartridge.compute_rom_hash()),
+                None            => (String::new(), 0),
+            };
+
+            let lcd = gb.get_peripherals().ppu.get_lcd();
+
+            Self {
+                emulator_version:   env!("CARGO_PKG_VERSION").to_string(),
+                rom_title,
+                rom_hash,
+                device:             gb.get_config().device,
+                created_at_cycles:  gb.get_total_cycles_processed(),
+                thumbnail:          Some(SnapshotThumbnail {
+                    width:  lcd.get_width(),
+                    height: lcd.get_height(),
+                    pixels: lcd.get_pixels_as_slice().to_vec().into(),
+                }),
+            }
+        }
+    }
+
+
     impl Snapshot {
         /// Creates a new snapshot from an existing emulator instance.
-        pub fn create_from(gb: &GameBoy) -> io::Result<Self> {
-            let data = bincode::serde::encode_to_vec(
-                gb,
-                bincode::config::standard()
-            ).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        pub fn create_from(gb: &GameBoy) -> Result<Self, SnapshotError> {
+            let data = encode_state(gb)?;
 
-            Ok(Self { data: data.into() })
+            Ok(Self {
+                header: SnapshotHeader::create_from(gb),
+                data:   data.into(),
+            })
         }
 
 
         /// Restores a previously serialized snapshot into a new [GameBoy] instance.
-        pub fn restore(&self) -> io::Result<GameBoy> {
-            let (result, _) = bincode::serde::decode_from_slice::<GameBoy, _>(
-                &self.data,
-                bincode::config::standard()
-            ).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        pub fn restore(&self) -> Result<GameBoy, SnapshotError> {
+            let result = decode_state(&self.data)?;
+
+            // check whether the data is matching the header
+            let header = SnapshotHeader::create_from(&result);
+            if header.rom_hash != self.header.rom_hash || header.device != self.header.device {
+                return Err(SnapshotError::InvalidData("Snapshot data does not match its header".to_string()));
+            }
 
             Ok(result)
         }
 
 
+        /// Restores a previously serialized snapshot, but only if it was created
+        /// from the same ROM and on the same device as a currently running instance.
+        pub fn restore_for(&self, current: &GameBoy) -> Result<GameBoy, SnapshotError> {
+            self.check_compatible(current)?;
+            self.restore()
+        }
+
+
+        /// Checks whether the snapshot was created from the same ROM and
+        /// on the same device as a currently running emulator instance.
+        pub fn check_compatible(&self, current: &GameBoy) -> Result<(), SnapshotError> {
+            let current_header = SnapshotHeader::create_from(current);
+
+            if current_header.rom_hash != self.header.rom_hash {
+                return Err(SnapshotError::RomMismatch {
+                    expected: current_header.rom_title,
+                    found:    self.header.rom_title.clone(),
+                });
+            }
+
+            if current_header.device != self.header.device {
+                return Err(SnapshotError::DeviceMismatch {
+                    expected: current_header.device,
+                    found:    self.header.device,
+                });
+            }
+
+            Ok(())
+        }
+
+
+        /// Parses a snapshot from its binary representation.
+        /// Snapshots created with older versions of the format will be migrated,
+        /// except legacy snapshots without a header, which will be rejected.
+        pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+            // data without any header is a legacy snapshot
+            let Some(remaining) = bytes.strip_prefix(&SNAPSHOT_MAGIC) else {
+                return Err(SnapshotError::LegacyFormat);
+            };
+
+            if remaining.len() < 2 {
+                return Err(SnapshotError::InvalidData("Incomplete header".to_string()));
+            }
+
+            let mut version = u16::from_le_bytes([remaining[0], remaining[1]]);
+            let payload     = &remaining[2..];
+
+            if version == 0 {
+                return Err(SnapshotError::LegacyFormat);
+            }
+
+            if version > SNAPSHOT_FORMAT_VERSION {
+                return Err(SnapshotError::UnsupportedVersion {
+                    found:     version,
+                    supported: SNAPSHOT_FORMAT_VERSION,
+                });
+            }
+
+            let original_version = version;
+            let mut payload      = payload.to_vec();
+            while version < SNAPSHOT_FORMAT_VERSION {
+                payload  = MIGRATIONS[version as usize - 1](&payload)?;
+                version += 1;
+            }
+
+            let (mut snapshot, _) = bincode::serde::decode_from_slice::<Snapshot, _>(
+                &payload,
+                bincode::config::standard()
+            ).map_err(|e| SnapshotError::InvalidData(e.to_string()))?;
+
+            if original_version < SNAPSHOT_FORMAT_VERSION {
+                snapshot.data = upgrade_state(original_version, &snapshot.data)?.into();
+            }
+
+            Ok(snapshot)
+        }
+
+
+        /// Converts the snapshot into its binary representation,
+        /// starting with the magic number and format version.
+        pub fn to_bytes(&self) -> Result<Vec<u8>, SnapshotError> {
+            let payload = bincode::serde::encode_to_vec(
+                self,
+                bincode::config::standard()
+            ).map_err(|e| SnapshotError::InvalidData(e.to_string()))?;
+
+            let mut bytes = Vec::with_capacity(SNAPSHOT_MAGIC.len() + 2 + payload.len());
+            bytes.extend_from_slice(&SNAPSHOT_MAGIC);
+            bytes.extend_from_slice(&SNAPSHOT_FORMAT_VERSION.to_le_bytes());
+            bytes.extend_from_slice(&payload);
+
+            Ok(bytes)
+        }
+
+
         /// Reads a snapshot from a file path.
-        pub fn read_from_file(filepath: &Path) -> io::Result<Self> {
+        pub fn read_from_file(filepath: &Path) -> Result<Self, SnapshotError> {
             let mut file = File::open(filepath)?;
             let mut data = Vec::new();
             file.read_to_end(&mut data)?;
 
-            Ok(Self { data: data.into() })
+            Self::from_bytes(&data)
         }
 
 
         /// Saves a snapshot into a file path.
-        pub fn save_to_file(&self, filepath: &Path) -> io::Result<()> {
+        pub fn save_to_file(&self, filepath: &Path) -> Result<(), SnapshotError> {
             let mut file = File::create(filepath)?;
-            file.write_all(&self.data)?;
+            file.write_all(&self.to_bytes()?)?;
 
             Ok(())
         }
 
 
+        /// Get the header describing the content of this snapshot.
+        pub fn get_header(&self) -> &SnapshotHeader {
+            &self.header
+        }
+
+
+        /// Removes the thumbnail image to reduce the size of the snapshot.
+        pub fn remove_thumbnail(&mut self) {
+            self.header.thumbnail = None;
+        }
+
+
         /// Get the serialized data of this snapshot.
         pub fn get_data(&self) -> &[u8] {
             &self.data
         }
     }
+
+
+    /// Serializes the state of an emulator instance into its binary form.
+    pub(crate) fn encode_state(gb: &GameBoy) -> Result<Vec<u8>, SnapshotError> {
+        bincode::serde::encode_to_vec(
+            gb,
+            bincode::config::standard()
+        ).map_err(|e| SnapshotError::InvalidData(e.to_string()))
+    }
+
+
+    /// Restores an emulator instance from the binary form of its state.
+    pub(crate) fn decode_state(data: &[u8]) -> Result<GameBoy, SnapshotError> {
+        let (gb, _) = bincode::serde::decode_from_slice::<GameBoy, _>(
+            data,
+            bincode::config::standard()
+        ).map_err(|e| SnapshotError::InvalidData(e.to_string()))?;
+
+        Ok(gb)
+    }
+
+
+    /// Converts the emulator state of a snapshot created with an older version of the format
+    /// into the current one, where fields missing in the old version get their default values.
+    fn upgrade_state(version: u16, data: &[u8]) -> Result<Vec<u8>, SnapshotError> {
+        let gb = with_format_version(version, || decode_state(data))?;
+        encode_state(&gb)
+    }
+
+
+    /// Migration hook for versions, which did only change the layout of
+    /// the emulator state, but not the snapshot itself.
+    fn migrate_unchanged(payload: &[u8]) -> Result<Vec<u8>, SnapshotError> {
+        Ok(payload.to_vec())
+    }
 }
@@ -0,0 +1,579 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+
+use crate::cartridge::{current_timestamp, ROM_OFFSET_GLOBAL_CHECKSUM, ROM_OFFSET_TITLE_STRING};
+use crate::cpu::cpu::{HaltState, RegisterR16};
+use crate::device_type::DeviceType;
+use crate::gameboy::{EmulationType, GameBoy};
+use crate::mmu::locations::*;
+use crate::mmu::mbc::MbcImpl;
+use crate::mmu::memory_data::MemoryData;
+use crate::mmu::rtc::RealTimeClock;
+use crate::snapshots::{Snapshot, SnapshotError};
+
+
+/// The magic number at the end of each BESS file.
+pub const BESS_MAGIC: [u8; 4] = *b"BESS";
+
+/// The major version of the BESS format being supported.
+pub const BESS_VERSION_MAJOR: u16 = 1;
+
+/// The minor version of the BESS format being written.
+pub const BESS_VERSION_MINOR: u16 = 1;
+
+const BLOCK_ID_NAME: [u8; 4] = *b"NAME";
+const BLOCK_ID_INFO: [u8; 4] = *b"INFO";
+const BLOCK_ID_CORE: [u8; 4] = *b"CORE";
+const BLOCK_ID_MBC:  [u8; 4] = *b"MBC ";
+const BLOCK_ID_RTC:  [u8; 4] = *b"RTC ";
+const BLOCK_ID_END:  [u8; 4] = *b"END ";
+
+/// The size of the CORE block, including all buffer descriptors.
+const CORE_BLOCK_SIZE: usize = 0xd0;
+
+/// The offset of the IO registers within the CORE block.
+const CORE_OFFSET_IO_REGISTERS: usize = 0x18;
+
+/// The offset of the first buffer descriptor within the CORE block.
+const CORE_OFFSET_BUFFERS: usize = 0x98;
+
+/// The size of the INFO block, containing the title and global checksum of the ROM.
+const INFO_BLOCK_SIZE: usize = 0x12;
+
+/// The size of a block header, containing the block ID and its length.
+const BLOCK_HEADER_SIZE: usize = 8;
+
+/// The size of the footer at the end of the file.
+const FOOTER_SIZE: usize = 8;
+
+/// The size of the GBC palette memory for background and objects each.
+const GBC_PALETTE_SIZE: usize = 0x40;
+
+
+/// Describes the location of a buffer within the raw memory dump.
+#[derive(Copy, Clone, Default)]
+struct BessBuffer {
+    size:   u32,
+    offset: u32,
+}
+
+
+/// A single block of a BESS file.
+struct BessBlock<'a> {
+    id:   [u8; 4],
+    data: &'a [u8],
+}
+
+
+impl BessBuffer {
+    /// Get the content of this buffer within the data of a BESS file.
+    fn get<'a>(&self, bytes: &'a [u8]) -> Result<&'a [u8], SnapshotError> {
+        let begin = self.offset as usize;
+        let end   = begin + self.size as usize;
+
+        bytes.get(begin .. end).ok_or_else(|| SnapshotError::InvalidData(
+            "Buffer exceeds the size of the BESS file".to_string()
+        ))
+    }
+}
+
+
+impl Snapshot {
+    /// Converts the snapshot into a BESS file, which can be loaded by other emulators.
+    pub fn to_bess(&self) -> Result<Vec<u8>, SnapshotError> {
+        let gb = self.restore()?;
+        export_bess(&gb)
+    }
+
+
+    /// Creates a snapshot from a BESS file. Since BESS files do not contain the ROM,
+    /// the state is applied onto an emulator instance with the matching cartridge loaded.
+    pub fn from_bess(bytes: &[u8], mut gb: GameBoy) -> Result<Self, SnapshotError> {
+        import_bess(&mut gb, bytes)?;
+        Snapshot::create_from(&gb)
+    }
+}
+
+
+/// Checks whether the data ends with a BESS footer.
+pub fn is_bess(bytes: &[u8]) -> bool {
+    bytes.len() >= FOOTER_SIZE && bytes.ends_with(&BESS_MAGIC)
+}
+
+
+/// Writes the current state of an emulator instance into a BESS (Best Effort Save State) file,
+/// which allows to exchange save states with other emulators. A BESS file consists of a
+/// raw memory dump, followed by a chain of blocks and a footer pointing to the first block.
+/// Fails if the CPU is locked up, which cannot be represented in a BESS file.
+pub fn export_bess(gb: &GameBoy) -> Result<Vec<u8>, SnapshotError> {
+    let mem   = &gb.get_peripherals().mem;
+    let ppu   = &gb.get_peripherals().ppu;
+    let mut bytes = Vec::new();
+
+    // raw memory dump, which will be referenced by the CORE block
+    let wram: Vec<u8> = mem.get_wram_banks().iter().flat_map(|bank| bank.as_slice()).copied().collect();
+    let wram = append_buffer(&mut bytes, &wram);
+
+    let vram: Vec<u8> = (0 .. get_vram_bank_count(gb)).flat_map(|bank| ppu.get_vram(bank)).copied().collect();
+    let vram = append_buffer(&mut bytes, &vram);
+
+    let mbc_ram = match mem.get_cartridge() {
+        Some(cartridge) if cartridge.has_ram() => append_buffer(&mut bytes, cartridge.get_ram().as_slice()),
+        _ => BessBuffer::default(),
+    };
+
+    let oam  = append_buffer(&mut bytes, ppu.get_oam_bank().as_slice());
+    let hram = append_buffer(&mut bytes, mem.get_hram().as_slice());
+
+    let (bg_palettes, obj_palettes) = match gb.get_config().emulation {
+        EmulationType::DMG => (BessBuffer::default(), BessBuffer::default()),
+        EmulationType::GBC => {
+            let palettes = ppu.get_palettes();
+            let bg  = read_palette_memory(|i| palettes.gbc_background_palette.get_at(i));
+            let obj = read_palette_memory(|i| palettes.gbc_object_palette.get_at(i));
+
+            (append_buffer(&mut bytes, &bg), append_buffer(&mut bytes, &obj))
+        }
+    };
+
+    let first_block_offset = bytes.len() as u32;
+
+    // NAME: the emulator which created the file
+    let name = format!("gemi {}", env!("CARGO_PKG_VERSION"));
+    append_block(&mut bytes, BLOCK_ID_NAME, name.as_bytes());
+
+    // INFO: title and global checksum of the ROM
+    if let Some(cartridge) = mem.get_cartridge() {
+        let rom  = cartridge.get_rom();
+        let mut info = Vec::with_capacity(INFO_BLOCK_SIZE);
+        info.extend((0 .. 16).map(|i| rom.get_at(ROM_OFFSET_TITLE_STRING + i)));
+        info.extend((0 .. 2).map(|i| rom.get_at(ROM_OFFSET_GLOBAL_CHECKSUM + i)));
+        append_block(&mut bytes, BLOCK_ID_INFO, &info);
+    }
+
+    // CORE: CPU registers, IO registers and the memory buffers
+    let cpu = &gb.cpu;
+    let mut core = Vec::with_capacity(CORE_BLOCK_SIZE);
+    core.extend_from_slice(&BESS_VERSION_MAJOR.to_le_bytes());
+    core.extend_from_slice(&BESS_VERSION_MINOR.to_le_bytes());
+    core.extend_from_slice(get_model_identifier(gb.get_config().device));
+
+    for value in [
+        cpu.get_instruction_pointer(),
+        cpu.get_r16(RegisterR16::AF),
+        cpu.get_r16(RegisterR16::BC),
+        cpu.get_r16(RegisterR16::DE),
+        cpu.get_r16(RegisterR16::HL),
+        cpu.get_stack_pointer(),
+    ] {
+        core.extend_from_slice(&value.to_le_bytes());
+    }
+
+    let execution_state = match cpu.get_halt_state() {
+        HaltState::Running => 0,
+        HaltState::Halt    => 1,
+
+        HaltState::Locked => {
+            return Err(SnapshotError::InvalidData(
+                "The CPU is locked up, which cannot be stored in a BESS file".to_string()
+            ));
+        }
+    };
+
+    core.push(cpu.is_interrupts_enabled() as u8);
+    core.push(gb.get_mmu().peek_u8(MEMORY_LOCATION_INTERRUPTS_ENABLED));
+    core.push(execution_state);
+    core.push(0);
+
+    // the raw register values, since reading them via the memory bus would mask write-only bits
+    for address in 0xff00 ..= 0xff7f {
+        core.push(gb.get_mmu().get_register_value(address));
+    }
+
+    for buffer in [wram, vram, mbc_ram, oam, hram, bg_palettes, obj_palettes] {
+        core.extend_from_slice(&buffer.size.to_le_bytes());
+        core.extend_from_slice(&buffer.offset.to_le_bytes());
+    }
+
+    append_block(&mut bytes, BLOCK_ID_CORE, &core);
+
+    // MBC: register writes to restore the state of the memory bank controller
+    let mbc_writes = mem.get_mbc().get_register_writes();
+    if !mbc_writes.is_empty() {
+        let mut mbc = Vec::with_capacity(mbc_writes.len() * 3);
+        for (address, value) in mbc_writes {
+            mbc.extend_from_slice(&address.to_le_bytes());
+            mbc.push(value);
+        }
+
+        append_block(&mut bytes, BLOCK_ID_MBC, &mbc);
+    }
+
+    // RTC: state of the real time clock, if any
+    if let Some(rtc) = mem.get_cartridge().and_then(|cartridge| cartridge.get_rtc()) {
+        append_block(&mut bytes, BLOCK_ID_RTC, &rtc.to_footer_bytes(current_timestamp()));
+    }
+
+    append_block(&mut bytes, BLOCK_ID_END, &[]);
+
+    // footer
+    bytes.extend_from_slice(&first_block_offset.to_le_bytes());
+    bytes.extend_from_slice(&BESS_MAGIC);
+
+    Ok(bytes)
+}
+
+
+/// Applies the state stored in a BESS file onto an emulator instance.
+/// The emulator instance needs to have the same ROM loaded, which was
+/// used to create the BESS file.
+pub fn import_bess(gb: &mut GameBoy, bytes: &[u8]) -> Result<(), SnapshotError> {
+    let blocks = read_blocks(bytes)?;
+
+    let core = blocks.iter()
+        .find(|block| block.id == BLOCK_ID_CORE)
+        .map(|block| block.data)
+        .ok_or_else(|| SnapshotError::InvalidData("Missing CORE block".to_string()))?
+    ;
+
+    if core.len() < CORE_BLOCK_SIZE {
+        return Err(SnapshotError::InvalidData("Incomplete CORE block".to_string()));
+    }
+
+    let version_major = read_u16(core, 0x00);
+    if version_major != BESS_VERSION_MAJOR {
+        return Err(SnapshotError::UnsupportedVersion {
+            found:     version_major,
+            supported: BESS_VERSION_MAJOR,
+        });
+    }
+
+    // check whether the file was created from the same ROM
+    if let Some(info) = blocks.iter().find(|block| block.id == BLOCK_ID_INFO) {
+        check_rom_info(gb, info.data)?;
+    }
+
+    // check whether the file was created on a compatible device
+    let device = gb.get_config().device;
+    if let Some(found) = get_device_type(&core[0x04 .. 0x08]) {
+        if found.has_gbc_support() != device.has_gbc_support() {
+            return Err(SnapshotError::DeviceMismatch {
+                expected: device,
+                found,
+            });
+        }
+    }
+
+    let [wram, vram, mbc_ram, oam, hram, bg_palettes, obj_palettes] = std::array::from_fn(|i| {
+        let offset = CORE_OFFSET_BUFFERS + i * 8;
+
+        BessBuffer {
+            size:   read_u32(core, offset),
+            offset: read_u32(core, offset + 4),
+        }
+    });
+
+    // memory buffers
+    {
+        let vram_bank_count = get_vram_bank_count(gb);
+        let peripherals     = gb.get_peripherals_mut();
+
+        let wram = wram.get(bytes)?;
+        for (bank, data) in peripherals.mem.get_wram_banks_mut().iter_mut().zip(wram.chunks(0x1000)) {
+            copy_into(bank.as_slice_mut(), data);
+        }
+
+        let vram = vram.get(bytes)?;
+        for (bank, data) in (0 .. vram_bank_count).zip(vram.chunks(0x2000)) {
+            copy_into(peripherals.ppu.get_vram_mut(bank), data);
+        }
+
+        let mbc_ram = mbc_ram.get(bytes)?;
+        if let Some(cartridge) = peripherals.mem.get_cartridge_mut() {
+            if cartridge.has_ram() {
+                copy_into(cartridge.get_ram_mut().as_slice_mut(), mbc_ram);
+            }
+        }
+
+        copy_into(peripherals.ppu.get_oam_bank_mut().as_slice_mut(), oam.get(bytes)?);
+        copy_into(peripherals.mem.get_hram_mut().as_slice_mut(), hram.get(bytes)?);
+
+        let palettes = peripherals.ppu.get_palettes_mut();
+        for (index, value) in bg_palettes.get(bytes)?.iter().take(GBC_PALETTE_SIZE).enumerate() {
+            palettes.gbc_background_palette.set_at(index, *value);
+        }
+
+        for (index, value) in obj_palettes.get(bytes)?.iter().take(GBC_PALETTE_SIZE).enumerate() {
+            palettes.gbc_object_palette.set_at(index, *value);
+        }
+    }
+
+    // IO registers
+    apply_io_registers(gb, &core[CORE_OFFSET_IO_REGISTERS .. CORE_OFFSET_IO_REGISTERS + 0x80]);
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_INTERRUPTS_ENABLED, core[0x15]);
+
+    // CPU registers
+    let cpu = &mut gb.cpu;
+    cpu.set_instruction_pointer(read_u16(core, 0x08));
+    cpu.set_r16(RegisterR16::AF, read_u16(core, 0x0a));
+    cpu.set_r16(RegisterR16::BC, read_u16(core, 0x0c));
+    cpu.set_r16(RegisterR16::DE, read_u16(core, 0x0e));
+    cpu.set_r16(RegisterR16::HL, read_u16(core, 0x10));
+    cpu.set_stack_pointer(read_u16(core, 0x12));
+
+    if core[0x14] != 0 {
+        cpu.enable_interrupts();
+    }
+    else {
+        cpu.disable_interrupts();
+    }
+
+    // a stopped CPU is treated like a halted one
+    cpu.set_halt_state(match core[0x16] {
+        0 => HaltState::Running,
+        _ => HaltState::Halt,
+    });
+
+    // replay the writes into the memory bank controller
+    if let Some(mbc) = blocks.iter().find(|block| block.id == BLOCK_ID_MBC) {
+        for entry in mbc.data.chunks_exact(3) {
+            let address = read_u16(entry, 0);
+
+            if address <= 0x7fff {
+                gb.get_mmu_mut().write_u8(address, entry[2]);
+            }
+        }
+    }
+
+    // real time clock, advanced by the time passed since the file was saved
+    if let Some(rtc_block) = blocks.iter().find(|block| block.id == BLOCK_ID_RTC) {
+        if let Some((mut rtc, timestamp)) = RealTimeClock::from_footer_bytes(rtc_block.data) {
+            rtc.advance_seconds(current_timestamp().saturating_sub(timestamp));
+
+            let cartridge = gb.get_peripherals_mut().mem.get_cartridge_mut();
+            if let Some(current_rtc) = cartridge.and_then(|cartridge| cartridge.get_rtc_mut()) {
+                *current_rtc = rtc;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+
+/// Writes the IO registers stored in the CORE block via the memory bus.
+/// Registers, which would trigger an action when being written, like
+/// DMA transfers, are skipped or masked.
+fn apply_io_registers(gb: &mut GameBoy, registers: &[u8]) {
+    let get = |address: u16| registers[(address - 0xff00) as usize];
+
+    // the APU needs to be enabled before writing any other sound register
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_APU_NR52, get(MEMORY_LOCATION_APU_NR52));
+
+    for address in 0xff00 ..= 0xff7f {
+        let value = match address {
+            // registers which will trigger some action or being handled separately
+            MEMORY_LOCATION_REGISTER_DIV
+            | MEMORY_LOCATION_APU_NR52
+            | MEMORY_LOCATION_LY
+            | MEMORY_LOCATION_DMA_ADDRESS
+            | MEMORY_LOCATION_KEY1
+            | MEMORY_LOCATION_BOOT_ROM_DISABLE
+            | MEMORY_LOCATION_HDMA5
+            | MEMORY_LOCATION_BCPD
+            | MEMORY_LOCATION_OCPD => continue,
+
+            // don't trigger any sound channels
+            MEMORY_LOCATION_APU_NR14
+            | MEMORY_LOCATION_APU_NR24
+            | MEMORY_LOCATION_APU_NR34
+            | MEMORY_LOCATION_APU_NR44 => get(address) & 0x7f,
+
+            _ => get(address),
+        };
+
+        gb.get_mmu_mut().write_u8(address, value);
+    }
+
+    // the boot ROM can only be disabled, but not enabled again
+    if get(MEMORY_LOCATION_BOOT_ROM_DISABLE) != 0x00 {
+        gb.get_mmu_mut().write_u8(MEMORY_LOCATION_BOOT_ROM_DISABLE, 0x01);
+    }
+
+    gb.get_peripherals_mut().mem.set_double_speed((get(MEMORY_LOCATION_KEY1) & 0x80) != 0);
+}
+
+
+/// Checks whether the ROM title and global checksum stored
+/// in the INFO block match the currently loaded cartridge.
+fn check_rom_info(gb: &GameBoy, info: &[u8]) -> Result<(), SnapshotError> {
+    if info.len() < INFO_BLOCK_SIZE {
+        return Err(SnapshotError::InvalidData("Incomplete INFO block".to_string()));
+    }
+
+    let expected: Vec<u8> = match gb.get_peripherals().mem.get_cartridge() {
+        Some(cartridge) => {
+            let rom = cartridge.get_rom();
+
+            (0 .. 16).map(|i| rom.get_at(ROM_OFFSET_TITLE_STRING + i))
+                .chain((0 .. 2).map(|i| rom.get_at(ROM_OFFSET_GLOBAL_CHECKSUM + i)))
+                .collect()
+        }
+
+        None => vec![0; INFO_BLOCK_SIZE],
+    };
+
+    if expected[..] != info[.. INFO_BLOCK_SIZE] {
+        let title_of = |data: &[u8]| {
+            String::from_utf8_lossy(&data[0 .. 16])
+                .trim_end_matches('\0')
+                .to_string()
+        };
+
+        return Err(SnapshotError::RomMismatch {
+            expected: title_of(&expected),
+            found:    title_of(info),
+        });
+    }
+
+    Ok(())
+}
+
+
+/// Reads all blocks of a BESS file, starting at the offset stored in the footer
+/// until the END block.
+fn read_blocks(bytes: &[u8]) -> Result<Vec<BessBlock<'_>>, SnapshotError> {
+    if !is_bess(bytes) {
+        return Err(SnapshotError::InvalidData("Missing BESS footer".to_string()));
+    }
+
+    let footer_offset = bytes.len() - FOOTER_SIZE;
+    let mut offset    = read_u32(bytes, footer_offset) as usize;
+    let mut blocks    = Vec::new();
+
+    loop {
+        if offset + BLOCK_HEADER_SIZE > footer_offset {
+            return Err(SnapshotError::InvalidData("Missing END block".to_string()));
+        }
+
+        let mut id = [0u8; 4];
+        id.copy_from_slice(&bytes[offset .. offset + 4]);
+
+        let begin = offset + BLOCK_HEADER_SIZE;
+        let end   = begin + read_u32(bytes, offset + 4) as usize;
+
+        if end > footer_offset {
+            return Err(SnapshotError::InvalidData("Block exceeds the size of the BESS file".to_string()));
+        }
+
+        if id == BLOCK_ID_END {
+            break;
+        }
+
+        blocks.push(BessBlock {
+            id,
+            data: &bytes[begin .. end],
+        });
+
+        offset = end;
+    }
+
+    Ok(blocks)
+}
+
+
+/// Appends a buffer to the raw memory dump and returns its location.
+fn append_buffer(bytes: &mut Vec<u8>, data: &[u8]) -> BessBuffer {
+    let buffer = BessBuffer {
+        size:   data.len() as u32,
+        offset: bytes.len() as u32,
+    };
+
+    bytes.extend_from_slice(data);
+
+    buffer
+}
+
+
+/// Appends a block with its header to the BESS file.
+fn append_block(bytes: &mut Vec<u8>, id: [u8; 4], data: &[u8]) {
+    bytes.extend_from_slice(&id);
+    bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(data);
+}
+
+
+/// Reads the whole GBC palette memory via a getter function.
+fn read_palette_memory(get: impl Fn(usize) -> u8) -> Vec<u8> {
+    (0 .. GBC_PALETTE_SIZE).map(get).collect()
+}
+
+
+/// Copies as many bytes as possible from a buffer into the emulator's memory.
+fn copy_into(target: &mut [u8], source: &[u8]) {
+    let length = target.len().min(source.len());
+    target[.. length].copy_from_slice(&source[.. length]);
+}
+
+
+/// Get the number of VRAM banks available on the emulated device.
+fn get_vram_bank_count(gb: &GameBoy) -> usize {
+    match gb.get_config().emulation {
+        EmulationType::DMG => 1,
+        EmulationType::GBC => 2,
+    }
+}
+
+
+/// Get the model identifier used in the CORE block for a device type.
+fn get_model_identifier(device: DeviceType) -> &'static [u8; 4] {
+    match device {
+        DeviceType::GameBoyDmg       => b"GD  ",
+        DeviceType::GameBoyPocket    => b"GM  ",
+        DeviceType::GameBoyColor     => b"CC  ",
+        DeviceType::GameBoyAdvance   => b"CA  ",
+        DeviceType::GameBoyAdvanceSP => b"CA  ",
+        DeviceType::SuperGameBoy     => b"SN  ",
+        DeviceType::SuperGameBoy2    => b"S2  ",
+    }
+}
+
+
+/// Get the device type of a model identifier stored in the CORE block.
+/// Only the family and model will be taken into account, the revision is ignored.
+fn get_device_type(model: &[u8]) -> Option<DeviceType> {
+    match (model[0], model[1]) {
+        (b'G', b'M')    => Some(DeviceType::GameBoyPocket),
+        (b'G', _)       => Some(DeviceType::GameBoyDmg),
+        (b'S', b'2')    => Some(DeviceType::SuperGameBoy2),
+        (b'S', _)       => Some(DeviceType::SuperGameBoy),
+        (b'C', b'A')    => Some(DeviceType::GameBoyAdvance),
+        (b'C', _)       => Some(DeviceType::GameBoyColor),
+        _               => None,
+    }
+}
+
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
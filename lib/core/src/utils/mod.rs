@@ -20,6 +20,9 @@ pub use serializable_array::SerializableArray;
 pub use serializable_buffer::SerializableBuffer;
 pub use utils::*;
 
+#[cfg(feature = "serde")]
+pub(crate) use serde::serialize::{deserialize_since, with_compression_disabled, with_format_version};
+
 mod serde;
 mod serializable_array;
 mod serializable_buffer;
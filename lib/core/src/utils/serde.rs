@@ -33,10 +33,86 @@ pub mod serialize {
     use base64::Engine;
     use serde::de::Error;
     use serde::Deserialize;
+    use std::cell::Cell;
     use std::mem::size_of;
     use std::ptr::slice_from_raw_parts;
 
 
+    thread_local! {
+        /// Whether buffers are compressed when being serialized on the current thread.
+        static COMPRESSION_ENABLED: Cell<bool> = const { Cell::new(true) };
+
+        /// The version of the snapshot format being deserialized on the current thread,
+        /// unless it is the current version.
+        static FORMAT_VERSION: Cell<Option<u16>> = const { Cell::new(None) };
+    }
+
+
+    /// Runs a function with the compression of serialized buffers being disabled.
+    /// This needs to be used for both serializing and deserializing the same data.
+    /// Useful when the serialized data will be compressed as a whole, for example
+    /// to store the difference between two serialized states.
+    pub fn with_compression_disabled<R>(f: impl FnOnce() -> R) -> R {
+        let _guard = CompressionGuard {
+            previous: COMPRESSION_ENABLED.replace(false),
+        };
+
+        f()
+    }
+
+
+    /// Restores the previous compression setting when being dropped,
+    /// even if the function running with compression disabled panics.
+    struct CompressionGuard {
+        previous: bool,
+    }
+
+    impl Drop for CompressionGuard {
+        fn drop(&mut self) {
+            COMPRESSION_ENABLED.set(self.previous);
+        }
+    }
+
+
+    /// Runs a function deserializing data, which was written with an older version of the
+    /// snapshot format. Fields added in a later version will not be read from the data,
+    /// but get their default value instead, see [deserialize_since].
+    pub fn with_format_version<R>(version: u16, f: impl FnOnce() -> R) -> R {
+        let _guard = FormatVersionGuard {
+            previous: FORMAT_VERSION.replace(Some(version)),
+        };
+
+        f()
+    }
+
+
+    /// Restores the previous format version when being dropped.
+    struct FormatVersionGuard {
+        previous: Option<u16>,
+    }
+
+    impl Drop for FormatVersionGuard {
+        fn drop(&mut self) {
+            FORMAT_VERSION.set(self.previous);
+        }
+    }
+
+
+    /// Deserializes a field, which was added in version `VERSION` of the snapshot format.
+    /// When reading data of an older version, the field is skipped and gets its default value.
+    /// Use with `#[serde(deserialize_with = "deserialize_since::<VERSION, _, _>")]`.
+    pub fn deserialize_since<'de, const VERSION: u16, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+            D: serde::Deserializer<'de>,
+            T: Deserialize<'de> + Default
+    {
+        match FORMAT_VERSION.get() {
+            Some(version) if version < VERSION => Ok(T::default()),
+            _ => T::deserialize(deserializer),
+        }
+    }
+
+
     /// Serialize a slice of data as a stream of bytes.
     /// The data will be compressed and for human-readable export formats like JSON
     /// written as Base64 encoded string to keep data size low.
@@ -53,8 +129,13 @@ pub mod serialize {
             &*slice_from_raw_parts(data_ptr, data_len)
         };
 
-        // compress using zstd
-        let compressed = zstd::encode_all(bytes, 1).unwrap_or_else(|_| Vec::from(bytes));
+        // compress using zstd, unless disabled
+        let compressed = if COMPRESSION_ENABLED.get() {
+            zstd::encode_all(bytes, 1).unwrap_or_else(|_| Vec::from(bytes))
+        }
+        else {
+            Vec::from(bytes)
+        };
 
         if serializer.is_human_readable() {
             // for human-readable formats: serialize as base64 string
@@ -87,9 +168,14 @@ pub mod serialize {
             Vec::<u8>::deserialize(deserializer)?
         };
 
-        // decompress zstd compressed data
-        let uncompressed = zstd::decode_all(v.as_slice())
-                .map_err(|e| Error::custom(format!("Failed to decompress: {e}")))?;
+        // decompress zstd compressed data, unless compression is disabled
+        let uncompressed = if COMPRESSION_ENABLED.get() {
+            zstd::decode_all(v.as_slice())
+                    .map_err(|e| Error::custom(format!("Failed to decompress: {e}")))?
+        }
+        else {
+            v
+        };
 
         // convert into vector of type `T`.
         let typed = {
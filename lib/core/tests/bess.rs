@@ -0,0 +1,169 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+#![cfg(feature = "snapshots")]
+
+use gemi_core::cpu::cpu::RegisterR16;
+use gemi_core::gameboy::GameBoy;
+use gemi_core::mmu::locations::*;
+use gemi_core::mmu::memory_data::MemoryData;
+use gemi_core::snapshots::*;
+
+mod common;
+
+
+/// Creates a GameBoy running an MBC5 ROM with 8kiB battery powered RAM.
+fn create_gameboy(title: &str) -> GameBoy {
+    let mut rom = common::create_mbc_rom(0x8000, 0x1b, 0x02);
+    common::set_title(&mut rom, title);
+    common::create_gameboy(rom)
+}
+
+
+#[test]
+fn bess_roundtrip() {
+    let mut gb = create_gameboy("BESS");
+    gb.run_frame();
+
+    gb.cpu.set_r16(RegisterR16::BC, 0x1234);
+    gb.cpu.set_r16(RegisterR16::HL, 0xc0de);
+    gb.cpu.set_stack_pointer(0xdff0);
+    gb.cpu.set_instruction_pointer(0x0150);
+
+    let mmu = gb.get_mmu_mut();
+    mmu.write_u8(0x0000, 0x0a);
+    mmu.write_u8(0xa010, 0x42);
+    mmu.write_u8(0xc123, 0x11);
+    mmu.write_u8(0xd456, 0x22);
+    mmu.write_u8(0xff90, 0x33);
+    mmu.write_u8(0xff06, 0x80);
+    mmu.write_u8(0xffff, 0x05);
+    gb.get_peripherals_mut().ppu.get_oam_bank_mut().set_at(0x04, 0x77);
+
+    let bytes = export_bess(&gb).unwrap();
+    assert!(is_bess(&bytes));
+    assert_eq!(BESS_MAGIC, bytes[bytes.len() - 4 ..]);
+
+    let snapshot = Snapshot::from_bess(&bytes, create_gameboy("BESS")).unwrap();
+    let restored = snapshot.restore().unwrap();
+
+    assert_eq!(0x1234, restored.cpu.get_r16(RegisterR16::BC));
+    assert_eq!(0xc0de, restored.cpu.get_r16(RegisterR16::HL));
+    assert_eq!(0xdff0, restored.cpu.get_stack_pointer());
+    assert_eq!(0x0150, restored.cpu.get_instruction_pointer());
+
+    let mmu = restored.get_mmu();
+    assert_eq!(0x42, mmu.read_u8(0xa010));
+    assert_eq!(0x11, mmu.read_u8(0xc123));
+    assert_eq!(0x22, mmu.read_u8(0xd456));
+    assert_eq!(0x33, mmu.read_u8(0xff90));
+    assert_eq!(0x80, mmu.read_u8(0xff06));
+    assert_eq!(0x05, mmu.read_u8(0xffff) & 0x1f);
+    assert_eq!(0x77, restored.get_peripherals().ppu.get_oam_bank().get_at(0x04));
+
+    // exporting the snapshot again creates the same memory dump
+    let exported = snapshot.to_bess().unwrap();
+    assert_eq!(bytes[.. 0x2000], exported[.. 0x2000]);
+}
+
+
+#[test]
+fn bess_errors() {
+    let gb    = create_gameboy("GAME A");
+    let bytes = export_bess(&gb).unwrap();
+
+    assert!(matches!(
+        Snapshot::from_bess(&bytes, create_gameboy("GAME B")),
+        Err(SnapshotError::RomMismatch { .. })
+    ));
+
+    assert!(matches!(
+        Snapshot::from_bess(&bytes[.. bytes.len() - 1], create_gameboy("GAME A")),
+        Err(SnapshotError::InvalidData(_))
+    ));
+}
+
+
+#[test]
+fn bess_write_only_registers() {
+    let mut gb = create_gameboy("BESS");
+    let mmu    = gb.get_mmu_mut();
+    mmu.write_u8(MEMORY_LOCATION_APU_NR52, 0x80);
+    mmu.write_u8(MEMORY_LOCATION_APU_NR11, 0x95);
+    mmu.write_u8(MEMORY_LOCATION_APU_NR13, 0x34);
+    mmu.write_u8(MEMORY_LOCATION_APU_NR14, 0x45);
+    mmu.write_u8(MEMORY_LOCATION_APU_NR31, 0x80);
+    mmu.write_u8(MEMORY_LOCATION_APU_NR33, 0x56);
+    mmu.write_u8(MEMORY_LOCATION_APU_NR34, 0x03);
+
+    let bytes    = export_bess(&gb).unwrap();
+    let restored = Snapshot::from_bess(&bytes, create_gameboy("BESS")).unwrap().restore().unwrap();
+
+    // write-only bits are not visible on the memory bus, but need to be restored
+    assert_eq!(0xff, restored.get_mmu().read_u8(MEMORY_LOCATION_APU_NR13));
+
+    for (address, expected) in [
+        (MEMORY_LOCATION_APU_NR11, 0x95),
+        (MEMORY_LOCATION_APU_NR13, 0x34),
+        (MEMORY_LOCATION_APU_NR14, 0x45),
+        (MEMORY_LOCATION_APU_NR31, 0x80),
+        (MEMORY_LOCATION_APU_NR33, 0x56),
+        (MEMORY_LOCATION_APU_NR34, 0x03),
+    ] {
+        assert_eq!(expected, gb.get_mmu().get_register_value(address),       "{address:04x}");
+        assert_eq!(expected, restored.get_mmu().get_register_value(address), "{address:04x}");
+    }
+}
+
+
+#[test]
+fn bess_vram_dma_registers() {
+    let mut gb = common::create_gameboy(common::create_gbc_rom());
+    let mmu    = gb.get_mmu_mut();
+    mmu.write_u8(MEMORY_LOCATION_HDMA1, 0x12);
+    mmu.write_u8(MEMORY_LOCATION_HDMA2, 0x30);
+    mmu.write_u8(MEMORY_LOCATION_HDMA3, 0x05);
+    mmu.write_u8(MEMORY_LOCATION_HDMA4, 0x40);
+
+    let bytes    = export_bess(&gb).unwrap();
+    let restored = Snapshot::from_bess(&bytes, common::create_gameboy(common::create_gbc_rom())).unwrap().restore().unwrap();
+
+    for (address, expected) in [
+        (MEMORY_LOCATION_HDMA1, 0x12),
+        (MEMORY_LOCATION_HDMA2, 0x30),
+        (MEMORY_LOCATION_HDMA3, 0x05),
+        (MEMORY_LOCATION_HDMA4, 0x40),
+    ] {
+        assert_eq!(0xff, restored.get_mmu().read_u8(address),                 "{address:04x}");
+        assert_eq!(expected, restored.get_mmu().get_register_value(address), "{address:04x}");
+    }
+}
+
+
+#[test]
+fn bess_locked_cpu() {
+    let mut gb = common::create_gameboy(common::create_rom_with_program(&[
+        0xd3,           // illegal opcode
+    ]));
+
+    gb.run_single_step();
+    assert!(gb.cpu.is_locked());
+
+    // BESS files have no way to describe a locked CPU
+    assert!(matches!(export_bess(&gb), Err(SnapshotError::InvalidData(_))));
+    assert!(matches!(Snapshot::create_from(&gb).unwrap().to_bess(), Err(SnapshotError::InvalidData(_))));
+}
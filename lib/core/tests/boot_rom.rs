@@ -0,0 +1,160 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::boot_rom::{BootRom, BootRomLayout, BOOT_ROM_SIZE_CLASSIC, BOOT_ROM_SIZE_COLOR};
+use gemi_core::cartridge::{ROM_OFFSET_FLAG_CGB, ROM_OFFSET_OLD_LICENSEE_CODE};
+use gemi_core::gameboy::{DeviceType, GameBoy};
+use gemi_core::mmu::locations::{MEMORY_LOCATION_BOOT_ROM_DISABLE, MEMORY_LOCATION_KEY1, MEMORY_LOCATION_OPRI, MEMORY_LOCATION_SVBK};
+
+mod common;
+
+
+fn create_gameboy(boot_rom_size: usize, device_type: Option<DeviceType>) -> Result<GameBoy, String> {
+    let boot_rom  = BootRom::load_from_bytes(vec![0xb0; boot_rom_size]).unwrap();
+    let mut rom = vec![0xca; 0x8000];
+    rom[ROM_OFFSET_FLAG_CGB ..= ROM_OFFSET_OLD_LICENSEE_CODE].fill(0x00);
+
+    common::try_create_gameboy_with(rom, None, |builder| {
+        builder.set_boot_rom(boot_rom);
+
+        if let Some(device_type) = device_type {
+            builder.set_device_type(device_type);
+        }
+    })
+}
+
+
+#[test]
+fn invalid_boot_rom_size() {
+    assert!(BootRom::load_from_bytes(vec![0x00; 512]).is_err());
+}
+
+
+#[test]
+fn classic_boot_rom_overlay() {
+    let mut gb = create_gameboy(BOOT_ROM_SIZE_CLASSIC, None).unwrap();
+    assert!(DeviceType::GameBoyDmg == gb.get_config().device);
+
+    assert_eq!(0xb0, gb.get_mmu().read_u8(0x00ff));
+    assert_eq!(0xca, gb.get_mmu().read_u8(0x0100));
+    assert_eq!(0xca, gb.get_mmu().read_u8(0x0200));
+
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_BOOT_ROM_DISABLE, 0x01);
+    assert_eq!(0xca, gb.get_mmu().read_u8(0x0000));
+}
+
+
+#[test]
+fn color_boot_rom_overlay() {
+    let mut gb = create_gameboy(BOOT_ROM_SIZE_COLOR, None).unwrap();
+    assert!(DeviceType::GameBoyColor == gb.get_config().device);
+
+    // the cartridge header remains visible while the boot ROM is active
+    assert_eq!(0xb0, gb.get_mmu().read_u8(0x00ff));
+    assert_eq!(0xca, gb.get_mmu().read_u8(0x0100));
+    assert_eq!(0xca, gb.get_mmu().read_u8(0x01ff));
+    assert_eq!(0xb0, gb.get_mmu().read_u8(0x0200));
+    assert_eq!(0xb0, gb.get_mmu().read_u8(0x08ff));
+    assert_eq!(0xca, gb.get_mmu().read_u8(0x0900));
+
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_BOOT_ROM_DISABLE, 0x01);
+    assert_eq!(0xca, gb.get_mmu().read_u8(0x0200));
+}
+
+
+#[test]
+fn boot_rom_device_mismatch() {
+    assert!(create_gameboy(BOOT_ROM_SIZE_COLOR,   Some(DeviceType::GameBoyDmg)).is_err());
+    assert!(create_gameboy(BOOT_ROM_SIZE_CLASSIC, Some(DeviceType::GameBoyColor)).is_err());
+    assert!(create_gameboy(BOOT_ROM_SIZE_CLASSIC, Some(DeviceType::SuperGameBoy)).is_ok());
+
+    assert_eq!(BootRomLayout::Color, BootRomLayout::for_device(DeviceType::GameBoyAdvance));
+}
+
+
+/// Creates a GBC boot ROM, which writes a value into KEY0 and OPRI
+/// and unmaps itself right before entering the cartridge at 0x0100.
+fn create_color_boot_rom(key0: u8, opri: u8) -> BootRom {
+    let mut boot_rom = vec![0x00; BOOT_ROM_SIZE_COLOR];
+
+    boot_rom[0x0000 .. 0x0008].copy_from_slice(&[
+        0x3e, key0,     // LD A, key0
+        0xe0, 0x4c,     // LDH (0x4c), A
+        0x3e, opri,     // LD A, opri
+        0xe0, 0x6c,     // LDH (0x6c), A
+    ]);
+
+    boot_rom[0x00fc .. 0x0100].copy_from_slice(&[
+        0x3e, 0x11,     // LD A, 0x11
+        0xe0, 0x50,     // LDH (0x50), A
+    ]);
+
+    BootRom::load_from_bytes(boot_rom).unwrap()
+}
+
+
+/// Runs the boot ROM until the CPU enters the cartridge.
+fn run_boot_rom(gb: &mut GameBoy) {
+    gb.initialize();
+
+    while gb.cpu.get_instruction_pointer() != 0x0100 {
+        assert!(gb.get_config().is_gbc_enabled());
+        gb.run_single_step();
+    }
+}
+
+
+#[test]
+fn dmg_cartridge_through_color_boot_rom() {
+    let boot_rom = create_color_boot_rom(0x04, 0x01);
+    let mut gb   = common::create_gameboy_with(common::create_rom(0x8000), None, |builder| {
+        builder.set_boot_rom(boot_rom);
+    });
+
+    run_boot_rom(&mut gb);
+
+    // the boot ROM selected the DMG compatibility mode, which disables GBC registers
+    assert!(DeviceType::GameBoyColor == gb.get_config().device);
+    assert!(!gb.get_config().is_gbc_enabled());
+    assert!(!gb.get_mmu().get_device_config().is_gbc_enabled());
+    assert_eq!(0xff, gb.get_mmu().read_u8(MEMORY_LOCATION_KEY1));
+    assert_eq!(0xff, gb.get_mmu().read_u8(MEMORY_LOCATION_SVBK));
+    assert_eq!(0xff, gb.get_mmu().read_u8(MEMORY_LOCATION_OPRI));
+
+    // KEY0 and OPRI are locked after the boot ROM got unmapped
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_OPRI, 0x00);
+    assert_eq!(0xff, gb.get_mmu().read_u8(MEMORY_LOCATION_OPRI));
+}
+
+
+#[test]
+fn gbc_cartridge_through_color_boot_rom() {
+    let rom      = common::create_gbc_rom();
+    let boot_rom = create_color_boot_rom(0x80, 0x00);
+    let mut gb   = common::create_gameboy_with(rom, None, |builder| {
+        builder.set_boot_rom(boot_rom);
+    });
+
+    run_boot_rom(&mut gb);
+
+    // the GBC mode remains active
+    assert!(gb.get_config().is_gbc_enabled());
+    assert_eq!(0xfe, gb.get_mmu().read_u8(MEMORY_LOCATION_OPRI));
+
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_OPRI, 0x01);
+    assert_eq!(0xfe, gb.get_mmu().read_u8(MEMORY_LOCATION_OPRI));
+}
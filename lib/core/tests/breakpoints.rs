@@ -0,0 +1,193 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::cpu::cpu::RegisterR8;
+use gemi_core::cpu::interrupts::Interrupt;
+use gemi_core::debug::DebugEvent;
+use gemi_core::debug::breakpoints::{Breakpoint, MemoryAccess, ValueCondition};
+use gemi_core::gameboy::GameBoy;
+
+mod common;
+
+
+fn create_gameboy_with_program(program: &[u8]) -> GameBoy {
+    let mut rom = common::create_rom_with_program(program);
+
+    // return from any interrupt handler
+    for handler in [0x40, 0x48, 0x50, 0x58, 0x60] {
+        rom[handler] = 0xd9; // RETI
+    }
+
+    common::create_gameboy(rom)
+}
+
+
+/// A program counting up a value in WRAM and writing it into the SCX register.
+fn create_counting_gameboy() -> GameBoy {
+    create_gameboy_with_program(&[
+        0x21, 0x00, 0xc0,   // LD HL, 0xc000
+        0x34,               // INC (HL)
+        0x7e,               // LD A, (HL)
+        0xe0, 0x43,         // LDH (0x43), A
+        0x18, 0xfa,         // JR -6
+    ])
+}
+
+
+#[test]
+fn execute_breakpoint_stops_at_instruction() {
+    let mut gb = create_counting_gameboy();
+    let id     = gb.get_breakpoints_mut().add(Breakpoint::Execute { address: 0x0104, bank: None }).unwrap();
+
+    // the emulator stops before executing the instruction
+    let results = gb.run_frame();
+    assert!(results.events.contains(DebugEvent::BreakpointHit));
+    assert_eq!(1, results.breakpoint_hits.len());
+    assert_eq!(id, results.breakpoint_hits[0].id);
+    assert_eq!(0x0104, results.breakpoint_hits[0].instruction_address);
+    assert_eq!(0x0104, gb.cpu.get_instruction_pointer());
+    assert_eq!(0x01, gb.get_mmu().read_u8(0xc000));
+
+    // continuing executes the instruction and stops on the next iteration
+    let results = gb.run_frame();
+    assert!(results.events.contains(DebugEvent::BreakpointHit));
+    assert_eq!(0x0104, gb.cpu.get_instruction_pointer());
+    assert_eq!(0x02, gb.get_mmu().read_u8(0xc000));
+    assert_eq!(2, gb.get_breakpoints().get(id).unwrap().hit_count);
+
+    // disabled breakpoints are ignored
+    gb.get_breakpoints_mut().set_enabled(id, false);
+    let results = gb.run_frame();
+    assert!(!results.events.contains(DebugEvent::BreakpointHit));
+    assert!(results.breakpoint_hits.is_empty());
+}
+
+
+#[test]
+fn write_watchpoint_with_value_condition() {
+    let mut gb = create_counting_gameboy();
+    let id     = gb.get_breakpoints_mut().add(Breakpoint::Memory {
+        begin:      0xc000,
+        end:        0xc0ff,
+        access:     MemoryAccess::Write,
+        condition:  ValueCondition::Equals(0x10),
+    }).unwrap();
+
+    let results = gb.run_frame();
+    assert_eq!(1, results.breakpoint_hits.len());
+
+    // stops right after the instruction writing the value
+    let hit    = results.breakpoint_hits[0];
+    let access = hit.memory_access.unwrap();
+    assert_eq!(id, hit.id);
+    assert_eq!(0x0103, hit.instruction_address);
+    assert_eq!(0xc000, access.address);
+    assert_eq!(0x10, access.value);
+    assert_eq!(MemoryAccess::Write, access.access);
+    assert_eq!(0x0104, gb.cpu.get_instruction_pointer());
+
+    // reading values by the frontend does not trigger any watchpoints
+    gb.get_breakpoints_mut().add(Breakpoint::Memory {
+        begin:      0xc000,
+        end:        0xc000,
+        access:     MemoryAccess::Read,
+        condition:  ValueCondition::Any,
+    }).unwrap();
+
+    assert_eq!(0x10, gb.get_mmu().read_u8(0xc000));
+    assert_eq!(1, gb.get_breakpoints().get(id).unwrap().hit_count);
+}
+
+
+#[test]
+fn io_register_watchpoint() {
+    let mut gb = create_counting_gameboy();
+
+    // registers outside of the IO range are rejected
+    assert!(gb.get_breakpoints_mut().add(Breakpoint::IoRegister {
+        address:    0xc000,
+        access:     MemoryAccess::Write,
+        condition:  ValueCondition::Any,
+    }).is_err());
+
+    gb.get_breakpoints_mut().add(Breakpoint::IoRegister {
+        address:    0xff43,
+        access:     MemoryAccess::Write,
+        condition:  ValueCondition::Masked { mask: 0x0f, value: 0x03 },
+    }).unwrap();
+
+    let results = gb.run_frame();
+    assert_eq!(1, results.breakpoint_hits.len());
+    assert_eq!(0x0105, results.breakpoint_hits[0].instruction_address);
+    assert_eq!(0x03, results.breakpoint_hits[0].memory_access.unwrap().value);
+
+    let results = gb.run_frame();
+    assert_eq!(1, results.breakpoint_hits.len());
+    assert_eq!(0x13, results.breakpoint_hits[0].memory_access.unwrap().value);
+}
+
+
+#[test]
+fn interrupt_breakpoint() {
+    let mut gb = create_gameboy_with_program(&[
+        0x3e, 0x04,         // LD A, 0x04
+        0xe0, 0xff,         // LDH (0xff), A    ; enable timer interrupt
+        0x3e, 0x05,         // LD A, 0x05
+        0xe0, 0x07,         // LDH (0x07), A    ; start timer
+        0xfb,               // EI
+        0x18, 0xfe,         // JR -2
+    ]);
+
+    let id = gb.get_breakpoints_mut().add(Breakpoint::Interrupt(Interrupt::Timer)).unwrap();
+
+    // stops before executing the first instruction of the interrupt handler
+    let results = gb.run_frame();
+    assert_eq!(1, results.breakpoint_hits.len());
+    assert_eq!(id, results.breakpoint_hits[0].id);
+    assert_eq!(0x0109, results.breakpoint_hits[0].instruction_address);
+    assert_eq!(Interrupt::Timer.address(), gb.cpu.get_instruction_pointer());
+}
+
+
+#[test]
+fn bank_aware_execute_breakpoint() {
+    // MBC1 with 4 banks
+    let mut rom = common::create_mbc_rom(0x10000, 0x01, 0x00);
+
+    common::set_program(&mut rom, &[
+        0x3e, 0x01,         // LD A, 0x01
+        0xea, 0x00, 0x20,   // LD (0x2000), A
+        0xcd, 0x00, 0x40,   // CALL 0x4000
+        0x3c,               // INC A
+        0xea, 0x00, 0x20,   // LD (0x2000), A
+        0xcd, 0x00, 0x40,   // CALL 0x4000
+        0x76,               // HALT
+    ]);
+
+    // each bank only contains a RET instruction
+    for bank in 1 .. 4 {
+        rom[bank * 0x4000] = 0xc9;
+    }
+
+    let mut gb = common::create_gameboy(rom);
+    gb.get_breakpoints_mut().add(Breakpoint::Execute { address: 0x4000, bank: Some(2) }).unwrap();
+
+    let results = gb.run_frame();
+    assert_eq!(1, results.breakpoint_hits.len());
+    assert_eq!(0x4000, gb.cpu.get_instruction_pointer());
+    assert_eq!(0x02, gb.cpu.get_r8(RegisterR8::A));
+}
@@ -0,0 +1,187 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+use gemi_core::camera::{CameraImage, CameraImageSource, StaticImageSource, CAMERA_IMAGE_WIDTH};
+use gemi_core::gameboy::GameBoy;
+use gemi_core::mmu::mbc::MemoryBankController;
+
+mod common;
+
+
+/// The number of cycles a capture takes without N flag and with an exposure time of 0x1000.
+const CAPTURE_CYCLES: u64 = 129_792 + 2_048 + 0x1000 * 64;
+
+
+/// An image source with white pixels on the left half and black pixels on the right half.
+struct HalfImageSource;
+
+impl CameraImageSource for HalfImageSource {
+    fn capture(&mut self, image: &mut CameraImage) {
+        for (index, pixel) in image.iter_mut().enumerate() {
+            *pixel = if (index % CAMERA_IMAGE_WIDTH) < CAMERA_IMAGE_WIDTH / 2 { 0xff } else { 0x00 };
+        }
+    }
+}
+
+
+fn create_camera() -> GameBoy {
+    let gb = common::create_gameboy(common::create_mbc_rom(0x20000, 0xfc, 0x04));
+
+    let cartridge = gb.get_peripherals().mem.get_cartridge().unwrap();
+    assert!(matches!(cartridge.get_mbc(), MemoryBankController::PocketCamera));
+    assert_eq!(16, cartridge.get_ram_bank_count());
+
+    gb
+}
+
+
+/// Configures the sensor with a neutral exposure and a dithering matrix
+/// mapping brightness below 0x40, 0x80 and 0xc0 to the darker shades.
+fn configure_sensor(gb: &mut GameBoy) {
+    gb.get_mmu_mut().write_u8(0x0000, 0x0a);
+    gb.get_mmu_mut().write_u8(0x4000, 0x10);
+    gb.get_mmu_mut().write_u8(0xa001, 0x00);
+    gb.get_mmu_mut().write_u8(0xa002, 0x10);
+    gb.get_mmu_mut().write_u8(0xa003, 0x00);
+
+    for entry in 0..16 {
+        gb.get_mmu_mut().write_u8(0xa006 + entry * 3, 0x40);
+        gb.get_mmu_mut().write_u8(0xa007 + entry * 3, 0x80);
+        gb.get_mmu_mut().write_u8(0xa008 + entry * 3, 0xc0);
+    }
+}
+
+
+/// Starts a capture and waits until it is completed.
+fn capture(gb: &mut GameBoy) {
+    gb.get_mmu_mut().write_u8(0x4000, 0x10);
+    gb.get_mmu_mut().write_u8(0xa000, 0x01);
+    gb.get_peripherals_mut().mem.update(CAPTURE_CYCLES);
+    gb.get_mmu_mut().write_u8(0x4000, 0x00);
+}
+
+
+#[test]
+fn camera_capture_timing() {
+    let mut gb = create_camera();
+    configure_sensor(&mut gb);
+    gb.get_peripherals_mut().mem.set_camera_image_source(Box::new(StaticImageSource::with_brightness(0xff)));
+
+    gb.get_mmu_mut().write_u8(0xa000, 0x01);
+    assert_eq!(0x01, gb.get_mmu().read_u8(0xa000) & 0x01);
+
+    // RAM can not be accessed while capturing
+    gb.get_peripherals_mut().mem.update(CAPTURE_CYCLES - 4);
+    assert_eq!(0x01, gb.get_mmu().read_u8(0xa000) & 0x01);
+    gb.get_mmu_mut().write_u8(0x4000, 0x00);
+    assert_eq!(0x00, gb.get_mmu().read_u8(0xa100));
+    gb.get_mmu_mut().write_u8(0x4000, 0x10);
+
+    gb.get_peripherals_mut().mem.update(4);
+    assert_eq!(0x00, gb.get_mmu().read_u8(0xa000) & 0x01);
+}
+
+
+#[test]
+fn camera_capture_shades() {
+    let mut gb = create_camera();
+    configure_sensor(&mut gb);
+
+    let shades = [
+        (0xff, [0x00, 0x00]),
+        (0xa0, [0xff, 0x00]),
+        (0x60, [0x00, 0xff]),
+        (0x00, [0xff, 0xff]),
+    ];
+
+    for (brightness, expected) in shades {
+        gb.get_peripherals_mut().mem.set_camera_image_source(Box::new(StaticImageSource::with_brightness(brightness)));
+        capture(&mut gb);
+
+        // first and last row of the first and last tile
+        for address in [0xa100, 0xa10e, 0xae00, 0xae0e] {
+            assert_eq!(expected[0], gb.get_mmu().read_u8(address),     "brightness {brightness:02x} at {address:04x}");
+            assert_eq!(expected[1], gb.get_mmu().read_u8(address + 1), "brightness {brightness:02x} at {address:04x}");
+        }
+    }
+}
+
+
+#[test]
+fn camera_capture_tile_layout() {
+    let mut gb = create_camera();
+    configure_sensor(&mut gb);
+    gb.get_peripherals_mut().mem.set_camera_image_source(Box::new(HalfImageSource));
+    capture(&mut gb);
+
+    // the image is stored as 16 tiles per row, so the left 8 tiles are white and the right 8 tiles black
+    for tile in 0..16 {
+        let expected = if tile < 8 { 0x00 } else { 0xff };
+        let address  = 0xa100 + tile * 16;
+        assert_eq!(expected, gb.get_mmu().read_u8(address),     "tile {tile}");
+        assert_eq!(expected, gb.get_mmu().read_u8(address + 1), "tile {tile}");
+    }
+}
+
+
+#[test]
+fn camera_capture_inverted() {
+    let mut gb = create_camera();
+    configure_sensor(&mut gb);
+    gb.get_mmu_mut().write_u8(0xa004, 0x08);
+    gb.get_peripherals_mut().mem.set_camera_image_source(Box::new(HalfImageSource));
+    capture(&mut gb);
+
+    // with the invert flag set, the left 8 tiles are black and the right 8 tiles white
+    for tile in 0..16 {
+        let expected = if tile < 8 { 0xff } else { 0x00 };
+        let address  = 0xa100 + tile * 16;
+        assert_eq!(expected, gb.get_mmu().read_u8(address),     "tile {tile}");
+        assert_eq!(expected, gb.get_mmu().read_u8(address + 1), "tile {tile}");
+    }
+}
+
+
+#[cfg(feature = "snapshots")]
+#[test]
+fn camera_image_source_after_restore() {
+    use gemi_core::movie::{MovieConfig, MoviePlayer, MovieRecorder};
+    use gemi_core::snapshots::Snapshot;
+
+    let mut gb = create_camera();
+    configure_sensor(&mut gb);
+    gb.get_peripherals_mut().mem.set_camera_image_source(Box::new(HalfImageSource));
+
+    // the image source is not part of the snapshot
+    let mut restored = Snapshot::create_from(&gb).unwrap().restore_for(&gb).unwrap();
+    assert!(restored.get_peripherals_mut().mem.take_camera_image_source().is_none());
+
+    // but can be carried over from the previous instance
+    let image_source = gb.get_peripherals_mut().mem.take_camera_image_source().unwrap();
+    assert!(gb.get_peripherals_mut().mem.take_camera_image_source().is_none());
+    restored.get_peripherals_mut().mem.set_camera_image_source(image_source);
+    capture(&mut restored);
+
+    for tile in 0..16 {
+        let expected = if tile < 8 { 0x00 } else { 0xff };
+        assert_eq!(expected, restored.get_mmu().read_u8(0xa100 + tile * 16), "tile {tile}");
+    }
+
+    // a movie starting from a snapshot keeps the image source of the device playing it
+    let movie = MovieRecorder::from_snapshot(&restored, MovieConfig::default()).unwrap().finish();
+    MoviePlayer::new(movie, &mut restored).unwrap();
+    assert!(restored.get_peripherals_mut().mem.take_camera_image_source().is_some());
+}
@@ -0,0 +1,208 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+use gemi_core::cartridge::*;
+use gemi_core::mmu::mbc::MemoryBankController;
+use gemi_core::mmu::memory_data::{MemoryData, MemoryDataDynamic};
+
+mod common;
+
+
+/// Creates a ROM with a valid header and the given size, where each bank is marked with its number.
+fn create_rom(size: usize, rom_type: u8, rom_size: u8, ram_size: u8) -> Vec<u8> {
+    let mut rom = vec![0x00; size];
+    common::mark_rom_banks(&mut rom);
+    write_header(&mut rom, 0, rom_type, rom_size, ram_size);
+
+    rom
+}
+
+
+/// Writes a valid header at a particular offset, including the checksums.
+fn write_header(rom: &mut [u8], offset: usize, rom_type: u8, rom_size: u8, ram_size: u8) {
+    let header = &mut rom[offset ..];
+    header[ROM_OFFSET_LOGO_BITMAP .. ROM_OFFSET_LOGO_BITMAP + NINTENDO_LOGO.len()].copy_from_slice(&NINTENDO_LOGO);
+    header[ROM_OFFSET_ROM_TYPE] = rom_type;
+    header[ROM_OFFSET_ROM_SIZE] = rom_size;
+    header[ROM_OFFSET_RAM_SIZE] = ram_size;
+    header[ROM_OFFSET_HEADER_CHECKSUM] = compute_header_checksum(&header[ROM_OFFSET_TITLE_STRING .. ROM_OFFSET_HEADER_CHECKSUM]);
+    header[ROM_OFFSET_GLOBAL_CHECKSUM .. ROM_OFFSET_GLOBAL_CHECKSUM + 2].fill(0x00);
+
+    let global_checksum = rom.iter().fold(0u16, |checksum, b| checksum.wrapping_add(*b as u16));
+    rom[offset + ROM_OFFSET_GLOBAL_CHECKSUM .. offset + ROM_OFFSET_GLOBAL_CHECKSUM + 2].copy_from_slice(&global_checksum.to_be_bytes());
+}
+
+
+#[test]
+fn rom_too_small() {
+    for size in [0, 0x100, ROM_MIN_SIZE - 1] {
+        assert!(matches!(
+            Cartridge::load_from_bytes(vec![0x00; size], None),
+            Err(CartridgeError::TooSmall { size: s }) if s == size
+        ));
+    }
+
+    assert!(Cartridge::load_from_bytes(vec![0x00; ROM_MIN_SIZE], None).is_ok());
+}
+
+
+#[test]
+fn unsupported_mapper() {
+    for rom_type in [0x04, 0x07, 0x0a, 0x14, 0x21, 0x80, 0xfb] {
+        assert!(matches!(
+            Cartridge::load_from_bytes(create_rom(0x8000, rom_type, 0x00, 0x00), None),
+            Err(CartridgeError::UnsupportedMapper(t)) if t == rom_type
+        ));
+    }
+}
+
+
+#[test]
+fn ram_size_mismatch() {
+    let rom    = create_rom(0x8000, 0x03, 0x00, 0x02);
+    let result = Cartridge::load_from_bytes(rom.clone(), Some(vec![0x00; 100]));
+    assert!(matches!(result, Err(CartridgeError::RamSizeMismatch { expected: 0x2000, found: 100 })));
+
+    assert!(Cartridge::load_from_bytes(rom, Some(vec![0x00; 0x2000])).is_ok());
+}
+
+
+#[test]
+fn header_validation() {
+    let rom       = create_rom(0x8000, 0x00, 0x00, 0x00);
+    let cartridge = Cartridge::load_from_bytes(rom.clone(), None).unwrap();
+    assert!(cartridge.get_header_validation().is_valid());
+
+    let mut bad_logo = rom.clone();
+    bad_logo[ROM_OFFSET_LOGO_BITMAP] ^= 0xff;
+    let validation = *Cartridge::load_from_bytes(bad_logo, None).unwrap().get_header_validation();
+    assert!(!validation.logo_valid);
+    assert!(!validation.global_checksum_valid);
+    assert!(validation.header_checksum_valid);
+
+    let mut bad_header = rom.clone();
+    bad_header[ROM_OFFSET_TITLE_STRING] = b'X';
+    let validation = *Cartridge::load_from_bytes(bad_header, None).unwrap().get_header_validation();
+    assert!(validation.logo_valid);
+    assert!(!validation.header_checksum_valid);
+    assert!(!validation.global_checksum_valid);
+
+    let mut bad_global = rom.clone();
+    bad_global[0x4000] = 0x42;
+    let validation = *Cartridge::load_from_bytes(bad_global, None).unwrap().get_header_validation();
+    assert!(validation.logo_valid);
+    assert!(validation.header_checksum_valid);
+    assert!(!validation.global_checksum_valid);
+}
+
+
+#[test]
+fn mmm01_menu_header_validation() {
+    // the menu's header in the last 32kiB contains the checksum for the whole ROM
+    let mut rom = vec![0x00; 0x20000];
+    common::mark_rom_banks(&mut rom);
+    write_header(&mut rom, 0x18000, 0x0b, 0x02, 0x00);
+
+    let cartridge = Cartridge::load_from_bytes(rom.clone(), None).unwrap();
+    assert!(matches!(cartridge.get_mbc(), MemoryBankController::MMM01));
+    assert!(cartridge.get_header_validation().is_valid());
+
+    rom[0x4000] = 0x42;
+    let validation = *Cartridge::load_from_bytes(rom, None).unwrap().get_header_validation();
+    assert!(validation.header_checksum_valid);
+    assert!(!validation.global_checksum_valid);
+}
+
+
+#[test]
+fn truncated_rom_is_mirrored() {
+    // the header claims 2MiB, but the file only contains 4 banks
+    let mut gb = common::create_gameboy(create_rom(0x10000, 0x01, 0x06, 0x00));
+    assert_eq!(4, gb.get_peripherals().mem.get_cartridge().unwrap().get_rom_bank_count());
+
+    for bank in 1 .. 16 {
+        gb.get_mmu_mut().write_u8(0x2000, bank);
+        assert_eq!(bank & 0x03, gb.get_mmu().read_u8(0x5000), "bank {bank}");
+    }
+}
+
+
+#[test]
+fn incomplete_bank_reads_open_bus() {
+    // 1.5 banks will be mapped into a 32kiB ROM chip
+    let gb = common::create_gameboy(create_rom(0x6000, 0x00, 0x00, 0x00));
+    assert_eq!(2, gb.get_peripherals().mem.get_cartridge().unwrap().get_rom_bank_count());
+    assert_eq!(1,    gb.get_mmu().read_u8(0x5000));
+    assert_eq!(0xff, gb.get_mmu().read_u8(0x7000));
+}
+
+
+#[test]
+fn oversized_rom_is_accessible() {
+    // the header claims 32kiB, but the file contains 8 banks
+    let mut gb = common::create_gameboy(create_rom(0x20000, 0x01, 0x00, 0x00));
+    assert_eq!(8, gb.get_peripherals().mem.get_cartridge().unwrap().get_rom_bank_count());
+
+    gb.get_mmu_mut().write_u8(0x2000, 0x05);
+    assert_eq!(5, gb.get_mmu().read_u8(0x5000));
+}
+
+
+#[test]
+fn small_ram_is_mirrored() {
+    // 2kiB of RAM are mirrored within the 8kiB RAM area
+    let mut gb = common::create_gameboy(create_rom(0x8000, 0x03, 0x00, 0x01));
+    gb.get_mmu_mut().write_u8(0x0000, 0x0a);
+    gb.get_mmu_mut().write_u8(0xa123, 0x42);
+    assert_eq!(0x42, gb.get_mmu().read_u8(0xa923));
+    assert_eq!(0x42, gb.get_mmu().read_u8(0xb923));
+}
+
+
+#[test]
+fn empty_memory_data_reads_open_bus() {
+    let mut data = MemoryDataDynamic::alloc(0);
+    data.set_at(0x0123, 0x42);
+    assert_eq!(0xff, data.get_at(0x0123));
+}
+
+
+#[test]
+fn random_roms_do_not_panic() {
+    let mut seed = 0x1234_5678u32;
+    let mut next_random = || {
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (seed >> 24) as u8
+    };
+
+    let rom_types = [
+        0x00, 0x01, 0x02, 0x03, 0x05, 0x06, 0x08, 0x09, 0x0b, 0x0c, 0x0d, 0x0f, 0x10, 0x11, 0x12, 0x13,
+        0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x20, 0x22, 0xfc, 0xfd, 0xfe, 0xff,
+    ];
+
+    // run random code on each mapper with random ROM and RAM sizes
+    for _ in 0 .. 4 {
+        for rom_type in rom_types {
+            let size    = ROM_MIN_SIZE + (next_random() as usize) * 0x400;
+            let mut rom = (0 .. size).map(|_| next_random()).collect::<Vec<_>>();
+            rom[ROM_OFFSET_ROM_TYPE] = rom_type;
+
+            let mut gb = common::create_gameboy(rom);
+            gb.initialize();
+            gb.run_frame();
+        }
+    }
+}
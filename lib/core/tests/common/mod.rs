@@ -0,0 +1,117 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Shared helpers to construct ROM images and emulator instances for the integration tests.
+//! Not every test uses every helper.
+#![allow(dead_code)]
+
+use gemi_core::cartridge::{Cartridge, ROM_OFFSET_ENTRY_POINT, ROM_OFFSET_FLAG_CGB, ROM_OFFSET_RAM_SIZE, ROM_OFFSET_ROM_SIZE, ROM_OFFSET_ROM_TYPE, ROM_OFFSET_TITLE_STRING};
+use gemi_core::gameboy::{Builder, GameBoy};
+
+
+/// The size of a single ROM bank.
+pub const ROM_BANK_SIZE: usize = 0x4000;
+
+/// The offset within each ROM bank where [mark_rom_banks] stores the bank number.
+pub const ROM_BANK_MARKER_OFFSET: usize = 0x1000;
+
+
+/// Creates an empty ROM image of the given size with a matching ROM size in its header.
+pub fn create_rom(size: usize) -> Vec<u8> {
+    let mut rom = vec![0x00; size];
+    rom[ROM_OFFSET_ROM_SIZE] = (size / 0x8000).max(1).trailing_zeros() as u8;
+    rom
+}
+
+
+/// Creates a ROM image for a cartridge with a memory bank controller,
+/// where each ROM bank is marked with its number.
+pub fn create_mbc_rom(size: usize, rom_type: u8, ram_size: u8) -> Vec<u8> {
+    let mut rom = create_rom(size);
+    rom[ROM_OFFSET_ROM_TYPE] = rom_type;
+    rom[ROM_OFFSET_RAM_SIZE] = ram_size;
+    mark_rom_banks(&mut rom);
+    rom
+}
+
+
+/// Creates a 32kiB ROM image with a program placed at its entry point.
+pub fn create_rom_with_program(program: &[u8]) -> Vec<u8> {
+    let mut rom = create_rom(0x8000);
+    set_program(&mut rom, program);
+    rom
+}
+
+
+/// Creates a 32kiB ROM image, which enables the GBC mode.
+pub fn create_gbc_rom() -> Vec<u8> {
+    let mut rom = create_rom(0x8000);
+    rom[ROM_OFFSET_FLAG_CGB] = 0x80;
+    rom
+}
+
+
+/// Stores the number of each ROM bank at offset [ROM_BANK_MARKER_OFFSET] within the bank.
+pub fn mark_rom_banks(rom: &mut [u8]) {
+    for (bank, data) in rom.chunks_mut(ROM_BANK_SIZE).enumerate() {
+        if let Some(b) = data.get_mut(ROM_BANK_MARKER_OFFSET) {
+            *b = bank as u8;
+        }
+    }
+}
+
+
+/// Writes the title into the cartridge header.
+pub fn set_title(rom: &mut [u8], title: &str) {
+    rom[ROM_OFFSET_TITLE_STRING .. ROM_OFFSET_TITLE_STRING + title.len()].copy_from_slice(title.as_bytes());
+}
+
+
+/// Places a program at the entry point of the ROM.
+pub fn set_program(rom: &mut [u8], program: &[u8]) {
+    rom[ROM_OFFSET_ENTRY_POINT .. ROM_OFFSET_ENTRY_POINT + program.len()].copy_from_slice(program);
+}
+
+
+/// Creates an emulator instance running the given ROM.
+pub fn create_gameboy(rom: Vec<u8>) -> GameBoy {
+    create_gameboy_with(rom, None, |_| {})
+}
+
+
+/// Creates an emulator instance running the given ROM with some initial cartridge RAM.
+pub fn create_gameboy_with_ram(rom: Vec<u8>, ram: Option<Vec<u8>>) -> GameBoy {
+    create_gameboy_with(rom, ram, |_| {})
+}
+
+
+/// Creates an emulator instance running the given ROM,
+/// allowing to further configure the builder before the instance is created.
+pub fn create_gameboy_with(rom: Vec<u8>, ram: Option<Vec<u8>>, configure: impl FnOnce(&mut Builder)) -> GameBoy {
+    try_create_gameboy_with(rom, ram, configure).unwrap()
+}
+
+
+/// Like [create_gameboy_with], but returns any error of the builder instead of panicking.
+pub fn try_create_gameboy_with(rom: Vec<u8>, ram: Option<Vec<u8>>, configure: impl FnOnce(&mut Builder)) -> Result<GameBoy, String> {
+    let cartridge = Cartridge::load_from_bytes(rom, ram).unwrap();
+
+    let mut builder = GameBoy::build();
+    builder.set_cartridge(cartridge);
+    configure(&mut builder);
+    builder.finish()
+}
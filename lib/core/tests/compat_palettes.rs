@@ -0,0 +1,78 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::cartridge::ROM_OFFSET_OLD_LICENSEE_CODE;
+use gemi_core::gameboy::{DeviceType, GameBoy};
+use gemi_core::ppu::compat_palettes::ManualPaletteSelection;
+use gemi_core::ppu::graphic_data::GbcPaletteData;
+
+mod common;
+
+
+/// Creates a classic GameBoy on a GameBoy Color running a cartridge with the given title and licensee.
+fn create_gameboy(title: &str, licensee: u8, selection: Option<ManualPaletteSelection>) -> GameBoy {
+    let mut rom = common::create_rom(0x8000);
+    common::set_title(&mut rom, title);
+    rom[ROM_OFFSET_OLD_LICENSEE_CODE] = licensee;
+
+    let mut gb = common::create_gameboy_with(rom, None, |builder| {
+        builder.set_device_type(DeviceType::GameBoyColor);
+
+        if let Some(selection) = selection {
+            builder.set_dmg_compatibility_palette(selection);
+        }
+    });
+
+    gb.initialize();
+    gb
+}
+
+
+fn check_palettes(gb: &GameBoy, bg: [u16; 4], obj0: [u16; 4], obj1: [u16; 4]) {
+    let palettes = gb.get_peripherals().ppu.get_palettes();
+
+    assert!(palettes.gbc_background_palette.get()[0] == GbcPaletteData::from_rgb_555(bg));
+    assert!(palettes.gbc_object_palette.get()[0]     == GbcPaletteData::from_rgb_555(obj0));
+    assert!(palettes.gbc_object_palette.get()[1]     == GbcPaletteData::from_rgb_555(obj1));
+}
+
+
+#[test]
+fn palette_by_title_checksum() {
+    let gb = create_gameboy("TETRIS", 0x01, None);
+    let colors = [0x7fff, 0x03ff, 0x001f, 0x0000];
+
+    check_palettes(&gb, colors, colors, colors);
+}
+
+
+#[test]
+fn default_palette_for_unlicensed_games() {
+    let gb = create_gameboy("TETRIS", 0x33, None);
+    let obj = [0x7fff, 0x421f, 0x1cf2, 0x0000];
+
+    check_palettes(&gb, [0x7fff, 0x1bef, 0x6180, 0x0000], obj, obj);
+}
+
+
+#[test]
+fn manual_palette_selection() {
+    let gb = create_gameboy("TETRIS", 0x01, Some(ManualPaletteSelection::Down));
+    let colors = [0x53ff, 0x4a5f, 0x7e52, 0x0000];
+
+    check_palettes(&gb, colors, colors, colors);
+}
@@ -0,0 +1,147 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::cpu::cpu::{RegisterR16, RegisterR8};
+use gemi_core::debug::DebugEvent;
+use gemi_core::gameboy::GameBoy;
+use gemi_core::mmu::locations::{MEMORY_LOCATION_INTERRUPTS_ENABLED, MEMORY_LOCATION_INTERRUPTS_FLAGGED};
+
+mod common;
+
+
+fn create_gameboy(program: &[u8]) -> GameBoy {
+    common::create_gameboy(common::create_rom_with_program(program))
+}
+
+
+#[test]
+fn illegal_opcode_locks_cpu() {
+    let mut gb = create_gameboy(&[
+        0xd3,           // illegal opcode
+        0x3c,           // INC A
+    ]);
+
+    let result = gb.run_single_step();
+    assert!(result.events.contains(DebugEvent::CpuLocked));
+    assert!(gb.cpu.is_locked());
+
+    // the CPU won't execute any further instructions, even with interrupts pending
+    gb.cpu.enable_interrupts();
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_INTERRUPTS_ENABLED, 0x1f);
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_INTERRUPTS_FLAGGED, 0x1f);
+
+    for _ in 0..16 {
+        gb.run_single_step();
+    }
+
+    assert!(gb.cpu.is_locked());
+    assert_eq!(0x0101, gb.cpu.get_instruction_pointer());
+}
+
+
+#[test]
+fn halt_bug_reads_next_byte_twice() {
+    let mut gb = create_gameboy(&[
+        0x3e, 0x01,     // LD A, $01
+        0xe0, 0xff,     // LDH ($ff), A
+        0xe0, 0x0f,     // LDH ($0f), A
+        0x76,           // HALT
+        0x3c,           // INC A
+        0x18, 0xfe,     // JR -2
+    ]);
+
+    // HALT with interrupts disabled but pending does not halt the CPU,
+    // but executes the following INC A twice
+    for _ in 0..6 {
+        gb.run_single_step();
+    }
+
+    assert!(gb.cpu.is_running());
+    assert_eq!(0x03, gb.cpu.get_r8(RegisterR8::A));
+    assert_eq!(0x0108, gb.cpu.get_instruction_pointer());
+}
+
+
+#[test]
+fn halt_is_reported_as_event() {
+    let mut gb = create_gameboy(&[
+        0x3e, 0x40,     // LD A, $40
+        0xe0, 0x41,     // LDH ($41), A     ; STAT interrupt on LY == LYC
+        0x3e, 0x0a,     // LD A, $0a
+        0xe0, 0x45,     // LDH ($45), A     ; LYC = 10
+        0x3e, 0x02,     // LD A, $02
+        0xe0, 0xff,     // LDH ($ff), A
+        0xaf,           // XOR A
+        0xe0, 0x0f,     // LDH ($0f), A
+        0x76,           // HALT
+        0x18, 0xfe,     // JR -2
+    ]);
+
+    for _ in 0..8 {
+        let result = gb.run_single_step();
+        assert!(!result.events.contains(DebugEvent::CpuHalted));
+    }
+
+    let result = gb.run_single_step();
+    assert!(result.events.contains(DebugEvent::CpuHalted));
+    assert!(!gb.cpu.is_running());
+
+    // the CPU gets woken up by the STAT interrupt on line 10 and won't halt again
+    let result = gb.run_frame();
+    assert!(!result.events.contains(DebugEvent::CpuHalted));
+    assert!(gb.cpu.is_running());
+
+    // halting within a frame is reported, even if the CPU was woken up before the frame ended
+    gb.cpu.set_instruction_pointer(0x010c);
+
+    let result = gb.run_frame();
+    assert!(result.events.contains(DebugEvent::CpuHalted));
+    assert!(gb.cpu.is_running());
+}
+
+
+#[test]
+fn address_arithmetic_wraps_around() {
+    let mut gb = create_gameboy(&[
+        0x21, 0xff, 0xff,   // LD HL, $ffff
+        0x2a,               // LD A, (HL+)
+        0x32,               // LD (HL-), A
+    ]);
+
+    for _ in 0..2 {
+        gb.run_single_step();
+    }
+
+    assert_eq!(0x0000, gb.cpu.get_r16(RegisterR16::HL));
+    gb.run_single_step();
+    assert_eq!(0xffff, gb.cpu.get_r16(RegisterR16::HL));
+
+    // jumping backwards from the start of the address space
+    let mut rom = common::create_rom(0x8000);
+    rom[0x0000] = 0x18; // JR -3
+    rom[0x0001] = 0xfd;
+
+    let mut gb = common::create_gameboy(rom);
+    gb.cpu.set_instruction_pointer(0x0000);
+    gb.run_single_step();
+    assert_eq!(0xffff, gb.cpu.get_instruction_pointer());
+
+    // executing the last byte of the address space as NOP continues at its start
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_INTERRUPTS_ENABLED, 0x00);
+    gb.run_single_step();
+    assert_eq!(0x0000, gb.cpu.get_instruction_pointer());
+}
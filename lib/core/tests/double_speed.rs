@@ -0,0 +1,161 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::debug::DebugEvent;
+use gemi_core::gameboy::{Clock, GameBoy};
+use gemi_core::mmu::locations::{MEMORY_LOCATION_KEY1, MEMORY_LOCATION_REGISTER_DIV, MEMORY_LOCATION_REGISTER_TAC, MEMORY_LOCATION_REGISTER_TIMA};
+
+mod common;
+
+
+/// Creates a GameBoy running a GBC ROM, which arms the speed switch and executes STOP.
+fn create_gameboy() -> GameBoy {
+    let mut rom = common::create_gbc_rom();
+
+    common::set_program(&mut rom, &[
+        0x3e, 0x01,     // LD A, $01
+        0xe0, 0x4d,     // LDH ($4d), A
+        0x10,           // STOP
+        0x00,           // NOP
+        0x18, 0xfe,     // JR -2
+    ]);
+
+    common::create_gameboy(rom)
+}
+
+
+#[test]
+fn key1_speed_switch_via_stop() {
+    let mut gb = create_gameboy();
+
+    assert_eq!(0x7e, gb.get_mmu().read_u8(MEMORY_LOCATION_KEY1));
+
+    // LD A, $01; LDH ($4d), A
+    gb.run_single_step();
+    gb.run_single_step();
+    assert_eq!(0x7f, gb.get_mmu().read_u8(MEMORY_LOCATION_KEY1));
+
+    // STOP switches into double speed mode and resets the armed bit
+    gb.run_single_step();
+    assert_eq!(0xfe, gb.get_mmu().read_u8(MEMORY_LOCATION_KEY1));
+    assert!(gb.get_peripherals().mem.is_double_speed());
+
+    // in double speed mode a NOP takes only 2 cycles of device time
+    let cycles_before = gb.get_total_cycles_processed();
+    let result = gb.run_single_step();
+    assert_eq!(2, result.cycles);
+    assert_eq!(cycles_before + 2, gb.get_total_cycles_processed());
+}
+
+
+#[test]
+fn key1_not_available_on_dmg() {
+    let mut gb = GameBoy::build().finish().unwrap();
+
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_KEY1, 0x01);
+    assert_eq!(0xff, gb.get_mmu().read_u8(MEMORY_LOCATION_KEY1));
+    assert!(!gb.get_peripherals().mem.is_speed_switch_armed());
+}
+
+
+/// The number of ticks of each component counted while running a number of NOPs.
+#[derive(Debug, PartialEq)]
+struct Rates {
+    /// The time passed in device cycles.
+    device_cycles: Clock,
+
+    /// The number of increments of the DIV register.
+    div_ticks: u8,
+
+    /// The number of increments of the TIMA register.
+    tima_ticks: u8,
+
+    /// The number of lines completed by the PPU.
+    ppu_lines: u32,
+}
+
+
+/// Runs a number of NOPs and counts the ticks of timer and PPU meanwhile.
+fn measure_rates(gb: &mut GameBoy, nops: usize) -> Rates {
+    let cycles_before = gb.get_total_cycles_processed();
+    let div_before    = gb.get_mmu().read_u8(MEMORY_LOCATION_REGISTER_DIV);
+    let tima_before   = gb.get_mmu().read_u8(MEMORY_LOCATION_REGISTER_TIMA);
+    let mut ppu_lines = 0;
+
+    for _ in 0..nops {
+        let result = gb.run_single_step();
+
+        if result.events.contains(DebugEvent::PpuLineCompleted) {
+            ppu_lines += 1;
+        }
+    }
+
+    Rates {
+        device_cycles: gb.get_total_cycles_processed() - cycles_before,
+        div_ticks:     gb.get_mmu().read_u8(MEMORY_LOCATION_REGISTER_DIV).wrapping_sub(div_before),
+        tima_ticks:    gb.get_mmu().read_u8(MEMORY_LOCATION_REGISTER_TIMA).wrapping_sub(tima_before),
+        ppu_lines,
+    }
+}
+
+
+#[test]
+fn component_rates_in_double_speed() {
+    const NOPS: usize = 4096;
+
+    let mut rom = common::create_gbc_rom();
+
+    // a block of NOPs behind the header to be measured in normal speed, followed by the speed switch
+    // and the remaining ROM filled with NOPs to be measured in double speed
+    common::set_program(&mut rom, &[
+        0xc3, 0x50, 0x01,   // JP $0150
+    ]);
+
+    rom[0x0150 + NOPS .. 0x0150 + NOPS + 5].copy_from_slice(&[
+        0x3e, 0x01,         // LD A, $01
+        0xe0, 0x4d,         // LDH ($4d), A
+        0x10,               // STOP
+    ]);
+
+    let mut gb = common::create_gameboy(rom);
+    gb.run_single_step();
+
+    // timer running at 4096 Hz, which increments TIMA every 1024 CPU cycles in normal speed
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_REGISTER_TAC, 0x04);
+
+    // 4096 NOPs take 16384 CPU cycles, where each of DIV, TIMA and the PPU run at the device's speed
+    let normal_speed = measure_rates(&mut gb, NOPS);
+    assert_eq!(16384, normal_speed.device_cycles);
+    assert_eq!(64, normal_speed.div_ticks);
+    assert_eq!(16, normal_speed.tima_ticks);
+    assert!((35 ..= 36).contains(&normal_speed.ppu_lines), "{normal_speed:?}");
+
+    // LD A, $01; LDH ($4d), A; STOP
+    for _ in 0..3 {
+        gb.run_single_step();
+    }
+
+    assert!(gb.get_peripherals().mem.is_double_speed());
+
+    // in double speed, the same number of CPU cycles takes half the time of the device,
+    // where DIV and TIMA run at the speed of the CPU, while the PPU keeps its speed
+    let double_speed = measure_rates(&mut gb, NOPS);
+    assert_eq!(8192, double_speed.device_cycles);
+    assert_eq!(64, double_speed.div_ticks);
+    assert_eq!(16, double_speed.tima_ticks);
+    assert!((17 ..= 18).contains(&double_speed.ppu_lines), "{double_speed:?}");
+}
@@ -0,0 +1,309 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::cartridge::{MBC6_RAM_SIZE, ROM_OFFSET_RAM_SIZE, ROM_OFFSET_ROM_SIZE, ROM_OFFSET_ROM_TYPE, TAMA5_RAM_SIZE};
+use gemi_core::cpu::cpu::CPU_CLOCK_SPEED;
+use gemi_core::gameboy::GameBoy;
+use gemi_core::mmu::rtc::RTC_FOOTER_SIZE;
+
+mod common;
+
+use common::create_gameboy;
+
+
+/// Creates an empty ROM with 8 banks and the given header values at a header offset.
+fn create_rom(header_offset: usize, rom_type: u8, ram_size: u8) -> Vec<u8> {
+    let mut rom = vec![0x00; 0x20000];
+    rom[header_offset + ROM_OFFSET_ROM_TYPE] = rom_type;
+    rom[header_offset + ROM_OFFSET_ROM_SIZE] = 0x02;
+    rom[header_offset + ROM_OFFSET_RAM_SIZE] = ram_size;
+    common::mark_rom_banks(&mut rom);
+
+    rom
+}
+
+
+fn get_save_data(gb: &GameBoy) -> Vec<u8> {
+    gb.get_peripherals().mem.get_cartridge().unwrap().get_save_data(0)
+}
+
+
+#[test]
+fn mbc5_ram_banks() {
+    let mut gb = create_gameboy(create_rom(0, 0x1b, 0x04));
+    gb.get_mmu_mut().write_u8(0x0000, 0x0a);
+
+    for bank in 0..16 {
+        gb.get_mmu_mut().write_u8(0x4000, bank);
+        gb.get_mmu_mut().write_u8(0xa000, bank);
+    }
+
+    // each RAM bank keeps its own value and is stored at its position within the save data
+    for bank in 0..16 {
+        gb.get_mmu_mut().write_u8(0x4000, bank);
+        assert_eq!(bank, gb.get_mmu().read_u8(0xa000));
+    }
+
+    let save_data = get_save_data(&gb);
+    for bank in 0..16 {
+        assert_eq!(bank as u8, save_data[bank * 0x2000]);
+    }
+}
+
+
+#[test]
+fn mbc6_flash() {
+    let mut gb = create_gameboy(create_rom(0, 0x20, 0x00));
+
+    // map ROM banks in 8kiB units
+    gb.get_mmu_mut().write_u8(0x2000, 0x04);
+    gb.get_mmu_mut().write_u8(0x3000, 0x04);
+    assert_eq!(2, gb.get_mmu().read_u8(0x5000));
+    assert_eq!(2, gb.get_mmu().read_u8(0x7000));
+    assert_eq!(2, gb.get_mapped_rom_bank(0x7000).unwrap());
+
+    // enable flash and map flash bank 2 into slot A and bank 1 into slot B,
+    // so the command addresses 0x5555 and 0x2aaa are mapped to the same CPU addresses
+    gb.get_mmu_mut().write_u8(0x0c00, 0x01);
+    gb.get_mmu_mut().write_u8(0x1000, 0x01);
+    gb.get_mmu_mut().write_u8(0x2000, 0x02);
+    gb.get_mmu_mut().write_u8(0x2800, 0x08);
+    gb.get_mmu_mut().write_u8(0x3000, 0x01);
+    gb.get_mmu_mut().write_u8(0x3800, 0x08);
+
+    // slots mapping flash don't report a ROM bank
+    assert_eq!(Some(0), gb.get_mapped_rom_bank(0x0000));
+    assert_eq!(None,    gb.get_mapped_rom_bank(0x4000));
+    assert_eq!(None,    gb.get_mapped_rom_bank(0x6000));
+
+    let send_command = |gb: &mut GameBoy, command: u8| {
+        gb.get_mmu_mut().write_u8(0x5555, 0xaa);
+        gb.get_mmu_mut().write_u8(0x6aaa, 0x55);
+        gb.get_mmu_mut().write_u8(0x5555, command);
+    };
+
+    // ID mode
+    send_command(&mut gb, 0x90);
+    assert_eq!(0xc2, gb.get_mmu().read_u8(0x4000));
+    assert_eq!(0x81, gb.get_mmu().read_u8(0x4001));
+    gb.get_mmu_mut().write_u8(0x4000, 0xf0);
+
+    // chip erase, then program a single byte
+    send_command(&mut gb, 0x80);
+    send_command(&mut gb, 0x10);
+    assert_eq!(0xff, gb.get_mmu().read_u8(0x6000));
+
+    send_command(&mut gb, 0xa0);
+    gb.get_mmu_mut().write_u8(0x6000, 0x5a);
+    assert_eq!(0x5a, gb.get_mmu().read_u8(0x6000));
+    assert_eq!(0xff, gb.get_mmu().read_u8(0x6001));
+
+    // writes without a command do not change the flash
+    gb.get_mmu_mut().write_u8(0x6001, 0x00);
+    assert_eq!(0xff, gb.get_mmu().read_u8(0x6001));
+
+    // the flash memory is stored behind the RAM
+    let save_data = get_save_data(&gb);
+    assert_eq!(0x5a, save_data[MBC6_RAM_SIZE + 0x2000]);
+}
+
+
+#[test]
+fn mmm01_menu_mapping() {
+    // the header at the beginning of the ROM belongs to the first game,
+    // the cartridge is described by the menu's header in the last 32kiB
+    let mut rom = create_rom(0x18000, 0x0b, 0x00);
+    rom[ROM_OFFSET_ROM_TYPE] = 0x01;
+    let mut gb = create_gameboy(rom);
+
+    // the menu is mapped on startup
+    assert_eq!(6, gb.get_mmu().read_u8(0x1000));
+    assert_eq!(7, gb.get_mmu().read_u8(0x5000));
+
+    // select a 32kiB game at bank 2, fix bits 1-4 of the ROM bank and map the game
+    gb.get_mmu_mut().write_u8(0x2000, 0x02);
+    gb.get_mmu_mut().write_u8(0x6000, 0x0f << 2);
+    assert_eq!(6, gb.get_mmu().read_u8(0x1000));
+
+    gb.get_mmu_mut().write_u8(0x0000, 0x40);
+    assert_eq!(2, gb.get_mmu().read_u8(0x1000));
+    assert_eq!(3, gb.get_mmu().read_u8(0x5000));
+    assert_eq!(2, gb.get_mapped_rom_bank(0x1000).unwrap());
+
+    // the game cannot leave its area anymore
+    gb.get_mmu_mut().write_u8(0x2000, 0x1f);
+    assert_eq!(2, gb.get_mmu().read_u8(0x1000));
+    assert_eq!(3, gb.get_mmu().read_u8(0x5000));
+
+    gb.get_mmu_mut().write_u8(0x0000, 0x00);
+    assert_eq!(2, gb.get_mmu().read_u8(0x1000));
+}
+
+
+#[test]
+fn huc1_banking_and_ir() {
+    let mut gb = create_gameboy(create_rom(0, 0xff, 0x03));
+
+    gb.get_mmu_mut().write_u8(0x2000, 0x05);
+    assert_eq!(5, gb.get_mmu().read_u8(0x5000));
+
+    // RAM is accessible without being enabled
+    gb.get_mmu_mut().write_u8(0x4000, 0x01);
+    gb.get_mmu_mut().write_u8(0xa000, 0x42);
+    assert_eq!(0x42, gb.get_mmu().read_u8(0xa000));
+    assert_eq!(0x42, get_save_data(&gb)[0x2000]);
+
+    // infrared mode replaces the RAM area
+    gb.get_mmu_mut().write_u8(0x0000, 0x0e);
+    assert_eq!(0xc0, gb.get_mmu().read_u8(0xa000));
+    gb.get_mmu_mut().write_u8(0xa000, 0x01);
+
+    gb.get_mmu_mut().write_u8(0x0000, 0x00);
+    assert_eq!(0x42, gb.get_mmu().read_u8(0xa000));
+}
+
+
+/// Sends a command to the HuC3 clock and returns its response.
+fn send_huc3_command(gb: &mut GameBoy, command: u8) -> u8 {
+    gb.get_mmu_mut().write_u8(0x0000, 0x0b);
+    gb.get_mmu_mut().write_u8(0xa000, command);
+    gb.get_mmu_mut().write_u8(0x0000, 0x0d);
+    gb.get_mmu_mut().write_u8(0xa000, 0xfe);
+    gb.get_mmu_mut().write_u8(0x0000, 0x0c);
+    gb.get_mmu().read_u8(0xa000) & 0x0f
+}
+
+
+/// Reads the minutes and days of the HuC3 clock.
+fn read_huc3_time(gb: &mut GameBoy) -> (u16, u16) {
+    send_huc3_command(gb, 0x40);
+    send_huc3_command(gb, 0x50);
+
+    let nibbles: Vec<u16> = (0..6).map(|_| send_huc3_command(gb, 0x10) as u16).collect();
+    let minutes = nibbles[0] | (nibbles[1] << 4) | (nibbles[2] << 8);
+    let days    = nibbles[3] | (nibbles[4] << 4) | (nibbles[5] << 8);
+
+    (minutes, days)
+}
+
+
+#[test]
+fn huc3_clock() {
+    let mut gb = create_gameboy(create_rom(0, 0xfe, 0x03));
+
+    // RAM is only writable in mode 0x0a
+    gb.get_mmu_mut().write_u8(0x0000, 0x00);
+    let initial = gb.get_mmu().read_u8(0xa000);
+    gb.get_mmu_mut().write_u8(0xa000, 0x42);
+    assert_eq!(initial, gb.get_mmu().read_u8(0xa000));
+    gb.get_mmu_mut().write_u8(0x0000, 0x0a);
+    gb.get_mmu_mut().write_u8(0xa000, 0x42);
+    assert_eq!(0x42, gb.get_mmu().read_u8(0xa000));
+
+    // write 23:59 at day 2
+    let time = [0x0f, 0x09, 0x05, 0x02, 0x00, 0x00];
+    send_huc3_command(&mut gb, 0x40);
+    send_huc3_command(&mut gb, 0x50);
+    for nibble in time {
+        send_huc3_command(&mut gb, 0x30 | nibble);
+    }
+
+    assert_eq!((23 * 60 + 59, 2), read_huc3_time(&mut gb));
+
+    // the clock continues running and increments the day
+    gb.get_peripherals_mut().mem.update(60 * CPU_CLOCK_SPEED);
+    assert_eq!((0, 3), read_huc3_time(&mut gb));
+
+    // RAM and clock are stored in the save file
+    let save_data = get_save_data(&gb);
+    assert_eq!(32 * 1024 + RTC_FOOTER_SIZE, save_data.len());
+    assert_eq!(0x42, save_data[0]);
+}
+
+
+#[test]
+fn huc3_12_bit_day_counter() {
+    let mut gb = create_gameboy(create_rom(0, 0xfe, 0x03));
+
+    // write 23:59 at day 0x5ff, which exceeds the 9 bit counter of the real time clock
+    let time = [0x0f, 0x09, 0x05, 0x0f, 0x0f, 0x05];
+    send_huc3_command(&mut gb, 0x40);
+    send_huc3_command(&mut gb, 0x50);
+    for nibble in time {
+        send_huc3_command(&mut gb, 0x30 | nibble);
+    }
+
+    assert_eq!((23 * 60 + 59, 0x5ff), read_huc3_time(&mut gb));
+
+    // the day counter continues after the real time clock wrapped around
+    gb.get_peripherals_mut().mem.update(60 * CPU_CLOCK_SPEED);
+    assert_eq!((0, 0x600), read_huc3_time(&mut gb));
+
+    gb.get_peripherals_mut().mem.update(24 * 60 * 60 * CPU_CLOCK_SPEED);
+    assert_eq!((0, 0x601), read_huc3_time(&mut gb));
+}
+
+
+/// Writes a value into a TAMA5 register.
+fn write_tama5_register(gb: &mut GameBoy, register: u8, value: u8) {
+    gb.get_mmu_mut().write_u8(0xa001, register);
+    gb.get_mmu_mut().write_u8(0xa000, value);
+}
+
+
+/// Reads a byte via the TAMA5 read registers.
+fn read_tama5_result(gb: &mut GameBoy) -> u8 {
+    gb.get_mmu_mut().write_u8(0xa001, 0x0c);
+    let low = gb.get_mmu().read_u8(0xa000) & 0x0f;
+    gb.get_mmu_mut().write_u8(0xa001, 0x0d);
+    let high = gb.get_mmu().read_u8(0xa000) & 0x0f;
+
+    (high << 4) | low
+}
+
+
+#[test]
+fn tama5_ram_and_clock() {
+    let mut gb = create_gameboy(create_rom(0, 0xfd, 0x00));
+
+    // ROM bank selection via the bank registers
+    write_tama5_register(&mut gb, 0x00, 0x06);
+    write_tama5_register(&mut gb, 0x01, 0x00);
+    assert_eq!(6, gb.get_mmu().read_u8(0x5000));
+
+    // write 0xa5 into RAM address 0x13
+    write_tama5_register(&mut gb, 0x04, 0x05);
+    write_tama5_register(&mut gb, 0x05, 0x0a);
+    write_tama5_register(&mut gb, 0x06, 0x01);
+    write_tama5_register(&mut gb, 0x07, 0x03);
+
+    // read it back
+    write_tama5_register(&mut gb, 0x06, 0x03);
+    write_tama5_register(&mut gb, 0x07, 0x03);
+    assert_eq!(0xa5, read_tama5_result(&mut gb));
+
+    // set the tens of the minutes to 4 and read them back
+    write_tama5_register(&mut gb, 0x04, 0x04);
+    write_tama5_register(&mut gb, 0x06, 0x04);
+    write_tama5_register(&mut gb, 0x07, 0x03);
+    assert_eq!(0x04, read_tama5_result(&mut gb));
+
+    // RAM and clock are stored in the save file
+    let save_data = get_save_data(&gb);
+    assert_eq!(TAMA5_RAM_SIZE + RTC_FOOTER_SIZE, save_data.len());
+    assert_eq!(0xa5, save_data[0x13]);
+}
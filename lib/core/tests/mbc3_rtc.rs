@@ -0,0 +1,119 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::cpu::cpu::CPU_CLOCK_SPEED;
+use gemi_core::gameboy::GameBoy;
+use gemi_core::mmu::rtc::RTC_FOOTER_SIZE;
+
+mod common;
+
+
+/// Creates a GameBoy running an empty MBC3 ROM with timer, battery and 32kiB RAM.
+fn create_gameboy(ram: Option<Vec<u8>>) -> GameBoy {
+    common::create_gameboy_with_ram(common::create_mbc_rom(0x10000, 0x10, 0x03), ram)
+}
+
+
+/// Selects a clock register, latches the current time and reads the register.
+fn read_rtc_register(gb: &mut GameBoy, register: u8) -> u8 {
+    gb.get_mmu_mut().write_u8(0x4000, register);
+    gb.get_mmu_mut().write_u8(0x6000, 0x00);
+    gb.get_mmu_mut().write_u8(0x6000, 0x01);
+    gb.get_mmu().read_u8(0xa000)
+}
+
+
+#[test]
+fn mbc3_rom_banking() {
+    let mut gb = create_gameboy(None);
+
+    assert_eq!(1, gb.get_mmu().read_u8(0x5000));
+
+    gb.get_mmu_mut().write_u8(0x2000, 0x03);
+    assert_eq!(3, gb.get_mmu().read_u8(0x5000));
+
+    // bank 0 maps to bank 1
+    gb.get_mmu_mut().write_u8(0x2000, 0x00);
+    assert_eq!(1, gb.get_mmu().read_u8(0x5000));
+}
+
+
+#[test]
+fn mbc3_rtc_counting_and_latch() {
+    let mut gb = create_gameboy(None);
+
+    // enable RAM and RTC, write the seconds register
+    gb.get_mmu_mut().write_u8(0x0000, 0x0a);
+    gb.get_mmu_mut().write_u8(0x4000, 0x08);
+    gb.get_mmu_mut().write_u8(0xa000, 58);
+
+    // let three seconds pass
+    gb.get_peripherals_mut().mem.update(3 * CPU_CLOCK_SPEED);
+
+    // latched registers keep their value until latched again
+    assert_eq!(58, gb.get_mmu().read_u8(0xa000) & 0x3f);
+    assert_eq!( 1, read_rtc_register(&mut gb, 0x08) & 0x3f);
+    assert_eq!( 1, read_rtc_register(&mut gb, 0x09) & 0x3f);
+
+    // halt the clock, time does not pass anymore
+    gb.get_mmu_mut().write_u8(0x4000, 0x0c);
+    gb.get_mmu_mut().write_u8(0xa000, 0x40);
+    gb.get_peripherals_mut().mem.update(5 * CPU_CLOCK_SPEED);
+    assert_eq!( 1, read_rtc_register(&mut gb, 0x08) & 0x3f);
+}
+
+
+#[test]
+fn mbc3_rtc_day_carry() {
+    let mut gb = create_gameboy(None);
+
+    gb.get_mmu_mut().write_u8(0x0000, 0x0a);
+
+    // set the clock to day 511, 23:59:59
+    for (register, value) in [(0x08, 59), (0x09, 59), (0x0a, 23), (0x0b, 0xff), (0x0c, 0x01)] {
+        gb.get_mmu_mut().write_u8(0x4000, register);
+        gb.get_mmu_mut().write_u8(0xa000, value);
+    }
+
+    gb.get_peripherals_mut().mem.update(CPU_CLOCK_SPEED);
+
+    assert_eq!(0x00, read_rtc_register(&mut gb, 0x0b));
+    assert_eq!(0x80, read_rtc_register(&mut gb, 0x0c) & 0xc1);
+}
+
+
+#[test]
+fn mbc3_rtc_save_data() {
+    let mut gb = create_gameboy(None);
+
+    gb.get_mmu_mut().write_u8(0x0000, 0x0a);
+    gb.get_mmu_mut().write_u8(0x4000, 0x00);
+    gb.get_mmu_mut().write_u8(0xa000, 0x42);
+    gb.get_mmu_mut().write_u8(0x4000, 0x0a);
+    gb.get_mmu_mut().write_u8(0xa000, 17);
+
+    let save_data = gb.get_peripherals().mem.get_cartridge().unwrap().get_save_data(0);
+    assert_eq!(32 * 1024 + RTC_FOOTER_SIZE, save_data.len());
+
+    // restore the data into a new instance
+    let mut gb = create_gameboy(Some(save_data));
+
+    gb.get_mmu_mut().write_u8(0x0000, 0x0a);
+    gb.get_mmu_mut().write_u8(0x4000, 0x00);
+    assert_eq!(0x42, gb.get_mmu().read_u8(0xa000));
+    assert_eq!(17, read_rtc_register(&mut gb, 0x0a) & 0x1f);
+}
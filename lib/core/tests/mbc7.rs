@@ -0,0 +1,146 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::cartridge::MBC7_EEPROM_SIZE;
+use gemi_core::gameboy::GameBoy;
+
+mod common;
+
+
+/// Creates a GameBoy running an empty MBC7 ROM with 4 banks and the register area enabled.
+fn create_gameboy(ram: Option<Vec<u8>>) -> GameBoy {
+    let mut gb = common::create_gameboy_with_ram(common::create_mbc_rom(0x10000, 0x22, 0x00), ram);
+
+    // enable the register area
+    gb.get_mmu_mut().write_u8(0x0000, 0x0a);
+    gb.get_mmu_mut().write_u8(0x4000, 0x40);
+
+    gb
+}
+
+
+/// Sends a sequence of bits to the EEPROM and returns the bits read back.
+fn transfer_eeprom_bits(gb: &mut GameBoy, bits: &[u8]) -> Vec<u8> {
+    let mut result = Vec::new();
+
+    for bit in bits {
+        gb.get_mmu_mut().write_u8(0xa080, 0x80 | (bit << 1));
+        gb.get_mmu_mut().write_u8(0xa080, 0xc0 | (bit << 1));
+        result.push(gb.get_mmu().read_u8(0xa080) & 0x01);
+    }
+
+    result
+}
+
+
+/// Sends a command to the EEPROM, consisting of a start bit, the opcode,
+/// address and data bits, and releases chip select afterwards.
+fn send_eeprom_command(gb: &mut GameBoy, opcode: u8, address: u8, data: Option<u16>) -> Vec<u8> {
+    let mut bits = vec![1, (opcode >> 1) & 1, opcode & 1];
+    bits.extend((0..8).rev().map(|bit| (address >> bit) & 1));
+
+    match data {
+        Some(data) => bits.extend((0..16).rev().map(|bit| ((data >> bit) & 1) as u8)),
+        None       => bits.extend([0; 16]),
+    }
+
+    let result = transfer_eeprom_bits(gb, &bits);
+    gb.get_mmu_mut().write_u8(0xa080, 0x00);
+
+    result
+}
+
+
+#[test]
+fn mbc7_rom_banking() {
+    let mut gb = create_gameboy(None);
+
+    assert_eq!(1, gb.get_mmu().read_u8(0x5000));
+
+    gb.get_mmu_mut().write_u8(0x2000, 0x03);
+    assert_eq!(3, gb.get_mmu().read_u8(0x5000));
+    assert_eq!(3, gb.get_mapped_rom_bank(0x5000).unwrap());
+}
+
+
+#[test]
+fn mbc7_accelerometer() {
+    let mut gb = create_gameboy(None);
+
+    gb.get_peripherals_mut().input.set_tilt(0.5, -1.0);
+    gb.run_single_step();
+
+    // latching requires the previous values to be erased
+    gb.get_mmu_mut().write_u8(0xa010, 0xaa);
+    assert_eq!(0x8000, read_accelerometer(&gb, 0xa020));
+
+    gb.get_mmu_mut().write_u8(0xa000, 0x55);
+    gb.get_mmu_mut().write_u8(0xa010, 0xaa);
+    assert_eq!(0x81d0 + 0x38, read_accelerometer(&gb, 0xa020));
+    assert_eq!(0x81d0 - 0x70, read_accelerometer(&gb, 0xa040));
+
+    // the latched value does not change with the tilt
+    gb.get_peripherals_mut().input.set_tilt(0.0, 0.0);
+    gb.run_single_step();
+    assert_eq!(0x81d0 + 0x38, read_accelerometer(&gb, 0xa020));
+
+    // latching again picks up the new tilt
+    gb.get_mmu_mut().write_u8(0xa000, 0x55);
+    gb.get_mmu_mut().write_u8(0xa010, 0xaa);
+    assert_eq!(0x81d0, read_accelerometer(&gb, 0xa020));
+    assert_eq!(0x81d0, read_accelerometer(&gb, 0xa040));
+
+    // registers are not accessible while RAM is disabled
+    gb.get_mmu_mut().write_u8(0x4000, 0x00);
+    assert_eq!(0xff, gb.get_mmu().read_u8(0xa020));
+}
+
+
+fn read_accelerometer(gb: &GameBoy, address: u16) -> u16 {
+    u16::from_le_bytes([gb.get_mmu().read_u8(address), gb.get_mmu().read_u8(address + 0x10)])
+}
+
+
+#[test]
+fn mbc7_eeprom() {
+    let mut gb = create_gameboy(None);
+
+    // writing is ignored until enabled
+    let initial = send_eeprom_command(&mut gb, 0b10, 0x05, None);
+    send_eeprom_command(&mut gb, 0b01, 0x05, Some(0x1234));
+    assert_eq!(send_eeprom_command(&mut gb, 0b10, 0x05, None), initial);
+
+    // EWEN, then WRITE
+    send_eeprom_command(&mut gb, 0b00, 0xc0, None);
+    send_eeprom_command(&mut gb, 0b01, 0x05, Some(0x1234));
+
+    // READ starts with a dummy bit followed by the data
+    let read  = send_eeprom_command(&mut gb, 0b10, 0x05, None);
+    let value = read[11..27].iter().fold(0u16, |value, bit| (value << 1) | (*bit as u16));
+    assert_eq!(read[10], 0);
+    assert_eq!(value, 0x1234);
+
+    // the EEPROM is stored in the save data
+    let save_data = gb.get_peripherals().mem.get_cartridge().unwrap().get_save_data(0);
+    assert_eq!(save_data.len(), MBC7_EEPROM_SIZE);
+    assert_eq!(save_data[10..12], [0x34, 0x12]);
+
+    // and restored when loading the save data
+    let mut gb = create_gameboy(Some(save_data));
+    let read = send_eeprom_command(&mut gb, 0b10, 0x05, None);
+    assert_eq!(read[11..15], [0, 0, 0, 1]);
+}
@@ -0,0 +1,167 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::gameboy::GameBoy;
+use gemi_core::input::InputButton;
+use gemi_core::mmu::memory_data::MemoryData;
+use gemi_core::movie::{compute_state_hash, Movie, MovieConfig, MovieError, MovieGranularity, MoviePlayer, MovieRecorder};
+
+mod common;
+
+
+fn create_gameboy() -> GameBoy {
+    // endless loop summing up the action button states in WRAM
+    common::create_gameboy(common::create_rom_with_program(&[
+        0x3e, 0x10,         // LD A, 0x10
+        0xe0, 0x00,         // LDH (0x00), A
+        0xf0, 0x00,         // LDH A, (0x00)
+        0x21, 0x00, 0xc0,   // LD HL, 0xc000
+        0x86,               // ADD A, (HL)
+        0x77,               // LD (HL), A
+        0x18, 0xf7,         // JR -9
+    ]))
+}
+
+
+fn play_back(movie: Movie) -> Result<GameBoy, MovieError> {
+    let mut gb     = create_gameboy();
+    let mut player = MoviePlayer::new(movie, &mut gb)?;
+
+    while !player.is_finished(&gb) {
+        player.run_frame(&mut gb)?;
+    }
+
+    Ok(gb)
+}
+
+
+#[test]
+fn movie_play_back_per_frame() {
+    let mut gb       = create_gameboy();
+    let mut recorder = MovieRecorder::new(&gb, MovieConfig {
+        granularity:            MovieGranularity::PerFrame,
+        checkpoint_interval:    10,
+    }).unwrap();
+
+    for frame in 0 .. 100 {
+        gb.get_peripherals_mut().input.set_button_pressed(InputButton::A,     frame % 7 < 3);
+        gb.get_peripherals_mut().input.set_button_pressed(InputButton::Start, frame % 11 == 0);
+        recorder.run_frame(&mut gb);
+    }
+
+    let movie = recorder.finish();
+    assert_eq!(100, movie.get_length_frames());
+    assert_eq!(10,  movie.get_checkpoints().len());
+    assert!(movie.get_events().len() > 10);
+
+    // play back the movie after a round trip through the file format
+    let movie    = Movie::from_bytes(&movie.to_bytes()).unwrap();
+    let replayed = play_back(movie).unwrap();
+
+    assert_eq!(gb.get_total_cycles_processed(), replayed.get_total_cycles_processed());
+    assert_eq!(compute_state_hash(&gb), compute_state_hash(&replayed));
+}
+
+
+#[test]
+fn movie_play_back_per_cycle() {
+    let mut gb       = create_gameboy();
+    let mut recorder = MovieRecorder::new(&gb, MovieConfig {
+        granularity:            MovieGranularity::PerCycle,
+        checkpoint_interval:    5,
+    }).unwrap();
+
+    // change the input in the middle of frames
+    for step in 0 .. 50_000 {
+        gb.get_peripherals_mut().input.set_button_pressed(InputButton::B, step % 1_000 < 300);
+        recorder.run_single_step(&mut gb);
+    }
+
+    let movie = recorder.finish();
+    assert!(movie.get_events().iter().any(|event| event.cycle % 70_224 != 0));
+
+    // run single steps to stop on the exact same cycle as the recording
+    let mut replayed = create_gameboy();
+    let mut player   = MoviePlayer::new(movie, &mut replayed).unwrap();
+    while !player.is_finished(&replayed) {
+        player.run_single_step(&mut replayed).unwrap();
+    }
+
+    assert_eq!(gb.get_total_cycles_processed(), replayed.get_total_cycles_processed());
+    assert_eq!(compute_state_hash(&gb), compute_state_hash(&replayed));
+}
+
+
+#[test]
+fn movie_detect_desync() {
+    let mut gb       = create_gameboy();
+    let mut recorder = MovieRecorder::new(&gb, MovieConfig {
+        checkpoint_interval:    10,
+        .. MovieConfig::default()
+    }).unwrap();
+
+    for frame in 0 .. 30 {
+        gb.get_peripherals_mut().input.set_button_pressed(InputButton::A, frame % 2 == 0);
+        recorder.run_frame(&mut gb);
+    }
+
+    let movie          = recorder.finish();
+    let checkpoint     = movie.get_checkpoints()[0];
+    let mut movie_data = movie.to_bytes();
+
+    // modify the RAM, so the playback differs from the recording
+    let mut gb     = create_gameboy();
+    let mut player = MoviePlayer::new(Movie::from_bytes(&movie_data).unwrap(), &mut gb).unwrap();
+    gb.get_peripherals_mut().mem.get_wram_banks_mut()[0].set_at(0x0100, 0x55);
+
+    let mut result = Ok(());
+    while result.is_ok() && !player.is_finished(&gb) {
+        result = player.run_frame(&mut gb).map(|_| ());
+    }
+
+    assert!(matches!(result, Err(MovieError::Desync { frame: 10 })));
+
+    // the playback stops right on the step where the checkpoint did not match
+    assert_eq!(10, player.get_current_frame());
+    assert_eq!(checkpoint.cycle, gb.get_total_cycles_processed());
+
+    // a corrupted file is rejected
+    movie_data.truncate(movie_data.len() - 1);
+    assert!(matches!(Movie::from_bytes(&movie_data), Err(MovieError::InvalidData(_))));
+}
+
+
+#[cfg(feature = "snapshots")]
+#[test]
+fn movie_start_from_snapshot() {
+    let mut gb = create_gameboy();
+    for _ in 0 .. 10 {
+        gb.run_frame();
+    }
+
+    let mut recorder = MovieRecorder::from_snapshot(&gb, MovieConfig::default()).unwrap();
+    for frame in 0 .. 60 {
+        gb.get_peripherals_mut().input.set_button_pressed(InputButton::Select, frame % 3 == 0);
+        recorder.run_frame(&mut gb);
+    }
+
+    // a movie starting from a snapshot can be played on a fresh device
+    let replayed = play_back(recorder.finish()).unwrap();
+
+    assert_eq!(gb.get_total_cycles_processed(), replayed.get_total_cycles_processed());
+    assert_eq!(compute_state_hash(&gb), compute_state_hash(&replayed));
+}
@@ -0,0 +1,139 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::device_type::DeviceType;
+use gemi_core::gameboy::GameBoy;
+use gemi_core::mmu::locations::*;
+use gemi_core::mmu::memory_data::MemoryData;
+use gemi_core::ppu::ppu::OamCorruption;
+
+
+fn create_gameboy(device_type: DeviceType) -> GameBoy {
+    let mut builder = GameBoy::build();
+    builder.set_device_type(device_type);
+
+    let mut gb = builder.finish().unwrap();
+    gb.initialize();
+    gb
+}
+
+
+fn get_mode(gb: &GameBoy) -> u8 {
+    gb.get_mmu().read_u8(MEMORY_LOCATION_LCD_STATUS) & 0x03
+}
+
+
+/// Runs the PPU until it enters the given mode.
+fn run_until_mode(gb: &mut GameBoy, mode: u8) {
+    while get_mode(gb) == mode {
+        gb.get_peripherals_mut().ppu.update(1);
+    }
+
+    while get_mode(gb) != mode {
+        gb.get_peripherals_mut().ppu.update(1);
+    }
+}
+
+
+/// Fills OAM with a sequence of numbers, bypassing any access restrictions.
+fn fill_oam(gb: &mut GameBoy) {
+    let oam = gb.get_peripherals_mut().ppu.get_oam_bank_mut();
+
+    for i in 0 .. 160 {
+        oam.set_at(i, i as u8);
+    }
+}
+
+
+#[test]
+fn vram_locked_during_draw_line() {
+    let mut gb = create_gameboy(DeviceType::GameBoyDmg);
+
+    run_until_mode(&mut gb, 0);
+    gb.get_mmu_mut().write_u8(0x8000, 0x12);
+    gb.get_mmu_mut().write_u8(0xfe00, 0x34);
+
+    // VRAM remains accessible during OAM scan, while OAM gets locked
+    run_until_mode(&mut gb, 2);
+    assert_eq!(0x12, gb.get_mmu().read_u8(0x8000));
+    assert_eq!(0xff, gb.get_mmu().read_u8(0xfe00));
+
+    // both are locked while drawing, and writes are ignored
+    run_until_mode(&mut gb, 3);
+    assert_eq!(0xff, gb.get_mmu().read_u8(0x8000));
+    assert_eq!(0xff, gb.get_mmu().read_u8(0xfe00));
+    gb.get_mmu_mut().write_u8(0x8000, 0x56);
+    gb.get_mmu_mut().write_u8(0xfe00, 0x78);
+
+    run_until_mode(&mut gb, 0);
+    assert_eq!(0x12, gb.get_mmu().read_u8(0x8000));
+    assert_eq!(0x34, gb.get_mmu().read_u8(0xfe00));
+}
+
+
+#[test]
+fn debug_reads_bypass_locks() {
+    let mut gb = create_gameboy(DeviceType::GameBoyDmg);
+
+    run_until_mode(&mut gb, 0);
+    gb.get_mmu_mut().write_u8(0x8000, 0x12);
+    gb.get_mmu_mut().write_u8(0xfe00, 0x34);
+
+    // while drawing, the CPU cannot see VRAM and OAM, but a debugger can
+    run_until_mode(&mut gb, 3);
+    assert_eq!(0xff, gb.get_mmu().peek_u8(0x8000));
+    assert_eq!(0xff, gb.get_mmu().peek_u8(0xfe00));
+    assert_eq!(0x12, gb.get_mmu().debug_read_u8(0x8000));
+    assert_eq!(0x34, gb.get_mmu().debug_read_u8(0xfe00));
+}
+
+
+#[test]
+fn oam_bug_write_corruption() {
+    let mut gb = create_gameboy(DeviceType::GameBoyDmg);
+    fill_oam(&mut gb);
+
+    // the PPU is reading the third row of OAM
+    run_until_mode(&mut gb, 2);
+    gb.get_peripherals_mut().ppu.update(8);
+
+    // an address outside of OAM does not corrupt anything
+    gb.cpu.trigger_oam_bug(0xc000, OamCorruption::Write);
+    assert_eq!(0x10, gb.get_peripherals_mut().ppu.get_oam_bank_mut().get_at(0x10));
+
+    // the first word gets mixed with the preceding row, the remaining ones get copied
+    gb.cpu.trigger_oam_bug(0xfe10, OamCorruption::Write);
+
+    let oam = gb.get_peripherals_mut().ppu.get_oam_bank_mut();
+    let row: Vec<u8> = (0x10 .. 0x18).map(|i| oam.get_at(i)).collect();
+    assert_eq!(vec![0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f], row);
+    assert_eq!(0x08, oam.get_at(0x08));
+}
+
+
+#[test]
+fn no_oam_bug_on_gbc() {
+    let mut gb = create_gameboy(DeviceType::GameBoyColor);
+    fill_oam(&mut gb);
+
+    run_until_mode(&mut gb, 2);
+    gb.get_peripherals_mut().ppu.update(8);
+    gb.cpu.trigger_oam_bug(0xfe10, OamCorruption::Write);
+
+    let oam = gb.get_peripherals_mut().ppu.get_oam_bank_mut();
+    assert!((0 .. 160).all(|i| oam.get_at(i) == i as u8));
+}
@@ -0,0 +1,110 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::gameboy::{Clock, GameBoy};
+use gemi_core::mmu::locations::*;
+use gemi_core::ppu::ppu::CPU_CYCLES_PER_FRAME;
+
+
+fn create_gameboy() -> GameBoy {
+    let mut gb = GameBoy::build().finish().unwrap();
+    gb.initialize();
+    gb
+}
+
+
+fn get_mode(gb: &GameBoy) -> u8 {
+    gb.get_mmu().read_u8(MEMORY_LOCATION_LCD_STATUS) & 0x03
+}
+
+
+/// Runs the PPU until the next scanline enters mode 3 and
+/// returns the number of dots until it enters HBlank.
+fn measure_next_draw_line(gb: &mut GameBoy) -> Clock {
+    while get_mode(gb) == 3 {
+        gb.get_peripherals_mut().ppu.update(1);
+    }
+
+    while get_mode(gb) != 3 || gb.get_mmu().read_u8(MEMORY_LOCATION_LY) == 0 {
+        gb.get_peripherals_mut().ppu.update(1);
+    }
+
+    let mut dots = 0;
+    while get_mode(gb) == 3 {
+        gb.get_peripherals_mut().ppu.update(1);
+        dots += 1;
+    }
+
+    dots
+}
+
+
+#[test]
+fn draw_line_length_by_fine_scroll() {
+    let mut gb = create_gameboy();
+    assert_eq!(172, measure_next_draw_line(&mut gb));
+
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_SCX, 0x05);
+    assert_eq!(177, measure_next_draw_line(&mut gb));
+}
+
+
+#[test]
+fn draw_line_length_with_objects() {
+    let mut gb = create_gameboy();
+
+    // an object aligned with a background tile stalls the pixel output for 11 dots
+    gb.get_mmu_mut().write_u8(0xfe00, 16);
+    gb.get_mmu_mut().write_u8(0xfe01, 8 + 16);
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_LCD_CONTROL, 0x93);
+    assert_eq!(183, measure_next_draw_line(&mut gb));
+
+    // objects are not fetched while being disabled
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_LCD_CONTROL, 0x91);
+    assert_eq!(172, measure_next_draw_line(&mut gb));
+}
+
+
+#[test]
+fn draw_line_length_with_window() {
+    let mut gb = create_gameboy();
+
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_WY, 0);
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_WX, 7 + 80);
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_LCD_CONTROL, 0xb1);
+
+    // run into the next frame, where the window is visible
+    for _ in 0..CPU_CYCLES_PER_FRAME {
+        gb.get_peripherals_mut().ppu.update(1);
+    }
+
+    assert_eq!(178, measure_next_draw_line(&mut gb));
+}
+
+
+#[test]
+fn ly_is_read_only() {
+    let mut gb = create_gameboy();
+
+    while gb.get_mmu().read_u8(MEMORY_LOCATION_LY) != 10 {
+        gb.get_peripherals_mut().ppu.update(1);
+    }
+
+    // writing into LY has no effect on the current line
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_LY, 100);
+    assert_eq!(10, gb.get_mmu().read_u8(MEMORY_LOCATION_LY));
+}
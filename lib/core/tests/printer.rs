@@ -0,0 +1,107 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::ppu::graphic_data::DmgDisplayPalette;
+use gemi_core::printer::*;
+use gemi_core::serial::SerialLinkEndpoint;
+
+
+/// Sends a single byte to the printer and returns the byte received in exchange.
+fn send_byte(printer: &mut Printer, byte: u8) -> u8 {
+    let mut received = 0x00;
+
+    for bit in (0 .. 8).rev() {
+        let bit_in = printer.exchange_bit((byte >> bit) & 1 != 0);
+        received = (received << 1) | (bit_in as u8);
+    }
+
+    received
+}
+
+
+/// Sends a packet to the printer and returns the 'alive' byte and the printer status.
+fn send_packet(printer: &mut Printer, command: u8, compressed: bool, data: &[u8]) -> (u8, u8) {
+    let length    = data.len() as u16;
+    let header    = [command, compressed as u8, (length & 0xff) as u8, (length >> 8) as u8];
+    let checksum  = header.iter().chain(data.iter()).fold(0u16, |sum, b| sum.wrapping_add(*b as u16));
+
+    for byte in [0x88, 0x33].iter().chain(header.iter()).chain(data.iter()) {
+        assert_eq!(0x00, send_byte(printer, *byte));
+    }
+
+    send_byte(printer, (checksum & 0xff) as u8);
+    send_byte(printer, (checksum >> 8) as u8);
+
+    let alive  = send_byte(printer, 0x00);
+    let status = send_byte(printer, 0x00);
+
+    (alive, status)
+}
+
+
+#[test]
+fn print_compressed_band() {
+    let mut printer = Printer::default();
+
+    assert_eq!((0x81, 0x00), send_packet(&mut printer, 0x01, false, &[]));
+
+    // a row of 20 tiles, where each tile uses color #0 on the left and color #3 on the right half
+    let compressed = [
+        0xff, 0x0f,         // repeat 0x0f 129 times
+        0xff, 0x0f,         // repeat 0x0f 129 times
+        0xbb, 0x0f,         // repeat 0x0f 61 times
+        0x00, 0x0f,         // copy a single byte
+    ];
+
+    let (_, status) = send_packet(&mut printer, 0x04, true, &compressed);
+    assert_eq!(STATUS_UNPROCESSED_DATA, status);
+
+    // print one sheet, with a margin of 1 unit after the image, using the default palette
+    let (_, status) = send_packet(&mut printer, 0x02, false, &[0x01, 0x01, 0xe4, 0x40]);
+    assert_ne!(0, status & STATUS_PRINTER_BUSY);
+
+    let printouts = printer.take_printouts();
+    assert_eq!(1, printouts.len());
+
+    // a single row of tiles, plus the margin
+    let image   = &printouts[0];
+    let palette = DmgDisplayPalette::new_gray();
+    let colors  = palette.get_colors();
+    assert_eq!(160, image.get_width());
+    assert_eq!(8 + 16, image.get_height());
+
+    assert_eq!(colors[0].to_u32(), image.get_pixel(0, 0).to_u32());
+    assert_eq!(colors[3].to_u32(), image.get_pixel(4, 0).to_u32());
+    assert_eq!(colors[0].to_u32(), image.get_pixel(0, 20).to_u32());
+
+    // the printer stays busy for a while
+    while send_packet(&mut printer, 0x0f, false, &[]).1 & STATUS_PRINTER_BUSY != 0 { }
+    assert_eq!(0x00, printer.get_status());
+}
+
+
+#[test]
+fn checksum_error() {
+    let mut printer = Printer::default();
+
+    for byte in [0x88, 0x33, 0x0f, 0x00, 0x00, 0x00, 0x12, 0x34] {
+        send_byte(&mut printer, byte);
+    }
+
+    assert_eq!(0x81, send_byte(&mut printer, 0x00));
+    assert_eq!(STATUS_CHECKSUM_ERROR, send_byte(&mut printer, 0x00));
+}
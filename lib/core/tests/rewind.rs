@@ -0,0 +1,107 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+#![cfg(feature = "snapshots")]
+
+use gemi_core::gameboy::GameBoy;
+use gemi_core::rewind::{RewindBuffer, RewindConfig};
+use gemi_core::snapshots::Snapshot;
+
+mod common;
+
+
+fn create_gameboy() -> GameBoy {
+    let mut rom = common::create_rom(0x8000);
+
+    // fill the ROM with some data, which cannot be compressed easily
+    let mut seed = 0x1234_5678u32;
+    for value in rom.iter_mut().skip(0x0200) {
+        seed   = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        *value = (seed >> 16) as u8;
+    }
+
+    // endless loop incrementing a counter in WRAM
+    common::set_program(&mut rom, &[
+        0x21, 0x00, 0xc0,   // LD HL, 0xc000
+        0x34,               // INC (HL)
+        0x18, 0xfd,         // JR -3
+    ]);
+
+    common::create_gameboy(rom)
+}
+
+
+#[test]
+fn rewind_step_back() {
+    let mut gb     = create_gameboy();
+    let mut rewind = RewindBuffer::new(RewindConfig {
+        frames_per_state:       2,
+        capacity:               8,
+        states_per_keyframe:    3,
+    });
+
+    let mut captured = Vec::new();
+    for _ in 0 .. 20 {
+        gb.run_frame();
+
+        if rewind.on_frame_completed(&gb).unwrap() {
+            captured.push((gb.get_total_cycles_processed(), gb.get_mmu().read_u8(0xc000)));
+        }
+    }
+
+    // only the newest states are kept
+    assert_eq!(10, captured.len());
+    assert_eq!(8, rewind.len());
+    assert_eq!(Some((captured[2].0, captured[9].0)), rewind.get_cycles_range());
+
+    for (cycles, counter) in captured.iter().skip(2).rev() {
+        let restored = rewind.step_back().unwrap().unwrap();
+        assert_eq!(*cycles,  restored.get_total_cycles_processed());
+        assert_eq!(*counter, restored.get_mmu().read_u8(0xc000));
+    }
+
+    assert!(rewind.is_empty());
+    assert!(rewind.step_back().unwrap().is_none());
+}
+
+
+#[test]
+fn rewind_seek_to_cycles() {
+    let mut gb     = create_gameboy();
+    let mut rewind = RewindBuffer::default();
+    rewind.capture(&gb).unwrap();
+
+    for _ in 0 .. 20 {
+        gb.run_frame();
+        rewind.on_frame_completed(&gb).unwrap();
+    }
+
+    // deltas are much smaller than the full states
+    let state_size = Snapshot::create_from(&gb).unwrap().get_data().len();
+    assert!(rewind.get_memory_usage() < rewind.len() * state_size / 2);
+
+    let (oldest, newest) = rewind.get_cycles_range().unwrap();
+    let target   = (oldest + newest) / 2;
+    let restored = rewind.seek_to_cycles(target).unwrap().unwrap();
+    assert!(restored.get_total_cycles_processed() >= target);
+    assert!(restored.get_total_cycles_processed() <  target + 32);
+
+    // states after the requested cycle count were removed
+    assert!(rewind.get_cycles_range().unwrap().1 <= target);
+    assert!(rewind.seek_to_cycles(0).unwrap().is_some());
+    assert!(rewind.seek_to_cycles(newest).unwrap().unwrap().get_total_cycles_processed() >= newest);
+}
@@ -0,0 +1,132 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+use gemi_core::debug::DebugEvent;
+use gemi_core::gameboy::GameBoy;
+use gemi_core::mmu::memory::RumbleEvent;
+
+mod common;
+
+
+fn create_gameboy(rom_type: u8) -> GameBoy {
+    let mut gb = common::create_gameboy(common::create_mbc_rom(0x20000, rom_type, 0x04));
+    gb.get_peripherals_mut().mem.enable_rumble_events(true);
+
+    gb
+}
+
+
+/// Writes a value into each of the first 16 RAM banks, using the number of the bank.
+fn write_ram_banks(gb: &mut GameBoy) {
+    gb.get_mmu_mut().write_u8(0x0000, 0x0a);
+
+    for bank in 0..16 {
+        gb.get_mmu_mut().write_u8(0x4000, bank);
+        gb.get_mmu_mut().write_u8(0xa000, bank);
+    }
+}
+
+
+#[test]
+fn mbc5_rumble_events() {
+    let mut gb = create_gameboy(0x1e);
+
+    // turn the motor on
+    gb.get_mmu_mut().write_u8(0x4000, 0x08);
+    let cycle_on = gb.get_total_cycles_processed();
+    let results  = gb.run_single_step();
+    assert!(results.events.contains(DebugEvent::RumbleChanged));
+    assert!(gb.get_peripherals().mem.is_rumble_active());
+
+    // no further events while the motor keeps running
+    gb.get_mmu_mut().write_u8(0x4000, 0x09);
+    let results = gb.run_frame();
+    assert!(!results.events.contains(DebugEvent::RumbleChanged));
+
+    // turn the motor off
+    gb.get_mmu_mut().write_u8(0x4000, 0x01);
+    let cycle_off = gb.get_total_cycles_processed();
+    let results   = gb.run_single_step();
+    assert!(results.events.contains(DebugEvent::RumbleChanged));
+    assert!(!gb.get_peripherals().mem.is_rumble_active());
+
+    assert_eq!(
+        vec![
+            RumbleEvent { cycle: cycle_on,  active: true  },
+            RumbleEvent { cycle: cycle_off, active: false },
+        ],
+        gb.get_peripherals_mut().mem.take_rumble_events()
+    );
+
+    assert!(gb.get_peripherals_mut().mem.take_rumble_events().is_empty());
+}
+
+
+#[test]
+fn mbc5_rumble_ram_banks() {
+    let mut gb = create_gameboy(0x1e);
+    write_ram_banks(&mut gb);
+
+    // bit 3 controls the motor, so only 8 RAM banks can be selected
+    gb.get_mmu_mut().write_u8(0x4000, 0x02);
+    assert_eq!(0x0a, gb.get_mmu().read_u8(0xa000));
+    gb.get_mmu_mut().write_u8(0x4000, 0x0a);
+    assert_eq!(0x0a, gb.get_mmu().read_u8(0xa000));
+}
+
+
+#[test]
+fn mbc5_without_rumble() {
+    let mut gb = create_gameboy(0x1b);
+
+    // the motor is never turned on
+    gb.get_mmu_mut().write_u8(0x4000, 0x08);
+    let results = gb.run_single_step();
+    assert!(!results.events.contains(DebugEvent::RumbleChanged));
+    assert!(gb.get_peripherals_mut().mem.take_rumble_events().is_empty());
+}
+
+
+#[cfg(feature = "snapshots")]
+#[test]
+fn mbc5_rumble_after_restore() {
+    use gemi_core::snapshots::Snapshot;
+
+    let mut gb = create_gameboy(0x1e);
+    gb.get_mmu_mut().write_u8(0x4000, 0x08);
+    gb.run_single_step();
+
+    // the state of the motor is restored, but not the pending events and whether they are recorded
+    let mut restored = Snapshot::create_from(&gb).unwrap().restore_for(&gb).unwrap();
+    assert!(restored.get_peripherals().mem.is_rumble_active());
+    assert!(restored.get_peripherals_mut().mem.take_rumble_events().is_empty());
+
+    restored.get_mmu_mut().write_u8(0x4000, 0x00);
+    restored.run_single_step();
+    assert!(!restored.get_peripherals().mem.is_rumble_active());
+    assert!(restored.get_peripherals_mut().mem.take_rumble_events().is_empty());
+
+    // taking over the frontend settings from the previous instance enables the events again
+    restored.get_peripherals_mut().mem.take_frontend_state_from(&mut gb.get_peripherals_mut().mem);
+    restored.get_mmu_mut().write_u8(0x4000, 0x08);
+    let cycle = restored.get_total_cycles_processed();
+    restored.run_single_step();
+
+    assert_eq!(
+        vec![RumbleEvent { cycle, active: true }],
+        restored.get_peripherals_mut().mem.take_rumble_events()
+    );
+}
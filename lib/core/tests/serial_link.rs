@@ -0,0 +1,106 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::gameboy::GameBoy;
+use gemi_core::mmu::locations::*;
+use gemi_core::serial::LinkCable;
+
+mod common;
+
+
+/// Creates a GameBoy running a program, which writes a byte into SB
+/// and starts a transfer with the given value of SC.
+fn create_gameboy(data: u8, control: u8) -> GameBoy {
+    common::create_gameboy(common::create_rom_with_program(&[
+        0x3e, data,     // LD A, data
+        0xe0, 0x01,     // LDH ($01), A
+        0x3e, control,  // LD A, control
+        0xe0, 0x02,     // LDH ($02), A
+        0x18, 0xfe,     // JR -2
+    ]))
+}
+
+
+/// Starts a transfer via SB and SC registers and returns
+/// the number of cycles until it completes.
+fn measure_transfer(gb: &mut GameBoy, data: u8, control: u8) -> u64 {
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_SB, data);
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_SC, control);
+
+    let mut cycles = 0;
+    while gb.get_mmu().read_u8(MEMORY_LOCATION_SC) & 0x80 != 0 {
+        gb.get_peripherals_mut().serial.update(1);
+        cycles += 1;
+    }
+
+    cycles
+}
+
+
+#[test]
+fn transfer_without_remote_device() {
+    let mut gb = GameBoy::build().finish().unwrap();
+    gb.get_peripherals_mut().serial.enable_output_queue(true);
+
+    // 8 bits with 8192 Hz, while nothing is received
+    assert_eq!(4096, measure_transfer(&mut gb, 0x42, 0x81));
+    assert_eq!(0xff, gb.get_mmu().read_u8(MEMORY_LOCATION_SB));
+    assert_eq!(vec![0x42], gb.get_peripherals().serial.get_output());
+
+    // the fast clock is not available on DMG
+    assert_eq!(4096, measure_transfer(&mut gb, 0x42, 0x83));
+}
+
+
+#[test]
+fn fast_clock_on_gbc() {
+    let mut gb = common::create_gameboy(common::create_gbc_rom());
+
+    assert_eq!(128, measure_transfer(&mut gb, 0x42, 0x83));
+}
+
+
+#[test]
+fn external_clock_waits_for_remote_device() {
+    let mut gb = create_gameboy(0x42, 0x80);
+    gb.run_frame();
+
+    assert_eq!(0xfe, gb.get_mmu().read_u8(MEMORY_LOCATION_SC));
+    assert_eq!(0x42, gb.get_mmu().read_u8(MEMORY_LOCATION_SB));
+}
+
+
+#[test]
+fn exchange_data_via_link_cable() {
+    let master = create_gameboy(0x42, 0x81);
+    let slave  = create_gameboy(0x99, 0x80);
+
+    let mut cable = LinkCable::connect(master, slave);
+    cable.run_frame();
+
+    for index in 0 .. 2 {
+        let gb = cable.get_device(index);
+        assert_eq!(0x7e, gb.get_mmu().read_u8(MEMORY_LOCATION_SC) & 0xfe);
+        assert_ne!(0, gb.get_mmu().read_u8(MEMORY_LOCATION_INTERRUPTS_FLAGGED) & 0x08);
+    }
+
+    assert_eq!(0x99, cable.get_device(0).get_mmu().read_u8(MEMORY_LOCATION_SB));
+    assert_eq!(0x42, cable.get_device(1).get_mmu().read_u8(MEMORY_LOCATION_SB));
+
+    let (master, _) = cable.disconnect();
+    assert!(!master.get_peripherals().serial.is_connected());
+}
@@ -0,0 +1,136 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+#![cfg(feature = "snapshots")]
+
+use gemi_core::device_type::DeviceType;
+use gemi_core::gameboy::GameBoy;
+use gemi_core::snapshots::*;
+
+mod common;
+
+
+fn create_gameboy(title: &str) -> GameBoy {
+    let mut rom = common::create_rom(0x8000);
+    common::set_title(&mut rom, title);
+    common::create_gameboy(rom)
+}
+
+
+#[test]
+fn snapshot_roundtrip_with_header() {
+    let mut gb = create_gameboy("SNAPSHOT");
+    gb.run_frame();
+
+    let bytes = Snapshot::create_from(&gb).unwrap().to_bytes().unwrap();
+    assert_eq!(SNAPSHOT_MAGIC, bytes[0..8]);
+
+    let snapshot = Snapshot::from_bytes(&bytes).unwrap();
+    let header   = snapshot.get_header();
+    assert_eq!("SNAPSHOT", header.rom_title);
+    assert_eq!(DeviceType::GameBoyDmg, header.device);
+    assert_eq!(gb.get_total_cycles_processed(), header.created_at_cycles);
+    assert_eq!(160, header.thumbnail.as_ref().unwrap().width);
+
+    let restored = snapshot.restore_for(&gb).unwrap();
+    assert_eq!(gb.cpu.get_instruction_pointer(), restored.cpu.get_instruction_pointer());
+}
+
+
+/// A snapshot in version 1 of the snapshot format, which was produced by checking out
+/// the commit introducing the versioned snapshot container (b40e361) and saving
+/// `Snapshot::create_from(&gb)` via `save_to_file` after running the ROM of
+/// `create_gameboy("FIXTURE")` for two frames.
+const SNAPSHOT_V1: &[u8] = include_bytes!("fixtures/snapshot_v1.gemisnap");
+
+/// The same snapshot produced with the current version of the snapshot format.
+/// Any change to the layout of the emulator state needs to increase
+/// SNAPSHOT_FORMAT_VERSION and add a new fixture for the new version.
+const SNAPSHOT_CURRENT: &[u8] = include_bytes!("fixtures/snapshot_v2.gemisnap");
+
+
+#[test]
+fn reject_legacy_snapshot() {
+    let gb = create_gameboy("LEGACY");
+
+    // legacy snapshots were just the serialized emulator state without any header
+    let legacy_data = Snapshot::create_from(&gb).unwrap().get_data().to_vec();
+    assert!(matches!(Snapshot::from_bytes(&legacy_data), Err(SnapshotError::LegacyFormat)));
+
+    let mut bytes = Snapshot::create_from(&gb).unwrap().to_bytes().unwrap();
+    bytes[8..10].copy_from_slice(&0u16.to_le_bytes());
+    assert!(matches!(Snapshot::from_bytes(&bytes), Err(SnapshotError::LegacyFormat)));
+}
+
+
+#[test]
+fn restore_snapshot_v1() {
+    let gb       = create_gameboy("FIXTURE");
+    let snapshot = Snapshot::from_bytes(SNAPSHOT_V1).unwrap();
+    assert_eq!("FIXTURE", snapshot.get_header().rom_title);
+    assert_eq!(135888, snapshot.get_header().created_at_cycles);
+    assert_eq!(160, snapshot.get_header().thumbnail.as_ref().unwrap().width);
+
+    let mut restored = snapshot.restore_for(&gb).unwrap();
+    assert_eq!(0x05e6, restored.cpu.get_instruction_pointer());
+    assert_eq!(135888, restored.get_total_cycles_processed());
+
+    restored.run_frame();
+}
+
+
+#[test]
+fn snapshot_format_is_unchanged() {
+    assert_eq!(
+        SNAPSHOT_FORMAT_VERSION,
+        u16::from_le_bytes([SNAPSHOT_CURRENT[8], SNAPSHOT_CURRENT[9]]),
+        "SNAPSHOT_FORMAT_VERSION was increased, a fixture for the new version needs to be added"
+    );
+
+    let gb       = create_gameboy("FIXTURE");
+    let snapshot = Snapshot::from_bytes(SNAPSHOT_CURRENT).unwrap();
+    let restored = snapshot.restore_for(&gb).unwrap();
+    assert_eq!(0x05e6, restored.cpu.get_instruction_pointer());
+
+    // storing the restored state again has to give the same data, otherwise
+    // the layout of the emulator state was changed without increasing the format version
+    assert_eq!(
+        snapshot.get_data(),
+        Snapshot::create_from(&restored).unwrap().get_data(),
+        "The layout of the emulator state was changed, which requires to increase SNAPSHOT_FORMAT_VERSION"
+    );
+}
+
+
+#[test]
+fn snapshot_errors() {
+    let gb    = create_gameboy("GAME A");
+    let other = create_gameboy("GAME B");
+    let snapshot = Snapshot::create_from(&gb).unwrap();
+
+    assert!(matches!(snapshot.restore_for(&other), Err(SnapshotError::RomMismatch { .. })));
+
+    let mut bytes = snapshot.to_bytes().unwrap();
+    bytes[8] = 0xff;
+    assert!(matches!(
+        Snapshot::from_bytes(&bytes),
+        Err(SnapshotError::UnsupportedVersion { found: 0x00ff, supported: SNAPSHOT_FORMAT_VERSION })
+    ));
+
+    assert!(matches!(Snapshot::from_bytes(&[0x01, 0x02, 0x03]), Err(SnapshotError::LegacyFormat)));
+    assert!(matches!(Snapshot::from_bytes(&SNAPSHOT_MAGIC), Err(SnapshotError::InvalidData(_))));
+}
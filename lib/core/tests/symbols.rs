@@ -0,0 +1,100 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::debug::symbols::SymbolTable;
+
+
+#[test]
+fn sym_file() {
+    let symbols = SymbolTable::parse_sym("
+        ; File generated by rgblink
+        00:0150 Main
+        00:0158 Main.loop
+        01:4000 Banked   ; comment
+        01:4010 Banked.end
+        00:c000 wCounter
+        invalid line
+    ");
+
+    assert_eq!(symbols.get_symbols().len(), 5);
+    assert_eq!(symbols.find_by_name("Banked").map(|symbol| (symbol.bank, symbol.address)), Some((1, 0x4000)));
+    assert_eq!(symbols.find_at(0x0158, Some(0)).map(|symbol| symbol.name.as_str()), Some("Main.loop"));
+    assert_eq!(symbols.find_at(0x4000, Some(2)), None);
+
+    // symbols are looked up within the same bank and memory area only
+    assert_eq!(symbols.format_address(0x0153, Some(0)).as_deref(), Some("Main+$3"));
+    assert_eq!(symbols.format_address(0x4012, Some(1)).as_deref(), Some("Banked.end+$2"));
+    assert_eq!(symbols.format_address(0x4012, Some(2)),            None);
+    assert_eq!(symbols.format_address(0x4000, None).as_deref(),    Some("Banked"));
+    assert_eq!(symbols.format_address(0x3fff, Some(0)).as_deref(), Some("Main.loop+$3ea7"));
+    assert_eq!(symbols.format_address(0xd000, None),               None);
+}
+
+
+#[test]
+fn map_file() {
+    let mut symbols = SymbolTable::parse_map("
+SUMMARY:
+	ROM0: 352 bytes used / 16032 free
+	ROMX: 16 bytes used / 16368 free in 1 bank
+
+ROM0 bank #0:
+	SECTION: $0150-$015f ($0010 bytes) [\"Main\"]
+	         $0150 = Main
+	         $0158 = Main.loop
+	EMPTY: $0160-$3fff ($3ea0 bytes)
+
+ROMX bank #3:
+	SECTION: $4000-$400f ($0010 bytes) [\"Banked\"]
+	         $4000 = Banked
+
+WRAM0 bank #0:
+	SECTION: $c000-$c000 ($0001 bytes) [\"Variables\"]
+	         $c000 = wCounter
+    ");
+
+    assert_eq!(symbols.get_symbols().len(), 4);
+    assert_eq!(symbols.find_by_name("Banked").map(|symbol| (symbol.bank, symbol.address)), Some((3, 0x4000)));
+    assert_eq!(symbols.find_by_name("wCounter").map(|symbol| (symbol.bank, symbol.address)), Some((0, 0xc000)));
+
+    // merging keeps existing symbols
+    symbols.merge(SymbolTable::parse_sym("00:0151 Main\n00:0200 Other"));
+    assert_eq!(symbols.get_symbols().len(), 5);
+    assert_eq!(symbols.find_by_name("Main").map(|symbol| symbol.address), Some(0x0150));
+    assert_eq!(symbols.find_by_name("Other").map(|symbol| symbol.address), Some(0x0200));
+}
+
+
+#[test]
+fn edit_and_write() {
+    let mut symbols = SymbolTable::parse_sym("00:0150 Main\n01:4000 Banked");
+
+    // labels cannot be renamed into an existing name
+    assert!(symbols.rename("Main", "Banked").is_err());
+    assert!(symbols.rename("Missing", "Other").is_err());
+    assert!(symbols.rename("Main", "Start").is_ok());
+    assert_eq!(symbols.find_at(0x0150, Some(0)).map(|symbol| symbol.name.as_str()), Some("Start"));
+    assert_eq!(symbols.find_by_name("Main"), None);
+
+    assert_eq!(symbols.remove("Banked").map(|symbol| symbol.address), Some(0x4000));
+    assert_eq!(symbols.remove("Banked"), None);
+
+    // the written text can be parsed again
+    let text = symbols.to_sym_string();
+    assert_eq!(text, "00:0150 Start\n");
+    assert_eq!(SymbolTable::parse_sym(&text).get_symbols(), symbols.get_symbols());
+}
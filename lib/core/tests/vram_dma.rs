@@ -0,0 +1,132 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::gameboy::GameBoy;
+use gemi_core::mmu::locations::*;
+
+mod common;
+
+
+fn create_gbc_gameboy() -> GameBoy {
+    let mut gb = common::create_gameboy(common::create_gbc_rom());
+    gb.initialize();
+    gb
+}
+
+
+/// Fills WRAM at 0xc000 with test data and sets up source and destination of a VRAM DMA.
+fn setup_transfer(gb: &mut GameBoy, length: u16) {
+    for i in 0 .. length {
+        gb.get_mmu_mut().write_u8(0xc000 + i, (i as u8) ^ 0x5a);
+    }
+
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_HDMA1, 0xc0);
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_HDMA2, 0x00);
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_HDMA3, 0x01);
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_HDMA4, 0x00);
+}
+
+
+fn check_transferred(gb: &GameBoy, length: u16) {
+    for i in 0 .. length {
+        assert_eq!((i as u8) ^ 0x5a, gb.get_mmu().read_u8(0x8100 + i), "mismatch at offset {i}");
+    }
+}
+
+
+#[test]
+fn general_purpose_dma() {
+    let mut gb = create_gbc_gameboy();
+    assert_eq!(0xff, gb.get_mmu().read_u8(MEMORY_LOCATION_HDMA5));
+
+    setup_transfer(&mut gb, 0x40);
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_HDMA5, 0x03);
+
+    // data is transferred at once, but the CPU is stalled for the transfer time
+    check_transferred(&gb, 0x40);
+    assert_eq!(0xff, gb.get_mmu().read_u8(MEMORY_LOCATION_HDMA5));
+    assert!(gb.get_mmu().is_cpu_stalled());
+
+    let pc = gb.cpu.get_instruction_pointer();
+    gb.run_single_step();
+    assert_eq!(pc, gb.cpu.get_instruction_pointer());
+}
+
+
+#[test]
+fn hblank_dma() {
+    let mut gb = create_gbc_gameboy();
+
+    setup_transfer(&mut gb, 0x30);
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_HDMA5, 0x82);
+    assert_eq!(0x02, gb.get_mmu().read_u8(MEMORY_LOCATION_HDMA5));
+
+    // one block gets transferred on each HBlank
+    gb.run_frame();
+    check_transferred(&gb, 0x30);
+    assert_eq!(0xff, gb.get_mmu().read_u8(MEMORY_LOCATION_HDMA5));
+}
+
+
+#[test]
+fn hblank_dma_cancel() {
+    let mut gb = create_gbc_gameboy();
+
+    setup_transfer(&mut gb, 0x40);
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_HDMA5, 0x83);
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_HDMA5, 0x00);
+
+    // the remaining length is still readable, but no transfer active anymore
+    assert_eq!(0x83, gb.get_mmu().read_u8(MEMORY_LOCATION_HDMA5));
+
+    gb.run_frame();
+    assert_eq!(0x00, gb.get_mmu().read_u8(0x8100));
+}
+
+
+#[test]
+fn hblank_dma_starts_on_hblank_edge() {
+    let mut gb = create_gbc_gameboy();
+
+    // run until the PPU starts scanning OAM of the next line
+    while get_mode(&gb) != 2 {
+        gb.run_single_step();
+    }
+
+    let line_started_at = gb.get_total_cycles_processed();
+
+    setup_transfer(&mut gb, 0x20);
+    gb.get_mmu_mut().write_u8(MEMORY_LOCATION_HDMA5, 0x81);
+
+    // no data will be transferred before the PPU enters HBlank
+    while get_mode(&gb) != 0 {
+        assert_eq!(0x01, gb.get_mmu().read_u8(MEMORY_LOCATION_HDMA5));
+        gb.run_single_step();
+    }
+
+    // the first block is transferred on the same cycle the PPU entered HBlank,
+    // which is after 80 cycles of OAM scan and 172 cycles of drawing
+    assert_eq!(252, gb.get_total_cycles_processed() - line_started_at);
+    assert_eq!(0x00, gb.get_mmu().read_u8(MEMORY_LOCATION_HDMA5));
+    check_transferred(&gb, 0x10);
+    assert_eq!(0x00, gb.get_mmu().read_u8(0x8110));
+}
+
+
+fn get_mode(gb: &GameBoy) -> u8 {
+    gb.get_mmu().read_u8(MEMORY_LOCATION_LCD_STATUS) & 0x03
+}
@@ -0,0 +1,76 @@
+/*
+ * Copyright (C) 2022-2023 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::io;
+use std::io::{BufRead, ErrorKind, Write};
+
+use serde_json::Value;
+
+/// The header containing the length of a message's content.
+const HEADER_CONTENT_LENGTH: &str = "Content-Length";
+
+
+/// Reads a single message, which consists of a header and a JSON body.
+/// Returns `None` when the stream was closed.
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+
+    // read header lines until the empty line separating them from the content
+    loop {
+        let mut line = String::new();
+
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+
+        if line.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+
+            continue;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case(HEADER_CONTENT_LENGTH) {
+                let length = value.trim().parse::<usize>()
+                    .map_err(|_| io::Error::new(ErrorKind::InvalidData, format!("Invalid content length '{value}'")))
+                    ?;
+
+                content_length = Some(length);
+            }
+        }
+    }
+
+    let mut content = vec![0; content_length.unwrap_or_default()];
+    reader.read_exact(&mut content)?;
+
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+
+/// Writes a single message including its header.
+pub fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+
+    write!(writer, "{HEADER_CONTENT_LENGTH}: {}\r\n\r\n{content}", content.len())?;
+    writer.flush()
+}
@@ -0,0 +1,87 @@
+/*
+ * Copyright (C) 2022-2023 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::io;
+use std::io::{BufRead, Write};
+use std::sync::mpsc;
+use std::sync::mpsc::TryRecvError;
+use std::thread;
+
+use crate::protocol::{read_message, write_message};
+use crate::session::DapSession;
+
+
+/// Runs a debugging session, receiving requests from `reader` and sending responses
+/// and events to `writer`, until the client ends the session or closes the stream.
+///
+/// Messages are received on a separate thread, so the client is able to pause
+/// the emulator while it's running.
+pub fn serve<R, W>(reader: R, mut writer: W) -> io::Result<()>
+    where
+        R: BufRead + Send + 'static,
+        W: Write
+{
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut reader = reader;
+
+        loop {
+            let message = read_message(&mut reader).transpose();
+            let is_last = !matches!(message, Some(Ok(_)));
+
+            if let Some(message) = message {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+
+            if is_last {
+                break;
+            }
+        }
+    });
+
+    let mut session = DapSession::new();
+
+    while !session.is_terminated() {
+        let message = if session.is_running() {
+            match receiver.try_recv() {
+                Ok(message)                     => Some(message),
+                Err(TryRecvError::Empty)        => None,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+        else {
+            match receiver.recv() {
+                Ok(message) => Some(message),
+                Err(_)      => break,
+            }
+        };
+
+        match message {
+            Some(message) => session.handle_message(&message?),
+            None          => session.run_slice(),
+        }
+
+        for message in session.take_messages() {
+            write_message(&mut writer, &message)?;
+        }
+    }
+
+    Ok(())
+}
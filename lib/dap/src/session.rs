@@ -0,0 +1,855 @@
+/*
+ * Copyright (C) 2022-2023 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+use std::mem::take;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use gemi_core::cartridge::Cartridge;
+use gemi_core::cpu::cpu::{CpuFlag, RegisterR16, RegisterR8};
+use gemi_core::debug::breakpoints::{Breakpoint, BreakpointId};
+use gemi_core::debug::symbols::{SymbolTable, FILE_EXT_MAP, FILE_EXT_SYM};
+use gemi_core::debug::DebugEvent;
+use gemi_core::gameboy::{Clock, EmulatorUpdateResults, GameBoy};
+use gemi_core::ppu::ppu::CPU_CYCLES_PER_FRAME;
+
+use crate::sources::{normalize_path, SourceLocation, SourceMap};
+
+/// The ID of the only thread reported to the client, which represents the CPU.
+const THREAD_ID: i64 = 1;
+
+/// The number of cycles to run before checking for new requests while the emulator is running.
+const CYCLES_PER_SLICE: Clock = CPU_CYCLES_PER_FRAME;
+
+/// Variables reference of the CPU registers scope.
+const VARIABLES_REGISTERS: i64 = 1;
+
+/// Variables reference of the scope containing all symbols located in RAM.
+const VARIABLES_SYMBOLS: i64 = 2;
+
+/// Variables reference of the first memory scope, followed by all others.
+const VARIABLES_MEMORY: i64 = 10;
+
+/// The memory areas shown as scopes, with their name and address range.
+const MEMORY_SCOPES: [(&str, u16, u16); 6] = [
+    ("Video RAM",       0x8000, 0x9fff),
+    ("Cartridge RAM",   0xa000, 0xbfff),
+    ("Work RAM",        0xc000, 0xdfff),
+    ("OAM",             0xfe00, 0xfe9f),
+    ("I/O Registers",   0xff00, 0xff7f),
+    ("High RAM",        0xff80, 0xfffe),
+];
+
+/// The number of bytes shown per variable within a memory scope.
+const BYTES_PER_ROW: u16 = 16;
+
+/// Opcodes calling a function, either conditionally or unconditionally.
+const OPCODES_CALL: [u8; 13] = [
+    0xc4, 0xcc, 0xcd, 0xd4, 0xdc,                   // CALL
+    0xc7, 0xcf, 0xd7, 0xdf, 0xe7, 0xef, 0xf7, 0xff, // RST
+];
+
+/// Opcodes returning from a function.
+const OPCODES_RETURN: [u8; 6] = [0xc0, 0xc8, 0xc9, 0xd0, 0xd8, 0xd9];
+
+
+/// How the emulator is being run.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+enum RunMode {
+    /// The emulator is stopped and waits for requests.
+    #[default]
+    Stopped,
+
+    /// Runs until a breakpoint is hit or the client pauses.
+    Continue,
+
+    /// Runs a single instruction.
+    StepIn,
+
+    /// Runs a single instruction, including any function being called.
+    StepOver { depth: usize },
+
+    /// Runs until the current function returns.
+    StepOut { depth: usize },
+}
+
+
+/// A function call being tracked to build the call stack.
+#[derive(Copy, Clone, Debug)]
+struct CallFrame {
+    /// The address of the instruction which called the function,
+    /// or the address of the instruction being interrupted.
+    call_address: u16,
+
+    /// The value of the stack pointer after the return address was pushed.
+    stack_pointer: u16,
+}
+
+
+/// A breakpoint set by the client on a line of a source file.
+#[derive(Copy, Clone, Debug)]
+struct SourceBreakpoint {
+    /// The ID reported to the client.
+    id: i64,
+
+    /// The line requested by the client.
+    line: usize,
+
+    /// The location the breakpoint was resolved to.
+    location: Option<SourceLocation>,
+
+    /// The ID of the breakpoint installed into the emulator.
+    core_id: Option<BreakpointId>,
+}
+
+
+/// A debugging session serving requests of a Debug Adapter Protocol client.
+///
+/// The session does not perform any I/O on its own. Requests are passed into
+/// [DapSession::handle_message], while all responses and events are collected
+/// to be taken by [DapSession::take_messages]. While the emulator is running,
+/// [DapSession::run_slice] needs to be called repeatedly.
+#[derive(Default)]
+pub struct DapSession {
+    /// The emulator being debugged, after the client has launched a program.
+    gb: Option<GameBoy>,
+
+    /// Symbols loaded from the program's symbol and map files.
+    symbols: SymbolTable,
+
+    /// The assembly source files of the program.
+    sources: SourceMap,
+
+    /// The breakpoints set by the client for each source file.
+    breakpoints: HashMap<PathBuf, Vec<SourceBreakpoint>>,
+
+    /// The ID of the next breakpoint set by the client.
+    next_breakpoint_id: i64,
+
+    /// The stack of function calls observed while running.
+    call_stack: Vec<CallFrame>,
+
+    /// How the emulator is currently being run.
+    mode: RunMode,
+
+    /// Whether the emulator was just resumed and did not process any step yet.
+    resumed: bool,
+
+    /// Whether the client has finished its configuration.
+    configured: bool,
+
+    /// Whether to stop on the program's entry point instead of running after the configuration was done.
+    stop_on_entry: bool,
+
+    /// Whether the client has ended the session.
+    terminated: bool,
+
+    /// The sequence number of the next message being sent.
+    next_seq: i64,
+
+    /// Messages to be sent to the client.
+    outgoing: Vec<Value>,
+}
+
+
+impl DapSession {
+    /// Creates a new session without any program being loaded.
+    pub fn new() -> Self {
+        Self {
+            next_breakpoint_id: 1,
+            next_seq:           1,
+            .. Self::default()
+        }
+    }
+
+
+    /// Checks whether the emulator is running and [DapSession::run_slice]
+    /// needs to be called until it stops.
+    pub fn is_running(&self) -> bool {
+        self.mode != RunMode::Stopped && !self.terminated
+    }
+
+
+    /// Checks whether the client has ended the session.
+    pub fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+
+
+    /// Takes all messages to be sent to the client.
+    pub fn take_messages(&mut self) -> Vec<Value> {
+        take(&mut self.outgoing)
+    }
+
+
+    /// Handles a message received from the client.
+    /// Any message other than requests will be ignored.
+    pub fn handle_message(&mut self, message: &Value) {
+        if message["type"] != "request" {
+            return;
+        }
+
+        let command   = message["command"].as_str().unwrap_or_default();
+        let arguments = &message["arguments"];
+
+        let result = match command {
+            "initialize"                => Ok(get_capabilities()),
+            "launch"                    => self.launch(arguments),
+            "setBreakpoints"            => self.set_breakpoints(arguments),
+            "setExceptionBreakpoints"   => Ok(json!({ "breakpoints": [] })),
+            "configurationDone"         => Ok(Value::Null),
+            "threads"                   => Ok(json!({ "threads": [ { "id": THREAD_ID, "name": "CPU" } ] })),
+            "stackTrace"                => self.stack_trace(),
+            "scopes"                    => self.scopes(),
+            "variables"                 => self.variables(arguments),
+            "continue"                  => self.resume(RunMode::Continue),
+            "next"                      => self.resume(RunMode::StepOver { depth: self.call_stack.len() }),
+            "stepIn"                    => self.resume(RunMode::StepIn),
+            "stepOut"                   => self.resume(RunMode::StepOut { depth: self.call_stack.len() }),
+            "pause"                     => Ok(Value::Null),
+            "disconnect" | "terminate"  => Ok(Value::Null),
+            _                           => Err(format!("Unsupported request '{command}'")),
+        };
+
+        let success = result.is_ok();
+        self.send_response(message, command, result);
+
+        if !success {
+            return;
+        }
+
+        // events following the response
+        match command {
+            "launch" => {
+                self.send_event("initialized", Value::Null);
+                self.start_if_ready();
+            }
+
+            "configurationDone" => {
+                self.configured = true;
+                self.start_if_ready();
+            }
+
+            "pause" if self.is_running() => {
+                self.stop("pause", Vec::new(), None);
+            }
+
+            "terminate" => {
+                self.send_event("terminated", Value::Null);
+                self.terminated = true;
+            }
+
+            "disconnect" => {
+                self.terminated = true;
+            }
+
+            _ => { }
+        }
+    }
+
+
+    /// Runs the emulator for a limited amount of time, until it stops
+    /// or new requests from the client need to be checked.
+    pub fn run_slice(&mut self) {
+        let Some(gb) = self.gb.as_mut() else {
+            self.mode = RunMode::Stopped;
+            return;
+        };
+
+        let mut cycles = 0;
+
+        while cycles < CYCLES_PER_SLICE {
+            let address        = gb.cpu.get_instruction_pointer();
+            let stack_pointer  = gb.cpu.get_stack_pointer();
+            let opcode         = gb.get_mmu().peek_u8(address);
+            let was_running    = gb.cpu.is_running();
+            let resumed        = take(&mut self.resumed);
+
+            let results = gb.run_single_step();
+            cycles += results.cycles;
+
+            // when resuming on a breakpoint, the emulator stops before executing it,
+            // which is ignored as the client has already seen it
+            if resumed && results.cycles == 0 {
+                continue;
+            }
+
+            update_call_stack(&mut self.call_stack, gb, &results, opcode, address, stack_pointer);
+
+            if !results.breakpoint_hits.is_empty() {
+                let ids = results.breakpoint_hits
+                    .iter()
+                    .filter_map(|hit| self.find_breakpoint_id(hit.id))
+                    .collect();
+
+                self.stop("breakpoint", ids, None);
+                return;
+            }
+
+            if results.events.contains(DebugEvent::CpuLocked) {
+                self.stop("exception", Vec::new(), Some("The CPU locked up after executing an illegal opcode"));
+                return;
+            }
+
+            let depth = self.call_stack.len();
+            let step_completed = match self.mode {
+                RunMode::Stopped                     => true,
+                RunMode::Continue                    => false,
+                RunMode::StepIn                      => was_running,
+                RunMode::StepOver { depth: initial } => was_running && depth <= initial,
+
+                // without a known caller, stop after any function returned
+                RunMode::StepOut { depth: 0 } => {
+                        OPCODES_RETURN.contains(&opcode)
+                    &&  gb.cpu.get_stack_pointer() > stack_pointer
+                }
+
+                RunMode::StepOut { depth: initial }  => depth < initial,
+            };
+
+            if step_completed {
+                self.stop("step", Vec::new(), None);
+                return;
+            }
+        }
+    }
+
+
+    /// Loads the program given by the client's launch arguments.
+    fn launch(&mut self, arguments: &Value) -> Result<Value, String> {
+        let program = arguments["program"].as_str()
+            .map(PathBuf::from)
+            .ok_or_else(|| "Missing argument 'program'".to_string())
+            ?;
+
+        let cartridge = Cartridge::load_file(&program)
+            .map_err(|e| format!("Failed to load cartridge: {e}"))
+            ?;
+
+        let mut builder = GameBoy::build();
+        builder.set_cartridge(cartridge);
+
+        let mut gb = builder.finish()?;
+        gb.initialize();
+
+        // load symbols from the files given or from files next to the program
+        let symbol_files = match (arguments["symbols"].as_str(), arguments["map"].as_str()) {
+            (None, None) => {
+                [FILE_EXT_SYM, FILE_EXT_MAP]
+                    .iter()
+                    .map(|extension| program.with_extension(extension))
+                    .filter(|path| path.exists())
+                    .collect()
+            }
+
+            (symbols, map) => {
+                symbols.into_iter().chain(map).map(PathBuf::from).collect::<Vec<_>>()
+            }
+        };
+
+        for path in symbol_files {
+            let symbols = SymbolTable::read_from_file(&path)
+                .map_err(|e| format!("Failed to load symbols from {}: {e}", path.display()))
+                ?;
+
+            self.symbols.merge(symbols);
+        }
+
+        // load sources from the directories given or from the program's directory
+        let source_roots = match arguments["sourceRoots"].as_array() {
+            Some(roots) => roots.iter().filter_map(Value::as_str).map(PathBuf::from).collect(),
+            None        => program.parent().map(Path::to_path_buf).into_iter().collect::<Vec<_>>(),
+        };
+
+        for root in source_roots {
+            self.sources.scan_directory(&root)
+                .map_err(|e| format!("Failed to load sources from {}: {e}", root.display()))
+                ?;
+        }
+
+        self.stop_on_entry = arguments["stopOnEntry"].as_bool().unwrap_or(false);
+        self.gb            = Some(gb);
+
+        // install breakpoints which were set before the program was loaded
+        let paths: Vec<PathBuf> = self.breakpoints.keys().cloned().collect();
+        for path in paths {
+            self.install_breakpoints(&path);
+        }
+
+        Ok(Value::Null)
+    }
+
+
+    /// Replaces all breakpoints of a source file.
+    fn set_breakpoints(&mut self, arguments: &Value) -> Result<Value, String> {
+        let path = arguments["source"]["path"].as_str()
+            .map(|path| normalize_path(Path::new(path)))
+            .ok_or_else(|| "Missing argument 'source.path'".to_string())
+            ?;
+
+        // the file may be located outside of the source directories
+        if !self.sources.has_file(&path) {
+            self.sources.load_file(&path)
+                .map_err(|e| format!("Failed to load {}: {e}", path.display()))
+                ?;
+        }
+
+        self.remove_breakpoints(&path);
+
+        let lines: Vec<usize> = arguments["breakpoints"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|breakpoint| breakpoint["line"].as_u64())
+            .map(|line| line as usize)
+            .collect()
+        ;
+
+        let breakpoints = lines
+            .into_iter()
+            .map(|line| {
+                let id = self.next_breakpoint_id;
+                self.next_breakpoint_id += 1;
+
+                SourceBreakpoint { id, line, location: None, core_id: None }
+            })
+            .collect()
+        ;
+
+        self.breakpoints.insert(path.clone(), breakpoints);
+        self.install_breakpoints(&path);
+
+        let breakpoints: Vec<Value> = self.breakpoints[&path]
+            .iter()
+            .map(|breakpoint| {
+                match breakpoint.location {
+                    Some(location) => json!({
+                        "id":       breakpoint.id,
+                        "verified": true,
+                        "line":     location.line,
+                        "source":   { "path": path },
+                    }),
+
+                    None => json!({
+                        "id":       breakpoint.id,
+                        "verified": false,
+                        "line":     breakpoint.line,
+                        "message":  "No code found for this line",
+                    }),
+                }
+            })
+            .collect()
+        ;
+
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+
+    /// Resolves the breakpoints of a source file and installs them into the emulator.
+    fn install_breakpoints(&mut self, path: &Path) {
+        let Some(gb) = self.gb.as_mut() else {
+            return;
+        };
+
+        let Some(breakpoints) = self.breakpoints.get_mut(path) else {
+            return;
+        };
+
+        for breakpoint in breakpoints {
+            breakpoint.location = self.sources.resolve_line(path, breakpoint.line, &self.symbols, |bank, address| {
+                read_code(gb, bank, address)
+            });
+
+            breakpoint.core_id = breakpoint.location.and_then(|location| {
+                gb.get_breakpoints_mut().add(Breakpoint::Execute {
+                    address: location.address,
+                    bank:    (location.address < 0x8000).then_some(location.bank),
+                }).ok()
+            });
+        }
+    }
+
+
+    /// Removes all breakpoints of a source file from the emulator.
+    fn remove_breakpoints(&mut self, path: &Path) {
+        let breakpoints = self.breakpoints.remove(path).unwrap_or_default();
+
+        if let Some(gb) = self.gb.as_mut() {
+            for core_id in breakpoints.iter().filter_map(|breakpoint| breakpoint.core_id) {
+                gb.get_breakpoints_mut().remove(core_id);
+            }
+        }
+    }
+
+
+    /// Get the ID reported to the client of a breakpoint installed into the emulator.
+    fn find_breakpoint_id(&self, core_id: BreakpointId) -> Option<i64> {
+        self.breakpoints
+            .values()
+            .flatten()
+            .find(|breakpoint| breakpoint.core_id == Some(core_id))
+            .map(|breakpoint| breakpoint.id)
+    }
+
+
+    /// Starts running the program, after it was launched and the client has finished its configuration.
+    fn start_if_ready(&mut self) {
+        if self.gb.is_none() || !self.configured {
+            return;
+        }
+
+        if self.stop_on_entry {
+            self.stop("entry", Vec::new(), None);
+        }
+        else {
+            self.mode    = RunMode::Continue;
+            self.resumed = true;
+        }
+    }
+
+
+    /// Resumes running the emulator in the given mode.
+    fn resume(&mut self, mode: RunMode) -> Result<Value, String> {
+        if self.gb.is_none() {
+            return Err("No program running".to_string());
+        }
+
+        self.mode    = mode;
+        self.resumed = true;
+
+        Ok(json!({ "allThreadsContinued": true }))
+    }
+
+
+    /// Stops the emulator and notifies the client about the reason.
+    fn stop(&mut self, reason: &str, breakpoint_ids: Vec<i64>, description: Option<&str>) {
+        self.mode = RunMode::Stopped;
+
+        let mut body = json!({
+            "reason":               reason,
+            "threadId":             THREAD_ID,
+            "allThreadsStopped":    true,
+        });
+
+        if !breakpoint_ids.is_empty() {
+            body["hitBreakpointIds"] = json!(breakpoint_ids);
+        }
+
+        if let Some(description) = description {
+            body["description"] = json!(description);
+        }
+
+        self.send_event("stopped", body);
+    }
+
+
+    /// Get the call stack, with the current location on top.
+    fn stack_trace(&self) -> Result<Value, String> {
+        let gb = self.get_gameboy()?;
+
+        let addresses = std::iter::once(gb.cpu.get_instruction_pointer())
+            .chain(self.call_stack.iter().rev().map(|frame| frame.call_address))
+        ;
+
+        let frames: Vec<Value> = addresses
+            .enumerate()
+            .map(|(index, address)| {
+                let bank = gb.get_mapped_rom_bank(address);
+
+                let name = self.symbols
+                    .format_address(address, bank)
+                    .unwrap_or_else(|| format!("${address:04x}"))
+                ;
+
+                let mut frame = json!({
+                    "id":                           index,
+                    "name":                         name,
+                    "line":                         0,
+                    "column":                       0,
+                    "instructionPointerReference":  format!("0x{address:04x}"),
+                });
+
+                let line = self.sources.find_line(address, bank, &self.symbols, |bank, address| {
+                    read_code(gb, bank, address)
+                });
+
+                if let Some((path, line)) = line {
+                    frame["line"]   = json!(line);
+                    frame["column"] = json!(1);
+                    frame["source"] = make_source(path);
+                }
+
+                frame
+            })
+            .collect()
+        ;
+
+        let total_frames = frames.len();
+
+        Ok(json!({
+            "stackFrames": frames,
+            "totalFrames": total_frames,
+        }))
+    }
+
+
+    /// Get the scopes of variables available.
+    /// As there are no local variables, all frames share the same scopes.
+    fn scopes(&self) -> Result<Value, String> {
+        self.get_gameboy()?;
+
+        let mut scopes = vec![
+            json!({ "name": "Registers", "presentationHint": "registers", "variablesReference": VARIABLES_REGISTERS, "expensive": false }),
+            json!({ "name": "Symbols",                                    "variablesReference": VARIABLES_SYMBOLS,   "expensive": false }),
+        ];
+
+        for (index, (name, _, _)) in MEMORY_SCOPES.iter().enumerate() {
+            scopes.push(json!({
+                "name":                 name,
+                "variablesReference":   VARIABLES_MEMORY + index as i64,
+                "expensive":            true,
+            }));
+        }
+
+        Ok(json!({ "scopes": scopes }))
+    }
+
+
+    /// Get the variables of a scope.
+    fn variables(&self, arguments: &Value) -> Result<Value, String> {
+        let gb        = self.get_gameboy()?;
+        let reference = arguments["variablesReference"].as_i64().unwrap_or_default();
+
+        let variables = match reference {
+            VARIABLES_REGISTERS => self.get_register_variables(gb),
+            VARIABLES_SYMBOLS   => self.get_symbol_variables(gb),
+
+            _ => {
+                let (_, begin, end) = usize::try_from(reference - VARIABLES_MEMORY)
+                    .ok()
+                    .and_then(|index| MEMORY_SCOPES.get(index))
+                    .ok_or_else(|| format!("Invalid variables reference {reference}"))
+                    ?;
+
+                get_memory_variables(gb, *begin, *end)
+            }
+        };
+
+        Ok(json!({ "variables": variables }))
+    }
+
+
+    /// Get the CPU registers as variables.
+    fn get_register_variables(&self, gb: &GameBoy) -> Vec<Value> {
+        let cpu = &gb.cpu;
+        let pc  = cpu.get_instruction_pointer();
+
+        let flags: String = [
+            (CpuFlag::Zero,      'Z'),
+            (CpuFlag::Negative,  'N'),
+            (CpuFlag::HalfCarry, 'H'),
+            (CpuFlag::Carry,     'C'),
+        ]
+            .iter()
+            .map(|(flag, name)| if cpu.is_flag_set(*flag) { *name } else { '-' })
+            .collect()
+        ;
+
+        let pc_value = match self.symbols.format_address(pc, gb.get_mapped_rom_bank(pc)) {
+            Some(name) => format!("${pc:04x} ({name})"),
+            None       => format!("${pc:04x}"),
+        };
+
+        vec![
+            make_variable("A",     format!("${:02x}", cpu.get_r8(RegisterR8::A))),
+            make_variable("F",     format!("${:02x}", cpu.get_r8(RegisterR8::F))),
+            make_variable("B",     format!("${:02x}", cpu.get_r8(RegisterR8::B))),
+            make_variable("C",     format!("${:02x}", cpu.get_r8(RegisterR8::C))),
+            make_variable("D",     format!("${:02x}", cpu.get_r8(RegisterR8::D))),
+            make_variable("E",     format!("${:02x}", cpu.get_r8(RegisterR8::E))),
+            make_variable("H",     format!("${:02x}", cpu.get_r8(RegisterR8::H))),
+            make_variable("L",     format!("${:02x}", cpu.get_r8(RegisterR8::L))),
+            make_variable("BC",    format!("${:04x}", cpu.get_r16(RegisterR16::BC))),
+            make_variable("DE",    format!("${:04x}", cpu.get_r16(RegisterR16::DE))),
+            make_variable("HL",    format!("${:04x}", cpu.get_r16(RegisterR16::HL))),
+            make_variable("SP",    format!("${:04x}", cpu.get_stack_pointer())),
+            make_variable("PC",    pc_value),
+            make_variable("Flags", flags),
+            make_variable("IME",   if cpu.is_interrupts_enabled() { "enabled" } else { "disabled" }.to_string()),
+        ]
+    }
+
+
+    /// Get the values of all symbols located in RAM as variables.
+    fn get_symbol_variables(&self, gb: &GameBoy) -> Vec<Value> {
+        self.symbols
+            .get_symbols()
+            .iter()
+            .filter(|symbol| symbol.address >= 0x8000)
+            .map(|symbol| {
+                let value = gb.get_mmu().debug_read_u8(symbol.address);
+                make_variable(&symbol.name, format!("${value:02x}"))
+            })
+            .collect()
+    }
+
+
+    /// Get the emulator, if a program was launched.
+    fn get_gameboy(&self) -> Result<&GameBoy, String> {
+        self.gb.as_ref().ok_or_else(|| "No program running".to_string())
+    }
+
+
+    /// Sends a response to a request.
+    fn send_response(&mut self, request: &Value, command: &str, result: Result<Value, String>) {
+        let mut response = json!({
+            "type":         "response",
+            "request_seq":  request["seq"],
+            "command":      command,
+        });
+
+        match result {
+            Ok(body) => {
+                response["success"] = json!(true);
+
+                if !body.is_null() {
+                    response["body"] = body;
+                }
+            }
+
+            Err(message) => {
+                response["success"] = json!(false);
+                response["message"] = json!(message);
+            }
+        }
+
+        self.send(response);
+    }
+
+
+    /// Sends an event.
+    fn send_event(&mut self, event: &str, body: Value) {
+        let mut message = json!({
+            "type":  "event",
+            "event": event,
+        });
+
+        if !body.is_null() {
+            message["body"] = body;
+        }
+
+        self.send(message);
+    }
+
+
+    /// Assigns the next sequence number to a message and queues it for sending.
+    fn send(&mut self, mut message: Value) {
+        message["seq"] = json!(self.next_seq);
+        self.next_seq += 1;
+
+        self.outgoing.push(message);
+    }
+}
+
+
+/// Get the capabilities of the debug adapter reported to the client.
+fn get_capabilities() -> Value {
+    json!({
+        "supportsConfigurationDoneRequest": true,
+        "supportsTerminateRequest":         true,
+        "supportsSteppingGranularity":      false,
+    })
+}
+
+
+/// Tracks function calls and returns of the last step executed.
+/// Calls are detected by CALL or RST instructions pushing their return address onto the stack,
+/// interrupts by the emulator reporting the interrupt being dispatched.
+/// Functions are considered to be returned as soon as the stack pointer moves above their frame.
+fn update_call_stack(call_stack: &mut Vec<CallFrame>, gb: &GameBoy, results: &EmulatorUpdateResults, opcode: u8, address: u16, stack_pointer: u16) {
+    let new_stack_pointer = gb.cpu.get_stack_pointer();
+
+    while call_stack.last().is_some_and(|frame| frame.stack_pointer < new_stack_pointer) {
+        call_stack.pop();
+    }
+
+    // conditional calls only push the return address when being taken
+    let is_call =
+            results.events.contains(DebugEvent::InterruptDispatched)
+        ||  (OPCODES_CALL.contains(&opcode) && new_stack_pointer == stack_pointer.wrapping_sub(2))
+    ;
+
+    if is_call {
+        call_stack.push(CallFrame {
+            call_address:  address,
+            stack_pointer: new_stack_pointer,
+        });
+    }
+}
+
+
+/// Reads a byte of code on a bank and address.
+/// For the cartridge ROM, the code is read from the given bank, regardless which bank is currently mapped.
+fn read_code(gb: &GameBoy, bank: u32, address: u16) -> u8 {
+    let cartridge = gb.get_peripherals().mem.get_cartridge();
+
+    match (address, cartridge) {
+        (0x0000 ..= 0x7fff, Some(cartridge)) if gb.get_mapped_rom_bank(address).is_some() => {
+            let offset = bank as usize * 0x4000 + (address as usize & 0x3fff);
+            cartridge.get_rom().get_data().get(offset).copied().unwrap_or(0xff)
+        }
+
+        _ => gb.get_mmu().debug_read_u8(address),
+    }
+}
+
+
+/// Get the contents of a memory range as variables, with a fixed number of bytes each.
+fn get_memory_variables(gb: &GameBoy, begin: u16, end: u16) -> Vec<Value> {
+    (begin ..= end)
+        .step_by(BYTES_PER_ROW as usize)
+        .map(|row| {
+            let values: Vec<String> = (row ..= end.min(row + BYTES_PER_ROW - 1))
+                .map(|address| format!("{:02x}", gb.get_mmu().debug_read_u8(address)))
+                .collect()
+            ;
+
+            make_variable(&format!("${row:04x}"), values.join(" "))
+        })
+        .collect()
+}
+
+
+/// Creates a variable without any children.
+fn make_variable(name: &str, value: String) -> Value {
+    json!({
+        "name":                 name,
+        "value":                value,
+        "variablesReference":   0,
+    })
+}
+
+
+/// Creates the description of a source file.
+fn make_source(path: &Path) -> Value {
+    json!({
+        "name": path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default(),
+        "path": path,
+    })
+}
@@ -0,0 +1,378 @@
+/*
+ * Copyright (C) 2022-2023 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use gemi_core::cpu::opcode::Instruction;
+use gemi_core::debug::symbols::{Symbol, SymbolTable};
+
+/// File extensions of assembly source files being scanned for labels.
+const SOURCE_FILE_EXTENSIONS: [&str; 4] = ["asm", "inc", "s", "z80"];
+
+/// All instruction mnemonics of the SM83 CPU.
+const MNEMONICS: [&str; 46] = [
+    "adc",  "add",  "and",  "bit",  "call", "ccf",  "cp",   "cpl",
+    "daa",  "dec",  "di",   "ei",   "halt", "inc",  "jp",   "jr",
+    "ld",   "ldh",  "ldi",  "ldd",  "nop",  "or",   "pop",  "push",
+    "res",  "ret",  "reti", "rl",   "rla",  "rlc",  "rlca", "rr",
+    "rra",  "rrc",  "rrca", "rst",  "sbc",  "scf",  "set",  "sla",
+    "sra",  "srl",  "stop", "sub",  "swap", "xor",
+];
+
+/// Directives which do not generate any code.
+const DIRECTIVES_WITHOUT_CODE: [&str; 12] = [
+    "def",  "redef", "export", "global", "purge", "assert",
+    "static_assert", "print", "println", "warn", "opt", "charmap",
+];
+
+/// Keywords used as second word in constant definitions like `NAME EQU 1`.
+const CONSTANT_KEYWORDS: [&str; 6] = ["equ", "equs", "set", "=", "rb", "rw"];
+
+
+/// A location in a source file, which was resolved to an address.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SourceLocation {
+    /// The line number, starting at 1.
+    pub line: usize,
+
+    /// The bank of the memory area the code is located in.
+    pub bank: u32,
+
+    /// The address of the code generated by this line.
+    pub address: u16,
+}
+
+
+/// The content of a source line, regarding the code it generates.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum LineContent {
+    /// The line does not generate any code, like empty lines, comments or labels.
+    Nothing,
+
+    /// The line contains a single CPU instruction.
+    Instruction,
+
+    /// The line contains anything else, like data, macros or section directives,
+    /// which prevents following the generated code.
+    Unknown,
+}
+
+
+/// A label defined in a source file.
+#[derive(Clone, Debug)]
+struct SourceLabel {
+    /// The line number, starting at 1.
+    line: usize,
+
+    /// The full name of the label, including the name of the parent label for local labels.
+    name: String,
+}
+
+
+/// An assembly source file, split into lines.
+#[derive(Clone, Debug)]
+struct SourceFile {
+    /// All lines of the file.
+    lines: Vec<String>,
+
+    /// All labels defined in this file, ordered by their line.
+    labels: Vec<SourceLabel>,
+}
+
+
+/// Maps lines of RGBDS assembly source files to addresses and vice versa.
+///
+/// Symbol files only contain the addresses of labels, so the location of any line
+/// is computed by following the instructions from the nearest label in front of it,
+/// using the instruction lengths of the actual code in memory.
+/// If the code between both cannot be followed, like for data or macros,
+/// the location of the label itself is used instead.
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    /// All source files loaded, by their path.
+    files: HashMap<PathBuf, SourceFile>,
+
+    /// The file and line number of each label.
+    labels: HashMap<String, (PathBuf, usize)>,
+}
+
+
+impl SourceFile {
+    /// Parses the content of a source file.
+    fn parse(text: &str) -> Self {
+        let lines: Vec<String> = text.lines().map(str::to_string).collect();
+        let mut labels = Vec::new();
+        let mut scope  = String::new();
+
+        for (index, line) in lines.iter().enumerate() {
+            if let Some((label, _)) = split_label(line) {
+                let name = match label.strip_prefix('.') {
+                    Some(_) => format!("{scope}{label}"),
+                    None    => label.to_string(),
+                };
+
+                // local labels may also be written with the parent label's name
+                scope = name.split('.').next().unwrap_or_default().to_string();
+
+                labels.push(SourceLabel { line: index + 1, name });
+            }
+        }
+
+        Self { lines, labels }
+    }
+
+
+    /// Get the label defined on a line, if any.
+    fn get_label_at(&self, line: usize) -> Option<&SourceLabel> {
+        self.labels
+            .binary_search_by_key(&line, |label| label.line)
+            .ok()
+            .map(|index| &self.labels[index])
+    }
+
+
+    /// Get the content of a line, regarding the code it generates.
+    fn get_content(&self, line: usize) -> LineContent {
+        let text = match self.lines.get(line - 1) {
+            Some(text) => text.as_str(),
+            None       => return LineContent::Unknown,
+        };
+
+        let code = match split_label(text) {
+            Some((_, code)) => code,
+            None            => text,
+        };
+
+        classify_code(code)
+    }
+}
+
+
+impl SourceMap {
+    /// Creates an empty source map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+
+    /// Loads all assembly source files within a directory and its subdirectories.
+    pub fn scan_directory(&mut self, directory: &Path) -> io::Result<()> {
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+
+            if path.is_dir() {
+                self.scan_directory(&path)?;
+            }
+            else if is_source_file(&path) {
+                self.load_file(&path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+
+    /// Loads a single source file.
+    pub fn load_file(&mut self, path: &Path) -> io::Result<()> {
+        let path = normalize_path(path);
+        let file = SourceFile::parse(&fs::read_to_string(&path)?);
+
+        for label in &file.labels {
+            self.labels.insert(label.name.clone(), (path.clone(), label.line));
+        }
+
+        self.files.insert(path, file);
+
+        Ok(())
+    }
+
+
+    /// Checks whether a source file was loaded.
+    pub fn has_file(&self, path: &Path) -> bool {
+        self.files.contains_key(&normalize_path(path))
+    }
+
+
+    /// Get the file and line number where a label is defined.
+    pub fn find_label(&self, name: &str) -> Option<(&Path, usize)> {
+        self.labels
+            .get(name)
+            .map(|(path, line)| (path.as_path(), *line))
+    }
+
+
+    /// Resolves a line in a source file to the address of the code it generates.
+    /// If the line does not generate any code, the next line generating code is used.
+    /// Returns `None` if the code could not be followed up to the requested line.
+    /// `read` reads a byte of code on a bank and address.
+    pub fn resolve_line<F>(&self, path: &Path, line: usize, symbols: &SymbolTable, read: F) -> Option<SourceLocation>
+        where F: Fn(u32, u16) -> u8
+    {
+        let file = self.files.get(&normalize_path(path))?;
+
+        // start at the nearest label in front of the line, which has a known address
+        let (label, symbol) = file.labels
+            .iter()
+            .rev()
+            .filter(|label| label.line <= line)
+            .find_map(|label| Some((label, symbols.find_by_name(&label.name)?)))
+            ?;
+
+        let mut address = symbol.address;
+
+        for current_line in label.line ..= file.lines.len() {
+            address = resync_address(file, current_line, symbol, symbols).unwrap_or(address);
+
+            match file.get_content(current_line) {
+                LineContent::Instruction if current_line >= line => {
+                    return Some(SourceLocation { line: current_line, bank: symbol.bank, address });
+                }
+
+                LineContent::Instruction => address = address.wrapping_add(get_instruction_length(symbol.bank, address, &read)),
+                LineContent::Nothing     => { }
+                LineContent::Unknown     => break,
+            }
+        }
+
+        // the code could not be followed up to the requested line
+        None
+    }
+
+
+    /// Finds the source file and line which generated the code on an address.
+    /// `read` reads a byte of code on a bank and address.
+    pub fn find_line<F>(&self, address: u16, bank: Option<u32>, symbols: &SymbolTable, read: F) -> Option<(&Path, usize)>
+        where F: Fn(u32, u16) -> u8
+    {
+        let symbol        = symbols.find_nearest(address, bank)?;
+        let (path, line)  = self.labels.get(&symbol.name)?;
+        let file          = self.files.get(path)?;
+        let mut current   = symbol.address;
+
+        for current_line in *line ..= file.lines.len() {
+            current = resync_address(file, current_line, symbol, symbols).unwrap_or(current);
+
+            match file.get_content(current_line) {
+                LineContent::Instruction if current == address => return Some((path, current_line)),
+                LineContent::Instruction if current >  address => break,
+                LineContent::Instruction => current = current.wrapping_add(get_instruction_length(symbol.bank, current, &read)),
+                LineContent::Nothing     => { }
+                LineContent::Unknown     => break,
+            }
+        }
+
+        Some((path, *line))
+    }
+}
+
+
+/// When another label within the same memory bank is defined on a line,
+/// get its address, which is more reliable than following the code.
+fn resync_address(file: &SourceFile, line: usize, start: &Symbol, symbols: &SymbolTable) -> Option<u16> {
+    let label  = file.get_label_at(line)?;
+    let symbol = symbols.find_by_name(&label.name)?;
+
+    (symbol.bank == start.bank && symbol.address >= start.address).then_some(symbol.address)
+}
+
+
+/// Get the length of the instruction located on an address.
+fn get_instruction_length<F>(bank: u32, address: u16, read: &F) -> u16
+    where F: Fn(u32, u16) -> u8
+{
+    Instruction::read_instruction(address, |address| read(bank, address)).get_instruction_length()
+}
+
+
+/// Splits a line into a label defined at its beginning and the remaining code.
+fn split_label(line: &str) -> Option<(&str, &str)> {
+    let code   = strip_comment(line);
+    let text   = code.trim_start();
+    let length = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || "_.@#$".contains(c)))
+        .unwrap_or(text.len())
+    ;
+
+    if length == 0 {
+        return None;
+    }
+
+    let (label, remaining) = text.split_at(length);
+
+    if let Some(remaining) = remaining.strip_prefix("::").or_else(|| remaining.strip_prefix(':')) {
+        return Some((label, remaining));
+    }
+
+    // local labels may be defined without a colon, if they start at the first column
+    let is_local_label =
+            line.starts_with('.')
+        &&  label.len() > 1
+        &&  (remaining.is_empty() || remaining.starts_with(char::is_whitespace))
+    ;
+
+    is_local_label.then_some((label, remaining))
+}
+
+
+/// Get the content of a line's code, without any labels.
+fn classify_code(code: &str) -> LineContent {
+    let mut words = strip_comment(code).split_whitespace().map(str::to_ascii_lowercase);
+
+    let Some(first) = words.next() else {
+        return LineContent::Nothing;
+    };
+
+    if MNEMONICS.contains(&first.as_str()) {
+        return LineContent::Instruction;
+    }
+
+    if DIRECTIVES_WITHOUT_CODE.contains(&first.as_str()) {
+        return LineContent::Nothing;
+    }
+
+    match words.next() {
+        Some(second) if CONSTANT_KEYWORDS.contains(&second.as_str()) => LineContent::Nothing,
+        _ => LineContent::Unknown,
+    }
+}
+
+
+/// Removes a comment starting with `;` from a line.
+fn strip_comment(line: &str) -> &str {
+    match line.split_once(';') {
+        Some((code, _)) => code,
+        None            => line,
+    }
+}
+
+
+/// Checks whether a file is an assembly source file based on its extension.
+fn is_source_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| SOURCE_FILE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+
+/// Get the canonical form of a path, so paths sent by the client match the files loaded.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
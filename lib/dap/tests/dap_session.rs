@@ -0,0 +1,460 @@
+/*
+ * Copyright (C) 2022-2023 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use gemi_dap::protocol::{read_message, write_message};
+use gemi_dap::server::serve;
+use gemi_dap::session::DapSession;
+
+const SOURCE: &str = r#"SECTION "Entry", ROM0[$100]
+    jp Main
+
+SECTION "Main", ROM0[$150]
+Main:
+    ld sp, $dfff
+    call Func
+    ld a, 2
+Loop:
+    jr Loop
+
+Func:
+    ld a, 1         ; increment the counter
+    ld [wCounter], a
+    ret
+
+SECTION "Variables", WRAM0
+wCounter: ds 1
+"#;
+
+const SYMBOLS: &str = "; File generated by rgblink
+00:0150 Main
+00:0158 Loop
+00:015a Func
+00:c000 wCounter
+";
+
+const PROGRAM: &[u8] = &[
+    0x31, 0xff, 0xdf,   // 0150: ld sp, $dfff
+    0xcd, 0x5a, 0x01,   // 0153: call Func
+    0x3e, 0x02,         // 0156: ld a, 2
+    0x18, 0xfe,         // 0158: jr Loop
+    0x3e, 0x01,         // 015a: ld a, 1
+    0xea, 0x00, 0xc0,   // 015c: ld [wCounter], a
+    0xc9,               // 015f: ret
+];
+
+
+/// Writes the ROM, symbol file and source file of the test program into a temporary directory.
+fn create_program(name: &str) -> PathBuf {
+    create_program_with(name, SOURCE, SYMBOLS, &[(0x0150, PROGRAM)])
+}
+
+
+/// Writes a ROM with the given code blocks, a symbol file and a source file into a temporary directory.
+/// The ROM's entry point jumps to the code at 0x0150.
+fn create_program_with(name: &str, source: &str, symbols: &str, code: &[(usize, &[u8])]) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("gemi-dap-{name}-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+
+    let mut rom = vec![0x00; 0x8000];
+    rom[0x0100 .. 0x0103].copy_from_slice(&[0xc3, 0x50, 0x01]);
+
+    for (address, bytes) in code {
+        rom[*address .. *address + bytes.len()].copy_from_slice(bytes);
+    }
+
+    fs::write(directory.join("test.gb"),  rom).unwrap();
+    fs::write(directory.join("test.sym"), symbols).unwrap();
+    fs::write(directory.join("main.asm"), source).unwrap();
+
+    directory
+}
+
+
+fn request(session: &mut DapSession, command: &str, arguments: Value) -> Vec<Value> {
+    session.handle_message(&json!({
+        "seq":          1,
+        "type":         "request",
+        "command":      command,
+        "arguments":    arguments,
+    }));
+
+    session.take_messages()
+}
+
+
+fn run_until_stopped(session: &mut DapSession) -> Vec<Value> {
+    for _ in 0 .. 100 {
+        if !session.is_running() {
+            break;
+        }
+
+        session.run_slice();
+    }
+
+    assert!(!session.is_running());
+    session.take_messages()
+}
+
+
+fn get_response<'a>(messages: &'a [Value], command: &str) -> &'a Value {
+    let response = messages.iter()
+        .find(|message| message["type"] == "response" && message["command"] == command)
+        .unwrap();
+
+    assert_eq!(response["success"], true, "{response}");
+    &response["body"]
+}
+
+
+fn get_event<'a>(messages: &'a [Value], event: &str) -> &'a Value {
+    &messages.iter()
+        .find(|message| message["type"] == "event" && message["event"] == event)
+        .unwrap()
+        ["body"]
+}
+
+
+fn get_top_frame(session: &mut DapSession) -> Value {
+    let messages = request(session, "stackTrace", json!({ "threadId": 1 }));
+    get_response(&messages, "stackTrace")["stackFrames"][0].clone()
+}
+
+
+fn get_variable(session: &mut DapSession, reference: i64, name: &str) -> Value {
+    let messages = request(session, "variables", json!({ "variablesReference": reference }));
+
+    get_response(&messages, "variables")["variables"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|variable| variable["name"] == name)
+        .unwrap()
+        ["value"]
+        .clone()
+}
+
+
+fn launch(session: &mut DapSession, directory: &Path, stop_on_entry: bool) {
+    request(session, "initialize", json!({ "adapterID": "gemi" }));
+
+    let messages = request(session, "launch", json!({
+        "program":      directory.join("test.gb"),
+        "stopOnEntry":  stop_on_entry,
+    }));
+
+    get_response(&messages, "launch");
+    get_event(&messages, "initialized");
+}
+
+
+#[test]
+fn dap_source_breakpoints() {
+    let directory   = create_program("breakpoints");
+    let mut session = DapSession::new();
+    launch(&mut session, &directory, false);
+
+    // line 14 is resolved by following the code from 'Func', while line 3 has no code
+    let messages = request(&mut session, "setBreakpoints", json!({
+        "source":       { "path": directory.join("main.asm") },
+        "breakpoints":  [ { "line": 14 }, { "line": 3 } ],
+    }));
+
+    let breakpoints = &get_response(&messages, "setBreakpoints")["breakpoints"];
+    assert_eq!(breakpoints[0]["verified"], true);
+    assert_eq!(breakpoints[0]["line"], 14);
+    assert_eq!(breakpoints[1]["verified"], false);
+
+    request(&mut session, "configurationDone", Value::Null);
+    assert!(session.is_running());
+
+    let messages = run_until_stopped(&mut session);
+    let stopped  = get_event(&messages, "stopped");
+    assert_eq!(stopped["reason"], "breakpoint");
+    assert_eq!(stopped["hitBreakpointIds"], json!([ breakpoints[0]["id"] ]));
+
+    // the call stack contains the caller with the line of the CALL instruction
+    let messages = request(&mut session, "stackTrace", json!({ "threadId": 1 }));
+    let frames   = &get_response(&messages, "stackTrace")["stackFrames"];
+    assert_eq!(frames.as_array().unwrap().len(), 2);
+    assert_eq!(frames[0]["name"], "Func+$2");
+    assert_eq!(frames[0]["line"], 14);
+    assert_eq!(frames[0]["source"]["name"], "main.asm");
+    assert_eq!(frames[1]["name"], "Main+$3");
+    assert_eq!(frames[1]["line"], 7);
+
+    // the breakpoint won't be hit again in the endless loop
+    request(&mut session, "continue", json!({ "threadId": 1 }));
+    session.run_slice();
+    session.run_slice();
+    assert!(session.is_running());
+
+    let messages = request(&mut session, "pause", json!({ "threadId": 1 }));
+    assert_eq!(get_event(&messages, "stopped")["reason"], "pause");
+    assert_eq!(get_top_frame(&mut session)["name"], "Loop");
+    assert_eq!(get_top_frame(&mut session)["line"], 10);
+
+    request(&mut session, "disconnect", json!({}));
+    assert!(session.is_terminated());
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+
+#[test]
+fn dap_unresolved_breakpoints() {
+    let source = r#"SECTION "Main", ROM0[$150]
+Main:
+    ld sp, $dfff
+    db $00
+    ld a, 2
+Loop:
+    jr Loop
+"#;
+
+    let directory   = create_program_with("unresolved", source, "00:0150 Main\n00:0154 Loop\n", &[
+        (0x0150, &[0x31, 0xff, 0xdf, 0x00, 0x3e, 0x02, 0x18, 0xfe]),
+    ]);
+
+    let mut session = DapSession::new();
+    launch(&mut session, &directory, false);
+
+    // the data on line 4 prevents following the code from 'Main' up to line 5
+    let messages = request(&mut session, "setBreakpoints", json!({
+        "source":       { "path": directory.join("main.asm") },
+        "breakpoints":  [ { "line": 5 }, { "line": 7 } ],
+    }));
+
+    let breakpoints = &get_response(&messages, "setBreakpoints")["breakpoints"];
+    assert_eq!(breakpoints[0]["verified"], false);
+    assert_eq!(breakpoints[0]["line"], 5);
+    assert_eq!(breakpoints[1]["verified"], true);
+    assert_eq!(breakpoints[1]["line"], 7);
+
+    // the unresolved breakpoint was not installed on the label instead
+    request(&mut session, "configurationDone", Value::Null);
+    let messages = run_until_stopped(&mut session);
+    assert_eq!(get_event(&messages, "stopped")["hitBreakpointIds"], json!([ breakpoints[1]["id"] ]));
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+
+#[test]
+fn dap_interrupt_call_stack() {
+    let source = r#"SECTION "VBlank", ROM0[$40]
+VBlank:
+    reti
+
+SECTION "Main", ROM0[$150]
+Main:
+    ld sp, $dfff
+    ld a, 1
+    ldh [$ff], a
+    xor a
+    ldh [$0f], a
+    ei
+Loop:
+    halt
+    push af
+    pop af
+    jr Loop
+"#;
+
+    let directory = create_program_with("interrupt", source, "00:0040 VBlank\n00:0150 Main\n00:015b Loop\n", &[
+        (0x0040, &[0xd9]),
+        (0x0150, &[0x31, 0xff, 0xdf, 0x3e, 0x01, 0xe0, 0xff, 0xaf, 0xe0, 0x0f, 0xfb, 0x76, 0xf5, 0xf1, 0x18, 0xfb]),
+    ]);
+
+    let mut session = DapSession::new();
+    launch(&mut session, &directory, false);
+
+    request(&mut session, "setBreakpoints", json!({
+        "source":       { "path": directory.join("main.asm") },
+        "breakpoints":  [ { "line": 3 } ],
+    }));
+
+    request(&mut session, "configurationDone", Value::Null);
+    run_until_stopped(&mut session);
+
+    // the interrupt was dispatched after HALT, while PC was pointing to PUSH
+    let messages = request(&mut session, "stackTrace", json!({ "threadId": 1 }));
+    let frames   = &get_response(&messages, "stackTrace")["stackFrames"];
+    assert_eq!(frames.as_array().unwrap().len(), 2);
+    assert_eq!(frames[0]["name"], "VBlank");
+    assert_eq!(frames[1]["name"], "Loop+$1");
+    assert_eq!(frames[1]["line"], 15);
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+
+#[test]
+fn dap_stepping_and_variables() {
+    let directory   = create_program("stepping");
+    let mut session = DapSession::new();
+    launch(&mut session, &directory, true);
+
+    let messages = request(&mut session, "configurationDone", Value::Null);
+    assert_eq!(get_event(&messages, "stopped")["reason"], "entry");
+
+    let mut step = |command: &str| {
+        request(&mut session, command, json!({ "threadId": 1 }));
+        let messages = run_until_stopped(&mut session);
+        assert_eq!(get_event(&messages, "stopped")["reason"], "step");
+
+        let frame = get_top_frame(&mut session);
+        (frame["name"].as_str().unwrap().to_string(), frame["line"].as_u64().unwrap())
+    };
+
+    assert_eq!(step("stepIn"),  ("Main".to_string(),      6));
+    assert_eq!(step("next"),    ("Main+$3".to_string(),   7));
+    assert_eq!(step("stepIn"),  ("Func".to_string(),      13));
+    assert_eq!(step("next"),    ("Func+$2".to_string(),   14));
+    assert_eq!(step("stepOut"), ("Main+$6".to_string(),   8));
+
+    // the function has written the counter before returning
+    let messages = request(&mut session, "scopes", json!({ "frameId": 0 }));
+    let scopes   = &get_response(&messages, "scopes")["scopes"];
+    assert_eq!(scopes[0]["name"], "Registers");
+    assert_eq!(scopes[1]["name"], "Symbols");
+
+    let registers = scopes[0]["variablesReference"].as_i64().unwrap();
+    let symbols   = scopes[1]["variablesReference"].as_i64().unwrap();
+    assert_eq!(get_variable(&mut session, registers, "A"),  "$01");
+    assert_eq!(get_variable(&mut session, registers, "SP"), "$dfff");
+    assert_eq!(get_variable(&mut session, registers, "PC"), "$0156 (Main+$6)");
+    assert_eq!(get_variable(&mut session, symbols,   "wCounter"), "$01");
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+
+#[test]
+fn dap_memory_ignores_ppu_locks() {
+    let source = r#"SECTION "Main", ROM0[$150]
+Main:
+    ld sp, $dfff
+    xor a
+    ldh [$40], a
+    ld a, $5a
+    ld [$8000], a
+    ld a, $a5
+    ld [$fe00], a
+    ld a, $91
+    ldh [$40], a
+WaitMode3:
+    ldh a, [$41]
+    and 3
+    cp 3
+    jr nz, WaitMode3
+Stop:
+    jr Stop
+"#;
+
+    let directory = create_program_with("ppu-locks", source, "00:0150 Main\n00:0164 WaitMode3\n00:016c Stop\n", &[
+        (0x0150, &[
+            0x31, 0xff, 0xdf, 0xaf, 0xe0, 0x40, 0x3e, 0x5a, 0xea, 0x00, 0x80, 0x3e, 0xa5, 0xea, 0x00, 0xfe,
+            0x3e, 0x91, 0xe0, 0x40, 0xf0, 0x41, 0xe6, 0x03, 0xfe, 0x03, 0x20, 0xf8, 0x18, 0xfe,
+        ]),
+    ]);
+
+    let mut session = DapSession::new();
+    launch(&mut session, &directory, false);
+
+    request(&mut session, "setBreakpoints", json!({
+        "source":       { "path": directory.join("main.asm") },
+        "breakpoints":  [ { "line": 18 } ],
+    }));
+
+    request(&mut session, "configurationDone", Value::Null);
+    run_until_stopped(&mut session);
+
+    // stopped while the PPU is in mode 3, where VRAM and OAM are locked
+    let messages = request(&mut session, "scopes", json!({ "frameId": 0 }));
+    let scopes   = &get_response(&messages, "scopes")["scopes"];
+    assert_eq!(scopes[2]["name"], "Video RAM");
+    assert_eq!(scopes[5]["name"], "OAM");
+    assert_eq!(scopes[6]["name"], "I/O Registers");
+
+    let vram = scopes[2]["variablesReference"].as_i64().unwrap();
+    let oam  = scopes[5]["variablesReference"].as_i64().unwrap();
+    let io   = scopes[6]["variablesReference"].as_i64().unwrap();
+    let stat = u8::from_str_radix(&get_variable(&mut session, io, "$ff40").as_str().unwrap()[3 .. 5], 16).unwrap();
+    assert_eq!(stat & 0x03, 3);
+    assert!(get_variable(&mut session, vram, "$8000").as_str().unwrap().starts_with("5a 00"));
+    assert!(get_variable(&mut session, oam,  "$fe00").as_str().unwrap().starts_with("a5 00"));
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+
+#[test]
+fn dap_over_stream() {
+    let directory = create_program("stream");
+
+    let requests = [
+        json!({ "seq": 1, "type": "request", "command": "initialize",        "arguments": { "adapterID": "gemi" } }),
+        json!({ "seq": 2, "type": "request", "command": "launch",            "arguments": { "program": directory.join("test.gb"), "stopOnEntry": true } }),
+        json!({ "seq": 3, "type": "request", "command": "configurationDone", "arguments": {} }),
+        json!({ "seq": 4, "type": "request", "command": "threads" }),
+        json!({ "seq": 5, "type": "request", "command": "disconnect",        "arguments": {} }),
+    ];
+
+    let mut input = Vec::new();
+    for request in &requests {
+        write_message(&mut input, request).unwrap();
+    }
+
+    let mut output = Vec::new();
+    serve(BufReader::new(Cursor::new(input)), &mut output).unwrap();
+
+    let mut reader   = Cursor::new(output);
+    let mut messages = Vec::new();
+    while let Some(message) = read_message(&mut reader).unwrap() {
+        messages.push(message);
+    }
+
+    let summary: Vec<String> = messages.iter()
+        .map(|message| format!("{}:{}", message["type"].as_str().unwrap(), message["command"].as_str().or(message["event"].as_str()).unwrap()))
+        .collect();
+
+    assert_eq!(summary, [
+        "response:initialize",
+        "response:launch",
+        "event:initialized",
+        "response:configurationDone",
+        "event:stopped",
+        "response:threads",
+        "response:disconnect",
+    ]);
+
+    // sequence numbers are assigned in the order of sending
+    for (index, message) in messages.iter().enumerate() {
+        assert_eq!(message["seq"], index as u64 + 1);
+    }
+
+    assert_eq!(messages[1]["request_seq"], 2);
+    assert_eq!(get_response(&messages, "threads")["threads"][0]["name"], "CPU");
+
+    fs::remove_dir_all(directory).unwrap();
+}
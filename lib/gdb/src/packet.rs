@@ -0,0 +1,215 @@
+/*
+ * Copyright (C) 2022-2023 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::VecDeque;
+use std::io;
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+
+/// The byte sent by the client to interrupt the running target.
+pub const INTERRUPT: u8 = 0x03;
+
+
+/// A message received from the GDB client.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Incoming {
+    /// A command packet with its payload, without framing and checksum.
+    Packet(Vec<u8>),
+
+    /// The client requests to interrupt the running target.
+    Interrupt,
+}
+
+
+/// A connection to a GDB client, which handles the packet framing
+/// of the remote serial protocol.
+pub struct Connection {
+    /// The socket connected to the client.
+    stream: TcpStream,
+
+    /// Bytes received from the client, which were not processed yet.
+    buffer: VecDeque<u8>,
+
+    /// Whether packets need to be acknowledged, which is the default
+    /// until the client requests 'no acknowledgment mode'.
+    ack_mode: bool,
+}
+
+
+/// Computes the checksum of a packet's payload.
+pub fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+
+/// Wraps a payload into a packet including framing and checksum.
+pub fn make_packet(data: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(data.len() + 4);
+    packet.push(b'$');
+    packet.extend_from_slice(data);
+    packet.extend_from_slice(format!("#{:02x}", checksum(data)).as_bytes());
+    packet
+}
+
+
+impl Connection {
+    /// Creates a new connection on a socket connected to a GDB client.
+    pub fn new(stream: TcpStream) -> Self {
+        // packets are small and should be sent immediately
+        _ = stream.set_nodelay(true);
+
+        Self {
+            stream,
+            buffer:   VecDeque::new(),
+            ack_mode: true,
+        }
+    }
+
+
+    /// Enables or disables the acknowledgment of packets.
+    pub fn set_ack_mode(&mut self, ack_mode: bool) {
+        self.ack_mode = ack_mode;
+    }
+
+
+    /// Reads the next byte from the client, waiting until it's available.
+    /// Returns `None` when the client closed the connection.
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        if let Some(byte) = self.buffer.pop_front() {
+            return Ok(Some(byte));
+        }
+
+        let mut data = [0u8; 1024];
+        let length = self.stream.read(&mut data)?;
+
+        if length == 0 {
+            return Ok(None);
+        }
+
+        self.buffer.extend(&data[.. length]);
+
+        Ok(self.buffer.pop_front())
+    }
+
+
+    /// Reads the next byte from the client or fails if the connection was closed.
+    fn expect_byte(&mut self) -> io::Result<u8> {
+        self.read_byte()?.ok_or_else(|| io::Error::from(ErrorKind::UnexpectedEof))
+    }
+
+
+    /// Waits for the next message of the client.
+    /// Returns `None` when the client closed the connection.
+    pub fn receive(&mut self) -> io::Result<Option<Incoming>> {
+        loop {
+            let byte = match self.read_byte()? {
+                Some(byte) => byte,
+                None       => return Ok(None),
+            };
+
+            match byte {
+                INTERRUPT => return Ok(Some(Incoming::Interrupt)),
+
+                b'$' => {
+                    let mut payload = Vec::new();
+
+                    loop {
+                        match self.expect_byte()? {
+                            b'#'  => break,
+                            other => payload.push(other),
+                        }
+                    }
+
+                    let checksum_high = self.expect_byte()?;
+                    let checksum_low  = self.expect_byte()?;
+                    let is_valid      = std::str::from_utf8(&[checksum_high, checksum_low])
+                        .ok()
+                        .and_then(|s| u8::from_str_radix(s, 16).ok())
+                        == Some(checksum(&payload))
+                    ;
+
+                    if self.ack_mode {
+                        self.stream.write_all(if is_valid { b"+" } else { b"-" })?;
+                    }
+
+                    // the client will resend packets with invalid checksums
+                    if is_valid || !self.ack_mode {
+                        return Ok(Some(Incoming::Packet(payload)));
+                    }
+                }
+
+                // ignore acknowledgments and any data outside of packets
+                _ => { }
+            }
+        }
+    }
+
+
+    /// Checks whether the client requested to interrupt the target without blocking.
+    pub fn poll_interrupt(&mut self) -> io::Result<bool> {
+        self.stream.set_nonblocking(true)?;
+
+        let mut data = [0u8; 1024];
+        let result   = self.stream.read(&mut data);
+
+        self.stream.set_nonblocking(false)?;
+
+        match result {
+            Ok(length) => self.buffer.extend(&data[.. length]),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => { }
+            Err(e) => return Err(e),
+        }
+
+        match self.buffer.iter().position(|byte| *byte == INTERRUPT) {
+            Some(index) => {
+                self.buffer.remove(index);
+                Ok(true)
+            }
+
+            None => Ok(false),
+        }
+    }
+
+
+    /// Sends a packet with the given payload to the client.
+    /// In acknowledgment mode, the packet is sent again until the client confirms it.
+    pub fn send(&mut self, data: &[u8]) -> io::Result<()> {
+        let packet = make_packet(data);
+
+        loop {
+            self.stream.write_all(&packet)?;
+
+            if !self.ack_mode {
+                return Ok(());
+            }
+
+            // wait for the acknowledgment, which may follow an interrupt request
+            loop {
+                match self.expect_byte()? {
+                    b'+' => return Ok(()),
+                    b'-' => break,
+
+                    INTERRUPT => {
+                        self.buffer.push_back(INTERRUPT);
+                    }
+
+                    _ => { }
+                }
+            }
+        }
+    }
+}
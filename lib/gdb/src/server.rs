@@ -0,0 +1,529 @@
+/*
+ * Copyright (C) 2022-2023 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+
+use gemi_core::debug::breakpoints::{Breakpoint, BreakpointHit, BreakpointId, MemoryAccess, ValueCondition};
+use gemi_core::debug::DebugEvent;
+use gemi_core::gameboy::GameBoy;
+
+use crate::packet::{Connection, Incoming};
+use crate::target::{Register, TARGET_XML};
+
+/// Signal reported when the target was interrupted by the client.
+const SIGINT: u8 = 2;
+
+/// Signal reported when the CPU was locked up by an illegal opcode.
+const SIGILL: u8 = 4;
+
+/// Signal reported after a single step or when a breakpoint was hit.
+const SIGTRAP: u8 = 5;
+
+
+/// A server waiting for GDB clients to connect.
+pub struct GdbServer {
+    listener: TcpListener,
+}
+
+
+/// The reason why a debugging session has ended.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SessionEnd {
+    /// The client detached from the target, which may continue running.
+    Detached,
+
+    /// The client requested to kill the target.
+    Killed,
+
+    /// The client closed the connection.
+    Disconnected,
+}
+
+
+/// The kinds of breakpoints and watchpoints as used by the 'Z' and 'z' packets.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+enum BreakpointType {
+    Software,
+    Hardware,
+    WriteWatchpoint,
+    ReadWatchpoint,
+    AccessWatchpoint,
+}
+
+
+/// A debugging session with a single GDB client on an emulator instance.
+pub struct GdbSession<'a> {
+    /// The connection to the client.
+    connection: Connection,
+
+    /// The emulator being debugged.
+    gb: &'a mut GameBoy,
+
+    /// The breakpoints installed by the client, with their IDs in the emulator.
+    breakpoints: HashMap<(BreakpointType, u16, u16), BreakpointId>,
+}
+
+
+impl GdbServer {
+    /// Creates a new server listening on the given address.
+    /// To only accept connections from the local machine, use a loopback address like `127.0.0.1:2345`.
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<GdbServer> {
+        Ok(GdbServer {
+            listener: TcpListener::bind(address)?,
+        })
+    }
+
+
+    /// Get the address the server is listening on.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+
+    /// Waits for a client to connect and runs a debugging session on the given emulator
+    /// until the client detaches or disconnects.
+    pub fn serve(&self, gb: &mut GameBoy) -> io::Result<SessionEnd> {
+        let (stream, _) = self.listener.accept()?;
+
+        GdbSession::new(Connection::new(stream), gb).run()
+    }
+}
+
+
+impl BreakpointType {
+    /// Get the breakpoint type by its number used in the 'Z' and 'z' packets.
+    fn by_number(number: u8) -> Option<BreakpointType> {
+        match number {
+            0 => Some(BreakpointType::Software),
+            1 => Some(BreakpointType::Hardware),
+            2 => Some(BreakpointType::WriteWatchpoint),
+            3 => Some(BreakpointType::ReadWatchpoint),
+            4 => Some(BreakpointType::AccessWatchpoint),
+            _ => None,
+        }
+    }
+
+
+    /// Creates the breakpoint to be installed into the emulator.
+    fn create_breakpoint(&self, address: u16, length: u16) -> Breakpoint {
+        let memory_breakpoint = |access| Breakpoint::Memory {
+            begin:      address,
+            end:        address.saturating_add(length.max(1) - 1),
+            access,
+            condition:  ValueCondition::Any,
+        };
+
+        match self {
+            BreakpointType::Software | BreakpointType::Hardware => {
+                Breakpoint::Execute {
+                    address,
+                    bank: None,
+                }
+            }
+
+            BreakpointType::WriteWatchpoint  => memory_breakpoint(MemoryAccess::Write),
+            BreakpointType::ReadWatchpoint   => memory_breakpoint(MemoryAccess::Read),
+            BreakpointType::AccessWatchpoint => memory_breakpoint(MemoryAccess::ReadWrite),
+        }
+    }
+
+
+    /// Get the stop reason reported to the client when this breakpoint was hit.
+    fn stop_reason(&self, hit: &BreakpointHit) -> String {
+        let address = hit.memory_access.map(|access| access.address).unwrap_or_default();
+
+        match self {
+            BreakpointType::Software         => "swbreak:;".to_string(),
+            BreakpointType::Hardware         => "hwbreak:;".to_string(),
+            BreakpointType::WriteWatchpoint  => format!("watch:{address:x};"),
+            BreakpointType::ReadWatchpoint   => format!("rwatch:{address:x};"),
+            BreakpointType::AccessWatchpoint => format!("awatch:{address:x};"),
+        }
+    }
+}
+
+
+impl<'a> GdbSession<'a> {
+    /// Creates a new session on an established connection.
+    pub fn new(connection: Connection, gb: &'a mut GameBoy) -> Self {
+        Self {
+            connection,
+            gb,
+            breakpoints: HashMap::new(),
+        }
+    }
+
+
+    /// Handles requests of the client until it detaches or disconnects.
+    /// All breakpoints installed by the client will be removed afterwards.
+    pub fn run(mut self) -> io::Result<SessionEnd> {
+        let result = self.process_packets();
+
+        for id in self.breakpoints.values() {
+            self.gb.get_breakpoints_mut().remove(*id);
+        }
+
+        result
+    }
+
+
+    /// Receives and handles packets until the session ends.
+    fn process_packets(&mut self) -> io::Result<SessionEnd> {
+        loop {
+            let packet = match self.connection.receive()? {
+                Some(Incoming::Packet(packet)) => packet,
+
+                // the target is not running, so just report it's stopped
+                Some(Incoming::Interrupt) => {
+                    self.send_stop_reply(SIGINT, "")?;
+                    continue;
+                }
+
+                None => return Ok(SessionEnd::Disconnected),
+            };
+
+            let packet = String::from_utf8_lossy(&packet).into_owned();
+
+            match packet.as_str() {
+                "D" => {
+                    self.connection.send(b"OK")?;
+                    return Ok(SessionEnd::Detached);
+                }
+
+                "k" => {
+                    return Ok(SessionEnd::Killed);
+                }
+
+                // the response is still acknowledged by the client
+                "QStartNoAckMode" => {
+                    self.connection.send(b"OK")?;
+                    self.connection.set_ack_mode(false);
+                }
+
+                _ => {
+                    let response = self.handle_packet(&packet)?;
+                    self.connection.send(response.as_bytes())?;
+                }
+            }
+        }
+    }
+
+
+    /// Handles a single packet and returns the response to be sent to the client.
+    /// An empty response tells the client the packet is not supported.
+    fn handle_packet(&mut self, packet: &str) -> io::Result<String> {
+        // split on the first character, which may be longer than a single byte
+        // when the packet contained invalid UTF-8 data
+        let mut chars = packet.chars();
+        let command   = chars.next();
+        let args      = chars.as_str();
+
+        let response = match command {
+            Some('?') => Some(make_stop_reply(SIGTRAP, "")),
+            Some('g') => self.read_registers(),
+            Some('G') => self.write_registers(args),
+            Some('p') => self.read_register(args),
+            Some('P') => self.write_register(args),
+            Some('m') => self.read_memory(args),
+            Some('M') => self.write_memory(args),
+            Some('s') => self.step(),
+            Some('c') => self.resume()?,
+            Some('Z') => self.insert_breakpoint(args),
+            Some('z') => self.remove_breakpoint(args),
+            Some('H') => Some("OK".to_string()),
+            Some('T') => Some("OK".to_string()),
+            Some('q') => self.handle_query(args),
+            _         => None,
+        };
+
+        Ok(response.unwrap_or_default())
+    }
+
+
+    /// Handles general queries.
+    fn handle_query(&mut self, query: &str) -> Option<String> {
+        if query.starts_with("Supported") {
+            return Some("PacketSize=1000;QStartNoAckMode+;qXfer:features:read+;swbreak+;hwbreak+".to_string());
+        }
+
+        if let Some(args) = query.strip_prefix("Xfer:features:read:target.xml:") {
+            let (offset, length) = args.split_once(',')?;
+            let offset = usize::from_str_radix(offset, 16).ok()?;
+            let length = usize::from_str_radix(length, 16).ok()?;
+
+            let begin = offset.min(TARGET_XML.len());
+            let end   = offset.saturating_add(length).min(TARGET_XML.len());
+            let more  = if end < TARGET_XML.len() { 'm' } else { 'l' };
+
+            return Some(format!("{more}{}", &TARGET_XML[begin .. end]));
+        }
+
+        match query {
+            "Attached"    => Some("1".to_string()),
+            "C"           => Some("QC1".to_string()),
+            "fThreadInfo" => Some("m1".to_string()),
+            "sThreadInfo" => Some("l".to_string()),
+            _             => None,
+        }
+    }
+
+
+    /// Reads all registers.
+    fn read_registers(&self) -> Option<String> {
+        Some(
+            Register::ALL
+                .iter()
+                .map(|register| encode_u16(register.read(self.gb)))
+                .collect()
+        )
+    }
+
+
+    /// Writes all registers.
+    fn write_registers(&mut self, data: &str) -> Option<String> {
+        let bytes = decode_hex(data)?;
+
+        for (register, value) in Register::ALL.iter().zip(bytes.chunks_exact(2)) {
+            register.write(self.gb, u16::from_le_bytes([value[0], value[1]]));
+        }
+
+        Some("OK".to_string())
+    }
+
+
+    /// Reads a single register.
+    fn read_register(&self, args: &str) -> Option<String> {
+        let register = usize::from_str_radix(args, 16).ok().and_then(Register::by_number);
+
+        match register {
+            Some(register) => Some(encode_u16(register.read(self.gb))),
+            None           => Some("E01".to_string()),
+        }
+    }
+
+
+    /// Writes a single register.
+    fn write_register(&mut self, args: &str) -> Option<String> {
+        let (number, value) = args.split_once('=')?;
+        let register = usize::from_str_radix(number, 16).ok().and_then(Register::by_number);
+        let value    = decode_hex(value).filter(|bytes| bytes.len() == 2);
+
+        match (register, value) {
+            (Some(register), Some(value)) => {
+                register.write(self.gb, u16::from_le_bytes([value[0], value[1]]));
+                Some("OK".to_string())
+            }
+
+            _ => Some("E01".to_string()),
+        }
+    }
+
+
+    /// Reads a memory range without triggering any watchpoints
+    /// and without being affected by the VRAM and OAM locks of the PPU.
+    fn read_memory(&self, args: &str) -> Option<String> {
+        let Some((address, length)) = parse_address_and_length(args) else {
+            return Some("E01".to_string());
+        };
+
+        Some(
+            (0 .. length)
+                .map(|offset| self.gb.get_mmu().debug_read_u8(address.wrapping_add(offset)))
+                .map(|value| format!("{value:02x}"))
+                .collect()
+        )
+    }
+
+
+    /// Writes data into memory.
+    /// Writes into the cartridge ROM are rejected, because they would change
+    /// the registers of the memory bank controller instead.
+    fn write_memory(&mut self, args: &str) -> Option<String> {
+        let parsed = args.split_once(':').and_then(|(range, data)| {
+            Some((parse_address_and_length(range)?, decode_hex(data)?))
+        });
+
+        match parsed {
+            Some(((address, length), _)) if touches_cartridge_rom(address, length) => {
+                Some("E02".to_string())
+            }
+
+            Some(((address, length), data)) if data.len() == length as usize => {
+                for (offset, value) in data.into_iter().enumerate() {
+                    self.gb.get_mmu_mut().write_u8(address.wrapping_add(offset as u16), value);
+                }
+
+                Some("OK".to_string())
+            }
+
+            _ => Some("E01".to_string()),
+        }
+    }
+
+
+    /// Runs a single instruction.
+    fn step(&mut self) -> Option<String> {
+        let mut results = self.gb.run_single_step();
+
+        // when stopped on a breakpoint before executing the instruction,
+        // the next step will execute it
+        if results.cycles == 0 {
+            results = self.gb.run_single_step();
+        }
+
+        if results.events.contains(DebugEvent::CpuLocked) {
+            return Some(make_stop_reply(SIGILL, ""));
+        }
+
+        let reason = self.get_stop_reason(&results.breakpoint_hits);
+
+        Some(make_stop_reply(SIGTRAP, &reason))
+    }
+
+
+    /// Continues running the emulator until a breakpoint is hit or the client interrupts.
+    fn resume(&mut self) -> io::Result<Option<String>> {
+        loop {
+            let results = self.gb.run_frame();
+
+            if !results.breakpoint_hits.is_empty() {
+                let reason = self.get_stop_reason(&results.breakpoint_hits);
+                return Ok(Some(make_stop_reply(SIGTRAP, &reason)));
+            }
+
+            if results.events.contains(DebugEvent::CpuLocked) {
+                return Ok(Some(make_stop_reply(SIGILL, "")));
+            }
+
+            if self.connection.poll_interrupt()? {
+                return Ok(Some(make_stop_reply(SIGINT, "")));
+            }
+        }
+    }
+
+
+    /// Inserts a breakpoint or watchpoint.
+    fn insert_breakpoint(&mut self, args: &str) -> Option<String> {
+        let Some((breakpoint_type, address, length)) = parse_breakpoint_args(args) else {
+            return Some(String::new());
+        };
+
+        let key = (breakpoint_type, address, length);
+
+        if !self.breakpoints.contains_key(&key) {
+            let breakpoint = breakpoint_type.create_breakpoint(address, length);
+
+            match self.gb.get_breakpoints_mut().add(breakpoint) {
+                Ok(id) => { self.breakpoints.insert(key, id); }
+                Err(_) => return Some("E01".to_string()),
+            }
+        }
+
+        Some("OK".to_string())
+    }
+
+
+    /// Removes a breakpoint or watchpoint.
+    fn remove_breakpoint(&mut self, args: &str) -> Option<String> {
+        let Some((breakpoint_type, address, length)) = parse_breakpoint_args(args) else {
+            return Some(String::new());
+        };
+
+        if let Some(id) = self.breakpoints.remove(&(breakpoint_type, address, length)) {
+            self.gb.get_breakpoints_mut().remove(id);
+        }
+
+        Some("OK".to_string())
+    }
+
+
+    /// Get the stop reason to be reported for the breakpoints being hit.
+    fn get_stop_reason(&self, hits: &[BreakpointHit]) -> String {
+        hits
+            .iter()
+            .find_map(|hit| {
+                self.breakpoints
+                    .iter()
+                    .find(|(_, id)| **id == hit.id)
+                    .map(|((breakpoint_type, _, _), _)| breakpoint_type.stop_reason(hit))
+            })
+            .unwrap_or_default()
+    }
+
+
+    /// Sends a stop reply packet to the client.
+    fn send_stop_reply(&mut self, signal: u8, reason: &str) -> io::Result<()> {
+        self.connection.send(make_stop_reply(signal, reason).as_bytes())
+    }
+}
+
+
+/// Creates a stop reply packet.
+fn make_stop_reply(signal: u8, reason: &str) -> String {
+    format!("T{signal:02x}{reason}thread:1;")
+}
+
+
+/// Encodes a 16 bit value as little endian hex string.
+fn encode_u16(value: u16) -> String {
+    let [low, high] = value.to_le_bytes();
+    format!("{low:02x}{high:02x}")
+}
+
+
+/// Decodes a string of hex digits into bytes.
+fn decode_hex(data: &str) -> Option<Vec<u8>> {
+    if !data.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0 .. data.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(data.get(index .. index + 2)?, 16).ok())
+        .collect()
+}
+
+
+/// Parses an address and a length, like `c000,10`.
+fn parse_address_and_length(args: &str) -> Option<(u16, u16)> {
+    let (address, length) = args.split_once(',')?;
+    let address = u16::from_str_radix(address, 16).ok()?;
+    let length  = u16::from_str_radix(length, 16).ok()?;
+
+    Some((address, length))
+}
+
+
+/// Checks whether a memory range overlaps the cartridge ROM area at 0x0000 - 0x7fff.
+fn touches_cartridge_rom(address: u16, length: u16) -> bool {
+    length != 0 && (address <= 0x7fff || address.checked_add(length - 1).is_none())
+}
+
+
+/// Parses the arguments of a 'Z' or 'z' packet, like `0,150,1`.
+fn parse_breakpoint_args(args: &str) -> Option<(BreakpointType, u16, u16)> {
+    let (breakpoint_type, range) = args.split_once(',')?;
+    let breakpoint_type = BreakpointType::by_number(breakpoint_type.parse().ok()?)?;
+
+    // additional conditions or commands are not supported
+    let range = range.split(';').next()?;
+    let (address, length) = parse_address_and_length(range)?;
+
+    Some((breakpoint_type, address, length))
+}
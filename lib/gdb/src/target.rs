@@ -0,0 +1,94 @@
+/*
+ * Copyright (C) 2022-2023 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::cpu::cpu::RegisterR16;
+use gemi_core::gameboy::GameBoy;
+
+/// The target description sent to the client, which describes the SM83 register set.
+/// GDB has no built-in knowledge about the GameBoy CPU, so the registers are
+/// described as six 16 bit registers, with the 8 bit registers as their high and low bytes.
+pub const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.gemi.sm83.core">
+    <reg name="af" bitsize="16" type="uint16" regnum="0"/>
+    <reg name="bc" bitsize="16" type="uint16"/>
+    <reg name="de" bitsize="16" type="uint16"/>
+    <reg name="hl" bitsize="16" type="uint16"/>
+    <reg name="sp" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+  </feature>
+</target>
+"#;
+
+
+/// The registers in the order defined by [TARGET_XML].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Register {
+    AF,
+    BC,
+    DE,
+    HL,
+    SP,
+    PC,
+}
+
+
+impl Register {
+    /// All registers in the order of their register numbers.
+    pub const ALL: [Register; 6] = [
+        Register::AF,
+        Register::BC,
+        Register::DE,
+        Register::HL,
+        Register::SP,
+        Register::PC,
+    ];
+
+
+    /// Get a register by its register number.
+    pub fn by_number(number: usize) -> Option<Register> {
+        Self::ALL.get(number).copied()
+    }
+
+
+    /// Reads the current value of this register.
+    pub fn read(&self, gb: &GameBoy) -> u16 {
+        match self {
+            Register::AF => gb.cpu.get_r16(RegisterR16::AF),
+            Register::BC => gb.cpu.get_r16(RegisterR16::BC),
+            Register::DE => gb.cpu.get_r16(RegisterR16::DE),
+            Register::HL => gb.cpu.get_r16(RegisterR16::HL),
+            Register::SP => gb.cpu.get_stack_pointer(),
+            Register::PC => gb.cpu.get_instruction_pointer(),
+        }
+    }
+
+
+    /// Changes the value of this register.
+    pub fn write(&self, gb: &mut GameBoy, value: u16) {
+        match self {
+            // the lower 4 bits of the flags register are always zero
+            Register::AF => gb.cpu.set_r16(RegisterR16::AF, value & 0xfff0),
+            Register::BC => gb.cpu.set_r16(RegisterR16::BC, value),
+            Register::DE => gb.cpu.set_r16(RegisterR16::DE, value),
+            Register::HL => gb.cpu.set_r16(RegisterR16::HL, value),
+            Register::SP => gb.cpu.set_stack_pointer(value),
+            Register::PC => gb.cpu.set_instruction_pointer(value),
+        }
+    }
+}
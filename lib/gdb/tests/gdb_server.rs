@@ -0,0 +1,262 @@
+/*
+ * Copyright (C) 2022-2023 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::thread;
+
+use gemi_core::cartridge::{Cartridge, ROM_OFFSET_ENTRY_POINT};
+use gemi_core::cpu::cpu::RegisterR16;
+use gemi_core::gameboy::GameBoy;
+use gemi_gdb::packet::make_packet;
+use gemi_gdb::server::{GdbServer, SessionEnd};
+
+
+/// A minimal GDB client sending commands over a TCP connection.
+struct Client {
+    stream:   TcpStream,
+    ack_mode: bool,
+}
+
+
+impl Client {
+    fn read_byte(&mut self) -> u8 {
+        let mut byte = [0u8; 1];
+        self.stream.read_exact(&mut byte).unwrap();
+        byte[0]
+    }
+
+
+    /// Sends a command and returns the response payload.
+    fn command(&mut self, command: &str) -> String {
+        self.command_bytes(command.as_bytes())
+    }
+
+
+    /// Sends a command, which may contain arbitrary bytes, and returns the response payload.
+    fn command_bytes(&mut self, command: &[u8]) -> String {
+        self.stream.write_all(&make_packet(command)).unwrap();
+
+        if self.ack_mode {
+            assert_eq!(b'+', self.read_byte());
+        }
+
+        assert_eq!(b'$', self.read_byte());
+
+        let mut payload = Vec::new();
+        loop {
+            match self.read_byte() {
+                b'#'  => break,
+                other => payload.push(other),
+            }
+        }
+
+        // checksum
+        self.read_byte();
+        self.read_byte();
+
+        if self.ack_mode {
+            self.stream.write_all(b"+").unwrap();
+        }
+
+        String::from_utf8(payload).unwrap()
+    }
+}
+
+
+fn create_gameboy() -> GameBoy {
+    let mut rom = vec![0x00; 0x8000];
+
+    // endless loop counting up a value in WRAM and writing it into the SCX register
+    rom[ROM_OFFSET_ENTRY_POINT .. ROM_OFFSET_ENTRY_POINT + 9].copy_from_slice(&[
+        0x21, 0x00, 0xc0,   // LD HL, 0xc000
+        0x34,               // INC (HL)
+        0x7e,               // LD A, (HL)
+        0xe0, 0x43,         // LDH (0x43), A
+        0x18, 0xfa,         // JR -6
+    ]);
+
+    let cartridge = Cartridge::load_from_bytes(rom, None).unwrap();
+
+    let mut builder = GameBoy::build();
+    builder.set_cartridge(cartridge);
+    builder.finish().unwrap()
+}
+
+
+/// Runs a GDB session on a new emulator, while the client function
+/// sends commands over a loopback connection.
+fn run_session(run_client: impl FnOnce(&mut Client) + Send + 'static) -> (GameBoy, SessionEnd) {
+    let mut gb = create_gameboy();
+    let server = GdbServer::bind("127.0.0.1:0").unwrap();
+    let addr   = server.local_addr().unwrap();
+
+    let client_thread = thread::spawn(move || {
+        let mut client = Client {
+            stream:   TcpStream::connect(addr).unwrap(),
+            ack_mode: true,
+        };
+
+        run_client(&mut client);
+    });
+
+    let session_end = server.serve(&mut gb).unwrap();
+    client_thread.join().unwrap();
+
+    (gb, session_end)
+}
+
+
+#[test]
+fn gdb_registers_and_memory() {
+    let (gb, session_end) = run_session(|client| {
+        assert!(client.command("qSupported:swbreak+;hwbreak+").contains("qXfer:features:read+"));
+        assert!(client.command("qXfer:features:read:target.xml:0,1000").starts_with("l<?xml"));
+        assert_eq!("T05thread:1;", client.command("?"));
+
+        // switch into 'no acknowledgment' mode
+        assert_eq!("OK", client.command("QStartNoAckMode"));
+        client.ack_mode = false;
+
+        // registers af, bc, de, hl, sp, pc
+        assert_eq!("000000000000000000000001", client.command("g"));
+        assert_eq!("OK", client.command("P1=3412"));
+        assert_eq!("3412", client.command("p1"));
+        assert_eq!("0001", client.command("p5"));
+
+        // memory
+        assert_eq!("2100c034", client.command("m100,4"));
+        assert_eq!("OK", client.command("Mc010,3:aabbcc"));
+        assert_eq!("aabbcc", client.command("mc010,3"));
+
+        // writes into the cartridge ROM would change the MBC registers instead
+        assert_eq!("E02", client.command("M2000,1:02"));
+        assert_eq!("E02", client.command("Mffff,2:0000"));
+        assert_eq!("2100c034", client.command("m100,4"));
+
+        // unsupported packets get an empty response
+        assert_eq!("", client.command("vMustReplyEmpty"));
+        assert_eq!("", client.command_bytes(&[0xc3, 0xa4]));
+        assert_eq!("", client.command_bytes(&[0xff]));
+
+        assert_eq!("OK", client.command("D"));
+    });
+
+    assert_eq!(SessionEnd::Detached, session_end);
+    assert_eq!(0x1234, gb.cpu.get_r16(RegisterR16::BC));
+    assert_eq!(0xbb, gb.get_mmu().read_u8(0xc011));
+}
+
+
+#[test]
+fn gdb_memory_ignores_ppu_locks() {
+    let (_, session_end) = run_session(|client| {
+        assert_eq!("OK", client.command("QStartNoAckMode"));
+        client.ack_mode = false;
+
+        // steps until the PPU is in the given mode, according to the STAT register
+        let step_until_mode = |client: &mut Client, mode: u8| {
+            for _ in 0 .. 1000 {
+                let stat = u8::from_str_radix(&client.command("mff41,1"), 16).unwrap();
+
+                if (stat & 0x03) == mode {
+                    return;
+                }
+
+                assert_eq!("T05thread:1;", client.command("s"));
+            }
+
+            panic!("PPU did not enter mode {mode}");
+        };
+
+        // write into VRAM and OAM while they are accessible
+        step_until_mode(client, 0);
+        assert_eq!("OK", client.command("M8000,1:5a"));
+        assert_eq!("OK", client.command("Mfe00,1:a5"));
+
+        // reading them while being locked by the PPU still returns their content
+        step_until_mode(client, 3);
+        assert_eq!("5a", client.command("m8000,1"));
+        assert_eq!("a5", client.command("mfe00,1"));
+
+        assert_eq!("OK", client.command("D"));
+    });
+
+    assert_eq!(SessionEnd::Detached, session_end);
+}
+
+
+#[test]
+fn gdb_breakpoints_and_stepping() {
+    let (gb, session_end) = run_session(|client| {
+        // single step
+        assert_eq!("T05thread:1;", client.command("s"));
+        assert_eq!("0301", client.command("p5"));
+
+        // software breakpoint on 'LD A, (HL)'
+        assert_eq!("OK", client.command("Z0,104,1"));
+        assert_eq!("T05swbreak:;thread:1;", client.command("c"));
+        assert_eq!("0401", client.command("p5"));
+        assert_eq!("01", client.command("mc000,1"));
+
+        // continuing stops on the next iteration
+        assert_eq!("T05swbreak:;thread:1;", client.command("c"));
+        assert_eq!("02", client.command("mc000,1"));
+        assert_eq!("OK", client.command("z0,104,1"));
+
+        // write watchpoint on the SCX register
+        assert_eq!("OK", client.command("Z2,ff43,1"));
+        assert_eq!("T05watch:ff43;thread:1;", client.command("c"));
+        assert_eq!("0701", client.command("p5"));
+        assert_eq!("OK", client.command("z2,ff43,1"));
+
+        // no breakpoints left, so an interrupt stops the emulator
+        client.stream.write_all(&make_packet(b"c")).unwrap();
+        client.read_byte();
+        client.stream.write_all(&[0x03]).unwrap();
+
+        let mut reply = [0u8; 16];
+        client.stream.read_exact(&mut reply).unwrap();
+        assert!(reply.starts_with(b"$T02thread:1;#"));
+        client.stream.write_all(b"+").unwrap();
+
+        client.stream.write_all(&make_packet(b"k")).unwrap();
+        client.read_byte();
+    });
+
+    assert_eq!(SessionEnd::Killed, session_end);
+
+    // all breakpoints are removed after the session
+    assert!(gb.get_breakpoints().is_empty());
+}
+
+
+#[test]
+fn gdb_step_into_lockup() {
+    let (gb, session_end) = run_session(|client| {
+        // place an illegal opcode in WRAM and jump there
+        assert_eq!("OK", client.command("Mc100,1:d3"));
+        assert_eq!("OK", client.command("P5=00c1"));
+
+        // stepping into it locks up the CPU
+        assert_eq!("T04thread:1;", client.command("s"));
+        assert_eq!("OK", client.command("D"));
+    });
+
+    assert_eq!(SessionEnd::Detached, session_end);
+    assert!(gb.cpu.is_locked());
+}
@@ -0,0 +1,34 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+use std::path::Path;
+
+use gemi_core::camera::StaticImageSource;
+
+
+/// Loads an image file to be used as the image seen by the camera sensor.
+/// The image gets converted into grayscale and scaled to the sensor size.
+pub fn load_camera_image(path: &Path) -> Result<StaticImageSource, String> {
+    let image = image::open(path)
+        .map_err(|e| format!("Failed to load camera image from {}: {e}", path.display()))?
+        .to_luma8()
+    ;
+
+    let width  = image.width()  as usize;
+    let height = image.height() as usize;
+
+    Ok(StaticImageSource::from_grayscale(image.as_raw(), width, height))
+}
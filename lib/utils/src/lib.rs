@@ -15,4 +15,9 @@
  * along with this program. If not, see <http://www.gnu.org/licenses/>.
  */
 
+pub mod camera;
 pub mod keybindings;
+pub mod printer;
+pub mod rumble;
+pub mod screenshot;
+pub mod test_results;
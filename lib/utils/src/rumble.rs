@@ -0,0 +1,140 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+use gemi_core::gameboy::Clock;
+use gemi_core::mmu::memory::RumbleEvent;
+
+
+/// Converts the changes of a cartridge's rumble motor into an intensity
+/// to be applied on a game controller once per frame.
+/// Games usually control the strength of the motor by turning it on and off
+/// multiple times per frame, so the intensity is the fraction of time the
+/// motor was turned on during the frame.
+#[derive(Default)]
+pub struct RumbleTracker {
+    /// The state of the motor at the end of the last frame.
+    active: bool,
+
+    /// The time when the current frame began, in device cycles.
+    frame_begin: Clock,
+}
+
+
+impl RumbleTracker {
+    /// Creates a new tracker with the motor turned off.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+
+    /// Applies the events of the frame ending at `frame_end` and returns the
+    /// intensity of the motor during this frame, from 0.0 (off) to 1.0 (on all the time).
+    pub fn on_frame_completed(&mut self, events: &[RumbleEvent], frame_end: Clock) -> f32 {
+        // the emulator time may go backwards, for example after rewinding
+        let frame_begin   = self.frame_begin.min(frame_end);
+        let mut last      = frame_begin;
+        let mut on_cycles = 0;
+
+        for event in events {
+            let cycle = event.cycle.clamp(frame_begin, frame_end);
+
+            if self.active {
+                on_cycles += cycle - last;
+            }
+
+            self.active = event.active;
+            last        = cycle;
+        }
+
+        if self.active {
+            on_cycles += frame_end - last;
+        }
+
+        self.frame_begin = frame_end;
+
+        let frame_cycles = frame_end - frame_begin;
+        if frame_cycles == 0 {
+            return if self.active { 1.0 } else { 0.0 };
+        }
+
+        (on_cycles as f32) / (frame_cycles as f32)
+    }
+
+
+    /// Checks whether the motor was turned on at the end of the last frame.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(cycle: Clock, active: bool) -> RumbleEvent {
+        RumbleEvent { cycle, active }
+    }
+
+
+    #[test]
+    fn duty_cycle() {
+        let mut rumble = RumbleTracker::new();
+
+        // no events keep the motor turned off
+        assert_eq!(0.0, rumble.on_frame_completed(&[], 1000));
+
+        // turned on for a quarter of the frame
+        assert_eq!(0.25, rumble.on_frame_completed(&[event(1250, true), event(1500, false)], 2000));
+
+        // turned on for the second half of the frame and keeps running
+        assert_eq!(0.5, rumble.on_frame_completed(&[event(2500, true)], 3000));
+        assert!(rumble.is_active());
+        assert_eq!(1.0, rumble.on_frame_completed(&[], 4000));
+
+        // turned off and on again multiple times
+        let events = [event(4100, false), event(4300, true), event(4500, false), event(4900, true)];
+        assert_eq!(0.4, rumble.on_frame_completed(&events, 5000));
+    }
+
+
+    #[test]
+    fn events_outside_the_frame_are_clamped() {
+        let mut rumble = RumbleTracker::new();
+        rumble.on_frame_completed(&[], 1000);
+
+        // an event before the frame counts as being turned on at its beginning
+        assert_eq!(1.0, rumble.on_frame_completed(&[event(500, true)], 2000));
+
+        // an event after the frame counts as being turned off at its end
+        assert_eq!(1.0, rumble.on_frame_completed(&[event(3500, false)], 3000));
+        assert!(!rumble.is_active());
+    }
+
+
+    #[test]
+    fn time_going_backwards() {
+        let mut rumble = RumbleTracker::new();
+        rumble.on_frame_completed(&[event(500, true)], 1000);
+
+        // after rewinding, the frame has no duration, so only the state of the motor counts
+        assert_eq!(1.0, rumble.on_frame_completed(&[], 400));
+        assert_eq!(0.0, rumble.on_frame_completed(&[event(300, false)], 200));
+
+        // the next frame starts at the rewound time
+        assert_eq!(0.5, rumble.on_frame_completed(&[event(300, true)], 400));
+    }
+}
@@ -0,0 +1,39 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::path::Path;
+
+use image::{Rgba, RgbaImage};
+
+use gemi_core::ppu::ppu::LcdBuffer;
+
+
+/// Converts the current content of the emulator display into an RGBA image.
+pub fn lcd_to_image(lcd: &LcdBuffer) -> RgbaImage {
+    RgbaImage::from_fn(lcd.get_width(), lcd.get_height(), |x, y| {
+        let color = lcd.get_pixel(x, y);
+        Rgba([color.r, color.g, color.b, color.a])
+    })
+}
+
+
+/// Saves the current content of the emulator display as PNG file.
+pub fn save_lcd_as_png(lcd: &LcdBuffer, path: &Path) -> Result<(), String> {
+    lcd_to_image(lcd)
+        .save_with_format(path, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to save screenshot to {}: {e}", path.display()))
+}
@@ -0,0 +1,94 @@
+/*
+ * Copyright (C) 2022-2024 by Christian Fischer
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use gemi_core::gameboy::GameBoy;
+use gemi_core::ppu::graphic_data::TileMap;
+
+
+/// The sequence sent by mooneye test ROMs via serial port, when the test passed.
+pub const MOONEYE_RESULT_SEQ_PASS : &[u8] = &[3, 5, 8, 13, 21, 34];
+
+/// The sequence sent by mooneye test ROMs via serial port, when the test failed.
+pub const MOONEYE_RESULT_SEQ_FAIL : &[u8] = &[0x42, 0x42, 0x42, 0x42, 0x42, 0x42];
+
+/// sequence of tile numbers representing the text 'Passed'.
+const TILE_SEQUENCE_PASSED : [u8; 6] = [ 0x50, 0x61, 0x73, 0x73, 0x65, 0x64 ];
+
+
+/// Takes a reference to an emulator running a mooneye test rom and checks whether it's passed or not.
+/// Mooneye test roms send a specific sequence of bytes to the serial port to indicate whether they passed or not.
+pub fn check_mooneye_test_passed(gb: &GameBoy) -> Result<(), String> {
+    let test_result_message = gb.get_peripherals().serial.get_output();
+
+    // Fail if the test rom sent the fail sequence
+    if test_result_message == MOONEYE_RESULT_SEQ_FAIL {
+        Err("ROM sent FAILED sequence.".to_string())
+    }
+    // Fail if the test rom didn't send the pass sequence
+    else if test_result_message != MOONEYE_RESULT_SEQ_PASS {
+        Err(format!("ROM sent unknown sequence: {test_result_message:?}"))
+    }
+    else {
+        Ok(())
+    }
+}
+
+
+/// Takes a reference to an emulator running a blargg test rom and checks whether it's passed or not.
+/// Usually blargg prints a message like 'Passed' or 'Passed all tests' to the serial port.
+/// Some tests are missing this output, for them we check if the 'Passed' text was written on the
+/// screen by searching for the according tiles on the tile map.
+pub fn check_blargg_test_passed(gb: &GameBoy) -> Result<(), String> {
+    // get any message written to the serial port
+    let output_message = gb.get_peripherals().serial.get_output_as_text();
+
+    match output_message.trim().split('\n').next_back() {
+        // Passed - return success
+        Some("Passed all tests") | Some("Passed") => return Ok(()),
+
+        // no message, continue
+        Some("") => { },
+
+        // other message will be taken as error
+        _ => return Err(format!("Unexpected output message: {output_message}")),
+    };
+
+    // Search for a 'Passed' message in the tile map
+    for line in 0..32 {
+        let mut line_match = true;
+
+        // compare tiles with expected sequence
+        for (tile_x, expected_tile) in TILE_SEQUENCE_PASSED.iter().enumerate() {
+            let tile_index   = (line * 32 + tile_x) as u16;
+            let tile_address = TileMap::H9800.base_address() + tile_index;
+            let tile         = gb.get_mmu().debug_read_u8(tile_address);
+
+            if tile != *expected_tile {
+                line_match = false;
+                break;
+            }
+        }
+
+        // success, if all tiles match the 'Passed' sequence
+        if line_match {
+            return Ok(());
+        }
+    }
+
+    // no success message
+    Err("No 'Passed' message received from the emulator.".to_string())
+}
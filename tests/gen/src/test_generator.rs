@@ -159,6 +159,10 @@ impl UnitTestGenerator {
                 test_code.push_str(&format!("            stop_on_infinite_loop: {stop_on_infinite_loop},\n"));
             }
 
+            if let Some(movie_path) = &test_case.run_config.movie_path {
+                test_code.push_str(&format!("            movie_path: Some(\"{movie_path}\".to_string()),\n"));
+            }
+
             test_code.push_str("            .. RunConfig::default()\n");
             test_code.push_str("        },\n");
         }
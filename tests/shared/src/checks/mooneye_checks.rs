@@ -18,24 +18,11 @@
 use gemi_core::gameboy::GameBoy;
 use crate::runner::TestCaseError;
 
-const MOONEYE_RESULT_SEQ_PASS : &[u8] = &[3, 5, 8, 13, 21, 34];
-const MOONEYE_RESULT_SEQ_FAIL : &[u8] = &[0x42, 0x42, 0x42, 0x42, 0x42, 0x42];
+pub use gemi_utils::test_results::{MOONEYE_RESULT_SEQ_FAIL, MOONEYE_RESULT_SEQ_PASS};
 
 
 /// Takes a reference to an emulator running a mooneye test rom and checks whether it's passed or not.
 /// Mooneye test roms send a specific sequence of bytes to the serial port to indicate whether they passed or not.
 pub fn check_mooneye_test_passed(gb: &GameBoy) -> Result<(), TestCaseError> {
-    let test_result_message = gb.get_peripherals().serial.get_output();
-
-    // Fail if the test rom sent the fail sequence
-    if test_result_message == MOONEYE_RESULT_SEQ_FAIL {
-        Err(TestCaseError::Failed(format!("ROM sent FAILED sequence.")))
-    }
-    // Fail if the test rom didn't send the pass sequence
-    else if test_result_message != MOONEYE_RESULT_SEQ_PASS {
-        Err(TestCaseError::Failed(format!("ROM sent unknown sequence: {test_result_message:?}")))
-    }
-    else {
-        Ok(())
-    }
+    gemi_utils::test_results::check_mooneye_test_passed(gb).map_err(TestCaseError::Failed)
 }
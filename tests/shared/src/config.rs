@@ -25,7 +25,6 @@ pub const SOURCE_URL:                   &str = "https://github.com/c-sp/gameboy-
 pub const TESTS_KNOWN_TO_FAIL : &'static [&str] = &[
     "blargg/interrupt_time/interrupt_time",
     "blargg/oam_bug/",
-    "blargg/blargg_other/halt_bug",
 
     "gambatte/bgen/",
     "gambatte/bgtiledata/",
@@ -22,6 +22,7 @@ use std::path::PathBuf;
 use gemi_core::boot_rom::BootRom;
 use gemi_core::cartridge::Cartridge;
 use gemi_core::gameboy::{DeviceType, GameBoy};
+use gemi_core::movie::{Movie, MoviePlayer};
 use gemi_core::utils::to_u8;
 
 use crate::checks::blargg_checks::check_blargg_test_passed;
@@ -148,15 +149,47 @@ pub fn create_device_with_config(workspace: &Workspace, device_type: &DeviceType
 }
 
 
+/// Loads the input movie to be played while running the emulator, if any.
+pub fn create_movie_player_with_config(workspace: &Workspace, gb: &mut GameBoy, config: &RunConfig) -> Result<Option<MoviePlayer>, TestCaseError> {
+    let Some(movie_path) = &config.movie_path else {
+        return Ok(None);
+    };
+
+    let movie_path = PathBuf::from(workspace.get_path_to_str(movie_path));
+    let movie = Movie::read_from_file(&movie_path)
+        .map_err(|e| TestCaseError::SetUpError(e.to_string()))
+        ?;
+
+    let player = MoviePlayer::new(movie, gb)
+        .map_err(|e| TestCaseError::SetUpError(e.to_string()))
+        ?;
+
+    Ok(Some(player))
+}
+
+
 /// Run the emulator until any stop condition is met, which is defined in the RunConfig.
-pub fn run_to_stop_conditions(gb: &mut GameBoy, config: &RunConfig) -> Result<u32, TestCaseError> {
+/// If a movie player is given, the movie will be played first before any stop conditions
+/// except `run_frames` are checked.
+pub fn run_to_stop_conditions(gb: &mut GameBoy, config: &RunConfig, mut movie: Option<&mut MoviePlayer>) -> Result<u32, TestCaseError> {
     let mut stop_next_frame           = false;
     let mut stop_next_frame_countdown = 2u32;
     let mut frames_to_process         = config.run_frames;
     let mut frames                    = 0;
 
     loop {
-        gb.run_frame();
+        let movie_playing = match &mut movie {
+            Some(player) if !player.is_finished(gb) => {
+                player.run_frame(gb).map_err(|e| TestCaseError::Failed(e.to_string()))?;
+                true
+            },
+
+            _ => {
+                gb.run_frame();
+                false
+            },
+        };
+
         frames += 1;
 
         // stop when the flag was set
@@ -181,6 +214,11 @@ pub fn run_to_stop_conditions(gb: &mut GameBoy, config: &RunConfig) -> Result<u3
             }
         }
 
+        // wait for the movie to be completed before checking other stop conditions
+        if movie_playing {
+            continue;
+        }
+
         // stop running when in HALT state
         if config.stop_on_halt {
             if !gb.cpu.is_running() {
@@ -284,8 +322,11 @@ pub fn run_test_case_for_result(workspace: &Workspace, test_case: &EmulatorTestC
         gb.get_peripherals_mut().serial.enable_output_queue(true);
     }
 
+    // load the input movie, if any
+    let mut movie = create_movie_player_with_config(workspace, &mut gb, run_cfg)?;
+
     // Run
-    run_to_stop_conditions(&mut gb, &run_cfg)?;
+    run_to_stop_conditions(&mut gb, &run_cfg, movie.as_mut())?;
 
     // Check
     check_results(&gb, workspace, &result)?;
@@ -62,6 +62,11 @@ pub struct RunConfig {
     /// Stops the emulator when an infinite loop was detected,
     /// like when a 'JR -2' instruction is invoked.
     pub stop_on_infinite_loop: bool,
+
+    /// Optional: Path to an input movie to be played while running the emulator,
+    /// for example to get past the title screen of a ROM.
+    /// Other stop conditions than `run_frames` are only checked after the movie was finished.
+    pub movie_path: Option<String>,
 }
 
 
@@ -134,6 +139,7 @@ impl Default for RunConfig {
             run_frames: None,
             stop_on_halt: false,
             stop_on_infinite_loop: false,
+            movie_path: None,
         }
     }
 }
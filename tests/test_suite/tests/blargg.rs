@@ -6279,7 +6279,6 @@ mod blargg_other {
 
 
     #[test]
-    #[ignore]
     fn halt_bug_dmg() {
         let test_case = EmulatorTestCase {
             device: DeviceType::GameBoyDmg,
@@ -6301,7 +6300,6 @@ mod blargg_other {
 
 
     #[test]
-    #[ignore]
     fn halt_bug_mgb() {
         let test_case = EmulatorTestCase {
             device: DeviceType::GameBoyPocket,
@@ -6323,7 +6321,6 @@ mod blargg_other {
 
 
     #[test]
-    #[ignore]
     fn halt_bug_gbc() {
         let test_case = EmulatorTestCase {
             device: DeviceType::GameBoyColor,
@@ -6345,7 +6342,6 @@ mod blargg_other {
 
 
     #[test]
-    #[ignore]
     fn halt_bug_gba() {
         let test_case = EmulatorTestCase {
             device: DeviceType::GameBoyAdvance,
@@ -6367,7 +6363,6 @@ mod blargg_other {
 
 
     #[test]
-    #[ignore]
     fn halt_bug_sgb() {
         let test_case = EmulatorTestCase {
             device: DeviceType::SuperGameBoy,
@@ -6389,7 +6384,6 @@ mod blargg_other {
 
 
     #[test]
-    #[ignore]
     fn halt_bug_sgb2() {
         let test_case = EmulatorTestCase {
             device: DeviceType::SuperGameBoy2,